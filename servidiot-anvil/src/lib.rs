@@ -1,4 +1,4 @@
-use std::{fs::File, io, path::PathBuf};
+use std::{fs::File, io, path::{Path, PathBuf}};
 
 use crate::nbt::level::LevelRoot;
 use crate::nbt::player::PlayerData;
@@ -35,11 +35,15 @@ impl WorldManager {
         }
     }
 
+    /// The world directory.
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
 
     /// Attempt to load playerdata for some UUID.
     pub fn load_player_data(&self, uuid: &Uuid) -> WorldManagerResult<Option<PlayerData>> {
-        let mut dir = self.directory.clone();
-        dir.push(format!("{}.dat", uuid.as_hyphenated()));
+        let dir = self.migrate_player_data(uuid)?;
         if !dir.try_exists().map_err(WorldManagerError::IOError)? {
             return Ok(None);
         }
@@ -51,28 +55,108 @@ impl WorldManager {
 
     /// Save playerdata to disk.
     pub fn save_player_data(&mut self, uuid: &Uuid, value: &PlayerData) -> WorldManagerResult<()> {
-        let mut dir = self.directory.clone();
-        dir.push(format!("{}.dat", uuid.as_hyphenated()));
+        let dir = self.player_data_path(uuid);
+        if let Some(parent) = dir.parent() {
+            std::fs::create_dir_all(parent).map_err(WorldManagerError::IOError)?;
+        }
         let mut file = File::options()
             .write(true)
             .create(true)
+            .truncate(true)
             .open(dir)
             .map_err(WorldManagerError::IOError)?;
         to_gzip_writer(&mut file, &value, None).map_err(WorldManagerError::NBTError)?;
         Ok(())
     }
 
+    /// Delete the playerdata for some UUID.
+    /// Returns `false` if there was none.
+    pub fn delete_player_data(&self, uuid: &Uuid) -> WorldManagerResult<bool> {
+        match std::fs::remove_file(self.migrate_player_data(uuid)?) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(WorldManagerError::IOError(e)),
+        }
+    }
+
+    /// Copy the playerdata of one UUID to another, replacing any
+    /// existing data. Does nothing if `from` has no playerdata.
+    pub fn copy_player_data(&mut self, from: &Uuid, to: &Uuid) -> WorldManagerResult<()> {
+        if let Some(data) = self.load_player_data(from)? {
+            self.save_player_data(to, &data)?;
+        }
+        Ok(())
+    }
 
+    /// Where the playerdata for some UUID is stored.
+    fn player_data_path(&self, uuid: &Uuid) -> PathBuf {
+        let mut dir = self.directory.clone();
+        dir.push("playerdata");
+        dir.push(format!("{}.dat", uuid.as_hyphenated()));
+        dir
+    }
+
+    /// Where the playerdata for some UUID was
+    /// stored before it moved to `playerdata/`.
+    fn legacy_player_data_path(&self, uuid: &Uuid) -> PathBuf {
+        let mut dir = self.directory.clone();
+        dir.push(format!("{}.dat", uuid.as_hyphenated()));
+        dir
+    }
+
+    /// Moves playerdata for some UUID from the root of the world
+    /// directory into `playerdata/`, unless it is there already.
+    /// Returns where the playerdata is now stored.
+    fn migrate_player_data(&self, uuid: &Uuid) -> WorldManagerResult<PathBuf> {
+        let path = self.player_data_path(uuid);
+        let legacy = self.legacy_player_data_path(uuid);
+        if !path.try_exists().map_err(WorldManagerError::IOError)?
+            && legacy.try_exists().map_err(WorldManagerError::IOError)?
+        {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent).map_err(WorldManagerError::IOError)?;
+            }
+            std::fs::rename(legacy, &path).map_err(WorldManagerError::IOError)?;
+        }
+        Ok(path)
+    }
 
     /// Loads a dimension.
     pub fn load_dimension(&self, dimension: DimensionID) -> WorldManagerResult<RegionManager> {
+        let dir = self.region_directory(dimension);
+        std::fs::create_dir_all(&dir).map_err(WorldManagerError::IOError)?;
+        Ok(RegionManager::new(dir, CompressionType::ZLib))
+    }
+
+    /// Whether some dimension is present on disk. The overworld
+    /// exists along with the world directory, while other
+    /// dimensions need at least one region file.
+    pub fn dimension_exists(&self, dimension: DimensionID) -> WorldManagerResult<bool> {
+        if dimension == 0 {
+            return self.directory.try_exists().map_err(WorldManagerError::IOError);
+        }
+        let entries = match std::fs::read_dir(self.region_directory(dimension)) {
+            Ok(v) => v,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(WorldManagerError::IOError(e)),
+        };
+        for entry in entries {
+            let entry = entry.map_err(WorldManagerError::IOError)?;
+            if entry.path().extension().is_some_and(|v| v == "mca") {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Where the region files of some dimension are stored.
+    fn region_directory(&self, dimension: DimensionID) -> PathBuf {
         let mut dir = self.directory.clone();
         if dimension != 0 {
             dir.push(format!("DIM{dimension}"));
         }
         dir.push("region");
-        std::fs::create_dir_all(&dir).map_err(WorldManagerError::IOError)?;
-        Ok(RegionManager::new(dir, CompressionType::ZLib))
+        dir
     }
 
 
@@ -105,6 +189,119 @@ impl WorldManager {
 
 #[cfg(test)]
 mod tests {
+    use servidiot_primitives::item::ItemStack;
+    use uuid::Uuid;
+
+    use crate::{nbt::{entity::ItemSlot, player::PlayerData}, WorldManager};
+
+    #[test]
+    fn new_player_data() {
+        let dir = std::env::temp_dir().join(format!("servidiot-new-player-{}", std::process::id()));
+        let mut manager = WorldManager::open(dir.clone());
+        let uuid = Uuid::from_u128(2);
+
+        let mut data = PlayerData::new(&uuid, [0.5, 64.0, -0.5], 1);
+        data.xp_level = 3;
+        data.inventory.push(ItemSlot {
+            stack_data: ItemStack { count: 5, meta: 0, id: 1, nbt_data: None },
+            slot: 2,
+        });
+        manager.save_player_data(&uuid, &data).unwrap();
+        let loaded = manager.load_player_data(&uuid).unwrap().unwrap();
+        assert_eq!(loaded.xp_level, 3);
+        assert_eq!(loaded.game_mode, 1);
+        assert_eq!(loaded.entity_data.position, [0.5, 64.0, -0.5]);
+        assert_eq!(loaded.entity_data.uuid_least_significant, 2);
+        assert!(loaded.abilities.can_fly);
+        assert_eq!(loaded.inventory[0].stack_data.count, 5);
+        assert_eq!(loaded.inventory[0].slot, 2);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn delete_player_data() {
+        let dir = std::env::temp_dir().join(format!("servidiot-world-{}", std::process::id()));
+        let manager = WorldManager::open(dir.clone());
+        let uuid = Uuid::from_u128(1);
+
+        assert!(!manager.delete_player_data(&uuid).unwrap());
+        std::fs::create_dir_all(dir.join("playerdata")).unwrap();
+        std::fs::write(manager.player_data_path(&uuid), []).unwrap();
+        assert!(manager.delete_player_data(&uuid).unwrap());
+        assert!(!manager.player_data_path(&uuid).exists());
+        assert!(manager.load_player_data(&uuid).unwrap().is_none());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn legacy_player_data() {
+        let dir = std::env::temp_dir().join(format!("servidiot-legacy-player-{}", std::process::id()));
+        let mut manager = WorldManager::open(dir.clone());
+        let uuid = Uuid::from_u128(3);
+
+        let mut data = PlayerData::new(&uuid, [0.0, 70.0, 0.0], 0);
+        data.xp_level = 7;
+        manager.save_player_data(&uuid, &data).unwrap();
+        std::fs::rename(manager.player_data_path(&uuid), manager.legacy_player_data_path(&uuid)).unwrap();
+
+        // The old file is moved into playerdata/ the first time it is loaded.
+        assert_eq!(manager.load_player_data(&uuid).unwrap().unwrap().xp_level, 7);
+        assert!(!manager.legacy_player_data_path(&uuid).exists());
+        assert!(manager.player_data_path(&uuid).exists());
+        assert_eq!(manager.load_player_data(&uuid).unwrap().unwrap().xp_level, 7);
+
+        // Deleting finds old files too.
+        manager.save_player_data(&uuid, &data).unwrap();
+        std::fs::rename(manager.player_data_path(&uuid), manager.legacy_player_data_path(&uuid)).unwrap();
+        assert!(manager.delete_player_data(&uuid).unwrap());
+        assert!(manager.load_player_data(&uuid).unwrap().is_none());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn copy_player_data() {
+        let dir = std::env::temp_dir().join(format!("servidiot-copy-player-{}", std::process::id()));
+        let mut manager = WorldManager::open(dir.clone());
+        let (from, to) = (Uuid::from_u128(4), Uuid::from_u128(5));
+
+        // Copying nothing leaves the target alone.
+        let mut data = PlayerData::new(&to, [0.0, 64.0, 0.0], 0);
+        data.xp_level = 1;
+        manager.save_player_data(&to, &data).unwrap();
+        manager.copy_player_data(&from, &to).unwrap();
+        assert_eq!(manager.load_player_data(&to).unwrap().unwrap().xp_level, 1);
+
+        data.xp_level = 9;
+        manager.save_player_data(&from, &data).unwrap();
+        manager.copy_player_data(&from, &to).unwrap();
+        assert_eq!(manager.load_player_data(&to).unwrap().unwrap().xp_level, 9);
+        assert_eq!(manager.load_player_data(&from).unwrap().unwrap().xp_level, 9);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn dimension_exists() {
+        let dir = std::env::temp_dir().join(format!("servidiot-dimensions-{}", std::process::id()));
+        let manager = WorldManager::open(dir.clone());
+        assert!(!manager.dimension_exists(0).unwrap());
+        assert!(!manager.dimension_exists(-1).unwrap());
+
+        let nether = manager.region_directory(-1);
+        std::fs::create_dir_all(&nether).unwrap();
+        assert!(manager.dimension_exists(0).unwrap());
+        assert!(!manager.dimension_exists(-1).unwrap());
+
+        std::fs::write(nether.join("r.0.0.mca"), []).unwrap();
+        assert!(manager.dimension_exists(-1).unwrap());
+        assert!(!manager.dimension_exists(1).unwrap());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
     // use std::{path::PathBuf, str::FromStr};
 
     // use servidiot_primitives::position::BlockPosition;
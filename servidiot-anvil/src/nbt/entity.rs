@@ -1,21 +1,39 @@
 use nbt::Value;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use servidiot_primitives::item::ItemStack;
 
+/// Writes an array as an NBT list, which
+/// NBT serializers cannot write tuples as.
+fn as_list<S: Serializer, T: Serialize>(value: &[T], serializer: S) -> Result<S::Ok, S::Error> {
+    value.serialize(serializer)
+}
+
+/// Reads an NBT byte as a bool. Flattened structs are read
+/// without knowing their fields' types, so bools in them
+/// cannot be read from bytes as they are elsewhere.
+fn from_byte<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    i8::deserialize(deserializer).map(|v| v != 0)
+}
+
+/// Reads an optional NBT byte as a bool, as [`from_byte`] does.
+fn from_optional_byte<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<bool>, D::Error> {
+    Option::<i8>::deserialize(deserializer).map(|v| v.map(|v| v != 0))
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct EntityBase {
     /// Describes the current X,Y,Z
     /// position of the entity.
-    #[serde(rename = "Pos")]
+    #[serde(rename = "Pos", serialize_with = "as_list")]
     pub position: [f64; 3],
     /// Describes the current dX,dY,dZ
     /// velocity of the entity in
     /// meters per tick.
-    #[serde(rename = "Motion")]
+    #[serde(rename = "Motion", serialize_with = "as_list")]
     pub motion: [f64; 3],
     /// Represents entity
     /// rotation in degrees.
-    #[serde(rename = "Rotation")]
+    #[serde(rename = "Rotation", serialize_with = "as_list")]
     pub rotation: [f32; 2],
     /// Distance the entity has fallen.
     /// Larger values cause more damage
@@ -32,10 +50,10 @@ pub struct EntityBase {
     #[serde(rename = "Air")]
     pub air: i16,
     /// True if the entity is touching the ground.
-    #[serde(rename = "OnGround")]
+    #[serde(rename = "OnGround", deserialize_with = "from_byte")]
     pub on_ground: bool,
     /// True if the entity should not take damage.
-    #[serde(rename = "Invulnerable")]
+    #[serde(rename = "Invulnerable", deserialize_with = "from_byte")]
     pub invulnerable: bool,
     /// The number of ticks before which the entity
     /// may be teleported back through a portal of
@@ -54,7 +72,7 @@ pub struct EntityBase {
     /// If true, and this entity has a custom name,
     /// it will always appear above them, whether or
     /// not the cursor is pointing at it.
-    #[serde(rename = "CustomNameVisible")]
+    #[serde(rename = "CustomNameVisible", default, deserialize_with = "from_optional_byte")]
     pub custom_name_visible: Option<bool>,
     /// The data of the entity being ridden. Note
     /// that if an entity is being ridden, the
@@ -155,7 +173,7 @@ pub struct AttributeModifier {
     pub uuid_least_significant: i64,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PotionEffect {
     /// The effect ID.
     #[serde(rename = "Id")]
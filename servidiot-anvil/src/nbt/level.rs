@@ -6,7 +6,7 @@ use serde::{Serialize, Deserialize};
 pub struct LevelRoot {
     /// This tag contains all the level data. 
     #[serde(rename = "Data")]
-    data: LevelData
+    pub data: LevelData
 }
 
 
@@ -1,3 +1,4 @@
 pub mod level;
 pub mod player;
-pub mod entity;
\ No newline at end of file
+pub mod entity;
+pub mod tile_entity;
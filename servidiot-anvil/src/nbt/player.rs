@@ -1,5 +1,7 @@
+use nbt::Value;
 use serde::{Serialize, Deserialize};
 use servidiot_primitives::player::PlayerAbilities;
+use uuid::Uuid;
 
 use super::entity::{EntityBase, MobBase, ItemSlot};
 
@@ -81,3 +83,69 @@ pub struct PlayerData {
     pub abilities: PlayerAbilities
 
 }
+
+impl PlayerData {
+    /// The data of a player joining for the first
+    /// time, at `position` and in `game_mode`.
+    pub fn new(uuid: &Uuid, position: [f64; 3], game_mode: i32) -> Self {
+        let (uuid_most_significant, uuid_least_significant) = uuid.as_u64_pair();
+        let creative = game_mode == 1;
+        Self {
+            entity_data: EntityBase {
+                position,
+                motion: [0.0; 3],
+                rotation: [0.0; 2],
+                fall_distance: 0.0,
+                fire: -20,
+                air: 300,
+                on_ground: false,
+                invulnerable: false,
+                portal_cooldown: 0,
+                uuid_most_significant: uuid_most_significant as i64,
+                uuid_least_significant: uuid_least_significant as i64,
+                custom_name: None,
+                custom_name_visible: None,
+                riding: Value::Compound(Default::default()),
+            },
+            mob_data: MobBase {
+                health_float: Some(20.0),
+                health: 20,
+                absorption_amount: 0.0,
+                attack_time: 0,
+                hurt_time: 0,
+                death_time: 0,
+                attributes: vec![],
+                effects: None,
+            },
+            dimension: 0,
+            game_mode,
+            score: 0,
+            selected_item_slot: 0,
+            spawnpoint_x: None,
+            spawnpoint_y: None,
+            spawnpoint_z: None,
+            spawn_forced: None,
+            sleeping: false,
+            sleep_timer: 0,
+            food_level: 20,
+            food_exhaustion_level: 0.0,
+            food_saturation_level: 5.0,
+            food_tick_timer: 0,
+            xp_level: 0,
+            xp_percentage: 0.0,
+            xp_total: 0,
+            xp_seed: None,
+            inventory: vec![],
+            ender_chest: vec![],
+            abilities: PlayerAbilities {
+                walk_speed: 0.1,
+                fly_speed: 0.05,
+                can_fly: creative,
+                is_flying: false,
+                invulnerable: creative,
+                may_build: true,
+                instabreak: creative,
+            },
+        }
+    }
+}
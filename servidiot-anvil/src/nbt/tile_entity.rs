@@ -0,0 +1,108 @@
+use nbt::{Map, Value};
+use serde::{Deserialize, Serialize};
+
+/// A sign, as stored in the `TileEntities` of a chunk.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TileEntitySign {
+    /// Always `Sign`.
+    pub id: String,
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+    /// The lines of text, from top to bottom.
+    #[serde(rename = "Text1")]
+    pub text1: String,
+    #[serde(rename = "Text2")]
+    pub text2: String,
+    #[serde(rename = "Text3")]
+    pub text3: String,
+    #[serde(rename = "Text4")]
+    pub text4: String,
+}
+
+impl TileEntitySign {
+    pub const ID: &'static str = "Sign";
+
+    /// Creates a sign at some position.
+    pub fn new(x: i32, y: i32, z: i32, lines: [String; 4]) -> Self {
+        let [text1, text2, text3, text4] = lines;
+        Self {
+            id: Self::ID.to_string(),
+            x,
+            y,
+            z,
+            text1,
+            text2,
+            text3,
+            text4,
+        }
+    }
+
+    /// Reads a sign out of a tile entity. Returns
+    /// `None` if the tile entity is not a sign.
+    pub fn from_value(value: &Value) -> Option<Self> {
+        let Value::Compound(map) = value else {
+            return None;
+        };
+        let string = |key: &str| match map.get(key) {
+            Some(Value::String(v)) => Some(v.clone()),
+            _ => None,
+        };
+        let int = |key: &str| match map.get(key) {
+            Some(Value::Int(v)) => Some(*v),
+            _ => None,
+        };
+        if string("id")? != Self::ID {
+            return None;
+        }
+        Some(Self {
+            id: Self::ID.to_string(),
+            x: int("x")?,
+            y: int("y")?,
+            z: int("z")?,
+            text1: string("Text1").unwrap_or_default(),
+            text2: string("Text2").unwrap_or_default(),
+            text3: string("Text3").unwrap_or_default(),
+            text4: string("Text4").unwrap_or_default(),
+        })
+    }
+
+    /// Converts this sign into a tile entity.
+    pub fn to_value(&self) -> Value {
+        let mut map = Map::new();
+        map.insert("id".to_string(), Value::String(self.id.clone()));
+        map.insert("x".to_string(), Value::Int(self.x));
+        map.insert("y".to_string(), Value::Int(self.y));
+        map.insert("z".to_string(), Value::Int(self.z));
+        map.insert("Text1".to_string(), Value::String(self.text1.clone()));
+        map.insert("Text2".to_string(), Value::String(self.text2.clone()));
+        map.insert("Text3".to_string(), Value::String(self.text3.clone()));
+        map.insert("Text4".to_string(), Value::String(self.text4.clone()));
+        Value::Compound(map)
+    }
+
+    /// The lines of text, from top to bottom.
+    pub fn lines(&self) -> [&str; 4] {
+        [&self.text1, &self.text2, &self.text3, &self.text4]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nbt::Value;
+
+    use super::TileEntitySign;
+
+    #[test]
+    fn sign_round_trip() {
+        let sign = TileEntitySign::new(1, 64, -3, ["a".into(), String::new(), "c".into(), "d".into()]);
+        let value = sign.to_value();
+        assert_eq!(TileEntitySign::from_value(&value), Some(sign));
+
+        let Value::Compound(mut map) = value else {
+            unreachable!()
+        };
+        map.insert("id".to_string(), Value::String("Chest".to_string()));
+        assert_eq!(TileEntitySign::from_value(&Value::Compound(map)), None);
+    }
+}
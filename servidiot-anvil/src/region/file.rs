@@ -4,7 +4,6 @@ use std::{
 };
 
 use bitvec::vec::BitVec;
-use bytemuck::cast_slice;
 use nbt::{from_gzip_reader, from_reader, from_zlib_reader, to_gzip_writer, to_zlib_writer, to_writer};
 use servidiot_primitives::position::ChunkPosition;
 use thiserror::Error;
@@ -49,11 +48,15 @@ pub struct RegionFile {
     free_sectors: BitVec,
     /// The file handle.
     file: File,
+    /// Whether to skip flushing when dropped.
+    closed: bool,
 }
 
 impl Drop for RegionFile {
     fn drop(&mut self) {
-        let _ = self.flush();
+        if !self.closed {
+            let _ = self.flush();
+        }
     }
 }
 
@@ -86,28 +89,44 @@ type ChunkResult<T> = std::result::Result<T, ChunkError>;
 impl RegionFile {
     pub const BYTES_PER_SECTOR: u64 = 4096;
     pub const MAX_OFFSET: u32 = u32::from_be_bytes([0, 255, 255, 255]);
+    /// The size of the location and timestamp tables.
+    pub const HEADER_SIZE: u64 = 2 * Self::BYTES_PER_SECTOR;
 
 
-    /// Creates a new region file.
+    /// Creates a new region file, writing an empty header.
     pub fn create(mut file: File) -> io::Result<Self> {
         file.rewind()?;
-        file.write_all(&[0; 8192])?;
+        file.write_all(&[0; Self::HEADER_SIZE as usize])?;
         Self::open(file)
     }
 
     /// Loads a file as a region file.
+    /// Errors if the file is too short to
+    /// hold the region header.
     pub fn open(mut file: File) -> io::Result<Self> {
+        if file.metadata()?.len() < Self::HEADER_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "region file shorter than its header",
+            ));
+        }
         file.rewind()?;
         let mut chunk_location = [0; 4096];
         file.read_exact(&mut chunk_location)?;
         let mut timestamps = [0; 4096];
         file.read_exact(&mut timestamps)?;
 
+        // Timestamps are big-endian on disk.
+        let timestamps = std::array::from_fn(|i| {
+            u32::from_be_bytes(timestamps[i * 4..i * 4 + 4].try_into().expect("four bytes"))
+        });
+
         let mut this = Self {
             chunk_location,
-            timestamps: *bytemuck::cast_ref(&timestamps),
+            timestamps,
             free_sectors: BitVec::new(),
             file,
+            closed: false,
         };
         let file_len = this.file.metadata()?.len();
 
@@ -145,6 +164,12 @@ impl RegionFile {
         }
     }
 
+    /// Whether some chunk is present
+    /// within this region file.
+    pub fn has_chunk(&self, position: ChunkPosition) -> bool {
+        self.get_chunk_location(position).is_some()
+    }
+
     /// Sets the on-disk location of
     /// some chunk. Does not flush.
     fn set_chunk_location(
@@ -168,16 +193,14 @@ impl RegionFile {
     /// Gets the on-disk timestamp
     /// of some chunk.
     fn get_chunk_timestamp(&self, position: ChunkPosition) -> u32 {
-        let offset = ((position.x & 31) + (position.z & 31) * 32) as usize;
-        self.timestamps[offset]
+        self.timestamps[Self::table_index(position)]
     }
 
     /// Set the on-disk timestamp
     /// of some chunk. Does not
     /// flush to disk.
     fn set_chunk_timestamp(&mut self, position: ChunkPosition, timestamp: u32) {
-        let offset = ((position.x & 31) + (position.z & 31) * 32) as usize;
-        self.timestamps[offset] = timestamp;
+        self.timestamps[Self::table_index(position)] = timestamp;
     }
 
     /// Writes a chunk to disk.
@@ -248,9 +271,10 @@ impl RegionFile {
             self.file.seek(SeekFrom::Start((sector_start as u64) * 4096)).map_err(|v| ChunkError::IOError(chunk_position, v))?;
             self.file.write_all(&full_data).map_err(|v| ChunkError::IOError(chunk_position, v))?;
         }
-        self.flush().map_err(|v| ChunkError::IOError(chunk_position, v))?;
-
-
+        self.flush_chunk_location(chunk_position)
+            .and_then(|_| self.flush_chunk_timestamp(chunk_position))
+            .and_then(|_| self.file.flush())
+            .map_err(|v| ChunkError::IOError(chunk_position, v))?;
 
         Ok(())
     }
@@ -299,24 +323,177 @@ impl RegionFile {
         Ok((data, timestamp))
     }
 
+    /// Iterates over the chunks present in this region file,
+    /// reading each in turn. Yields the chunk position within
+    /// the region, from 0 to 31 on each axis, its data and
+    /// its timestamp.
+    pub fn iterate_chunks(&mut self) -> RegionChunkIterator<'_> {
+        RegionChunkIterator { file: self, index: 0 }
+    }
+
+    /// Writes only the location table entry of some chunk.
+    pub fn flush_chunk_location(&mut self, position: ChunkPosition) -> io::Result<()> {
+        let offset = Self::table_index(position) * 4;
+        self.file.seek(SeekFrom::Start(offset as u64))?;
+        self.file.write_all(&self.chunk_location[offset..offset + 4])
+    }
+
+    /// Writes only the timestamp table entry of some chunk.
+    pub fn flush_chunk_timestamp(&mut self, position: ChunkPosition) -> io::Result<()> {
+        let index = Self::table_index(position);
+        self.file.seek(SeekFrom::Start(Self::BYTES_PER_SECTOR + index as u64 * 4))?;
+        self.file.write_all(&self.timestamps[index].to_be_bytes())
+    }
+
+    /// The index of some chunk within the header tables.
+    fn table_index(position: ChunkPosition) -> usize {
+        ((position.x & 31) + (position.z & 31) * 32) as usize
+    }
+
+    /// Closes the file without flushing it,
+    /// for files which were only read from.
+    pub fn close(mut self) {
+        self.closed = true;
+    }
+
     /// Flush file to disk.
     pub fn flush(&mut self) -> io::Result<()> {
         self.file.rewind()?;
         self.file.write_all(&self.chunk_location)?;
-        self.file.write_all(cast_slice(&self.timestamps))?;
+        let timestamps: Vec<u8> = self.timestamps.iter().flat_map(|v| v.to_be_bytes()).collect();
+        self.file.write_all(&timestamps)?;
 
         self.file.flush()?;
         Ok(())
     }
 }
 
+/// Iterator over the chunks present in a [`RegionFile`].
+pub struct RegionChunkIterator<'a> {
+    file: &'a mut RegionFile,
+    /// The next index into the location table.
+    index: usize,
+}
+
+impl Iterator for RegionChunkIterator<'_> {
+    type Item = ChunkResult<(ChunkPosition, ChunkRoot, u32)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.index < self.file.timestamps.len() {
+            let position = ChunkPosition::new((self.index % 32) as i32, (self.index / 32) as i32);
+            self.index += 1;
+            if self.file.has_chunk(position) {
+                return Some(self.file.read_chunk(position).map(|(data, timestamp)| (position, data, timestamp)));
+            }
+        }
+        None
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    // use std::fs::File;
+    use std::fs::File;
+
+    use servidiot_primitives::position::ChunkPosition;
 
-    // use servidiot_primitives::position::ChunkPosition;
+    use super::{CompressionType, RegionFile};
+    use crate::region::nbt::{ChunkRoot, IntArray, Level};
 
-    // use super::RegionFile;
+    #[test]
+    fn create_and_open_test() {
+        let path = std::env::temp_dir().join(format!("servidiot-region-file-{}.mca", std::process::id()));
+        let open = |truncate| File::options().read(true).write(true).create(true).truncate(truncate).open(&path).unwrap();
+
+        let file = RegionFile::create(open(true)).unwrap();
+        assert_eq!(file.free_sectors.len(), 2);
+        assert!(file.free_sectors.not_any());
+        drop(file);
+
+        let file = RegionFile::open(open(false)).unwrap();
+        assert!(!file.has_chunk(ChunkPosition::new(0, 0)));
+        drop(file);
+
+        let short = open(false);
+        short.set_len(RegionFile::HEADER_SIZE - 1).unwrap();
+        assert!(RegionFile::open(short).is_err());
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn flush_single_entries() {
+        let path = std::env::temp_dir().join(format!("servidiot-region-entries-{}.mca", std::process::id()));
+        let open = || File::options().read(true).write(true).create(true).truncate(true).open(&path).unwrap();
+        let position = ChunkPosition::new(3, 1);
+
+        let mut file = RegionFile::create(open()).unwrap();
+        file.set_chunk_location(position, 2, 1).unwrap();
+        file.set_chunk_timestamp(position, 1234);
+        file.set_chunk_location(ChunkPosition::new(0, 0), 3, 1).unwrap();
+        file.flush_chunk_location(position).unwrap();
+        file.flush_chunk_timestamp(position).unwrap();
+
+        let header = std::fs::read(&path).unwrap();
+        let index = RegionFile::table_index(position);
+        assert_eq!(&header[index * 4..index * 4 + 4], &[0, 0, 2, 1]);
+        assert_eq!(&header[0..4], &[0; 4]);
+        let timestamp = 4096 + index * 4;
+        assert_eq!(&header[timestamp..timestamp + 4], &1234u32.to_be_bytes());
+
+        file.flush().unwrap();
+        assert_eq!(&std::fs::read(&path).unwrap()[timestamp..timestamp + 4], &1234u32.to_be_bytes());
+
+        drop(file);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn iterate_chunks_test() {
+        let path = std::env::temp_dir().join(format!("servidiot-region-iterate-{}.mca", std::process::id()));
+        let open = || File::options().read(true).write(true).create(true).truncate(true).open(&path).unwrap();
+        let open_existing = || File::options().read(true).write(true).open(&path).unwrap();
+        let chunk = |x, z| ChunkRoot {
+            level: Level {
+                x_position: x,
+                z_position: z,
+                last_update: 0,
+                light_populated: None,
+                terrain_populated: true,
+                version: None,
+                inhabited_time: 0,
+                biomes: None,
+                heightmap: IntArray(vec![0; 256]),
+                sections: vec![],
+                entities: vec![],
+                tile_entities: vec![],
+                tile_ticks: None,
+            },
+        };
+
+        let mut file = RegionFile::create(open()).unwrap();
+        assert!(file.iterate_chunks().next().is_none());
+
+        for (x, z, timestamp) in [(31, 2, 7), (4, 0, 5)] {
+            file.write_chunk(CompressionType::ZLib, ChunkPosition::new(x, z), timestamp, chunk(x, z)).unwrap();
+        }
+        let found = file
+            .iterate_chunks()
+            .map(|v| {
+                let (position, data, timestamp) = v.unwrap();
+                assert_eq!((position.x, position.z), (data.level.x_position, data.level.z_position));
+                (position, timestamp)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(found, [(ChunkPosition::new(4, 0), 5), (ChunkPosition::new(31, 2), 7)]);
+
+        file.flush().unwrap();
+        let reopened = RegionFile::open(open_existing()).unwrap();
+        assert_eq!(reopened.get_chunk_timestamp(ChunkPosition::new(31, 2)), 7);
+        assert_eq!(reopened.get_chunk_timestamp(ChunkPosition::new(4, 0)), 5);
+
+        drop(file);
+        std::fs::remove_file(path).unwrap();
+    }
 
     #[test]
     pub fn epic_test() {
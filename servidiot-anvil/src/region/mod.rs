@@ -5,7 +5,8 @@ use std::{
     time::{Duration, SystemTime, SystemTimeError, UNIX_EPOCH},
 };
 
-use ahash::AHashMap;
+use ahash::{AHashMap, AHashSet};
+use ::nbt::Value;
 use servidiot_primitives::position::{ChunkPosition, RegionPosition};
 use thiserror::Error;
 
@@ -23,6 +24,8 @@ pub struct RegionManager {
     directory: PathBuf,
     /// Loaded region cache.
     cache: AHashMap<RegionPosition, RegionFile>,
+    /// Cached regions written to since they were last flushed.
+    dirty: AHashSet<RegionPosition>,
     /// The compression method to use when saving.
     compression_method: CompressionType,
 }
@@ -59,6 +62,7 @@ impl RegionManager {
             compression_method,
             directory,
             cache: AHashMap::default(),
+            dirty: AHashSet::default(),
         }
     }
 
@@ -91,7 +95,24 @@ impl RegionManager {
                     .as_secs() as u32,
                 data,
             )
-            .map_err(RegionManagerError::ChunkError)
+            .map_err(RegionManagerError::ChunkError)?;
+        // Only regions which loaded and were written to need flushing.
+        self.dirty.insert(position.region());
+        Ok(())
+    }
+
+    /// Loads the entities stored in some chunk.
+    pub fn load_entity_data(&mut self, position: ChunkPosition) -> RegionManagerResult<Vec<Value>> {
+        let (root, _) = self.load_chunk(position)?;
+        Ok(root.level.entities)
+    }
+
+    /// Replaces the entities stored in some chunk.
+    /// The chunk must already be present on disk.
+    pub fn save_entity_data(&mut self, position: ChunkPosition, entities: Vec<Value>) -> RegionManagerResult<()> {
+        let (mut root, _) = self.load_chunk(position)?;
+        root.level.entities = entities;
+        self.save_chunk(position, root)
     }
 
     /// Flush the whole cache.
@@ -99,20 +120,87 @@ impl RegionManager {
         for (_, data) in &mut self.cache {
             data.flush()?;
         }
+        self.dirty.clear();
+        Ok(())
+    }
+
+    /// Flush a single region. Does
+    /// nothing if it is not cached.
+    pub fn flush_region(&mut self, position: RegionPosition) -> RegionManagerResult<()> {
+        if let Some(region) = self.cache.get_mut(&position) {
+            region.flush()?;
+            self.dirty.remove(&position);
+        }
         Ok(())
     }
 
+    /// The cached regions which have been
+    /// written to since they were last flushed.
+    pub fn dirty_regions(&self) -> Vec<RegionPosition> {
+        let mut regions = self.dirty.iter().copied().collect::<Vec<_>>();
+        regions.sort();
+        regions
+    }
+
     /// Removes a region from cache. Returns
     /// `true` if the region was present in cache.
     pub fn unload_region(&mut self, position: RegionPosition) -> RegionManagerResult<bool> {
         if let Some(mut v) = self.cache.remove(&position) {
             v.flush()?;
+            self.dirty.remove(&position);
             Ok(true)
         } else {
             Ok(false)
         }
     }
 
+    /// Removes a region from cache, flushing
+    /// it only if it has been written to.
+    fn close_region(&mut self, position: RegionPosition) -> RegionManagerResult<()> {
+        if let Some(mut v) = self.cache.remove(&position) {
+            if self.dirty.remove(&position) {
+                v.flush()?;
+            } else {
+                v.close();
+            }
+        }
+        Ok(())
+    }
+
+    /// Discovers every region file within
+    /// the region directory.
+    pub fn region_positions(&self) -> RegionManagerResult<Vec<RegionPosition>> {
+        let mut regions = vec![];
+        for entry in std::fs::read_dir(&self.directory)? {
+            let entry = entry?;
+            if let Some(position) = entry.file_name().to_str().and_then(parse_region_file_name) {
+                regions.push(position);
+            }
+        }
+        regions.sort();
+        Ok(regions)
+    }
+
+    /// Iterates over every chunk stored in the region directory.
+    /// Yields the chunk position, data and timestamp.
+    ///
+    /// Chunks which fail to load are skipped with a warning.
+    /// Regions which were not cached beforehand are removed
+    /// from cache once they have been read.
+    pub fn iterate_all_chunks(
+        &mut self,
+    ) -> impl Iterator<Item = RegionManagerResult<(ChunkPosition, ChunkRoot, SystemTime)>> + '_ {
+        let (regions, error) = match self.region_positions() {
+            Ok(v) => (v, None),
+            Err(e) => (vec![], Some(e)),
+        };
+        error.map(Err).into_iter().chain(AllChunks {
+            manager: self,
+            regions: regions.into_iter(),
+            current: None,
+        })
+    }
+
     /// Caches a region. Returns `true` if
     /// the region was already cached.
     pub fn load_region(
@@ -137,7 +225,221 @@ impl RegionManager {
 
             let file = f(opts.open(path)?)?;
             self.cache.insert(position, file);
-            Ok((self.cache.get_mut(&position).unwrap(), true))
+            Ok((self.cache.get_mut(&position).unwrap(), false))
+        }
+    }
+}
+
+/// Parses a region file name of the form `r.x.z.mca`.
+fn parse_region_file_name(name: &str) -> Option<RegionPosition> {
+    let mut parts = name.strip_prefix("r.")?.strip_suffix(".mca")?.split('.');
+    let x = parts.next()?.parse().ok()?;
+    let z = parts.next()?.parse().ok()?;
+    if parts.next().is_some() {
+        return None;
+    }
+    Some(RegionPosition::new(x, z))
+}
+
+/// Every chunk position within some region.
+fn region_chunks(region: RegionPosition) -> impl Iterator<Item = ChunkPosition> {
+    let base_x = i32::from(region.x) * 32;
+    let base_z = i32::from(region.z) * 32;
+    (0..32).flat_map(move |z| (0..32).map(move |x| ChunkPosition::new(base_x + x, base_z + z)))
+}
+
+/// Iterator over every chunk within a region directory.
+struct AllChunks<'a> {
+    manager: &'a mut RegionManager,
+    regions: std::vec::IntoIter<RegionPosition>,
+    /// The region being read, whether it was
+    /// already cached, and its remaining chunks.
+    current: Option<(RegionPosition, bool, std::vec::IntoIter<ChunkPosition>)>,
+}
+
+impl Iterator for AllChunks<'_> {
+    type Item = RegionManagerResult<(ChunkPosition, ChunkRoot, SystemTime)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((region, was_cached, chunks)) = &mut self.current {
+                for position in chunks.by_ref() {
+                    match self.manager.load_chunk(position) {
+                        Ok((data, time)) => return Some(Ok((position, data, time))),
+                        Err(RegionManagerError::ChunkError(e)) => {
+                            tracing::warn!("Skipping unreadable chunk: {e}");
+                        }
+                        Err(e) => return Some(Err(e)),
+                    }
+                }
+                let (region, was_cached) = (*region, *was_cached);
+                self.current = None;
+                if !was_cached {
+                    if let Err(e) = self.manager.close_region(region) {
+                        return Some(Err(e));
+                    }
+                }
+            }
+
+            let region = self.regions.next()?;
+            match self.manager.load_region(region) {
+                Ok((file, was_cached)) => {
+                    let chunks = region_chunks(region)
+                        .filter(|v| file.has_chunk(*v))
+                        .collect::<Vec<_>>();
+                    self.current = Some((region, was_cached, chunks.into_iter()));
+                }
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::nbt::Value;
+    use servidiot_primitives::position::{ChunkPosition, RegionPosition};
+
+    use super::{
+        file::CompressionType,
+        nbt::{ChunkRoot, IntArray, Level},
+        parse_region_file_name, RegionManager,
+    };
+
+    fn empty_chunk(position: ChunkPosition) -> ChunkRoot {
+        ChunkRoot {
+            level: Level {
+                x_position: position.x,
+                z_position: position.z,
+                last_update: 0,
+                light_populated: None,
+                terrain_populated: true,
+                version: None,
+                inhabited_time: 0,
+                biomes: None,
+                heightmap: IntArray(vec![0; 256]),
+                sections: vec![],
+                entities: vec![],
+                tile_entities: vec![],
+                tile_ticks: None,
+            },
         }
     }
+
+    #[test]
+    fn region_file_name_test() {
+        assert_eq!(parse_region_file_name("r.0.-2.mca"), Some(RegionPosition::new(0, -2)));
+        assert_eq!(parse_region_file_name("r.12.4.mca"), Some(RegionPosition::new(12, 4)));
+        assert_eq!(parse_region_file_name("r.0.mca"), None);
+        assert_eq!(parse_region_file_name("r.0.0.0.mca"), None);
+        assert_eq!(parse_region_file_name("r.a.0.mca"), None);
+        assert_eq!(parse_region_file_name("r.0.0.mcr"), None);
+    }
+
+    #[test]
+    fn iterate_all_chunks_test() {
+        let mut dir = std::env::temp_dir();
+        dir.push(format!("servidiot-iterate-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let positions = [
+            ChunkPosition::new(0, 0),
+            ChunkPosition::new(5, 31),
+            ChunkPosition::new(-1, -40),
+        ];
+        {
+            let mut manager = RegionManager::new(dir.clone(), CompressionType::ZLib);
+            for position in positions {
+                manager.save_chunk(position, empty_chunk(position)).unwrap();
+            }
+        }
+
+        let modified = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|v| {
+                let path = v.unwrap().path();
+                let modified = std::fs::metadata(&path).unwrap().modified().unwrap();
+                (path, modified)
+            })
+            .collect::<Vec<_>>();
+
+        let mut manager = RegionManager::new(dir.clone(), CompressionType::ZLib);
+        let mut found = manager
+            .iterate_all_chunks()
+            .map(|v| {
+                let (position, data, _) = v.unwrap();
+                assert_eq!(position.x, data.level.x_position);
+                assert_eq!(position.z, data.level.z_position);
+                position
+            })
+            .collect::<Vec<_>>();
+        found.sort();
+        let mut expected = positions.to_vec();
+        expected.sort();
+        assert_eq!(found, expected);
+        assert!(manager.cache.is_empty());
+        // Reading regions does not write them back.
+        for (path, modified) in &modified {
+            assert_eq!(std::fs::metadata(path).unwrap().modified().unwrap(), *modified);
+        }
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn entity_data_test() {
+        let mut dir = std::env::temp_dir();
+        dir.push(format!("servidiot-entity-data-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let position = ChunkPosition::new(3, -2);
+        let mut manager = RegionManager::new(dir.clone(), CompressionType::ZLib);
+        // Chunks which were never saved have no entities to replace.
+        assert!(manager.save_entity_data(position, vec![]).is_err());
+        manager.save_chunk(position, empty_chunk(position)).unwrap();
+        manager.flush_cache().unwrap();
+        assert!(manager.load_entity_data(position).unwrap().is_empty());
+
+        let entity = Value::Compound([("id".to_string(), Value::String("Pig".to_string()))].into_iter().collect());
+        manager.save_entity_data(position, vec![entity.clone()]).unwrap();
+        assert_eq!(manager.dirty_regions(), [position.region()]);
+        assert_eq!(manager.load_entity_data(position).unwrap(), std::slice::from_ref(&entity));
+        drop(manager);
+
+        let mut manager = RegionManager::new(dir.clone(), CompressionType::ZLib);
+        assert_eq!(manager.load_entity_data(position).unwrap(), [entity]);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn dirty_regions_test() {
+        let mut dir = std::env::temp_dir();
+        dir.push(format!("servidiot-dirty-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let mut manager = RegionManager::new(dir.clone(), CompressionType::ZLib);
+        assert!(manager.dirty_regions().is_empty());
+
+        let (a, b) = (ChunkPosition::new(0, 0), ChunkPosition::new(-1, 40));
+        manager.save_chunk(a, empty_chunk(a)).unwrap();
+        manager.save_chunk(b, empty_chunk(b)).unwrap();
+        assert_eq!(manager.dirty_regions(), [RegionPosition::new(-1, 1), RegionPosition::new(0, 0)]);
+
+        manager.flush_region(a.region()).unwrap();
+        assert_eq!(manager.dirty_regions(), [b.region()]);
+        // Regions not in cache are ignored.
+        manager.flush_region(RegionPosition::new(7, 7)).unwrap();
+
+        manager.flush_cache().unwrap();
+        assert!(manager.dirty_regions().is_empty());
+
+        // Regions which fail to load are never dirty.
+        let c = ChunkPosition::new(64, 64);
+        std::fs::write(dir.join("r.2.2.mca"), [1, 2, 3]).unwrap();
+        assert!(manager.save_chunk(c, empty_chunk(c)).is_err());
+        assert!(manager.dirty_regions().is_empty());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
 }
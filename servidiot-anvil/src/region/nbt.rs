@@ -11,6 +11,23 @@ pub struct ChunkRoot {
     pub level: Level
 }
 
+impl ChunkRoot {
+    /// How many entities are saved in this chunk.
+    pub fn entity_count(&self) -> usize {
+        self.level.entities.len()
+    }
+
+    /// How many tile entities are saved in this chunk.
+    pub fn tile_entity_count(&self) -> usize {
+        self.level.tile_entities.len()
+    }
+
+    /// How many non-empty sections this chunk has.
+    pub fn section_count(&self) -> usize {
+        self.level.sections.len()
+    }
+}
+
 /// Byte array helper type.
 #[repr(transparent)]
 #[derive(Serialize, Deserialize, Debug)]
@@ -34,9 +51,7 @@ impl DerefMut for ByteArray {
 
 impl ByteArray {
     pub fn as_u8_array(&self) -> &[u8] {
-        unsafe {
-            std::slice::from_raw_parts(self.0.as_slice().as_ptr().cast::<u8>(), self.0.len())
-        }
+        bytemuck::cast_slice(&self.0)
     }
 }
 
@@ -120,6 +135,16 @@ pub struct Level {
 
 }
 
+impl Level {
+    pub fn has_entities(&self) -> bool {
+        !self.entities.is_empty()
+    }
+
+    pub fn is_terrain_populated(&self) -> bool {
+        self.terrain_populated
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Section {
     /// The Y index (not coordinate) of this 
@@ -160,7 +185,7 @@ pub struct Section {
 /// needing to continue updating, water and lava that should 
 /// continue flowing, recently placed sand or gravel that 
 /// should fall, etc. 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct TileTick {
     /// The ID of the block as an integer.
     #[serde(rename = "i")]
@@ -184,3 +209,51 @@ pub struct TileTick {
     pub z: i32
 }
 
+
+#[cfg(test)]
+mod tests {
+    use nbt::Value;
+
+    use super::{ChunkRoot, IntArray, Level, Section};
+    use servidiot_primitives::nibble_vec::NibbleVec;
+
+    #[test]
+    fn chunk_counts_test() {
+        let section = |y_index| Section {
+            y_index,
+            blocks: super::ByteArray(vec![0; 4096]),
+            additional: None,
+            data: NibbleVec::fill(0, 4096),
+            block_light: NibbleVec::fill(0, 4096),
+            sky_light: NibbleVec::fill(15, 4096),
+        };
+        let mut chunk = ChunkRoot {
+            level: Level {
+                x_position: 0,
+                z_position: 0,
+                last_update: 0,
+                light_populated: None,
+                terrain_populated: false,
+                version: None,
+                inhabited_time: 0,
+                biomes: None,
+                heightmap: IntArray(vec![0; 256]),
+                sections: vec![section(0), section(1)],
+                entities: vec![],
+                tile_entities: vec![Value::Compound(Default::default())],
+                tile_ticks: None,
+            },
+        };
+        assert_eq!(chunk.section_count(), 2);
+        assert_eq!(chunk.entity_count(), 0);
+        assert_eq!(chunk.tile_entity_count(), 1);
+        assert!(!chunk.level.has_entities());
+        assert!(!chunk.level.is_terrain_populated());
+
+        chunk.level.entities.push(Value::Compound(Default::default()));
+        chunk.level.terrain_populated = true;
+        assert_eq!(chunk.entity_count(), 1);
+        assert!(chunk.level.has_entities());
+        assert!(chunk.level.is_terrain_populated());
+    }
+}
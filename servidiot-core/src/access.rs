@@ -0,0 +1,142 @@
+use std::{fs, io, path::Path};
+
+use fxhash::{FxHashMap, FxHashSet};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use uuid::Uuid;
+
+/// The reason given to players banned without one.
+const DEFAULT_BAN_REASON: &str = "Banned by an operator.";
+
+/// Players allowed to join while the whitelist is enabled.
+#[derive(Default, Serialize, Deserialize)]
+pub struct Whitelist {
+    pub enabled: bool,
+    pub entries: FxHashSet<Uuid>,
+}
+
+impl Whitelist {
+    pub const FILE_NAME: &'static str = "whitelist.json";
+
+    /// Loads the whitelist from some world directory,
+    /// or an empty one if it has not been saved yet.
+    pub fn load(world_dir: &Path) -> anyhow::Result<Self> {
+        load_json(&world_dir.join(Self::FILE_NAME))
+    }
+
+    /// Saves the whitelist to some world directory.
+    pub fn save(&self, world_dir: &Path) -> anyhow::Result<()> {
+        save_json(&world_dir.join(Self::FILE_NAME), self)
+    }
+
+    pub fn enable(&mut self) {
+        self.enabled = true;
+    }
+
+    pub fn disable(&mut self) {
+        self.enabled = false;
+    }
+
+    /// Returns `false` if the player was already whitelisted.
+    pub fn add(&mut self, player: Uuid) -> bool {
+        self.entries.insert(player)
+    }
+
+    /// Returns `false` if the player was not whitelisted.
+    pub fn remove(&mut self, player: Uuid) -> bool {
+        self.entries.remove(&player)
+    }
+
+    /// Whether some player may join. Always
+    /// `true` while the whitelist is disabled.
+    pub fn is_allowed(&self, player: Uuid) -> bool {
+        !self.enabled || self.entries.contains(&player)
+    }
+}
+
+/// Players banned from the server, with an optional reason.
+#[derive(Default, Serialize, Deserialize)]
+pub struct BanList {
+    pub entries: FxHashMap<Uuid, Option<String>>,
+}
+
+impl BanList {
+    pub const FILE_NAME: &'static str = "banned-players.json";
+
+    /// Loads the ban list from some world directory,
+    /// or an empty one if it has not been saved yet.
+    pub fn load(world_dir: &Path) -> anyhow::Result<Self> {
+        load_json(&world_dir.join(Self::FILE_NAME))
+    }
+
+    /// Saves the ban list to some world directory.
+    pub fn save(&self, world_dir: &Path) -> anyhow::Result<()> {
+        save_json(&world_dir.join(Self::FILE_NAME), self)
+    }
+
+    /// Bans a player, replacing any existing reason.
+    pub fn ban(&mut self, player: Uuid, reason: Option<&str>) {
+        self.entries.insert(player, reason.map(str::to_string));
+    }
+
+    /// Returns `false` if the player was not banned.
+    pub fn unban(&mut self, player: Uuid) -> bool {
+        self.entries.remove(&player).is_some()
+    }
+
+    /// Returns the reason some player is banned,
+    /// or `None` if they are not banned.
+    pub fn is_banned(&self, player: Uuid) -> Option<&str> {
+        self.entries
+            .get(&player)
+            .map(|v| v.as_deref().unwrap_or(DEFAULT_BAN_REASON))
+    }
+}
+
+/// Players who are server operators.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct OpList {
+    pub entries: FxHashSet<Uuid>,
+}
+
+impl OpList {
+    pub const FILE_NAME: &'static str = "ops.json";
+
+    /// Loads the operator list from some world directory,
+    /// or an empty one if it has not been saved yet.
+    pub fn load(world_dir: &Path) -> anyhow::Result<Self> {
+        load_json(&world_dir.join(Self::FILE_NAME))
+    }
+
+    /// Saves the operator list to some world directory.
+    pub fn save(&self, world_dir: &Path) -> anyhow::Result<()> {
+        save_json(&world_dir.join(Self::FILE_NAME), self)
+    }
+
+    /// Returns `false` if the player was already an operator.
+    pub fn add(&mut self, player: Uuid) -> bool {
+        self.entries.insert(player)
+    }
+
+    /// Returns `false` if the player was not an operator.
+    pub fn remove(&mut self, player: Uuid) -> bool {
+        self.entries.remove(&player)
+    }
+
+    pub fn is_op(&self, player: Uuid) -> bool {
+        self.entries.contains(&player)
+    }
+}
+
+fn load_json<T: Default + DeserializeOwned>(path: &Path) -> anyhow::Result<T> {
+    match fs::read(path) {
+        Ok(data) => Ok(serde_json::from_slice(&data)?),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(T::default()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn save_json<T: Serialize>(path: &Path, value: &T) -> anyhow::Result<()> {
+    fs::write(path, serde_json::to_vec_pretty(value)?)?;
+    Ok(())
+}
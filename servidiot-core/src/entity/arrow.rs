@@ -0,0 +1,45 @@
+use servidiot_ecs::EntityRef;
+use servidiot_network::server::{id::NetworkID, Client};
+use servidiot_primitives::position::{BlockPosition, EntityLocation};
+
+use super::{projectile::ProjectileComponent, Entity};
+
+/// The object type of arrows.
+const ARROW_OBJECT_TYPE: i8 = 60;
+
+/// How much the vertical speed of arrows falls each tick.
+pub const ARROW_GRAVITY: f64 = 0.05;
+
+pub struct ArrowEntity;
+
+/// An arrow loosed by some player.
+pub struct ArrowComponent {
+    /// The `NetworkID` of the player who shot it.
+    pub shooter_id: NetworkID,
+}
+
+/// Marks an arrow as stuck in a block.
+pub struct StuckArrowComponent {
+    /// The block the arrow is stuck in,
+    /// which it falls out of once broken.
+    pub block: BlockPosition,
+}
+
+/// Marks a player as drawing a bow.
+pub struct BowChargingComponent {
+    /// The tick the player started drawing.
+    pub start_tick: u64,
+}
+
+impl Entity for ArrowEntity {
+    fn send_to_player(&self, this: EntityRef, cl: &Client) -> anyhow::Result<()> {
+        let id = *this.get::<&NetworkID>().unwrap();
+        let pos = this.get::<&EntityLocation>().unwrap().position;
+        let velocity = this
+            .get::<&ProjectileComponent>()
+            .map_or((0.0, 0.0, 0.0), |v| (v.velocity_x, v.velocity_y, v.velocity_z));
+        // The data of an arrow is the entity ID of its shooter.
+        let shooter = this.get::<&ArrowComponent>().unwrap().shooter_id;
+        cl.send_object(id, ARROW_OBJECT_TYPE, pos, shooter.0, velocity)
+    }
+}
@@ -0,0 +1,182 @@
+use std::collections::HashSet;
+
+use servidiot_ecs::Entity;
+use servidiot_primitives::{item::{InventorySlot, ItemStack}, position::{BlockPosition, Location}};
+
+use super::item::max_stack_size;
+
+/// The items held by a container block,
+/// and the players looking inside it.
+pub struct ContainerInventory {
+    pub slots: Vec<InventorySlot>,
+    pub viewers: HashSet<Entity>,
+}
+
+impl ContainerInventory {
+    /// Slots in a single chest.
+    pub const CHEST_SLOTS: usize = 27;
+    /// Slots in an anvil: two inputs, then the output.
+    pub const ANVIL_SLOTS: usize = 3;
+    /// The anvil slot holding the item to rename.
+    pub const ANVIL_INPUT: usize = 0;
+    /// The anvil slot holding the renamed item.
+    pub const ANVIL_OUTPUT: usize = 2;
+
+    /// An empty single chest.
+    pub fn chest() -> Self {
+        Self::empty(Self::CHEST_SLOTS)
+    }
+
+    /// An empty anvil.
+    pub fn anvil() -> Self {
+        Self::empty(Self::ANVIL_SLOTS)
+    }
+
+    fn empty(slots: usize) -> Self {
+        Self {
+            slots: vec![InventorySlot::Empty; slots],
+            viewers: HashSet::new(),
+        }
+    }
+}
+
+/// What kind of block a container is.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ContainerKind {
+    Chest,
+    Anvil,
+}
+
+/// The name a player typed into an anvil.
+#[derive(Default)]
+pub struct AnvilComponent {
+    pub item_name: Option<String>,
+}
+
+/// What an anvil makes of `input` when asked to name it `name`, and
+/// how many levels that costs. Nothing is made if the item would
+/// not change. An empty name clears the name the item had.
+pub fn anvil_output(input: &InventorySlot, name: Option<&str>) -> (InventorySlot, i32) {
+    let (InventorySlot::Filled(stack), Some(name)) = (input, name) else {
+        return (InventorySlot::Empty, 0);
+    };
+    let name = Some(name).filter(|v| !v.is_empty());
+    if stack.display_name() == name {
+        return (InventorySlot::Empty, 0);
+    }
+    let mut output = stack.clone();
+    output.set_display_name(name);
+    let cost = (name.map_or(0, |v| v.chars().count()) / 5).max(1);
+    (InventorySlot::Filled(output), cost as i32)
+}
+
+/// Where a container block is.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ContainerBlock {
+    pub position: BlockPosition,
+    pub location: Location,
+}
+
+/// The container a player has open.
+pub struct OpenContainer {
+    pub window_id: u8,
+    pub container: Entity,
+}
+
+/// The items a player has picked up in an open window.
+pub struct CursorItem(pub InventorySlot);
+
+/// Clicks a window slot holding `slot` with `cursor`. Left clicks put
+/// down the whole cursor, and right clicks one item of it. Clicking
+/// with an empty cursor picks up the whole stack, or half of it
+/// rounded up on a right click. Different items are swapped.
+pub fn click_slot(cursor: &mut InventorySlot, slot: &mut InventorySlot, right_click: bool) {
+    let same_item = |a: &ItemStack, b: &ItemStack| a.id == b.id && a.meta == b.meta && a.nbt_data == b.nbt_data;
+    match (&mut *cursor, &mut *slot) {
+        (InventorySlot::Empty, InventorySlot::Empty) => (),
+        (InventorySlot::Empty, InventorySlot::Filled(stack)) if right_click => {
+            let mut taken = stack.clone();
+            taken.count = stack.count - stack.count / 2;
+            stack.count /= 2;
+            *cursor = InventorySlot::Filled(taken);
+            if stack.count == 0 {
+                *slot = InventorySlot::Empty;
+            }
+        }
+        (InventorySlot::Filled(held), InventorySlot::Empty) if right_click => {
+            let mut placed = held.clone();
+            placed.count = 1;
+            held.count -= 1;
+            *slot = InventorySlot::Filled(placed);
+            if held.count == 0 {
+                *cursor = InventorySlot::Empty;
+            }
+        }
+        (InventorySlot::Filled(held), InventorySlot::Filled(stack)) if same_item(held, stack) => {
+            let wanted = if right_click { 1 } else { held.count };
+            let moved = wanted.min(max_stack_size(stack.id) - stack.count).max(0);
+            stack.count += moved;
+            held.count -= moved;
+            if held.count == 0 {
+                *cursor = InventorySlot::Empty;
+            }
+        }
+        _ => std::mem::swap(cursor, slot),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use servidiot_primitives::item::{InventorySlot, ItemStack};
+
+    use super::click_slot;
+
+    fn stack(id: i16, count: i8) -> InventorySlot {
+        InventorySlot::Filled(ItemStack { count, meta: 0, id, nbt_data: None })
+    }
+
+    /// Clicks `slot` with `cursor`, returning the cursor and slot after.
+    fn click(mut cursor: InventorySlot, mut slot: InventorySlot, right_click: bool) -> (InventorySlot, InventorySlot) {
+        click_slot(&mut cursor, &mut slot, right_click);
+        (cursor, slot)
+    }
+
+    #[test]
+    fn left_click_test() {
+        assert_eq!(click(InventorySlot::Empty, stack(1, 10), false), (stack(1, 10), InventorySlot::Empty));
+        assert_eq!(click(stack(1, 10), InventorySlot::Empty, false), (InventorySlot::Empty, stack(1, 10)));
+        assert_eq!(click(InventorySlot::Empty, InventorySlot::Empty, false), (InventorySlot::Empty, InventorySlot::Empty));
+    }
+
+    #[test]
+    fn right_click_test() {
+        // Picking up half a stack rounds up.
+        assert_eq!(click(InventorySlot::Empty, stack(1, 7), true), (stack(1, 4), stack(1, 3)));
+        assert_eq!(click(InventorySlot::Empty, stack(1, 1), true), (stack(1, 1), InventorySlot::Empty));
+        // Putting down one item at a time.
+        assert_eq!(click(stack(1, 3), InventorySlot::Empty, true), (stack(1, 2), stack(1, 1)));
+        assert_eq!(click(stack(1, 1), InventorySlot::Empty, true), (InventorySlot::Empty, stack(1, 1)));
+        assert_eq!(click(stack(1, 3), stack(1, 5), true), (stack(1, 2), stack(1, 6)));
+    }
+
+    #[test]
+    fn merge_test() {
+        assert_eq!(click(stack(1, 10), stack(1, 20), false), (InventorySlot::Empty, stack(1, 30)));
+        assert_eq!(click(stack(1, 40), stack(1, 40), false), (stack(1, 16), stack(1, 64)));
+        assert_eq!(click(stack(1, 1), stack(1, 64), true), (stack(1, 1), stack(1, 64)));
+        // Snowballs stack to 16, and swords not at all.
+        assert_eq!(click(stack(332, 10), stack(332, 10), false), (stack(332, 4), stack(332, 16)));
+        assert_eq!(click(stack(267, 1), stack(267, 1), false), (stack(267, 1), stack(267, 1)));
+    }
+
+    #[test]
+    fn swap_test() {
+        assert_eq!(click(stack(1, 10), stack(2, 5), false), (stack(2, 5), stack(1, 10)));
+        assert_eq!(click(stack(1, 10), stack(2, 5), true), (stack(2, 5), stack(1, 10)));
+
+        let mut named = ItemStack { count: 1, meta: 0, id: 1, nbt_data: None };
+        named.set_display_name(Some("Rock"));
+        let named = InventorySlot::Filled(named);
+        assert_eq!(click(stack(1, 1), named.clone(), false), (named, stack(1, 1)));
+    }
+}
@@ -0,0 +1,18 @@
+use servidiot_anvil::nbt::entity::PotionEffect;
+
+/// Heals an entity every tick.
+pub const REGENERATION: i8 = 10;
+/// Hurts an entity every tick, though never below one health.
+pub const POISON: i8 = 19;
+
+/// How much health regeneration and poison
+/// add or take away each tick.
+pub const HEALTH_PER_TICK: f32 = 0.5;
+
+/// The potion effects active on an entity.
+#[derive(Default)]
+pub struct ActiveEffectsComponent(pub Vec<PotionEffect>);
+
+/// Marks a player's effects as not yet shown to its own
+/// client. Other players are shown them as it is sent to them.
+pub struct EffectsDirty;
@@ -0,0 +1,24 @@
+use servidiot_ecs::EntityRef;
+use servidiot_network::server::{id::NetworkID, Client};
+use servidiot_primitives::position::EntityLocation;
+
+use super::Entity;
+
+pub struct XpOrbEntity;
+
+/// An experience orb waiting to be collected.
+pub struct XpOrbComponent {
+    /// How many experience points the orb is worth.
+    pub value: i16,
+    /// How many ticks the orb has existed for.
+    pub lifetime_ticks: u32,
+}
+
+impl Entity for XpOrbEntity {
+    fn send_to_player(&self, this: EntityRef, cl: &Client) -> anyhow::Result<()> {
+        let id = *this.get::<&NetworkID>().unwrap();
+        let pos = this.get::<&EntityLocation>().unwrap().position;
+        let value = this.get::<&XpOrbComponent>().unwrap().value;
+        cl.send_experience_orb(id, pos, value)
+    }
+}
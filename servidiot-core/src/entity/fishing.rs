@@ -0,0 +1,47 @@
+use servidiot_ecs::EntityRef;
+use servidiot_network::server::{id::NetworkID, Client};
+use servidiot_primitives::position::{EntityLocation, Position};
+
+use super::Entity;
+
+/// The object type of fishing hooks.
+const FISHING_HOOK_OBJECT_TYPE: i8 = 90;
+
+pub struct FishingHookEntity;
+
+/// How far a fishing hook is from catching something.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FishingState {
+    /// Thrown, and not yet in water.
+    Flying,
+    /// Floating in water, with this many ticks until a bite.
+    Settled(u32),
+    /// A fish is on the hook, waiting to be reeled in.
+    Biting,
+}
+
+/// A fishing hook cast by some player.
+pub struct FishingHookComponent {
+    pub caster: servidiot_ecs::Entity,
+    /// The `NetworkID` of `caster`, which
+    /// clients draw the fishing line to.
+    pub caster_id: NetworkID,
+    /// The tick the hook was cast on, which
+    /// the wait for a bite is counted from.
+    pub tick_cast: u64,
+    /// Where the hook is, which its caster
+    /// is pulled towards when reeling it in.
+    pub hook_position: Position,
+    pub velocity: (f64, f64, f64),
+    pub state: FishingState,
+}
+
+impl Entity for FishingHookEntity {
+    fn send_to_player(&self, this: EntityRef, cl: &Client) -> anyhow::Result<()> {
+        let id = *this.get::<&NetworkID>().unwrap();
+        let pos = this.get::<&EntityLocation>().unwrap().position;
+        let hook = this.get::<&FishingHookComponent>().unwrap();
+        // The data of a fishing hook is the entity ID of its caster.
+        cl.send_object(id, FISHING_HOOK_OBJECT_TYPE, pos, hook.caster_id.0, hook.velocity)
+    }
+}
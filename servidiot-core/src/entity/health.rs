@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+
+/// How much health an entity has.
+pub struct HealthComponent {
+    pub current: f32,
+    pub max: f32,
+}
+
+impl Default for HealthComponent {
+    fn default() -> Self {
+        Self {
+            current: 20.0,
+            max: 20.0,
+        }
+    }
+}
+
+/// How hungry a player is.
+pub struct FoodComponent {
+    pub food: i32,
+    pub saturation: f32,
+}
+
+impl Default for FoodComponent {
+    fn default() -> Self {
+        Self {
+            food: 20,
+            saturation: 5.0,
+        }
+    }
+}
+
+/// Marks a player's health or food as changed
+/// since it was last sent to them.
+pub struct HealthDirty;
+
+/// How far, in blocks, an entity has fallen
+/// since it last stood on the ground.
+#[derive(Default)]
+pub struct FallDistanceComponent(pub f32);
+
+/// Marks an entity as unable to take damage.
+pub struct Invulnerable;
+
+/// What caused some damage.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum DamageSource {
+    Generic,
+    Fall,
+    Attack,
+    Projectile,
+    Explosion,
+}
+
+/// The chat message shown when a player dies, for each
+/// [`DamageSource`]. `{killed}` is replaced by the name of the
+/// player who died, and `{killer}` by the name of their killer.
+pub struct DeathMessages {
+    templates: HashMap<DamageSource, String>,
+}
+
+impl Default for DeathMessages {
+    fn default() -> Self {
+        let templates = [
+            (DamageSource::Generic, "{killed} died"),
+            (DamageSource::Fall, "{killed} hit the ground too hard"),
+            (DamageSource::Attack, "{killed} was slain by {killer}"),
+            (DamageSource::Projectile, "{killed} was shot by {killer}"),
+            (DamageSource::Explosion, "{killed} blew up"),
+        ];
+        Self {
+            templates: templates.into_iter().map(|(k, v)| (k, v.to_string())).collect(),
+        }
+    }
+}
+
+impl DeathMessages {
+    /// Replaces the template for some source.
+    pub fn set(&mut self, source: DamageSource, template: &str) {
+        self.templates.insert(source, template.to_string());
+    }
+
+    /// The message for `killed` dying to `source`. Templates naming
+    /// a killer fall back to the generic one when there is none.
+    pub fn format(&self, source: DamageSource, killed: &str, killer: Option<&str>) -> String {
+        let generic = || self.templates.get(&DamageSource::Generic).map_or("{killed} died", String::as_str);
+        let template = self.templates.get(&source).map_or_else(generic, String::as_str);
+        let template = match killer {
+            None if template.contains("{killer}") => generic(),
+            _ => template,
+        };
+        template
+            .replace("{killed}", killed)
+            .replace("{killer}", killer.unwrap_or_default())
+    }
+}
@@ -0,0 +1,57 @@
+use servidiot_ecs::EntityRef;
+use servidiot_network::server::{id::NetworkID, Client};
+use servidiot_primitives::{item::{InventorySlot, ItemStack}, metadata::{Metadata, MetadataItem}, position::EntityLocation};
+
+use super::{projectile::ProjectileComponent, Entity};
+
+/// The object type of dropped items.
+const ITEM_OBJECT_TYPE: i8 = 2;
+/// The metadata key holding a dropped item's stack.
+const ITEM_METADATA_KEY: u8 = 10;
+
+/// How much the vertical speed of dropped items falls each tick.
+pub const ITEM_GRAVITY: f64 = 0.04;
+
+/// The largest stack of any item.
+pub const MAX_STACK_SIZE: i8 = 64;
+
+/// The largest stack of some item ID.
+pub fn max_stack_size(id: i16) -> i8 {
+    match id {
+        // Tools, weapons, armor, full buckets, vehicles,
+        // potions, books, horse armor, and records.
+        256..=259 | 261 | 267..=279 | 282..=286 | 290..=294 | 298..=317 | 326..=329 | 333
+        | 335 | 342 | 343 | 346 | 354 | 355 | 359 | 373 | 386 | 387 | 398 | 403 | 407
+        | 408 | 417..=419 | 422 | 2256..=2267 => 1,
+        // Signs, the empty bucket, snowballs, eggs, and ender pearls.
+        323 | 325 | 332 | 344 | 368 => 16,
+        _ => MAX_STACK_SIZE,
+    }
+}
+
+pub struct ItemEntity;
+
+/// The items held by a dropped item.
+pub struct ItemComponent(pub ItemStack);
+
+impl Entity for ItemEntity {
+    fn send_to_player(&self, this: EntityRef, cl: &Client) -> anyhow::Result<()> {
+        let id = *this.get::<&NetworkID>().unwrap();
+        let pos = this.get::<&EntityLocation>().unwrap().position;
+        let velocity = this
+            .get::<&ProjectileComponent>()
+            .map_or((0.0, 0.0, 0.0), |v| (v.velocity_x, v.velocity_y, v.velocity_z));
+        // Any positive data sends the velocity.
+        cl.send_object(id, ITEM_OBJECT_TYPE, pos, 1, velocity)?;
+
+        let stack = this.get::<&ItemComponent>().unwrap().0.clone();
+        cl.send_metadata(id, item_metadata(stack))
+    }
+}
+
+/// The metadata showing the stack a dropped item holds.
+pub fn item_metadata(stack: ItemStack) -> Metadata {
+    let mut meta = Metadata::default();
+    meta.insert(ITEM_METADATA_KEY, MetadataItem::Slot(InventorySlot::Filled(stack)));
+    meta
+}
@@ -0,0 +1,40 @@
+/// Which mob cap a mob counts towards.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MobCategory {
+    Hostile,
+    Passive,
+}
+
+/// Marks a mob which is never despawned to make room
+/// for others, such as a boss or a named mob.
+pub struct NeverDespawn;
+
+/// How many mobs of each category may be loaded before
+/// those far from every player are despawned.
+pub struct MobCapConfig {
+    pub max_hostile: usize,
+    pub max_passive: usize,
+    /// How far, in chunks, a mob must be from every
+    /// player before it may be despawned.
+    pub despawn_distance_chunks: u32,
+}
+
+impl Default for MobCapConfig {
+    fn default() -> Self {
+        Self {
+            max_hostile: 70,
+            max_passive: 10,
+            despawn_distance_chunks: 8,
+        }
+    }
+}
+
+impl MobCapConfig {
+    /// The most mobs of some category which may be loaded.
+    pub fn cap(&self, category: MobCategory) -> usize {
+        match category {
+            MobCategory::Hostile => self.max_hostile,
+            MobCategory::Passive => self.max_passive,
+        }
+    }
+}
@@ -1,7 +1,24 @@
-use servidiot_ecs::EntityRef;
-use servidiot_network::server::{id::NetworkID, Client};
+use anyhow::bail;
+use servidiot_ecs::{EntityBuilder, EntityRef};
+use servidiot_network::server::{id::NetworkID, Client, Server};
+use servidiot_primitives::position::{ChunkLocation, EntityLocation};
 
+use crate::{game::{EntityIdMap, GameState, TickCounter}, world::{view::View, GameWorld}};
+
+use self::{effect::ActiveEffectsComponent, sound::{AmbientSoundTimer, SoundProfile, AMBIENT_SOUND_INTERVAL_TICKS}};
+
+pub mod arrow;
+pub mod container;
+pub mod effect;
+pub mod experience;
+pub mod fishing;
+pub mod health;
+pub mod item;
+pub mod mob;
 pub mod player;
+pub mod projectile;
+pub mod riding;
+pub mod sound;
 
 pub trait Entity {
     fn send_to_player(&self, this: EntityRef, cl: &Client) -> anyhow::Result<()>;
@@ -15,10 +32,51 @@ impl EntityDispatch {
         Self(Box::new(e))
     }
 
+    /// Sends an entity to a player who does not know of it yet,
+    /// along with the potion effects active on it.
     pub fn send_to_player(&self, this_id: NetworkID, this: EntityRef, cl: &Client) -> anyhow::Result<()> {
         if !cl.client_knows_entity(this_id) {
             self.0.send_to_player(this, cl)?;
+            if let Some(effects) = this.get::<&ActiveEffectsComponent>() {
+                for effect in &effects.0 {
+                    cl.send_entity_effect(this_id, effect.id, effect.level, effect.duration)?;
+                }
+            }
         }
         Ok(())
     }
-}
\ No newline at end of file
+}
+/// Spawns an entity at `location`, sending it to the players
+/// near it and recording it in its chunk for players who come
+/// later. A fresh `NetworkID` is assigned unless `builder`
+/// already has one, and entities with a [`SoundProfile`] get
+/// an [`AmbientSoundTimer`] unless they already have one.
+///
+/// Fails if the chunk at `location` is not loaded.
+pub fn spawn_entity_at(state: &GameState, mut builder: EntityBuilder, location: EntityLocation) -> anyhow::Result<servidiot_ecs::Entity> {
+    let server = state.resource::<Server>();
+    let mut world = state.resource_mut::<GameWorld>();
+    let chunk = ChunkLocation::new(location.position.chunk(), location.location);
+    if !world.is_loaded(chunk) {
+        bail!("cannot spawn an entity in unloaded chunk {:?}", chunk);
+    }
+
+    if !builder.has::<NetworkID>() {
+        builder.add(NetworkID::new());
+    }
+    if builder.has::<SoundProfile>() && !builder.has::<AmbientSoundTimer>() {
+        let current_tick = state.resource::<TickCounter>().current_tick();
+        builder.add(AmbientSoundTimer::new(AMBIENT_SOUND_INTERVAL_TICKS, current_tick));
+    }
+    builder.add(location);
+    let entity = state.ecs().borrow_mut().spawn(builder.build());
+    world.set_entity_chunk(entity, chunk);
+
+    let ecs = state.ecs().borrow();
+    let this = ecs.entity(entity)?;
+    state.resource_mut::<EntityIdMap>().insert(*this.get::<&NetworkID>().unwrap(), entity);
+
+    let view = View::new(location.position.chunk(), state.view_distance(this));
+    state.load_entities_around(&ecs, &server, &world, this, location.location, view.chunks().into_iter())?;
+    Ok(entity)
+}
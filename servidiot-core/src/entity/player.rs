@@ -1,8 +1,8 @@
-use std::sync::Arc;
+use std::{collections::VecDeque, sync::Arc, time::{Duration, Instant}};
 
 use servidiot_ecs::EntityRef;
 use servidiot_network::server::{Client, id::NetworkID};
-use servidiot_primitives::{metadata::{Metadata, MetadataItem}, position::EntityLocation};
+use servidiot_primitives::{metadata::{Metadata, MetadataItem}, player::GamemodeType, position::{BlockPosition, EntityLocation, Location, Position}};
 use servidiot_yggdrasil::authenticate::Profile;
 
 use super::Entity;
@@ -10,6 +10,145 @@ use super::Entity;
 pub struct PlayerEntity;
 pub struct PlayerMarker;
 
+/// What a player may do, and how fast they move.
+#[derive(Clone, Copy, Debug)]
+pub struct PlayerAbilitiesComponent {
+    pub invulnerable: bool,
+    /// Whether the player says they are flying.
+    pub flying: bool,
+    pub allow_flying: bool,
+    /// Whether blocks break at once and placing them uses no items.
+    pub instant_build: bool,
+    pub fly_speed: f32,
+    pub walk_speed: f32,
+}
+
+impl PlayerAbilitiesComponent {
+    pub const INVULNERABLE_FLAG: i8 = 0x01;
+    pub const FLYING_FLAG: i8 = 0x02;
+    pub const ALLOW_FLYING_FLAG: i8 = 0x04;
+    pub const INSTANT_BUILD_FLAG: i8 = 0x08;
+
+    /// The abilities every player in some gamemode has.
+    pub fn for_gamemode(gamemode: GamemodeType) -> Self {
+        let creative = matches!(gamemode, GamemodeType::Creative);
+        Self {
+            invulnerable: creative,
+            flying: false,
+            allow_flying: creative,
+            instant_build: creative,
+            fly_speed: 0.05,
+            walk_speed: 0.1,
+        }
+    }
+
+    /// The abilities as sent in `PlayerAbilities`.
+    pub fn flags(&self) -> i8 {
+        [
+            (self.invulnerable, Self::INVULNERABLE_FLAG),
+            (self.flying, Self::FLYING_FLAG),
+            (self.allow_flying, Self::ALLOW_FLYING_FLAG),
+            (self.instant_build, Self::INSTANT_BUILD_FLAG),
+        ]
+        .into_iter()
+        .filter(|(set, _)| *set)
+        .fold(0, |flags, (_, flag)| flags | flag)
+    }
+}
+
+/// Marks a player's abilities as changed
+/// since they were last sent to them.
+pub struct AbilitiesDirty;
+
+/// Whether a player is crouching or sprinting.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MovementFlags {
+    pub sneaking: bool,
+    pub sprinting: bool,
+}
+
+impl MovementFlags {
+    /// The metadata key holding an entity's flags.
+    pub const METADATA_KEY: u8 = 0;
+    pub const SNEAKING_FLAG: u8 = 0x02;
+    pub const SPRINTING_FLAG: u8 = 0x08;
+
+    /// The flags as sent in metadata.
+    pub fn flags(&self) -> u8 {
+        [(self.sneaking, Self::SNEAKING_FLAG), (self.sprinting, Self::SPRINTING_FLAG)]
+            .into_iter()
+            .filter(|(set, _)| *set)
+            .fold(0, |flags, (_, flag)| flags | flag)
+    }
+}
+
+/// Marks a player's metadata as changed since
+/// it was last sent to the players near them.
+pub struct MetadataDirty;
+
+/// The last position of a player which passed movement validation,
+/// and how many movement packets they sent since. Each packet
+/// may move the player up to a tick's worth.
+pub struct ValidatedPosition {
+    pub position: Position,
+    pub moves: u32,
+}
+
+impl ValidatedPosition {
+    pub fn new(position: Position) -> Self {
+        Self { position, moves: 0 }
+    }
+}
+
+/// When a player recently failed movement validation.
+#[derive(Default)]
+pub struct AntiCheatViolations(pub VecDeque<Instant>);
+
+impl AntiCheatViolations {
+    /// Records a violation at `now`, forgetting those older
+    /// than `window`. Returns how many remain.
+    pub fn record(&mut self, now: Instant, window: Duration) -> usize {
+        self.0.push_back(now);
+        while self
+            .0
+            .front()
+            .is_some_and(|v| now.duration_since(*v) > window)
+        {
+            self.0.pop_front();
+        }
+        self.0.len()
+    }
+}
+
+/// Marks a player as a server operator.
+pub struct Operator;
+
+/// When a player last sent a chat message.
+pub struct ChatCooldown(pub Instant);
+
+/// The client brand a player's client reported,
+/// such as `vanilla`.
+pub struct ClientBrandComponent(pub String);
+
+/// Counts down while a player stands in a portal,
+/// sending them to `target_dimension` at zero.
+pub struct PortalCooldown {
+    pub remaining_ticks: u32,
+    pub target_dimension: i32,
+}
+
+/// The hotbar slot a player is holding, from 0 to 8.
+pub struct HeldItemSlot(pub u8);
+
+/// The sign a player placed and may still write on, if any.
+/// Only the player who placed a sign may write on it, once.
+#[derive(Default)]
+pub struct SignEditor(pub Option<(BlockPosition, Location)>);
+
+/// Marks a player's `Experience` as changed
+/// since it was last sent to them.
+pub struct ExperienceDirty;
+
 impl Entity for PlayerEntity {
     fn send_to_player(&self, this: EntityRef, cl: &Client) -> anyhow::Result<()> {
         
@@ -21,6 +160,9 @@ impl Entity for PlayerEntity {
 
         let mut temp_meta = Metadata::default();
         temp_meta.insert(6, MetadataItem::Float(20.0));
+        if let Some(movement) = this.get::<&MovementFlags>() {
+            temp_meta.insert(MovementFlags::METADATA_KEY, MetadataItem::Byte(movement.flags()));
+        }
         cl.send_player(id, &profile, pos, temp_meta)
 
     }
@@ -0,0 +1,13 @@
+use servidiot_ecs::Entity;
+
+/// A projectile in flight, such as an arrow or snowball.
+pub struct ProjectileComponent {
+    pub velocity_x: f64,
+    pub velocity_y: f64,
+    pub velocity_z: f64,
+    /// The entity which fired this projectile,
+    /// which it will never hit.
+    pub shooter: Option<Entity>,
+    /// How much `velocity_y` falls each tick.
+    pub gravity: f64,
+}
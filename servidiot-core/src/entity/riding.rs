@@ -0,0 +1,7 @@
+use servidiot_ecs::Entity;
+
+/// Seats an entity on some vehicle entity.
+pub struct RidingComponent(pub Entity);
+
+/// The vehicle players were last told an entity is riding.
+pub struct RidingSynced(pub Entity);
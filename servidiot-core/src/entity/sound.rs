@@ -0,0 +1,28 @@
+/// The least number of ticks between the ambient
+/// sounds of most mobs.
+pub const AMBIENT_SOUND_INTERVAL_TICKS: u32 = 80;
+
+/// Counts down to the next ambient sound an entity makes.
+pub struct AmbientSoundTimer {
+    /// The least number of ticks between ambient sounds.
+    pub interval_ticks: u32,
+    /// The tick at which the next ambient sound is played.
+    pub next_tick: u64,
+}
+
+impl AmbientSoundTimer {
+    /// A timer which first plays a sound
+    /// `interval_ticks` after `current_tick`.
+    pub fn new(interval_ticks: u32, current_tick: u64) -> Self {
+        Self {
+            interval_ticks,
+            next_tick: current_tick + u64::from(interval_ticks),
+        }
+    }
+}
+
+/// The sounds some entity makes.
+pub struct SoundProfile {
+    /// Played every so often, such as `mob.pig.say`.
+    pub ambient: String,
+}
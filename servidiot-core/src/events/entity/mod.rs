@@ -1,8 +1,9 @@
 use servidiot_ecs::Entity;
-use servidiot_primitives::position::Position;
+use servidiot_network::io::packet::client::play::InteractionType;
+use servidiot_primitives::{item::ItemStack, position::{BlockPosition, Position}};
 use servidiot_utils::events::Event;
 
-use crate::world::view::View;
+use crate::{entity::{container::{ContainerBlock, ContainerKind}, health::DamageSource}, world::view::View};
 
 pub struct EntityMoveEvent {
     pub entity: Entity,
@@ -23,3 +24,172 @@ impl Event for PlayerViewChangeEvent {
 }
 
 
+
+/// Fired when a player asks to leave their vehicle.
+pub struct DismountEvent {
+    pub entity: Entity
+}
+impl Event for DismountEvent {
+    const IMMEDIATE: bool = false;
+}
+
+pub struct ProjectileHitBlockEvent {
+    pub entity: Entity,
+    pub position: BlockPosition
+}
+impl Event for ProjectileHitBlockEvent {
+    const IMMEDIATE: bool = false;
+}
+
+pub struct ProjectileHitEntityEvent {
+    pub projectile: Entity,
+    pub target: Entity
+}
+impl Event for ProjectileHitEntityEvent {
+    const IMMEDIATE: bool = false;
+}
+
+/// Fired when an entity should lose health.
+pub struct DamageEvent {
+    pub entity: Entity,
+    pub amount: f32,
+    pub source: DamageSource,
+    /// The entity who dealt the damage, if any.
+    pub attacker: Option<Entity>
+}
+impl Event for DamageEvent {
+    const IMMEDIATE: bool = false;
+}
+
+/// Fired when damage takes the last of an entity's health.
+pub struct DeathEvent {
+    pub entity: Entity,
+    pub source: DamageSource,
+    pub killer: Option<Entity>
+}
+impl Event for DeathEvent {
+    const IMMEDIATE: bool = false;
+}
+
+/// Fired when a player dies, to show
+/// everyone how it happened.
+pub struct KillMessageEvent {
+    pub killed: Entity,
+    pub killer: Option<Entity>,
+    pub death_message: String
+}
+impl Event for KillMessageEvent {
+    const IMMEDIATE: bool = false;
+}
+
+/// Fired when a dead player asks to respawn.
+pub struct RespawnEvent {
+    pub entity: Entity
+}
+impl Event for RespawnEvent {
+    const IMMEDIATE: bool = false;
+}
+
+/// Fired when an operator makes a player an operator,
+/// or takes away their operator status.
+pub struct OperatorStatusEvent {
+    pub player: Entity,
+    pub operator: bool,
+}
+impl Event for OperatorStatusEvent {
+    const IMMEDIATE: bool = false;
+}
+
+/// Fired when a player drops the item they are holding.
+pub struct ItemDropEvent {
+    pub entity: Entity,
+    /// Whether to drop the whole stack, rather than one item.
+    pub whole_stack: bool
+}
+impl Event for ItemDropEvent {
+    const IMMEDIATE: bool = false;
+}
+
+/// Fired when a creative-mode player throws an
+/// item out of their inventory menu.
+pub struct CreativeItemDropEvent {
+    pub entity: Entity,
+    pub stack: ItemStack
+}
+impl Event for CreativeItemDropEvent {
+    const IMMEDIATE: bool = false;
+}
+
+/// Fired when a player right-clicks an entity.
+pub struct EntityInteractEvent {
+    pub actor: Entity,
+    pub target: Entity,
+    pub interaction_type: InteractionType
+}
+impl Event for EntityInteractEvent {
+    const IMMEDIATE: bool = false;
+}
+
+/// Fired when a player right-clicks with a fishing rod,
+/// casting or reeling in their hook.
+pub struct FishingRodUseEvent {
+    pub player: Entity
+}
+impl Event for FishingRodUseEvent {
+    const IMMEDIATE: bool = false;
+}
+
+/// Fired when a player right-clicks with a bow, starting to draw it.
+pub struct BowDrawEvent {
+    pub player: Entity
+}
+impl Event for BowDrawEvent {
+    const IMMEDIATE: bool = false;
+}
+
+/// Fired when a player lets go of the item they are using,
+/// loosing an arrow if they were drawing a bow.
+pub struct BowReleaseEvent {
+    pub player: Entity
+}
+impl Event for BowReleaseEvent {
+    const IMMEDIATE: bool = false;
+}
+
+/// Fired when a player reaches an experience orb,
+/// which they collect once the event is handled.
+pub struct CollectItemEvent {
+    pub collector: Entity,
+    pub collected: Entity
+}
+impl Event for CollectItemEvent {
+    const IMMEDIATE: bool = false;
+}
+
+/// Fired when a player left-clicks an entity.
+pub struct EntityAttackEvent {
+    pub attacker: Entity,
+    pub target: Entity
+}
+impl Event for EntityAttackEvent {
+    const IMMEDIATE: bool = false;
+}
+
+/// Fired when a player opens a container block.
+pub struct ContainerOpenEvent {
+    pub player: Entity,
+    pub container: ContainerBlock,
+    pub kind: ContainerKind
+}
+impl Event for ContainerOpenEvent {
+    const IMMEDIATE: bool = false;
+}
+
+/// Fired when a player closes the container they have open.
+pub struct ContainerCloseEvent {
+    pub player: Entity,
+    pub window_id: u8
+}
+impl Event for ContainerCloseEvent {
+    const IMMEDIATE: bool = false;
+}
@@ -0,0 +1,47 @@
+use servidiot_ecs::Entity;
+use servidiot_primitives::{block::BlockID, position::{BlockPosition, Direction, Location, Position}};
+use servidiot_utils::events::Event;
+
+/// An explosion, destroying blocks and
+/// pushing away entities within `radius`.
+pub struct ExplosionEvent {
+    pub center: Position,
+    /// The dimension the explosion happens in.
+    pub location: Location,
+    pub radius: f32,
+    /// The entity which exploded, if any.
+    pub entity: Option<Entity>,
+}
+impl Event for ExplosionEvent {
+    const IMMEDIATE: bool = false;
+}
+
+/// A fluid which may spread from `block`.
+pub struct FluidFlowEvent {
+    pub block: BlockPosition,
+    /// The dimension the fluid is in.
+    pub location: Location,
+    pub fluid_id: BlockID,
+    /// How many blocks the fluid has already
+    /// spread from its source.
+    pub source_level: u8,
+}
+impl Event for FluidFlowEvent {
+    const IMMEDIATE: bool = false;
+}
+
+/// A piston being powered or unpowered.
+pub struct PistonActivateEvent {
+    /// The block in front of the piston,
+    /// which its head extends into.
+    pub head_pos: BlockPosition,
+    /// The dimension the piston is in.
+    pub location: Location,
+    /// The direction the piston faces.
+    pub facing: Direction,
+    /// Whether the piston extends, rather than retracts.
+    pub extending: bool,
+}
+impl Event for PistonActivateEvent {
+    const IMMEDIATE: bool = false;
+}
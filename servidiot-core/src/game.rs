@@ -1,12 +1,13 @@
-use std::{sync::Arc, cell::RefCell, rc::Rc, path::PathBuf, str::FromStr, collections::HashMap};
+use std::{sync::Arc, cell::{Ref, RefCell, RefMut}, rc::Rc, path::PathBuf, str::FromStr, collections::{HashMap, HashSet}};
 
+use servidiot_anvil::{nbt::level::LevelData, WorldManager};
 use servidiot_ecs::{World, SystemExecutor, Entity, EntityRef};
-use servidiot_network::server::{id::NetworkID, Client, Server};
-use servidiot_primitives::position::{ChunkLocation, ChunkPosition, EntityLocation, Location};
+use servidiot_network::{connection::status::ServerListEntry, io::packet::client::play::ClientSettings, server::{id::NetworkID, Client, Server}};
+use servidiot_primitives::{player::{Gamemode, GamemodeType}, position::{ChunkLocation, ChunkPosition, EntityLocation, Location}};
 use servidiot_utils::{resources::Resources, events::EventManager};
 use tokio::runtime::Handle;
 
-use crate::{Config, systems, world::{view::View, GameWorld}, entity::{EntityDispatch, player::PlayerMarker}};
+use crate::{Config, events::entity::{EntityMoveEvent, PlayerViewChangeEvent}, access::{BanList, OpList, Whitelist}, scheduler::ScheduledTaskManager, scoreboard::ScoreboardManager, systems, world::{border::WorldBorder, generator::{ChunkGenerationQueue, FlatWorldGenerator, OrePopulator, PopulationQueue, WorldGeneratorConfig}, leaves::LeafDecayQueue, protection::SpawnProtection, view::View, weather::{WeatherState, WeatherSystem}, AutosaveConfig, GameWorld}, entity::{EntityDispatch, health::{DeathMessages, HealthDirty}, mob::MobCapConfig, player::{AbilitiesDirty, PlayerMarker, ValidatedPosition}}};
 
 #[derive(Default)]
 pub struct ClientMap(HashMap<NetworkID, Entity>);
@@ -20,11 +21,66 @@ impl ClientMap {
         self.0[&a]
     }
 
+    pub fn try_get_mapping(&self, a: NetworkID) -> Option<Entity> {
+        self.0.get(&a).copied()
+    }
+
     pub fn remove_mapping(&mut self, a: NetworkID) {
         self.0.remove(&a);
     }
 }
 
+/// Looks up the entity of every network ID,
+/// whether or not it belongs to a client.
+#[derive(Default)]
+pub struct EntityIdMap(HashMap<NetworkID, Entity>);
+
+impl EntityIdMap {
+    pub fn insert(&mut self, id: NetworkID, entity: Entity) {
+        self.0.insert(id, entity);
+    }
+
+    pub fn get(&self, id: NetworkID) -> Option<Entity> {
+        self.0.get(&id).copied()
+    }
+
+    pub fn remove(&mut self, id: NetworkID) {
+        self.0.remove(&id);
+    }
+}
+
+/// How many ticks the server has run.
+#[derive(Default)]
+pub struct TickCounter(u64);
+
+impl TickCounter {
+    pub fn current_tick(&self) -> u64 {
+        self.0
+    }
+
+    pub fn advance(&mut self) {
+        self.0 += 1;
+    }
+}
+
+/// How far the server is through stopping.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ShutdownState {
+    #[default]
+    Running,
+    /// Counting down to a restart on `restart_tick`.
+    /// New players are turned away.
+    Restarting { restart_tick: u64 },
+    /// Every player was saved and kicked, and
+    /// is waiting to be removed.
+    Disconnecting,
+    /// Loaded chunks are being written to disk.
+    Saving,
+    /// The tick loop stops after this tick,
+    /// and the server should be started again.
+    Restart,
+}
+
 pub struct GameState {
     ecs: RefCell<servidiot_ecs::World>,
     events: RefCell<EventManager<GameState>>,
@@ -41,15 +97,77 @@ impl GameState {
 
         let mut systems = SystemExecutor::<GameState>::new();
 
+        systems.begin_group("login");
         systems::login::register_systems(&mut systems);
+        systems.end_group().begin_group("world");
         systems::world::register_systems(&mut systems);
+        systems.end_group().begin_group("packet");
         systems::packet::register_systems(&mut systems);
+        systems.end_group().begin_group("entity");
         systems::entity::register_systems(&mut systems);
+        systems.end_group().begin_group("scoreboard");
+        systems::scoreboard::register_systems(&mut systems);
+        systems.end_group();
         
 
-        resources.add(ClientMap::default());        
-        resources.add(GameWorld::new(PathBuf::from_str("").unwrap()));
-        resources.add(net_runtime.block_on(Server::bind(cfg.bind_addr))?);
+        let world_dir = PathBuf::from_str("").unwrap();
+
+        resources.add(ClientMap::default());
+        resources.add(EntityIdMap::default());
+        resources.add(TickCounter::default());        
+        resources.add(ShutdownState::default());
+        resources.add(Whitelist::load(&world_dir)?);
+        resources.add(BanList::load(&world_dir)?);
+        resources.add(OpList::load(&world_dir)?);
+        let world_manager = WorldManager::open(world_dir.clone());
+        let level = match world_manager.load_level_dat() {
+            Ok(v) => v.map(|v| v.data),
+            Err(e) => {
+                tracing::error!("Failed to load level.dat: {:?}", e);
+                None
+            }
+        };
+
+        let seed = level.as_ref().map_or(0, |v| v.world_seed);
+        let generator = match &level {
+            Some(v) => WorldGeneratorConfig::for_generator(seed, &v.generator_name),
+            // New worlds have ground to stand on.
+            None => WorldGeneratorConfig::new(seed, FlatWorldGenerator),
+        };
+        resources.add(SpawnProtection::new(level.as_ref(), cfg.spawn_protection_radius));
+        resources.add(WeatherState::from_level(level.as_ref(), &mut rand::thread_rng()));
+        resources.add(level);
+        resources.add(WeatherSystem::default());
+        resources.add(world_manager);
+        resources.add(GameWorld::new(world_dir));
+        resources.add(ChunkGenerationQueue::default());
+        resources.add(PopulationQueue::default());
+        resources.add(LeafDecayQueue::default());
+        resources.add(generator.with_populator(OrePopulator::iron()));
+        resources.add(WorldBorder::default());
+        let mut scoreboard = ScoreboardManager::new();
+        systems::entity::add_kill_count_objective(&mut scoreboard);
+        resources.add(scoreboard);
+        let mut death_messages = DeathMessages::default();
+        for (source, template) in &cfg.death_messages {
+            death_messages.set(*source, template);
+        }
+        resources.add(death_messages);
+        resources.add(MobCapConfig::default());
+        let autosave = AutosaveConfig::default();
+        let mut tasks = ScheduledTaskManager::default();
+        systems::login::schedule_tasks(&mut tasks, &cfg);
+        systems::world::schedule_tasks(&mut tasks, &autosave);
+        systems::entity::schedule_tasks(&mut tasks);
+        resources.add(tasks);
+        resources.add(autosave);
+        let favicon = cfg.favicon_path.as_deref().and_then(|path| {
+            ServerListEntry::favicon_from_png_file(path)
+                .inspect_err(|e| tracing::error!("Failed to load server icon {}: {:?}", path.display(), e))
+                .ok()
+        });
+        resources.add(net_runtime.block_on(Server::bind(cfg.bind_addr, cfg.max_packets_per_second, favicon))?);
+        resources.add(cfg);
         Ok(Self {
             ecs,
             events: RefCell::new(events),
@@ -58,6 +176,22 @@ impl GameState {
         })
     } 
 
+    /// Gets a resource added at startup.
+    ///
+    /// # Panics
+    /// This method will panic if there is no resource of type `T`.
+    pub fn resource<T: 'static>(&self) -> Ref<'_, T> {
+        self.resources().expect("added at startup")
+    }
+
+    /// Mutably gets a resource added at startup.
+    ///
+    /// # Panics
+    /// This method will panic if there is no resource of type `T`.
+    pub fn resource_mut<T: 'static>(&self) -> RefMut<'_, T> {
+        self.resources().expect_mut("added at startup")
+    }
+
     pub fn resources(&self) -> &Rc<Resources> {
         &self.resources
     }
@@ -75,6 +209,151 @@ impl GameState {
     }
 
 
+    /// Shows a line of plain text in the chat of every connected client.
+    pub fn broadcast_message(&self, text: &str) -> anyhow::Result<()> {
+        let server = self.resource::<Server>();
+        for client in server.clients().filter(|v| !v.is_disconnected()) {
+            client.send_message(text)?;
+        }
+        Ok(())
+    }
+
+    /// How far, in chunks, some entity is seen from: the view
+    /// distance of a player, or the furthest any player may see.
+    pub fn view_distance(&self, entity: EntityRef) -> u32 {
+        match entity.get::<&ClientSettings>() {
+            Some(settings) => settings.view_distance.max(1) as u32,
+            None => u32::from(self.resource::<Arc<Config>>().view_distance.get()),
+        }
+    }
+
+    /// Sends a player a respawn into some dimension, as the client
+    /// needs to revive or to switch dimension. This resets what the
+    /// client thinks its player may do, so that is sent again.
+    pub fn respawn_player(&self, entity: Entity, dimension: i32) -> anyhow::Result<()> {
+        {
+            let ecs = self.ecs().borrow();
+            let server = self.resource::<Server>();
+            let level = self.resource::<Option<LevelData>>();
+            let player = ecs.entity(entity)?;
+            let client = server.get_client(*player.get::<&NetworkID>().unwrap())?;
+            let gamemode = player.get::<&Gamemode>().map_or(Gamemode::new(GamemodeType::Survival, false), |v| *v);
+            let difficulty = level.as_ref().map_or(0, |v| v.difficulty.max(0) as u8);
+            client.respawn(gamemode, dimension, difficulty, "default".to_string())?;
+        }
+        self.ecs().borrow_mut().insert(entity, (HealthDirty, AbilitiesDirty))?;
+        Ok(())
+    }
+
+    /// Moves an entity to `new_location`, telling every player who
+    /// could see it at either end, and moving a player's view with it.
+    ///
+    /// Within a dimension the move is finished by the `EntityMoveEvent`
+    /// and `PlayerViewChangeEvent` handlers. Across dimensions, players
+    /// are respawned into the new dimension, and chunks and entities
+    /// are swapped over here instead.
+    pub fn teleport_entity(&self, entity: Entity, new_location: EntityLocation) -> anyhow::Result<()> {
+        let (old_dimension, is_player) = {
+            let ecs = self.ecs().borrow();
+            let this = ecs.entity(entity)?;
+            let location = this.get::<&EntityLocation>().unwrap().location;
+            (location, this.has::<PlayerMarker>())
+        };
+        // The client only switches dimension on a respawn,
+        // which must come before it is sent the new position.
+        if is_player && old_dimension != new_location.location {
+            self.respawn_player(entity, new_location.location.dimension)?;
+        }
+
+        let old_location = {
+            let ecs = self.ecs().borrow();
+            let mut location = ecs.get::<&mut EntityLocation>(entity)?;
+            if let Ok(mut validated) = ecs.get::<&mut ValidatedPosition>(entity) {
+                *validated = ValidatedPosition::new(new_location.position);
+            }
+            std::mem::replace(&mut *location, new_location)
+        };
+        let (old_pos, new_pos) = (old_location.position, new_location.position);
+
+        let ecs = self.ecs().borrow();
+        let server = self.resource::<Server>();
+        let this = ecs.entity(entity)?;
+        let this_id = *this.get::<&NetworkID>().unwrap();
+        let client = if this.has::<PlayerMarker>() {
+            Some(server.get_client(this_id)?)
+        } else {
+            None
+        };
+        if let Some(client) = client {
+            client.set_position(new_pos)?;
+        }
+
+        let view_distance = self.view_distance(this);
+        let old_view = View::new(old_pos.chunk(), view_distance);
+        let new_view = View::new(new_pos.chunk(), view_distance);
+
+        if old_location.location == new_location.location {
+            {
+                let world = self.resource::<GameWorld>();
+                let mut notified = HashSet::new();
+                let chunks = old_view.chunks().into_iter().chain(new_view.chunks());
+                self.for_all_entities_nearby(&ecs, &world, new_location.location, chunks, |other| {
+                    if other.entity() == entity || !other.has::<PlayerMarker>() {
+                        return Ok(());
+                    }
+                    let other_id = *other.get::<&NetworkID>().unwrap();
+                    if notified.insert(other_id) {
+                        server.get_client(other_id)?.send_position(this_id, new_pos)?;
+                    }
+                    Ok(())
+                })?;
+            }
+
+            let events = self.events().borrow();
+            events.post_event(self, EntityMoveEvent {
+                entity,
+                old_pos,
+                new_pos
+            })?;
+            if let Some(client) = client {
+                if old_pos.chunk() != new_pos.chunk() {
+                    events.post_event(self, PlayerViewChangeEvent {
+                        entity,
+                        old_view,
+                        new_view
+                    })?;
+                }
+                client.set_client_known_position(new_pos);
+            }
+            return Ok(());
+        }
+
+        let mut world = self.resource_mut::<GameWorld>();
+        if let Some(client) = client {
+            self.unload_entities_for(&ecs, &server, &world, this, old_location.location, old_view.chunks().into_iter())?;
+
+            for chunk in old_view.chunks() {
+                let chunk = ChunkLocation::new(chunk, old_location.location);
+                world.remove_player_from_chunk(client, entity, chunk)?;
+                world.cancel_loading_request(chunk, this_id);
+            }
+            for chunk in new_view.chunks() {
+                world.add_player_to_chunk(client, entity, ChunkLocation::new(chunk, new_location.location))?;
+            }
+        } else {
+            self.for_all_entities_nearby(&ecs, &world, old_location.location, old_view.chunks().into_iter(), |other| {
+                if other.has::<PlayerMarker>() {
+                    let other_id = *other.get::<&NetworkID>().unwrap();
+                    server.get_client(other_id)?.unload_entities(&[this_id])?;
+                }
+                Ok(())
+            })?;
+            world.set_entity_chunk(entity, ChunkLocation::new(new_pos.chunk(), new_location.location));
+        }
+        self.load_entities_around(&ecs, &server, &world, this, new_location.location, new_view.chunks().into_iter())?;
+        Ok(())
+    }
+
     pub fn load_entities_around(&self, ecs: &World, server: &Server, world: &GameWorld, this: EntityRef, dim: Location, loc: impl Iterator<Item = ChunkPosition>) -> anyhow::Result<()> {
         let us_to_unload = vec![];
 
@@ -112,7 +391,9 @@ impl GameState {
 
         })?;
 
-        server.get_client(this_id)?.unload_entities(&us_to_unload)?;
+        if let Some(our_client) = our_client {
+            our_client.unload_entities(&us_to_unload)?;
+        }
         Ok(())
     }
 
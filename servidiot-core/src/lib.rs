@@ -1,11 +1,14 @@
 use std::{
-    net::SocketAddr,
-    num::{NonZeroU64, NonZeroUsize},
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    num::{NonZeroU64, NonZeroU8, NonZeroUsize},
+    path::PathBuf,
     sync::Arc,
+    time::Duration,
 };
 
-use game::GameState;
-use servidiot_utils::ticks::TickLoop;
+use game::{GameState, ShutdownState, TickCounter};
+use servidiot_network::connection::ServerState;
+use servidiot_utils::ticks::{TickLoop, TickStats};
 use thiserror::Error;
 use tokio::io;
 
@@ -14,13 +17,80 @@ mod systems;
 mod entity;
 mod world;
 mod events;
+mod scoreboard;
+mod scheduler;
+mod access;
 
+pub use entity::health::DamageSource;
+
+/// How many ticks to average when a tick runs late.
+const TICK_SAMPLES: usize = 100;
 
 pub struct Config {
     pub net_threads: NonZeroUsize,
     pub game_threads: NonZeroUsize,
     pub tps: NonZeroU64,
     pub bind_addr: SocketAddr,
+    /// How many packets a client may send each
+    /// second before being disconnected.
+    pub max_packets_per_second: u32,
+    /// How many missing chunks may be
+    /// generated each tick.
+    pub max_generated_chunks_per_tick: usize,
+    /// How far a player not flying may move
+    /// horizontally each tick, in blocks.
+    pub max_speed_blocks_per_tick: f64,
+    /// The furthest, in chunks, a player may see.
+    pub view_distance: NonZeroU8,
+    /// Whether to run ticks on the network runtime
+    /// rather than sleeping on the main thread.
+    pub async_ticks: bool,
+    /// How far, in blocks, from the world spawn
+    /// players may not change blocks.
+    pub spawn_protection_radius: u32,
+    /// How long a player must wait between chat
+    /// messages. Operators may chat freely.
+    pub min_chat_interval: Duration,
+    /// A 64×64 PNG file shown as
+    /// the icon in the server list.
+    pub favicon_path: Option<PathBuf>,
+    /// How many ticks a player must stand in
+    /// a portal before it takes them through.
+    pub portal_delay_ticks: u32,
+    /// Death messages replacing the built-in ones
+    /// for some sources, as `{killed}`/`{killer}`
+    /// templates.
+    pub death_messages: Vec<(DamageSource, String)>,
+}
+
+impl Config {
+    /// Limits the view distance some client asks
+    /// for to at most `view_distance`.
+    pub fn clamp_view_distance(&self, requested: i8) -> i8 {
+        let max = i8::try_from(self.view_distance.get()).unwrap_or(i8::MAX);
+        requested.clamp(1, max)
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            net_threads: NonZeroUsize::new(2).unwrap(),
+            game_threads: NonZeroUsize::new(2).unwrap(),
+            tps: NonZeroU64::new(20).unwrap(),
+            bind_addr: SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 25565),
+            max_packets_per_second: ServerState::DEFAULT_MAX_PACKETS_PER_SECOND,
+            max_generated_chunks_per_tick: 8,
+            max_speed_blocks_per_tick: 0.6,
+            view_distance: NonZeroU8::new(8).unwrap(),
+            async_ticks: false,
+            spawn_protection_radius: 16,
+            min_chat_interval: Duration::from_secs(1),
+            favicon_path: None,
+            portal_delay_ticks: 80,
+            death_messages: Vec::new(),
+        }
+    }
 }
 
 /// Represents the game runtime.
@@ -47,13 +117,31 @@ impl GameRuntime {
         })
     }
 
-    /// Begins running the runtime.
-    pub fn run(self) {
-        TickLoop::new(self.config.tps, || {
+    /// Runs the runtime until the server stops,
+    /// returning whether it should be started again.
+    pub fn run(self) -> bool {
+        let stats = TickStats::new(TICK_SAMPLES);
+        let recent = stats.clone();
+        let tick_loop = TickLoop::with_late_handler(self.config.tps, move |over| {
+            tracing::warn!(
+                "Tick took {}ms too long, recent ticks took {}ms on average and {}ms at most",
+                over.as_millis(),
+                recent.mean_tick_duration().as_millis(),
+                recent.max_tick_duration().as_millis()
+            );
+        }, || {
             self.state.systems().borrow().run_systems(&self.state);
+            self.state.resource_mut::<TickCounter>().advance();
 
-            true
-        }).run();
+            *self.state.resource::<ShutdownState>() != ShutdownState::Restart
+        })
+        .with_stats(stats);
+        if self.config.async_ticks {
+            self.net_runtime.block_on(tick_loop.run_async());
+        } else {
+            tick_loop.run();
+        }
+        *self.state.resource::<ShutdownState>() == ShutdownState::Restart
     }
 }
 
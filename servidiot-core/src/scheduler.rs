@@ -0,0 +1,192 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+    sync::Arc,
+};
+
+use crate::game::GameState;
+
+type OnceFn = dyn FnOnce(&GameState) -> anyhow::Result<()> + Send;
+type RepeatingFn = dyn Fn(&GameState) -> anyhow::Result<()> + Send + Sync;
+
+/// Identifies a scheduled task, so it can be cancelled. Repeating
+/// tasks are named by whoever schedules them, while one-shot
+/// tasks are numbered as they are scheduled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TaskId {
+    Named(&'static str),
+    Numbered(u64),
+}
+
+enum Task {
+    Once(Box<OnceFn>),
+    Repeating { interval: u64, task: Arc<RepeatingFn> },
+}
+
+/// A task whose time has come, taken out of
+/// the manager so it may schedule more tasks.
+pub enum DueTask {
+    Once(Box<OnceFn>),
+    Repeating(Arc<RepeatingFn>),
+}
+
+impl DueTask {
+    pub fn run(self, state: &GameState) -> anyhow::Result<()> {
+        match self {
+            Self::Once(task) => task(state),
+            Self::Repeating(task) => task(state),
+        }
+    }
+}
+
+/// Runs tasks some number of ticks from now, once or over
+/// and over. Ticks are those of the `TickCounter`, as
+/// passed to `take_due`.
+#[derive(Default)]
+pub struct ScheduledTaskManager {
+    /// The tick last passed to `take_due`.
+    tick: u64,
+    next_id: u64,
+    /// Counts every time a task is put in `queue`.
+    next_entry: u64,
+    /// When each task fires next. Tasks firing on the same
+    /// tick fire in the order they were scheduled. Entries
+    /// of cancelled or replaced tasks are skipped once
+    /// they come up.
+    queue: BinaryHeap<Reverse<(u64, u64, TaskId)>>,
+    /// Each task, with its entry in `queue`.
+    tasks: HashMap<TaskId, (u64, Task)>,
+}
+
+impl ScheduledTaskManager {
+    /// Runs `task` once, `delay_ticks` ticks from now.
+    pub fn schedule_once(&mut self, delay_ticks: u64, task: impl FnOnce(&GameState) -> anyhow::Result<()> + Send + 'static) -> TaskId {
+        let id = TaskId::Numbered(self.next_id);
+        self.next_id += 1;
+        self.schedule(delay_ticks, id, Task::Once(Box::new(task)));
+        id
+    }
+
+    /// Runs `task` every `interval_ticks` ticks, from `interval_ticks`
+    /// ticks from now. This replaces any task already scheduled as `id`.
+    pub fn schedule_repeating(
+        &mut self,
+        interval_ticks: u64,
+        id: TaskId,
+        task: impl Fn(&GameState) -> anyhow::Result<()> + Send + Sync + 'static,
+    ) -> TaskId {
+        let interval = interval_ticks.max(1);
+        self.schedule(interval, id, Task::Repeating { interval, task: Arc::new(task) });
+        id
+    }
+
+    /// Stops a task from running again. Returns
+    /// `false` if it was not scheduled.
+    pub fn cancel(&mut self, id: TaskId) -> bool {
+        self.tasks.remove(&id).is_some()
+    }
+
+    /// Takes every task due by `tick`, in the order they fire.
+    /// Repeating tasks are scheduled again as they are taken.
+    pub fn take_due(&mut self, tick: u64) -> Vec<(TaskId, DueTask)> {
+        self.tick = tick;
+        let mut due = vec![];
+        while let Some(Reverse((fires, entry, id))) = self.queue.peek().copied() {
+            if fires > tick {
+                break;
+            }
+            self.queue.pop();
+            if self.tasks.get(&id).is_none_or(|(current, _)| *current != entry) {
+                continue;
+            }
+            match self.tasks.remove(&id).map(|(_, v)| v) {
+                Some(Task::Once(task)) => due.push((id, DueTask::Once(task))),
+                Some(Task::Repeating { interval, task }) => {
+                    due.push((id, DueTask::Repeating(task.clone())));
+                    self.schedule(interval, id, Task::Repeating { interval, task });
+                }
+                None => (),
+            }
+        }
+        due
+    }
+
+    fn schedule(&mut self, delay_ticks: u64, id: TaskId, task: Task) {
+        let entry = self.next_entry;
+        self.next_entry += 1;
+        self.queue.push(Reverse((self.tick + delay_ticks, entry, id)));
+        self.tasks.insert(id, (entry, task));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::RangeInclusive;
+
+    use super::{DueTask, ScheduledTaskManager, TaskId};
+
+    /// Runs each tick in `ticks`, returning the tasks due on each.
+    fn run(tasks: &mut ScheduledTaskManager, ticks: RangeInclusive<u64>) -> Vec<Vec<TaskId>> {
+        ticks.map(|tick| tasks.take_due(tick).into_iter().map(|(id, _)| id).collect()).collect()
+    }
+
+    #[test]
+    fn firing_order_test() {
+        let mut tasks = ScheduledTaskManager::default();
+        let late = tasks.schedule_once(3, |_| Ok(()));
+        let first = tasks.schedule_once(1, |_| Ok(()));
+        let second = tasks.schedule_once(1, |_| Ok(()));
+        let now = tasks.schedule_once(0, |_| Ok(()));
+        assert_eq!(run(&mut tasks, 0..=3), [vec![now], vec![first, second], vec![], vec![late]]);
+    }
+
+    #[test]
+    fn repeating_test() {
+        let mut tasks = ScheduledTaskManager::default();
+        let every_two = tasks.schedule_repeating(2, TaskId::Named("every two"), |_| Ok(()));
+        let every_tick = tasks.schedule_repeating(0, TaskId::Named("every tick"), |_| Ok(()));
+        assert_eq!(run(&mut tasks, 1..=4), [
+            vec![every_tick],
+            vec![every_two, every_tick],
+            vec![every_tick],
+            vec![every_two, every_tick],
+        ]);
+        assert!(tasks.take_due(5).into_iter().all(|(_, v)| matches!(v, DueTask::Repeating(_))));
+    }
+
+    #[test]
+    fn skipped_ticks_test() {
+        let mut tasks = ScheduledTaskManager::default();
+        let once = tasks.schedule_once(2, |_| Ok(()));
+        let repeating = tasks.schedule_repeating(2, TaskId::Named("repeating"), |_| Ok(()));
+        // Tasks due on ticks that were never run fire late, and
+        // repeating tasks count their interval from then.
+        assert_eq!(run(&mut tasks, 5..=5), [vec![once, repeating]]);
+        assert_eq!(run(&mut tasks, 6..=7), [vec![], vec![repeating]]);
+    }
+
+    #[test]
+    fn replace_test() {
+        let mut tasks = ScheduledTaskManager::default();
+        let id = TaskId::Named("replaced");
+        tasks.schedule_repeating(1, id, |_| Ok(()));
+        tasks.schedule_repeating(3, id, |_| Ok(()));
+        assert_eq!(run(&mut tasks, 1..=3), [vec![], vec![], vec![id]]);
+    }
+
+    #[test]
+    fn cancel_test() {
+        let mut tasks = ScheduledTaskManager::default();
+        let once = tasks.schedule_once(1, |_| Ok(()));
+        let repeating = tasks.schedule_repeating(1, TaskId::Named("repeating"), |_| Ok(()));
+        let kept = tasks.schedule_once(2, |_| Ok(()));
+        assert!(tasks.cancel(once));
+        assert!(!tasks.cancel(once));
+        assert_eq!(run(&mut tasks, 1..=1), [vec![repeating]]);
+
+        assert!(tasks.cancel(repeating));
+        assert_eq!(run(&mut tasks, 2..=4), [vec![kept], vec![], vec![]]);
+        // Tasks which already fired are not scheduled.
+        assert!(!tasks.cancel(kept));
+    }
+}
@@ -0,0 +1,190 @@
+use std::collections::HashMap;
+
+use servidiot_network::{
+    io::packet::server::play::{ScoreboardObjective, ScoreboardScore},
+    server::Client,
+};
+
+/// A scoreboard objective.
+pub struct Objective {
+    /// The name shown to players.
+    pub display_name: String,
+}
+
+/// A change to the scoreboard not yet sent to players.
+enum ScoreboardChange {
+    Objective(String, i8),
+    Score(String, String, i8),
+    Display(i8, String),
+}
+
+/// Marks a player as having been sent the scoreboard.
+pub struct ScoreboardSynced;
+
+/// Manages named objectives and per-player scores.
+#[derive(Default)]
+pub struct ScoreboardManager {
+    objectives: HashMap<String, Objective>,
+    /// Scores keyed by objective, then player name.
+    scores: HashMap<String, HashMap<String, i32>>,
+    displays: HashMap<i8, String>,
+    pending: Vec<ScoreboardChange>,
+}
+
+impl ScoreboardManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an objective, or updates it if it already exists.
+    pub fn add_objective(&mut self, name: &str, display_name: &str) {
+        let action = if self.objectives.contains_key(name) {
+            ScoreboardObjective::UPDATE
+        } else {
+            ScoreboardObjective::CREATE
+        };
+        self.objectives.insert(
+            name.to_string(),
+            Objective {
+                display_name: display_name.to_string(),
+            },
+        );
+        self.pending
+            .push(ScoreboardChange::Objective(name.to_string(), action));
+    }
+
+    /// Sets a player's score on some objective.
+    /// Returns `false` if the objective does not exist.
+    pub fn set_score(&mut self, objective: &str, player: &str, value: i32) -> bool {
+        let Some(scores) = self.objective_scores(objective) else {
+            return false;
+        };
+        scores.insert(player.to_string(), value);
+        self.pending.push(ScoreboardChange::Score(
+            objective.to_string(),
+            player.to_string(),
+            ScoreboardScore::UPDATE,
+        ));
+        true
+    }
+
+    /// Adds `amount` to a player's score on some objective,
+    /// starting from 0. Returns `false` if the objective does not exist.
+    pub fn increment(&mut self, objective: &str, player: &str, amount: i32) -> bool {
+        let value = self.score(objective, player).unwrap_or(0).saturating_add(amount);
+        self.set_score(objective, player, value)
+    }
+
+    pub fn score(&self, objective: &str, player: &str) -> Option<i32> {
+        self.scores.get(objective)?.get(player).copied()
+    }
+
+    /// Shows an objective at some display position.
+    /// Returns `false` if the objective does not exist.
+    pub fn set_display(&mut self, position: i8, objective: &str) -> bool {
+        if !self.objectives.contains_key(objective) {
+            return false;
+        }
+        self.displays.insert(position, objective.to_string());
+        self.pending
+            .push(ScoreboardChange::Display(position, objective.to_string()));
+        true
+    }
+
+    fn objective_scores(&mut self, objective: &str) -> Option<&mut HashMap<String, i32>> {
+        if !self.objectives.contains_key(objective) {
+            return None;
+        }
+        Some(self.scores.entry(objective.to_string()).or_default())
+    }
+
+    /// Whether there are changes not yet sent to players.
+    pub fn has_changes(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Sends every pending change to `client`.
+    pub fn send_changes(&self, client: &Client) -> anyhow::Result<()> {
+        for change in &self.pending {
+            match change {
+                ScoreboardChange::Objective(name, action) => {
+                    let display_name = self.objectives.get(name).map_or("", |v| &*v.display_name);
+                    client.send_objective(name, *action, display_name)?;
+                }
+                ScoreboardChange::Score(objective, player, action) => {
+                    let value = self.score(objective, player).unwrap_or(0);
+                    client.send_score(player, *action, objective, value)?;
+                }
+                ScoreboardChange::Display(position, objective) => {
+                    client.send_scoreboard_display(*position, objective)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Sends the whole scoreboard to `client`.
+    pub fn send_all(&self, client: &Client) -> anyhow::Result<()> {
+        for (name, objective) in &self.objectives {
+            client.send_objective(
+                name,
+                ScoreboardObjective::CREATE,
+                &objective.display_name,
+            )?;
+        }
+        for (objective, scores) in &self.scores {
+            for (player, value) in scores {
+                client.send_score(player, ScoreboardScore::UPDATE, objective, *value)?;
+            }
+        }
+        for (position, objective) in &self.displays {
+            client.send_scoreboard_display(*position, objective)?;
+        }
+        Ok(())
+    }
+
+    /// Marks all pending changes as sent.
+    pub fn clear_changes(&mut self) {
+        self.pending.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use servidiot_network::io::packet::server::play::{ScoreboardObjective, ScoreboardScore};
+
+    use super::{ScoreboardChange, ScoreboardManager};
+
+    /// The score updates waiting to be sent, with the values sent for them.
+    fn pending_scores(scoreboard: &ScoreboardManager) -> Vec<(&str, &str, i8, i32)> {
+        scoreboard
+            .pending
+            .iter()
+            .filter_map(|change| match change {
+                ScoreboardChange::Score(objective, player, action) => {
+                    Some((&**objective, &**player, *action, scoreboard.score(objective, player).unwrap_or(0)))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn increment_test() {
+        let mut scoreboard = ScoreboardManager::new();
+        assert!(!scoreboard.increment("Kills", "Steve", 1));
+        assert!(!scoreboard.has_changes());
+
+        scoreboard.add_objective("Kills", "Kills");
+        assert!(matches!(scoreboard.pending[..], [ScoreboardChange::Objective(_, ScoreboardObjective::CREATE)]));
+        scoreboard.clear_changes();
+
+        assert!(scoreboard.increment("Kills", "Steve", 1));
+        assert_eq!(pending_scores(&scoreboard), [("Kills", "Steve", ScoreboardScore::UPDATE, 1)]);
+        scoreboard.clear_changes();
+
+        assert!(scoreboard.increment("Kills", "Steve", 2));
+        assert_eq!(scoreboard.score("Kills", "Steve"), Some(3));
+        assert_eq!(pending_scores(&scoreboard), [("Kills", "Steve", ScoreboardScore::UPDATE, 3)]);
+    }
+}
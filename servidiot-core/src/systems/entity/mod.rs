@@ -1,13 +1,1233 @@
-use servidiot_ecs::SystemExecutor;
-use servidiot_network::server::{id::NetworkID, Client, Server};
-use servidiot_primitives::{chunk, position::{EntityLocation, ChunkLocation}};
+use std::{collections::{HashMap, HashSet}, sync::Arc};
 
-use crate::{game::GameState, world::{GameWorld, view::View}, events::entity::EntityMoveEvent, entity::{player::PlayerMarker, EntityDispatch}};
+use rand::Rng;
+use servidiot_anvil::nbt::player::PlayerData;
+use servidiot_ecs::{Entity, EntityBuilder, EntityRef, SystemExecutor};
+use servidiot_network::{
+    io::packet::{client::play::InteractionType, server::play::{EntityStatusKind, ScoreboardDisplay}},
+    server::{id::NetworkID, Client, Server},
+};
+use servidiot_primitives::{
+    block,
+    item::ItemStack,
+    metadata::{Metadata, MetadataItem},
+    player::{Experience, Gamemode, GamemodeType},
+    position::{BlockPosition, ChunkLocation, EntityLocation, Position},
+};
+use servidiot_yggdrasil::authenticate::Profile;
+
+use crate::{
+    entity::{
+        arrow::{ArrowComponent, ArrowEntity, BowChargingComponent, StuckArrowComponent, ARROW_GRAVITY},
+        effect::{self, ActiveEffectsComponent, EffectsDirty, HEALTH_PER_TICK},
+        experience::{XpOrbComponent, XpOrbEntity},
+        fishing::{FishingHookComponent, FishingHookEntity, FishingState},
+        health::{DamageSource, DeathMessages, FallDistanceComponent, FoodComponent, HealthComponent, HealthDirty, Invulnerable},
+        item::{self, ItemComponent},
+        mob::{MobCapConfig, MobCategory, NeverDespawn},
+        player::{AbilitiesDirty, ExperienceDirty, MetadataDirty, MovementFlags, PlayerAbilitiesComponent, PlayerMarker},
+        projectile::ProjectileComponent,
+        riding::{RidingComponent, RidingSynced},
+        sound::{AmbientSoundTimer, SoundProfile},
+        spawn_entity_at, EntityDispatch,
+    },
+    events::entity::{
+        BowDrawEvent, BowReleaseEvent, CollectItemEvent, DamageEvent, DeathEvent, DismountEvent, EntityAttackEvent,
+        EntityInteractEvent, EntityMoveEvent, FishingRodUseEvent, KillMessageEvent, ProjectileHitBlockEvent, ProjectileHitEntityEvent,
+    },
+    game::{EntityIdMap, GameState, TickCounter},
+    scheduler::{ScheduledTaskManager, TaskId},
+    scoreboard::ScoreboardManager,
+    systems::packet::{give_item, take_item},
+    world::{view::View, GameWorld},
+};
 
 pub mod player;
+
+/// The furthest, in blocks, an entity may move
+/// before it is teleported rather than moved.
+const MAX_RELATIVE_MOVE: f64 = 4.0;
+
+/// How far, in blocks, an entity may fall
+/// before landing hurts it.
+const SAFE_FALL_DISTANCE: f32 = 3.0;
+
 pub fn register_systems(s: &mut SystemExecutor<GameState>) {
     player::register_systems(s);
-    s.add_system(handle_entity_move);
+    s.add_system(handle_projectile)
+        .add_system(handle_projectile_hits)
+        .add_system(handle_fishing)
+        .add_system(handle_bow_charging)
+        .add_system(handle_entity_interact)
+        .add_system(handle_riding)
+        .add_system(handle_entity_move)
+        .add_system(handle_entity_attack)
+        .add_system(handle_damage)
+        .add_system(handle_death)
+        .add_system(handle_kill_message)
+        .add_system(handle_ambient_sounds)
+        .add_system(handle_potion_effects)
+        .add_system(handle_xp_orb)
+        .add_system(handle_collect_item)
+        .add_system(sync_experience)
+        .add_system(sync_health)
+        .add_system(sync_abilities)
+        .add_system(sync_metadata);
+}
+
+pub fn schedule_tasks(tasks: &mut ScheduledTaskManager) {
+    tasks.schedule_repeating(MOB_CAP_INTERVAL_TICKS, TaskId::Named("enforce mob cap"), enforce_mob_cap);
+    tasks.schedule_repeating(ITEM_MERGE_INTERVAL_TICKS, TaskId::Named("merge items"), handle_item_merge);
+}
+
+/// How close, in blocks, a projectile must
+/// pass to an entity to hit it.
+const PROJECTILE_HIT_RADIUS: f64 = 0.5;
+
+enum ProjectileHit {
+    Block(BlockPosition),
+    Entity(Entity),
+}
+
+/// Moves projectiles along their velocity, telling
+/// everyone when they hit a block or another entity.
+pub fn handle_projectile(state: &GameState) -> anyhow::Result<()> {
+    let mut moved = vec![];
+    let mut hits = vec![];
+    let mut landed = vec![];
+    {
+        let world = state.resource::<GameWorld>();
+        let mut ecs = state.ecs().borrow_mut();
+        let targets = ecs
+            .query::<&EntityLocation>()
+            .without::<&ProjectileComponent>()
+            .iter()
+            .map(|(entity, loc)| (entity, *loc))
+            .collect::<Vec<_>>();
+
+        for (entity, (loc, projectile, item)) in ecs.query_mut::<(&mut EntityLocation, &mut ProjectileComponent, Option<&ItemComponent>)>() {
+            let old_pos = loc.position;
+            *loc = loc.translate(projectile.velocity_x, projectile.velocity_y, projectile.velocity_z);
+            let new_pos = loc.position;
+            projectile.velocity_y -= projectile.gravity;
+
+            let block = new_pos.block();
+            if is_solid(&world, ChunkLocation::new(block.chunk(), loc.location), block) {
+                if item.is_some() {
+                    // Dropped items come to rest rather than breaking.
+                    loc.position = old_pos;
+                    landed.push(entity);
+                } else {
+                    hits.push((entity, ProjectileHit::Block(block)));
+                }
+                continue;
+            }
+
+            let target = targets.iter().filter(|_| item.is_none()).find(|(target, target_loc)| {
+                Some(*target) != projectile.shooter
+                    && target_loc.location == loc.location
+                    && target_loc.position.distance_to(&new_pos) <= PROJECTILE_HIT_RADIUS
+            });
+            if let Some((target, _)) = target {
+                hits.push((entity, ProjectileHit::Entity(*target)));
+                continue;
+            }
+            moved.push((entity, old_pos, new_pos));
+        }
+    }
+
+    let events = state.events().borrow();
+    for (entity, old_pos, new_pos) in moved {
+        events.post_event(state, EntityMoveEvent {
+            entity,
+            old_pos,
+            new_pos
+        })?;
+    }
+
+    let mut ecs = state.ecs().borrow_mut();
+    for entity in landed {
+        ecs.remove_one::<ProjectileComponent>(entity)?;
+    }
+    for (entity, hit) in hits {
+        match hit {
+            ProjectileHit::Block(position) => events.post_event(state, ProjectileHitBlockEvent { entity, position })?,
+            ProjectileHit::Entity(target) => events.post_event(state, ProjectileHitEntityEvent { projectile: entity, target })?,
+        }
+    }
+    Ok(())
+}
+
+/// How much damage an arrow deals for each
+/// block per tick it was flying at.
+const ARROW_DAMAGE_PER_SPEED: f64 = 2.0;
+
+/// Damages entities hit by arrows, and sticks arrows into the
+/// blocks they hit, freeing them again once that block is gone.
+/// Every other projectile is despawned when it hits something.
+pub fn handle_projectile_hits(state: &GameState) -> anyhow::Result<()> {
+    let server = state.resource::<Server>();
+    let events = state.events().borrow();
+    let mut ecs = state.ecs().borrow_mut();
+
+    for e in events.deferred_events::<ProjectileHitEntityEvent>() {
+        let Ok((x, y, z, shooter)) = ecs
+            .get::<&ProjectileComponent>(e.projectile)
+            .map(|v| (v.velocity_x, v.velocity_y, v.velocity_z, v.shooter))
+        else {
+            continue;
+        };
+        if ecs.get::<&ArrowComponent>(e.projectile).is_ok() {
+            let speed = (x * x + y * y + z * z).sqrt();
+            events.post_event(state, DamageEvent {
+                entity: e.target,
+                amount: (speed * ARROW_DAMAGE_PER_SPEED).ceil() as f32,
+                source: DamageSource::Projectile,
+                attacker: shooter
+            })?;
+        }
+        despawn_entity(state, &server, &mut ecs, e.projectile)?;
+    }
+
+    for e in events.deferred_events::<ProjectileHitBlockEvent>() {
+        if ecs.get::<&ArrowComponent>(e.entity).is_err() {
+            despawn_entity(state, &server, &mut ecs, e.entity)?;
+            continue;
+        }
+        ecs.remove_one::<ProjectileComponent>(e.entity)?;
+        ecs.insert_one(e.entity, StuckArrowComponent { block: e.position })?;
+        if let Ok(id) = ecs.get::<&NetworkID>(e.entity).map(|v| *v) {
+            for client in server.clients() {
+                if client.client_knows_entity(id) {
+                    client.send_entity_velocity(id, (0.0, 0.0, 0.0))?;
+                }
+            }
+        }
+    }
+
+    let world = state.resource::<GameWorld>();
+    let freed = ecs
+        .query::<(&EntityLocation, &StuckArrowComponent)>()
+        .iter()
+        .filter(|(_, (loc, stuck))| !is_solid(&world, ChunkLocation::new(stuck.block.chunk(), loc.location), stuck.block))
+        .map(|(entity, _)| entity)
+        .collect::<Vec<_>>();
+    for entity in freed {
+        ecs.remove_one::<StuckArrowComponent>(entity)?;
+        ecs.insert_one(entity, ProjectileComponent {
+            velocity_x: 0.0,
+            velocity_y: 0.0,
+            velocity_z: 0.0,
+            shooter: None,
+            gravity: ARROW_GRAVITY,
+        })?;
+    }
+    Ok(())
+}
+
+/// Despawns an entity, unloading it for every player who knows of it.
+fn despawn_entity(state: &GameState, server: &Server, ecs: &mut servidiot_ecs::World, entity: Entity) -> anyhow::Result<()> {
+    state.resource_mut::<GameWorld>().remove_entity(entity);
+    if let Ok(id) = ecs.get::<&NetworkID>(entity).map(|v| *v) {
+        state.resource_mut::<EntityIdMap>().remove(id);
+        for client in server.clients() {
+            if client.client_knows_entity(id) {
+                client.unload_entities(&[id])?;
+            }
+        }
+    }
+    ecs.despawn(entity)?;
+    Ok(())
+}
+
+/// How many ticks it takes to fully draw a bow.
+const BOW_CHARGE_TICKS: f32 = 20.0;
+/// The least a bow must be drawn, from 0 to 1, to shoot.
+const MIN_BOW_CHARGE: f32 = 0.1;
+/// How fast a fully drawn bow shoots arrows, in blocks per tick.
+const ARROW_SPEED: f64 = 3.0;
+/// The item ID of arrows.
+const ARROW: i16 = 262;
+
+/// Starts players with arrows drawing their bows, and shoots
+/// an arrow as fast as the bow was drawn when they let go.
+pub fn handle_bow_charging(state: &GameState) -> anyhow::Result<()> {
+    let server = state.resource::<Server>();
+    let current_tick = state.resource::<TickCounter>().current_tick();
+
+    for e in state.events().borrow().deferred_events::<BowDrawEvent>() {
+        let has_arrows = {
+            let ecs = state.ecs().borrow();
+            let Ok(player) = ecs.entity(e.player) else {
+                continue;
+            };
+            is_creative(player) || player.get::<&PlayerData>().is_some_and(|data| {
+                data.inventory
+                    .iter()
+                    .any(|v| (0..=35).contains(&v.slot) && v.stack_data.id == ARROW)
+            })
+        };
+        if has_arrows {
+            state.ecs().borrow_mut().insert_one(e.player, BowChargingComponent {
+                start_tick: current_tick
+            })?;
+        }
+    }
+
+    let mut shots = vec![];
+    for e in state.events().borrow().deferred_events::<BowReleaseEvent>() {
+        let Ok(charging) = state.ecs().borrow_mut().remove_one::<BowChargingComponent>(e.player) else {
+            continue;
+        };
+        let charge = ((current_tick - charging.start_tick) as f32 / BOW_CHARGE_TICKS).clamp(0.0, 1.0);
+        if charge < MIN_BOW_CHARGE {
+            continue;
+        }
+
+        let ecs = state.ecs().borrow();
+        let player = ecs.entity(e.player)?;
+        let shooter_id = *player.get::<&NetworkID>().unwrap();
+        if !is_creative(player) && !take_item(state, server.get_client(shooter_id)?, player, ARROW)? {
+            continue;
+        }
+
+        let mut loc = *player.get::<&EntityLocation>().unwrap();
+        let (yaw, pitch) = (f64::from(loc.position.yaw).to_radians(), f64::from(loc.position.pitch).to_radians());
+        loc.position.y += EYE_HEIGHT;
+        let speed = ARROW_SPEED * f64::from(charge);
+        shots.push((loc, ProjectileComponent {
+            velocity_x: -yaw.sin() * pitch.cos() * speed,
+            velocity_y: -pitch.sin() * speed,
+            velocity_z: yaw.cos() * pitch.cos() * speed,
+            shooter: Some(e.player),
+            gravity: ARROW_GRAVITY,
+        }, ArrowComponent { shooter_id }));
+    }
+
+    for (loc, projectile, arrow) in shots {
+        let velocity = (projectile.velocity_x, projectile.velocity_y, projectile.velocity_z);
+        let mut builder = EntityBuilder::new();
+        builder.add(EntityDispatch::new(ArrowEntity));
+        builder.add(projectile);
+        builder.add(arrow);
+        let entity = spawn_entity_at(state, builder, loc)?;
+
+        let ecs = state.ecs().borrow();
+        broadcast_nearby(state, &ecs, ecs.entity(entity)?, |client, id| client.send_entity_velocity(id, velocity))?;
+    }
+    Ok(())
+}
+
+fn is_creative(player: EntityRef) -> bool {
+    player.get::<&Gamemode>().is_some_and(|v| matches!(v.ty, GamemodeType::Creative))
+}
+
+/// How fast fishing hooks are cast, in blocks per tick.
+const HOOK_CAST_SPEED: f64 = 0.6;
+/// How much the vertical speed of fishing hooks falls each tick.
+const HOOK_GRAVITY: f64 = 0.04;
+/// How much of their speed fishing hooks keep each tick.
+const HOOK_DRAG: f64 = 0.92;
+/// How far above a player's feet their eyes are.
+const EYE_HEIGHT: f64 = 1.62;
+/// How far, in blocks, a hook may be from its caster
+/// before the line snaps.
+const MAX_LINE_LENGTH: f64 = 32.0;
+/// How much of the distance to a hook on the
+/// ground its caster is pulled, per tick.
+const HOOK_PULL: f64 = 0.1;
+/// The least and most ticks after a cast that a hook waits for a bite.
+const BITE_DELAY_TICKS: std::ops::RangeInclusive<u32> = 200..=600;
+
+const FLOWING_WATER: u16 = 8;
+const WATER: u16 = 9;
+/// The item ID of raw fish.
+const RAW_FISH: i16 = 349;
+
+/// Casts and reels in fishing hooks, moves hooks through the
+/// air until they land in water, and has fish bite them.
+pub fn handle_fishing(state: &GameState) -> anyhow::Result<()> {
+    let server = state.resource::<Server>();
+    let current_tick = state.resource::<TickCounter>().current_tick();
+    let mut rng = rand::thread_rng();
+
+    let mut casts = vec![];
+    let mut reeled = vec![];
+    let mut pulls = vec![];
+    for e in state.events().borrow().deferred_events::<FishingRodUseEvent>() {
+        let ecs = state.ecs().borrow();
+        let Ok(player) = ecs.entity(e.player) else {
+            continue;
+        };
+        let hook = ecs
+            .query::<&FishingHookComponent>()
+            .iter()
+            .find(|(_, v)| v.caster == e.player)
+            .map(|(hook, v)| (hook, v.state, v.velocity == (0.0, 0.0, 0.0), v.hook_position));
+        match hook {
+            Some((hook, hook_state, resting, hook_position)) => {
+                // A hook resting on the ground pulls its caster towards it.
+                if hook_state == FishingState::Flying && resting {
+                    let pos = player.get::<&EntityLocation>().unwrap().position;
+                    pulls.push((*player.get::<&NetworkID>().unwrap(), (
+                        (hook_position.x - pos.x) * HOOK_PULL,
+                        (hook_position.y - pos.y) * HOOK_PULL,
+                        (hook_position.z - pos.z) * HOOK_PULL,
+                    )));
+                }
+                reeled.push((hook, hook_state == FishingState::Biting));
+            }
+            None => {
+                let mut loc = *player.get::<&EntityLocation>().unwrap();
+                let (yaw, pitch) = (f64::from(loc.position.yaw).to_radians(), f64::from(loc.position.pitch).to_radians());
+                loc.position.y += EYE_HEIGHT;
+                casts.push((loc, FishingHookComponent {
+                    caster: e.player,
+                    caster_id: *player.get::<&NetworkID>().unwrap(),
+                    tick_cast: current_tick,
+                    hook_position: loc.position,
+                    velocity: (
+                        -yaw.sin() * pitch.cos() * HOOK_CAST_SPEED,
+                        -pitch.sin() * HOOK_CAST_SPEED,
+                        yaw.cos() * pitch.cos() * HOOK_CAST_SPEED,
+                    ),
+                    state: FishingState::Flying,
+                }));
+            }
+        }
+    }
+
+    for (id, velocity) in pulls {
+        server.get_client(id)?.send_entity_velocity(id, velocity)?;
+    }
+
+    for (loc, hook) in casts {
+        let mut builder = EntityBuilder::new();
+        builder.add(EntityDispatch::new(FishingHookEntity));
+        builder.add(hook);
+        spawn_entity_at(state, builder, loc)?;
+    }
+
+    let mut moved = vec![];
+    let mut bites = vec![];
+    {
+        let world = state.resource::<GameWorld>();
+        let mut ecs = state.ecs().borrow_mut();
+        let casters = ecs
+            .query::<&EntityLocation>()
+            .with::<&PlayerMarker>()
+            .iter()
+            .map(|(entity, loc)| (entity, *loc))
+            .collect::<HashMap<_, _>>();
+
+        for (entity, (loc, hook)) in ecs.query_mut::<(&mut EntityLocation, &mut FishingHookComponent)>() {
+            let snapped = casters.get(&hook.caster).is_none_or(|caster| {
+                caster.location != loc.location || caster.position.distance_to(&loc.position) > MAX_LINE_LENGTH
+            });
+            if snapped {
+                reeled.push((entity, false));
+                continue;
+            }
+
+            match hook.state {
+                FishingState::Flying => {
+                    let old_pos = loc.position;
+                    let (vx, vy, vz) = hook.velocity;
+                    let new_pos = loc.translate(vx, vy, vz).position;
+
+                    let block = new_pos.block();
+                    match world.block_at(loc.location, block).map(|(v, _)| v) {
+                        Some(id) if matches!(*id, FLOWING_WATER | WATER) => {
+                            hook.velocity = (0.0, 0.0, 0.0);
+                            // Time spent in the air counts towards the wait.
+                            let flown = u32::try_from(current_tick - hook.tick_cast).unwrap_or(u32::MAX);
+                            hook.state = FishingState::Settled(rng.gen_range(BITE_DELAY_TICKS).saturating_sub(flown));
+                        }
+                        Some(id) if block::is_solid(id) => {
+                            // Resting on the ground, where nothing bites.
+                            hook.velocity = (0.0, 0.0, 0.0);
+                            continue;
+                        }
+                        _ => hook.velocity = (vx * HOOK_DRAG, (vy - HOOK_GRAVITY) * HOOK_DRAG, vz * HOOK_DRAG),
+                    }
+                    if new_pos.y < 0.0 {
+                        reeled.push((entity, false));
+                        continue;
+                    }
+                    loc.position = new_pos;
+                    hook.hook_position = new_pos;
+                    moved.push((entity, old_pos, new_pos));
+                }
+                FishingState::Settled(0) => {
+                    hook.state = FishingState::Biting;
+                    bites.push(*loc);
+                }
+                FishingState::Settled(ticks) => hook.state = FishingState::Settled(ticks - 1),
+                FishingState::Biting => (),
+            }
+        }
+    }
+
+    let events = state.events().borrow();
+    for (entity, old_pos, new_pos) in moved {
+        events.post_event(state, EntityMoveEvent {
+            entity,
+            old_pos,
+            new_pos
+        })?;
+    }
+
+    {
+        let world = state.resource::<GameWorld>();
+        let ecs = state.ecs().borrow();
+        for loc in bites {
+            let chunk = std::iter::once(loc.position.chunk());
+            state.for_all_entities_nearby(&ecs, &world, loc.location, chunk, |other| {
+                if !other.has::<PlayerMarker>() {
+                    return Ok(());
+                }
+                let other_id = *other.get::<&NetworkID>().unwrap();
+                server.get_client(other_id)?.play_sound("random.splash", loc.position, 0.25, 1.0)
+            })?;
+        }
+    }
+
+    for (hook, caught) in reeled {
+        // Already gone if it was reeled in the same tick its line snapped.
+        if !state.ecs().borrow().contains(hook) {
+            continue;
+        }
+        if caught {
+            let ecs = state.ecs().borrow();
+            let caster = ecs.get::<&FishingHookComponent>(hook)?.caster;
+            if let Ok(player) = ecs.entity(caster) {
+                let client = server.get_client(*player.get::<&NetworkID>().unwrap())?;
+                let fish = ItemStack { count: 1, meta: 0, id: RAW_FISH, nbt_data: None };
+                // A full inventory loses the catch.
+                give_item(state, client, player, fish)?;
+            }
+        }
+        despawn_entity(state, &server, &mut state.ecs().borrow_mut(), hook)?;
+    }
+    Ok(())
+}
+
+/// Whether entities collide with the block at `block`.
+/// Blocks in unloaded chunks are never solid.
+fn is_solid(world: &GameWorld, chunk: ChunkLocation, block: BlockPosition) -> bool {
+    let Ok(y) = usize::try_from(block.y) else {
+        return false;
+    };
+    world
+        .get_chunk(chunk)
+        .and_then(|v| v.0.block_type_at((block.x & 15) as usize, y, (block.z & 15) as usize))
+        .is_some_and(block::is_solid)
+}
+
+/// Mounts players onto the living entities they right-click,
+/// unless that entity is already carrying someone or is
+/// riding the player themselves.
+pub fn handle_entity_interact(state: &GameState) -> anyhow::Result<()> {
+    let mut ecs = state.ecs().borrow_mut();
+    for e in state.events().borrow().deferred_events::<EntityInteractEvent>() {
+        if !matches!(e.interaction_type, InteractionType::Interact)
+            || ecs.get::<&HealthComponent>(e.target).is_err()
+            || ecs.get::<&RidingComponent>(e.actor).is_ok()
+        {
+            continue;
+        }
+        let taken = ecs
+            .query::<&RidingComponent>()
+            .iter()
+            .any(|(rider, riding)| riding.0 == e.target || (rider == e.target && riding.0 == e.actor));
+        if !taken {
+            ecs.insert_one(e.actor, RidingComponent(e.target))?;
+        }
+    }
+    Ok(())
+}
+
+/// Tells players when entities mount or dismount,
+/// and carries riders along with their vehicles.
+pub fn handle_riding(state: &GameState) -> anyhow::Result<()> {
+    let server = state.resource::<Server>();
+    let mut ecs = state.ecs().borrow_mut();
+
+    for e in state.events().borrow().deferred_events::<DismountEvent>() {
+        let _ = ecs.remove_one::<RidingComponent>(e.entity);
+    }
+
+    let mut changes = vec![];
+    for (rider, (riding, synced)) in ecs.query::<(Option<&RidingComponent>, Option<&RidingSynced>)>().iter() {
+        let vehicle = riding.map(|v| v.0).filter(|v| ecs.contains(*v));
+        if vehicle != synced.map(|v| v.0) {
+            changes.push((rider, vehicle));
+        }
+    }
+
+    for (rider, vehicle) in changes {
+        let rider_id = *ecs.get::<&NetworkID>(rider)?;
+        let vehicle_id = match vehicle {
+            Some(vehicle) => {
+                let id = *ecs.get::<&NetworkID>(vehicle)?;
+                ecs.insert_one(rider, RidingSynced(vehicle))?;
+                Some(id)
+            }
+            None => {
+                let _ = ecs.remove_one::<RidingComponent>(rider);
+                let _ = ecs.remove_one::<RidingSynced>(rider);
+                None
+            }
+        };
+        for client in server.clients() {
+            if client.id == rider_id || client.client_knows_entity(rider_id) {
+                client.send_attach_entity(rider_id, vehicle_id)?;
+            }
+        }
+    }
+
+    let mut moved = vec![];
+    let vehicles = ecs
+        .query::<&RidingSynced>()
+        .iter()
+        .filter_map(|(rider, synced)| Some((rider, ecs.get::<&EntityLocation>(synced.0).ok()?.position)))
+        .collect::<Vec<_>>();
+    for (rider, vehicle_pos) in vehicles {
+        let mut loc = ecs.get::<&mut EntityLocation>(rider)?;
+        let old_pos = loc.position;
+        loc.position.x = vehicle_pos.x;
+        loc.position.y = vehicle_pos.y;
+        loc.position.z = vehicle_pos.z;
+        if loc.position != old_pos {
+            moved.push((rider, old_pos, loc.position));
+        }
+    }
+    drop(ecs);
+
+    let events = state.events().borrow();
+    for (entity, old_pos, new_pos) in moved {
+        events.post_event(state, EntityMoveEvent {
+            entity,
+            old_pos,
+            new_pos
+        })?;
+    }
+    Ok(())
+}
+
+/// How much damage players deal when they hit
+/// another entity with their fist.
+const FIST_DAMAGE: f32 = 1.0;
+
+/// Damages entities which players hit, unless the
+/// target cannot be hurt.
+pub fn handle_entity_attack(state: &GameState) -> anyhow::Result<()> {
+    let events = state.events().borrow();
+    for e in events.deferred_events::<EntityAttackEvent>() {
+        let immune = {
+            let ecs = state.ecs().borrow();
+            let Ok(target) = ecs.entity(e.target) else {
+                continue;
+            };
+            target.has::<Invulnerable>() || is_creative(target)
+        };
+        if immune {
+            continue;
+        }
+        events.post_event(state, DamageEvent {
+            entity: e.target,
+            amount: FIST_DAMAGE,
+            source: DamageSource::Attack,
+            attacker: Some(e.attacker)
+        })?;
+    }
+    Ok(())
+}
+
+/// Takes health from entities which were damaged,
+/// playing the hurt animation to nearby players.
+pub fn handle_damage(state: &GameState) -> anyhow::Result<()> {
+    let mut ecs = state.ecs().borrow_mut();
+    let events = state.events().borrow();
+
+    let mut damaged = vec![];
+    for e in events.deferred_events::<DamageEvent>() {
+        let Ok(mut health) = ecs.get::<&mut HealthComponent>(e.entity) else {
+            continue;
+        };
+        let was_alive = health.current > 0.0;
+        health.current = (health.current - e.amount).max(0.0);
+        if was_alive && health.current == 0.0 {
+            events.post_event(state, DeathEvent {
+                entity: e.entity,
+                source: e.source,
+                killer: e.attacker
+            })?;
+        }
+        damaged.push(e.entity);
+    }
+
+    for entity in &damaged {
+        ecs.insert_one(*entity, HealthDirty)?;
+    }
+    drop(ecs);
+
+    let ecs = state.ecs().borrow();
+    for entity in damaged {
+        broadcast_entity_status(state, &ecs, ecs.entity(entity)?, EntityStatusKind::HurtAnimation)?;
+    }
+    Ok(())
+}
+
+/// How many experience points a dying player drops for each of their levels.
+const DEATH_XP_PER_LEVEL: i32 = 7;
+/// The most experience points a dying player drops.
+const MAX_DEATH_XP: i32 = 100;
+
+/// Plays the death animation of entities which died to nearby
+/// players, tells everyone how players died, and drops the
+/// experience of dead players as an orb.
+pub fn handle_death(state: &GameState) -> anyhow::Result<()> {
+    let ecs = state.ecs().borrow();
+    let events = state.events().borrow();
+    let messages = state.resource::<DeathMessages>();
+    let mut dropped_experience = vec![];
+    for e in events.deferred_events::<DeathEvent>() {
+        let Ok(entity) = ecs.entity(e.entity) else {
+            continue;
+        };
+        broadcast_entity_status(state, &ecs, entity, EntityStatusKind::DeathAnimation)?;
+
+        if let Some(mut experience) = entity.get::<&mut Experience>() {
+            let value = (experience.level * DEATH_XP_PER_LEVEL).min(MAX_DEATH_XP);
+            *experience = Experience::default();
+            dropped_experience.push((e.entity, *entity.get::<&EntityLocation>().unwrap(), value));
+        }
+
+        let Some(killed) = entity.get::<&Arc<Profile>>().map(|v| v.name.clone()) else {
+            continue;
+        };
+        let killer = e.killer.and_then(|v| ecs.get::<&Arc<Profile>>(v).ok().map(|v| v.name.clone()));
+        events.post_event(state, KillMessageEvent {
+            killed: e.entity,
+            killer: e.killer,
+            death_message: messages.format(e.source, &killed, killer.as_deref())
+        })?;
+    }
+    drop(ecs);
+
+    for (entity, loc, value) in dropped_experience {
+        state.ecs().borrow_mut().insert_one(entity, ExperienceDirty)?;
+        if value > 0 {
+            let mut builder = EntityBuilder::new();
+            builder.add(EntityDispatch::new(XpOrbEntity));
+            builder.add(XpOrbComponent {
+                value: value as i16,
+                lifetime_ticks: 0,
+            });
+            spawn_entity_at(state, builder, loc)?;
+        }
+    }
+    Ok(())
+}
+
+/// The scoreboard objective counting
+/// how many players each player killed.
+const KILL_COUNT_OBJECTIVE: &str = "KillCount";
+
+/// Adds the `KillCount` objective, shown in the player list.
+pub fn add_kill_count_objective(scoreboard: &mut ScoreboardManager) {
+    scoreboard.add_objective(KILL_COUNT_OBJECTIVE, "Kills");
+    scoreboard.set_display(ScoreboardDisplay::LIST, KILL_COUNT_OBJECTIVE);
+}
+
+/// Shows death messages in chat, counting
+/// kills on the `KillCount` objective.
+pub fn handle_kill_message(state: &GameState) -> anyhow::Result<()> {
+    let ecs = state.ecs().borrow();
+    let mut scoreboard = state.resource_mut::<ScoreboardManager>();
+    for e in state.events().borrow().deferred_events::<KillMessageEvent>() {
+        state.broadcast_message(&e.death_message)?;
+        let killer = e.killer.filter(|v| *v != e.killed);
+        if let Some(profile) = killer.and_then(|v| ecs.get::<&Arc<Profile>>(v).ok()) {
+            scoreboard.increment(KILL_COUNT_OBJECTIVE, &profile.name, 1);
+        }
+    }
+    Ok(())
+}
+
+/// Sends an entity status to every player near
+/// some entity, including the entity itself.
+fn broadcast_entity_status(state: &GameState, ecs: &servidiot_ecs::World, entity: EntityRef, status: EntityStatusKind) -> anyhow::Result<()> {
+    broadcast_nearby(state, ecs, entity, |client, id| client.send_entity_status(id, status))
+}
+
+/// Calls `f` with the client of every player near some
+/// entity, including the entity itself, and the entity's ID.
+fn broadcast_nearby(state: &GameState, ecs: &servidiot_ecs::World, entity: EntityRef, mut f: impl FnMut(&Client, NetworkID) -> anyhow::Result<()>) -> anyhow::Result<()> {
+    let Some(id) = entity.get::<&NetworkID>().map(|v| *v) else {
+        return Ok(());
+    };
+    let server = state.resource::<Server>();
+    let world = state.resource::<GameWorld>();
+    let loc = *entity.get::<&EntityLocation>().unwrap();
+    let view = View::new(loc.position.chunk(), 8);
+
+    state.for_all_entities_nearby(ecs, &world, loc.location, view.chunks().into_iter(), |other| {
+        if !other.has::<PlayerMarker>() {
+            return Ok(());
+        }
+        let other_id = *other.get::<&NetworkID>().unwrap();
+        f(server.get_client(other_id)?, id)
+    })
+}
+
+/// Counts down active potion effects, applying regeneration and
+/// poison to living entities, and shows players effects as they end.
+pub fn handle_potion_effects(state: &GameState) -> anyhow::Result<()> {
+    let mut shown = vec![];
+    let mut removed = vec![];
+    let mut hurt_or_healed = vec![];
+    {
+        let mut ecs = state.ecs().borrow_mut();
+        for (entity, effects) in ecs
+            .query::<&ActiveEffectsComponent>()
+            .with::<&EffectsDirty>()
+            .iter()
+        {
+            shown.extend(effects.0.iter().map(|v| (entity, v.id, v.level, v.duration)));
+        }
+
+        for (entity, (effects, mut health)) in ecs
+            .query::<(&mut ActiveEffectsComponent, Option<&mut HealthComponent>)>()
+            .iter()
+        {
+            for effect in &mut effects.0 {
+                if let Some(health) = health.as_deref_mut().filter(|v| v.current > 0.0) {
+                    let before = health.current;
+                    match effect.id {
+                        effect::REGENERATION => health.current = (health.current + HEALTH_PER_TICK).min(health.max),
+                        effect::POISON if health.current > 1.0 => health.current = (health.current - HEALTH_PER_TICK).max(1.0),
+                        _ => (),
+                    }
+                    if health.current != before {
+                        hurt_or_healed.push(entity);
+                    }
+                }
+                effect.duration -= 1;
+                if effect.duration <= 0 {
+                    removed.push((entity, effect.id));
+                }
+            }
+            effects.0.retain(|v| v.duration > 0);
+        }
+
+        for (entity, ..) in &shown {
+            let _ = ecs.remove_one::<EffectsDirty>(*entity);
+        }
+        hurt_or_healed.dedup();
+        for entity in hurt_or_healed {
+            ecs.insert_one(entity, HealthDirty)?;
+        }
+    }
+
+    let ecs = state.ecs().borrow();
+    let server = state.resource::<Server>();
+    for (entity, effect_id, amplifier, duration) in shown {
+        let id = *ecs.get::<&NetworkID>(entity)?;
+        server.get_client(id)?.send_entity_effect(id, effect_id, amplifier, duration)?;
+    }
+    for (entity, effect_id) in removed {
+        broadcast_nearby(state, &ecs, ecs.entity(entity)?, |client, id| {
+            client.send_remove_entity_effect(id, effect_id)
+        })?;
+    }
+    Ok(())
+}
+
+/// How far, in blocks, experience orbs are drawn to players from.
+const XP_ORB_ATTRACT_RANGE: f64 = 6.0;
+/// How fast experience orbs are drawn to players, in blocks per tick.
+const XP_ORB_ATTRACT_SPEED: f64 = 0.25;
+/// How close, in blocks, a player must be to collect an orb.
+const XP_ORB_PICKUP_RANGE: f64 = 1.0;
+/// How many ticks an uncollected experience orb lasts.
+const XP_ORB_LIFETIME_TICKS: u32 = 6000;
+
+/// Draws experience orbs towards the nearest living player close by,
+/// firing a `CollectItemEvent` for the orbs players reach, and
+/// despawns old orbs.
+pub fn handle_xp_orb(state: &GameState) -> anyhow::Result<()> {
+    let mut moved = vec![];
+    let mut collected = vec![];
+    let mut expired = vec![];
+    {
+        let mut ecs = state.ecs().borrow_mut();
+        // Dead players would collect the experience they dropped.
+        let players = ecs
+            .query::<(&EntityLocation, &HealthComponent)>()
+            .with::<&PlayerMarker>()
+            .iter()
+            .filter(|(_, (_, health))| health.current > 0.0)
+            .map(|(entity, (loc, _))| (entity, *loc))
+            .collect::<Vec<_>>();
+
+        for (entity, (loc, orb)) in ecs.query_mut::<(&mut EntityLocation, &mut XpOrbComponent)>() {
+            orb.lifetime_ticks += 1;
+            if orb.lifetime_ticks > XP_ORB_LIFETIME_TICKS {
+                expired.push(entity);
+                continue;
+            }
+
+            let nearest = players
+                .iter()
+                .filter(|(_, v)| v.location == loc.location)
+                .map(|(player, v)| (*player, v.position, v.position.distance_to(&loc.position)))
+                .filter(|(.., distance)| *distance <= XP_ORB_ATTRACT_RANGE)
+                .min_by(|a, b| a.2.total_cmp(&b.2));
+            let Some((player, target, distance)) = nearest else {
+                continue;
+            };
+            if distance <= XP_ORB_PICKUP_RANGE {
+                collected.push((entity, player));
+                continue;
+            }
+
+            let old_pos = loc.position;
+            let step = XP_ORB_ATTRACT_SPEED / distance;
+            *loc = loc.translate(
+                (target.x - old_pos.x) * step,
+                (target.y - old_pos.y) * step,
+                (target.z - old_pos.z) * step,
+            );
+            moved.push((entity, old_pos, loc.position));
+        }
+    }
+
+    let events = state.events().borrow();
+    for (entity, old_pos, new_pos) in moved {
+        events.post_event(state, EntityMoveEvent {
+            entity,
+            old_pos,
+            new_pos
+        })?;
+    }
+
+    for (orb, player) in collected {
+        events.post_event(state, CollectItemEvent {
+            collector: player,
+            collected: orb
+        })?;
+    }
+
+    let server = state.resource::<Server>();
+    let mut ecs = state.ecs().borrow_mut();
+    for orb in expired {
+        despawn_entity(state, &server, &mut ecs, orb)?;
+    }
+    Ok(())
+}
+
+/// Gives players the experience in the orbs they collected,
+/// showing the orb flying to them, then despawns the orbs.
+pub fn handle_collect_item(state: &GameState) -> anyhow::Result<()> {
+    let collected = state.events().borrow().deferred_events::<CollectItemEvent>().collect::<Vec<_>>();
+    for e in &collected {
+        let ecs = state.ecs().borrow();
+        let (Ok(collector), Ok(orb)) = (ecs.entity(e.collector), ecs.entity(e.collected)) else {
+            continue;
+        };
+        let (Some(collector_id), Some(value)) = (collector.get::<&NetworkID>().map(|v| *v), orb.get::<&XpOrbComponent>().map(|v| v.value)) else {
+            continue;
+        };
+        if let Some(mut experience) = collector.get::<&mut Experience>() {
+            experience.add_points(i32::from(value));
+        }
+        broadcast_nearby(state, &ecs, orb, |client, id| client.send_collect_item(id, collector_id))?;
+    }
+
+    let server = state.resource::<Server>();
+    let mut ecs = state.ecs().borrow_mut();
+    for e in collected {
+        if !ecs.contains(e.collected) {
+            continue;
+        }
+        if ecs.contains(e.collector) {
+            ecs.insert_one(e.collector, ExperienceDirty)?;
+        }
+        despawn_entity(state, &server, &mut ecs, e.collected)?;
+    }
+    Ok(())
+}
+
+/// How many ticks apart nearby dropped items are merged.
+const ITEM_MERGE_INTERVAL_TICKS: u64 = 40;
+/// How close, in blocks, dropped items must be to merge.
+const ITEM_MERGE_RADIUS: f64 = 0.5;
+/// The most dropped items one item takes from each run,
+/// so piles of items do not all compare against each other.
+const MAX_MERGES_PER_ITEM: usize = 5;
+
+/// Moves the items of dropped items into identical dropped items
+/// next to them, up to a full stack, despawning those left empty.
+pub fn handle_item_merge(state: &GameState) -> anyhow::Result<()> {
+    let mut changed: HashMap<Entity, ItemStack> = HashMap::new();
+    let mut emptied = HashSet::new();
+    {
+        let world = state.resource::<GameWorld>();
+        let ecs = state.ecs().borrow();
+        let items = ecs
+            .query::<(&ItemComponent, &EntityLocation)>()
+            .iter()
+            .map(|(entity, (item, loc))| (entity, item.0.clone(), *loc))
+            .collect::<Vec<_>>();
+
+        for (entity, stack, loc) in items {
+            if emptied.contains(&entity) {
+                continue;
+            }
+            let mut stack = changed.get(&entity).cloned().unwrap_or(stack);
+            let mut merges = 0;
+            let view = View::new(loc.position.chunk(), 1);
+            state.for_all_entities_nearby(&ecs, &world, loc.location, view.chunks().into_iter(), |other| {
+                let other_entity = other.entity();
+                if merges == MAX_MERGES_PER_ITEM
+                    || stack.count >= item::max_stack_size(stack.id)
+                    || other_entity == entity
+                    || emptied.contains(&other_entity)
+                {
+                    return Ok(());
+                }
+                if !other.get::<&EntityLocation>().is_some_and(|v| v.position.distance_to(&loc.position) <= ITEM_MERGE_RADIUS) {
+                    return Ok(());
+                }
+                let Some(mut other_stack) = changed
+                    .get(&other_entity)
+                    .cloned()
+                    .or_else(|| other.get::<&ItemComponent>().map(|v| v.0.clone()))
+                else {
+                    return Ok(());
+                };
+                if (other_stack.id, other_stack.meta, &other_stack.nbt_data) != (stack.id, stack.meta, &stack.nbt_data) {
+                    return Ok(());
+                }
+
+                let moved = other_stack.count.min(item::max_stack_size(stack.id) - stack.count);
+                stack.count += moved;
+                other_stack.count -= moved;
+                merges += 1;
+                if other_stack.count <= 0 {
+                    changed.remove(&other_entity);
+                    emptied.insert(other_entity);
+                } else {
+                    changed.insert(other_entity, other_stack);
+                }
+                Ok(())
+            })?;
+            if merges > 0 {
+                changed.insert(entity, stack);
+            }
+        }
+    }
+
+    let server = state.resource::<Server>();
+    let mut ecs = state.ecs().borrow_mut();
+    for (entity, stack) in changed {
+        if let Ok(mut item) = ecs.get::<&mut ItemComponent>(entity) {
+            item.0 = stack.clone();
+        }
+        broadcast_nearby(state, &ecs, ecs.entity(entity)?, |client, id| client.send_metadata(id, item::item_metadata(stack.clone())))?;
+    }
+    for entity in emptied {
+        despawn_entity(state, &server, &mut ecs, entity)?;
+    }
+    Ok(())
+}
+
+/// How many ticks apart mob caps are enforced.
+const MOB_CAP_INTERVAL_TICKS: u64 = 200;
+
+/// Despawns mobs over the cap of their category, farthest from
+/// any player first. Mobs near a player or marked `NeverDespawn`
+/// are kept, even if that leaves their category over its cap.
+pub fn enforce_mob_cap(state: &GameState) -> anyhow::Result<()> {
+    let caps = state.resource::<MobCapConfig>();
+    let despawn_distance = f64::from(caps.despawn_distance_chunks) * 16.0;
+
+    let mut despawned = vec![];
+    {
+        let ecs = state.ecs().borrow();
+        let players = ecs
+            .query::<&EntityLocation>()
+            .with::<&PlayerMarker>()
+            .iter()
+            .map(|(_, loc)| *loc)
+            .collect::<Vec<_>>();
+        let nearest_player = |loc: &EntityLocation| {
+            players
+                .iter()
+                .filter(|v| v.location == loc.location)
+                .map(|v| v.position.distance_to(&loc.position))
+                .fold(f64::INFINITY, f64::min)
+        };
+
+        for category in [MobCategory::Hostile, MobCategory::Passive] {
+            let mut mobs = ecs
+                .query::<(&MobCategory, &EntityLocation, Option<&NeverDespawn>)>()
+                .iter()
+                .filter(|(_, (v, ..))| **v == category)
+                .map(|(entity, (_, loc, never))| (entity, nearest_player(loc), never.is_some()))
+                .collect::<Vec<_>>();
+            let excess = mobs.len().saturating_sub(caps.cap(category));
+            if excess == 0 {
+                continue;
+            }
+
+            mobs.retain(|(_, distance, never)| !never && *distance > despawn_distance);
+            mobs.sort_by(|a, b| b.1.total_cmp(&a.1));
+            despawned.extend(mobs.into_iter().take(excess).map(|(entity, ..)| entity));
+        }
+    }
+
+    let server = state.resource::<Server>();
+    let mut ecs = state.ecs().borrow_mut();
+    for entity in despawned {
+        despawn_entity(state, &server, &mut ecs, entity)?;
+    }
+    Ok(())
+}
+
+/// How far away, in blocks, players can hear ambient sounds.
+const AMBIENT_SOUND_RANGE: f64 = 16.0;
+
+/// Plays the ambient sounds of entities whose timer has run out
+/// to the players near them, then schedules their next sound.
+pub fn handle_ambient_sounds(state: &GameState) -> anyhow::Result<()> {
+    let server = state.resource::<Server>();
+    let world = state.resource::<GameWorld>();
+    let current_tick = state.resource::<TickCounter>().current_tick();
+    let mut rng = rand::thread_rng();
+
+    let mut sounds = vec![];
+    {
+        let mut ecs = state.ecs().borrow_mut();
+        for (_, (timer, profile, loc, _)) in ecs.query_mut::<(&mut AmbientSoundTimer, &SoundProfile, &EntityLocation, &NetworkID)>() {
+            if current_tick < timer.next_tick {
+                continue;
+            }
+            let offset = rng.gen_range(0..=timer.interval_ticks);
+            timer.next_tick = current_tick + u64::from(timer.interval_ticks) + u64::from(offset);
+            sounds.push((profile.ambient.clone(), *loc));
+        }
+    }
+
+    let ecs = state.ecs().borrow();
+    for (sound, loc) in sounds {
+        // The same variation in pitch as vanilla mobs.
+        let pitch = (rng.gen::<f32>() - rng.gen::<f32>()) * 0.2 + 1.0;
+        let chunk = std::iter::once(loc.position.chunk());
+        state.for_all_entities_nearby(&ecs, &world, loc.location, chunk, |other| {
+            if !other.has::<PlayerMarker>() {
+                return Ok(());
+            }
+            let other_pos = other.get::<&EntityLocation>().unwrap().position;
+            if other_pos.distance_to(&loc.position) > AMBIENT_SOUND_RANGE {
+                return Ok(());
+            }
+            server.get_client(*other.get::<&NetworkID>().unwrap())?.play_sound(&sound, loc.position, 1.0, pitch)
+        })?;
+    }
+    Ok(())
+}
+
+/// Sends changed experience to its player.
+pub fn sync_experience(state: &GameState) -> anyhow::Result<()> {
+    let server = state.resource::<Server>();
+    let mut ecs = state.ecs().borrow_mut();
+
+    let mut synced = vec![];
+    for (entity, (id, experience)) in ecs
+        .query::<(&NetworkID, &Experience)>()
+        .with::<&ExperienceDirty>()
+        .iter()
+    {
+        server.get_client(*id)?.send_experience(*experience)?;
+        synced.push(entity);
+    }
+
+    for entity in synced {
+        ecs.remove_one::<ExperienceDirty>(entity)?;
+    }
+    Ok(())
+}
+
+/// Sends players their health and food when either changes.
+pub fn sync_health(state: &GameState) -> anyhow::Result<()> {
+    let server = state.resource::<Server>();
+    let mut ecs = state.ecs().borrow_mut();
+
+    let mut synced = vec![];
+    for (entity, (id, health, food)) in ecs
+        .query::<(&NetworkID, &HealthComponent, &FoodComponent)>()
+        .with::<&HealthDirty>()
+        .iter()
+    {
+        server
+            .get_client(*id)?
+            .send_health_update(health.current, food.food, food.saturation)?;
+        synced.push(entity);
+    }
+
+    for entity in synced {
+        ecs.remove_one::<HealthDirty>(entity)?;
+    }
+    Ok(())
+}
+
+/// Sends players their abilities when they change,
+/// such as on joining or switching gamemode.
+pub fn sync_abilities(state: &GameState) -> anyhow::Result<()> {
+    let server = state.resource::<Server>();
+    let mut ecs = state.ecs().borrow_mut();
+
+    let mut synced = vec![];
+    for (entity, (id, abilities)) in ecs
+        .query::<(&NetworkID, &PlayerAbilitiesComponent)>()
+        .with::<&AbilitiesDirty>()
+        .iter()
+    {
+        server
+            .get_client(*id)?
+            .send_abilities(abilities.flags(), abilities.fly_speed, abilities.walk_speed)?;
+        synced.push(entity);
+    }
+
+    for entity in synced {
+        ecs.remove_one::<AbilitiesDirty>(entity)?;
+    }
+    Ok(())
+}
+
+/// Shows players near a player that they
+/// started or stopped crouching or sprinting.
+pub fn sync_metadata(state: &GameState) -> anyhow::Result<()> {
+    let mut ecs = state.ecs().borrow_mut();
+    let dirty = ecs
+        .query::<&MovementFlags>()
+        .with::<&MetadataDirty>()
+        .iter()
+        .map(|(entity, movement)| (entity, *movement))
+        .collect::<Vec<_>>();
+
+    for (entity, movement) in dirty {
+        broadcast_nearby(state, &ecs, ecs.entity(entity)?, |client, id| {
+            let mut meta = Metadata::default();
+            meta.insert(MovementFlags::METADATA_KEY, MetadataItem::Byte(movement.flags()));
+            client.send_metadata(id, meta)
+        })?;
+        ecs.remove_one::<MetadataDirty>(entity)?;
+    }
+    Ok(())
 }
 
 pub fn handle_entity_move(state: &GameState) -> anyhow::Result<()> {
@@ -16,6 +1236,7 @@ pub fn handle_entity_move(state: &GameState) -> anyhow::Result<()> {
     for e in state.events().borrow().deferred_events::<EntityMoveEvent>() {
 
         let this_entity = ecs.entity(e.entity)?;
+        handle_fall_damage(state, this_entity, e.old_pos, e.new_pos)?;
     
         let loc = this_entity.get::<&EntityLocation>().unwrap().location;
         let this_id = *this_entity.get::<&NetworkID>().unwrap();
@@ -29,8 +1250,8 @@ pub fn handle_entity_move(state: &GameState) -> anyhow::Result<()> {
         let old_chunks = old_view.chunks();
         let new_chunks = new_view.chunks();
 
-        let server = state.resources().get::<Server>();
-        let world = state.resources().get::<GameWorld>();
+        let server = state.resource::<Server>();
+        let mut world = state.resource_mut::<GameWorld>();
         if e.old_pos.chunk() != e.new_pos.chunk() {
             // crossed a chunk boundary
 
@@ -38,6 +1259,10 @@ pub fn handle_entity_move(state: &GameState) -> anyhow::Result<()> {
 
 
                 let we_are_player = this_entity.has::<PlayerMarker>();
+                if !we_are_player {
+                    let position = this_entity.get::<&EntityLocation>().unwrap().position;
+                    world.set_entity_chunk(e.entity, ChunkLocation::new(position.chunk(), loc));
+                }
                 if we_are_player {
                     state.unload_entities_for(&ecs, &server, &world, this_entity, loc, old_chunks.difference(&new_chunks).copied())?;
                 }
@@ -47,6 +1272,11 @@ pub fn handle_entity_move(state: &GameState) -> anyhow::Result<()> {
 
         }
 
+        // Entities only track one yaw, so a turn without
+        // moving or looking up or down is sent as a head turn.
+        let head_only = e.old_pos.distance_squared_to(&e.new_pos) == 0.0
+            && e.old_pos.pitch == e.new_pos.pitch
+            && e.old_pos.yaw != e.new_pos.yaw;
         state.for_all_entities_nearby(&ecs, &world, loc, new_chunks.iter().copied(), |other| {
             if other.entity() == this_entity.entity() {
                 return Ok(());
@@ -54,7 +1284,16 @@ pub fn handle_entity_move(state: &GameState) -> anyhow::Result<()> {
             if other.has::<PlayerMarker>() {
                 let other_id = *other.get::<&NetworkID>().unwrap();
                 let other = server.get_client(other_id)?;
-                other.send_position(this_id, e.new_pos)?;
+                if head_only {
+                    other.send_head_look(this_id, e.new_pos.yaw)?;
+                } else if e.old_pos.distance_to(&e.new_pos) > MAX_RELATIVE_MOVE {
+                    other.send_position(this_id, e.new_pos)?;
+                } else {
+                    other.send_relative_move(this_id, e.old_pos, e.new_pos)?;
+                }
+                if !head_only && e.old_pos.yaw != e.new_pos.yaw {
+                    other.send_head_look(this_id, e.new_pos.yaw)?;
+                }
             }
             Ok(())
         })?;
@@ -62,4 +1301,33 @@ pub fn handle_entity_move(state: &GameState) -> anyhow::Result<()> {
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Adds downward movement to an entity's fall distance,
+/// damaging it when it lands after falling too far.
+pub fn handle_fall_damage(state: &GameState, entity: EntityRef, old_pos: Position, new_pos: Position) -> anyhow::Result<()> {
+    let Some(mut fall_distance) = entity.get::<&mut FallDistanceComponent>() else {
+        return Ok(());
+    };
+    if new_pos.y < old_pos.y {
+        fall_distance.0 += (old_pos.y - new_pos.y) as f32;
+    }
+    if !new_pos.on_ground {
+        return Ok(());
+    }
+
+    let distance = std::mem::take(&mut fall_distance.0);
+    let immune = entity.has::<Invulnerable>()
+        || entity
+            .get::<&Gamemode>()
+            .is_some_and(|v| matches!(v.ty, GamemodeType::Creative));
+    if distance > SAFE_FALL_DISTANCE && !immune {
+        state.events().borrow().post_event(state, DamageEvent {
+            entity: entity.entity(),
+            amount: (distance - SAFE_FALL_DISTANCE).floor(),
+            source: DamageSource::Fall,
+            attacker: None
+        })?;
+    }
+    Ok(())
+}
@@ -11,8 +11,8 @@ pub fn register_systems(s: &mut SystemExecutor<GameState>) {
 pub fn handle_view_change(state: &GameState) -> anyhow::Result<()> {
 
     let ecs = state.ecs().borrow();
-    let server = state.resources().get::<Server>();
-    let mut world = state.resources().get_mut::<GameWorld>();
+    let server = state.resource::<Server>();
+    let mut world = state.resource_mut::<GameWorld>();
     for e in state.events().borrow().deferred_events::<PlayerViewChangeEvent>() {
         let player = ecs.entity(e.entity)?;
         let client = server.get_client(*player.get::<&NetworkID>().unwrap())?;
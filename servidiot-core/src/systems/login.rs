@@ -1,39 +1,84 @@
-use servidiot_ecs::{EntityBuilder, SystemExecutor};
-use servidiot_network::{server::Server, io::packet::client::play::ClientSettings};
+use std::sync::Arc;
+
+use rand::Rng;
+use servidiot_anvil::{nbt::{level::LevelData, player::PlayerData}, WorldManager};
+use servidiot_ecs::{EntityBuilder, EntityRef, SystemExecutor};
+use servidiot_network::{server::{id::NetworkID, Client, Server}, io::packet::client::play::ClientSettings};
 use servidiot_primitives::{
-    player::{Gamemode, GamemodeType},
+    item::InventorySlot,
+    player::{Experience, Gamemode, GamemodeType},
     position::{ChunkLocation, ChunkPosition, Location, Position, EntityLocation},
 };
+use servidiot_yggdrasil::authenticate::Profile;
 
 use crate::{
-    entity::{player::{PlayerEntity, PlayerMarker}, EntityDispatch},
-    game::{GameState, ClientMap},
+    access::{BanList, OpList, Whitelist},
+    entity::{container::CursorItem, effect::{ActiveEffectsComponent, EffectsDirty}, health::{FallDistanceComponent, FoodComponent, HealthComponent, HealthDirty, Invulnerable}, player::{AbilitiesDirty, AntiCheatViolations, ExperienceDirty, HeldItemSlot, MovementFlags, PlayerAbilitiesComponent, Operator, PlayerEntity, PlayerMarker, SignEditor, ValidatedPosition}, EntityDispatch},
+    events::entity::{OperatorStatusEvent, RespawnEvent},
+    game::{GameState, ClientMap, EntityIdMap, ShutdownState, TickCounter},
+    scheduler::{ScheduledTaskManager, TaskId},
+    systems::packet::{self, window_slots},
     world::{GameWorld, view::View},
+    Config,
 };
 
+/// The brand sent to clients, shown in their debug screen.
+const SERVER_BRAND: &str = "servidiot";
+
 pub fn register_systems(s: &mut SystemExecutor<GameState>) {
     s.add_system(handle_new_clients)
-        .add_system(handle_disconnected_clients);
+        .add_system(handle_disconnected_clients)
+        .add_system(handle_respawn)
+        .add_system(handle_operator_status)
+        .add_system(send_keepalives)
+        .add_system(handle_server_restart);
+}
+
+pub fn schedule_tasks(tasks: &mut ScheduledTaskManager, config: &Config) {
+    tasks.schedule_repeating(config.tps.get(), TaskId::Named("broadcast latency"), broadcast_latency);
 }
 
 pub fn handle_new_clients(state: &GameState) -> anyhow::Result<()> {
     let mut sync_entities = vec![];
     {
-        let mut server = state.resources().get_mut::<Server>();
-        let mut world = state.resources().get_mut::<GameWorld>();
-        let mut map = state.resources().get_mut::<ClientMap>();
+        let mut server = state.resource_mut::<Server>();
+        let mut world = state.resource_mut::<GameWorld>();
+        let mut map = state.resource_mut::<ClientMap>();
+        let whitelist = state.resource::<Whitelist>();
+        let bans = state.resource::<BanList>();
+        let ops = state.resource::<OpList>();
+        let world_manager = state.resource::<WorldManager>();
+        let config = state.resource::<Arc<Config>>();
+        let shutdown = *state.resource::<ShutdownState>();
+        let level = state.resource::<Option<LevelData>>();
     
     
         for client_id in server.accept_clients() {
             let client = server.get_client(client_id)?;
             tracing::info!("New client connected: {:?}", client.profile.name);
+
+            if shutdown != ShutdownState::Running {
+                client.kick(RESTART_MESSAGE)?;
+                continue;
+            }
+
+            if let Some(reason) = bans.is_banned(client.profile.id) {
+                tracing::info!("Kicking {}: banned", client.profile.name);
+                client.kick(&format!("You are banned from this server.\nReason: {reason}"))?;
+                continue;
+            }
+            if !whitelist.is_allowed(client.profile.id) {
+                tracing::info!("Kicking {}: not whitelisted", client.profile.name);
+                client.kick("You are not white-listed on this server!")?;
+                continue;
+            }
     
     
             let position = Position::new(0.0, 128.0, 0.0, 0.0, 0.0, false);
     
             let settings = ClientSettings {
                 locale: "en-US".to_string(),
-                view_distance: 4,
+                view_distance: config.clamp_view_distance(i8::MAX),
                 chat_flags: 0,
                 chat_colours: true,
                 difficuty: 0,
@@ -49,9 +94,66 @@ pub fn handle_new_clients(state: &GameState) -> anyhow::Result<()> {
             builder.add(EntityDispatch::new(PlayerEntity));
             builder.add(EntityLocation {
                 position,
-                location: Location::new(0, 0)
+                location: Location::overworld()
             });
             builder.add(settings);
+            builder.add(HeldItemSlot(0));
+            builder.add(CursorItem(InventorySlot::Empty));
+            builder.add(SignEditor::default());
+            builder.add(ValidatedPosition::new(position));
+            builder.add(AntiCheatViolations::default());
+            builder.add(MovementFlags::default());
+            if ops.is_op(client.profile.id) {
+                builder.add(Operator);
+            }
+
+            // Players play in the level's gamemode until they are given another.
+            let level_gamemode = level.as_ref().map_or(0, |v| v.game_type);
+            let hardcore = level.as_ref().is_some_and(|v| v.hardcore);
+            let data = match world_manager.load_player_data(&client.profile.id) {
+                Ok(Some(data)) => Some(data),
+                Ok(None) => Some(PlayerData::new(&client.profile.id, [position.x, position.y, position.z], level_gamemode)),
+                Err(e) => {
+                    tracing::error!("Failed to load player data for {}: {:?}", client.profile.name, e);
+                    None
+                }
+            };
+            let saved_gamemode = data.as_ref().map_or(level_gamemode, |v| v.game_mode);
+            let ty = u8::try_from(saved_gamemode)
+                .ok()
+                .and_then(Gamemode::decode)
+                .map_or(GamemodeType::Survival, |v| v.ty);
+            let gamemode = Gamemode::new(ty, hardcore);
+            match data {
+                Some(mut data) => {
+                    builder.add(FallDistanceComponent(data.entity_data.fall_distance));
+                    builder.add(Experience {
+                        level: data.xp_level,
+                        progress: data.xp_percentage,
+                        total_points: data.xp_total,
+                    });
+                    if data.entity_data.invulnerable {
+                        builder.add(Invulnerable);
+                    }
+                    if let Some(effects) = data.mob_data.effects.take() {
+                        builder.add(ActiveEffectsComponent(effects));
+                        builder.add(EffectsDirty);
+                    }
+                    builder.add(data);
+                }
+                None => {
+                    builder.add(FallDistanceComponent(0.0));
+                    builder.add(Experience::default());
+                }
+            }
+            builder.add(ExperienceDirty);
+            builder.add(HealthComponent::default());
+            builder.add(FoodComponent::default());
+            builder.add(HealthDirty);
+
+            builder.add(gamemode);
+            builder.add(PlayerAbilitiesComponent::for_gamemode(gamemode.ty));
+            builder.add(AbilitiesDirty);
     
     
     
@@ -59,36 +161,35 @@ pub fn handle_new_clients(state: &GameState) -> anyhow::Result<()> {
             let id = state.ecs().borrow_mut().spawn(builder.build());
     
             map.add_mapping(client.id, id);
+            state.resource_mut::<EntityIdMap>().insert(client.id, id);
     
             client.join_game(
-                Gamemode::new(GamemodeType::Creative, false),
+                gamemode,
                 0,
                 0,
                 16,
                 "default".to_string(),
             )?;
+            client.send_plugin_message("MC|Brand", SERVER_BRAND.as_bytes())?;
     
     
             client.set_position(position)?;
     
-            let view_chunks = view.chunks();
-            for chunk in &view_chunks {
-                world.add_player_to_chunk(
-                    client,
-                    id,
-                    ChunkLocation {
-                        position: *chunk,
-                        location: Location::new(0, 0),
-                    },
-                )?;
-            }
-            sync_entities.push((id, (view, Location::new(0, 0))));
+            world.add_player_to_chunks(
+                client,
+                id,
+                view.chunks().into_iter().map(|chunk| ChunkLocation {
+                    position: chunk,
+                    location: Location::overworld(),
+                }),
+            )?;
+            sync_entities.push((id, (view, Location::overworld())));
     
         }
     }
 
-    let server = state.resources().get::<Server>();
-    let world = state.resources().get::<GameWorld>();
+    let server = state.resource::<Server>();
+    let world = state.resource::<GameWorld>();
     for (id, view) in sync_entities {
         let ecs = state.ecs().borrow();
         state.load_entities_around(&ecs, &server, &world, ecs.entity(id)?, view.1, view.0.chunks().into_iter())?;
@@ -98,24 +199,36 @@ pub fn handle_new_clients(state: &GameState) -> anyhow::Result<()> {
 }
 
 pub fn handle_disconnected_clients(state: &GameState) -> anyhow::Result<()> {
-    let mut server = state.resources().get_mut::<Server>();
-    let mut map = state.resources().get_mut::<ClientMap>();
-
     let mut to_remove = vec![];
-    for cl in server.clients() {
+    for cl in state.resource::<Server>().clients() {
         if cl.is_disconnected() {
             tracing::info!("{} disconnected", cl.profile.name);
             to_remove.push(cl.id);
         }
     }
 
+    // Gives back what players had picked up in a window,
+    // and closes the chest they were looking inside.
+    for cl in &to_remove {
+        let player = state.resource::<ClientMap>().try_get_mapping(*cl);
+        if let Some(player) = player {
+            packet::close_container(state, player)?;
+        }
+    }
 
+    let mut server = state.resource_mut::<Server>();
+    let mut map = state.resource_mut::<ClientMap>();
     let mut ecs = state.ecs().borrow_mut();
 
-    let mut world = state.resources().get_mut::<GameWorld>();
+    let mut world = state.resource_mut::<GameWorld>();
+    let mut world_manager = state.resource_mut::<WorldManager>();
     for cl in to_remove {
 
-        let en = map.get_mapping(cl);
+        let Some(en) = map.try_get_mapping(cl) else {
+            // kicked before ever joining
+            server.remove_client(cl);
+            continue;
+        };
         {
             let entity = ecs.entity(en)?;
             let loc = *entity.get::<&EntityLocation>().unwrap();
@@ -140,10 +253,238 @@ pub fn handle_disconnected_clients(state: &GameState) -> anyhow::Result<()> {
             
             state.unload_entities_for(&ecs, &server, &world, entity, loc.location, view.chunks().into_iter())?;
     
+            save_player_data(&mut world_manager, server.get_client(cl)?, entity);
+
+            let name = server.get_client(cl)?.profile.name.clone();
             server.remove_client(cl);
+            for other in server.clients().filter(|v| !v.is_disconnected()) {
+                other.send_player_list_item(&name, false, 0)?;
+            }
             map.remove_mapping(cl);
+            state.resource_mut::<EntityIdMap>().remove(cl);
         }
         ecs.despawn(en)?;
     }
     Ok(())
 }
+
+/// Saves a player's data, if they had any when they joined.
+pub(crate) fn save_player_data(world_manager: &mut WorldManager, client: &Client, player: EntityRef) {
+    let Some(mut data) = player.get::<&mut PlayerData>() else {
+        return;
+    };
+    let experience = *player.get::<&Experience>().unwrap();
+    data.xp_level = experience.level;
+    data.xp_percentage = experience.progress;
+    data.xp_total = experience.total_points;
+    data.entity_data.fall_distance = player.get::<&FallDistanceComponent>().map_or(0.0, |v| v.0);
+    if let Some(gamemode) = player.get::<&Gamemode>() {
+        data.game_mode = i32::from(Gamemode::new(gamemode.ty, false).encode());
+    }
+    data.mob_data.effects = player.get::<&ActiveEffectsComponent>().map(|v| v.0.clone());
+
+    if let Err(e) = world_manager.save_player_data(&client.profile.id, &data) {
+        tracing::error!("Failed to save player data for {}: {:?}", client.profile.id, e);
+    }
+}
+
+/// Revives players who asked to respawn,
+/// moving them to the world spawn.
+pub fn handle_respawn(state: &GameState) -> anyhow::Result<()> {
+    for e in state.events().borrow().deferred_events::<RespawnEvent>() {
+        let (spawn, in_overworld) = {
+            let server = state.resource::<Server>();
+            let level = state.resource::<Option<LevelData>>();
+            let ecs = state.ecs().borrow();
+            let Ok(player) = ecs.entity(e.entity) else {
+                continue;
+            };
+
+            if let Some(mut health) = player.get::<&mut HealthComponent>() {
+                health.current = health.max;
+            }
+            if let Some(mut food) = player.get::<&mut FoodComponent>() {
+                *food = FoodComponent::default();
+            }
+            if let Some(mut fall_distance) = player.get::<&mut FallDistanceComponent>() {
+                fall_distance.0 = 0.0;
+            }
+            // Respawning clears the client's effects by itself.
+            if let Some(mut effects) = player.get::<&mut ActiveEffectsComponent>() {
+                effects.0.clear();
+            }
+
+            let client = server.get_client(*player.get::<&NetworkID>().unwrap())?;
+            let keep_inventory = level
+                .as_ref()
+                .and_then(|v| v.game_rules.get("keepInventory"))
+                .is_some_and(|v| v == "true");
+            if !keep_inventory {
+                if let Some(mut data) = player.get::<&mut PlayerData>() {
+                    data.inventory.clear();
+                    client.send_window_items(0, window_slots(&data.inventory))?;
+                }
+            }
+
+            let spawn = level.as_ref().map_or(Position::new(0.0, 128.0, 0.0, 0.0, 0.0, false), |v| {
+                Position::new(
+                    f64::from(v.spawn_x) + 0.5,
+                    f64::from(v.spawn_y),
+                    f64::from(v.spawn_z) + 0.5,
+                    0.0,
+                    0.0,
+                    false,
+                )
+            });
+            let in_overworld = player.get::<&EntityLocation>().unwrap().location == Location::overworld();
+            (spawn, in_overworld)
+        };
+        // Players elsewhere are respawned into the
+        // overworld by the teleport to its spawn.
+        if in_overworld {
+            state.respawn_player(e.entity, Location::overworld().dimension)?;
+            let server = state.resource::<Server>();
+            let world = state.resource::<GameWorld>();
+            let network_id = *state.ecs().borrow().get::<&NetworkID>(e.entity)?;
+            world.resend_known_chunks(server.get_client(network_id)?, Location::overworld())?;
+        }
+        state.teleport_entity(e.entity, EntityLocation {
+            position: spawn,
+            location: Location::overworld(),
+        })?;
+    }
+    Ok(())
+}
+
+/// Makes players operators or takes their operator
+/// status away, saving the operator list.
+pub fn handle_operator_status(state: &GameState) -> anyhow::Result<()> {
+    for e in state.events().borrow().deferred_events::<OperatorStatusEvent>() {
+        let mut ecs = state.ecs().borrow_mut();
+        let profile = ecs.get::<&Arc<Profile>>(e.player).ok().map(|v| Arc::clone(&v));
+        let Some(profile) = profile else {
+            continue;
+        };
+        {
+            let mut ops = state.resource_mut::<OpList>();
+            let changed = if e.operator {
+                ops.add(profile.id)
+            } else {
+                ops.remove(profile.id)
+            };
+            if !changed {
+                continue;
+            }
+            let world_manager = state.resource::<WorldManager>();
+            if let Err(e) = ops.save(world_manager.directory()) {
+                tracing::error!("Failed to save the operator list: {:?}", e);
+            }
+        }
+        if e.operator {
+            ecs.insert_one(e.player, Operator)?;
+        } else {
+            ecs.remove_one::<Operator>(e.player)?;
+        }
+        tracing::info!("{} is {} an operator", profile.name, if e.operator { "now" } else { "no longer" });
+    }
+    Ok(())
+}
+
+/// Sends keep-alives to clients which are due one.
+pub fn send_keepalives(state: &GameState) -> anyhow::Result<()> {
+    let server = state.resource::<Server>();
+    let mut rng = rand::thread_rng();
+    for client in server.clients().filter(|v| !v.is_disconnected()) {
+        client.send_keepalive(rng.gen())?;
+    }
+    Ok(())
+}
+
+/// Shows every player the ping of every other
+/// player in their tab list, once a second.
+pub fn broadcast_latency(state: &GameState) -> anyhow::Result<()> {
+    let server = state.resource::<Server>();
+    for client in server.clients().filter(|v| !v.is_disconnected()) {
+        for other in server.clients().filter(|v| !v.is_disconnected()) {
+            client.send_player_list_item(&other.profile.name, true, other.ping_ms())?;
+        }
+    }
+    Ok(())
+}
+
+/// Shown to players kicked by a restart.
+const RESTART_MESSAGE: &str = "Server restarting";
+
+/// Warns players of the restart each second of its countdown.
+const RESTART_COUNTDOWN_TASK: TaskId = TaskId::Named("restart countdown");
+
+/// Starts counting down to a restart in `countdown_seconds`,
+/// warning players each second. Then every player is saved
+/// and kicked, and `handle_server_restart` takes over.
+pub fn start_restart(state: &GameState, countdown_seconds: u64) -> anyhow::Result<()> {
+    let tps = state.resource::<Arc<Config>>().tps.get();
+    let countdown = countdown_seconds * tps;
+    let restart_tick = state.resource::<TickCounter>().current_tick() + countdown;
+    *state.resource_mut::<ShutdownState>() = ShutdownState::Restarting { restart_tick };
+    warn_restart(state)?;
+
+    let mut tasks = state.resource_mut::<ScheduledTaskManager>();
+    tasks.schedule_repeating(tps, RESTART_COUNTDOWN_TASK, warn_restart);
+    tasks.schedule_once(countdown, kick_for_restart);
+    Ok(())
+}
+
+fn warn_restart(state: &GameState) -> anyhow::Result<()> {
+    let ShutdownState::Restarting { restart_tick } = *state.resource::<ShutdownState>() else {
+        return Ok(());
+    };
+    let tps = state.resource::<Arc<Config>>().tps.get();
+    let ticks_left = restart_tick.saturating_sub(state.resource::<TickCounter>().current_tick());
+    let message = format!("Server restarting in {} seconds", ticks_left.div_ceil(tps));
+    let server = state.resource::<Server>();
+    for client in server.clients().filter(|v| !v.is_disconnected()) {
+        client.send_message(&message)?;
+    }
+    Ok(())
+}
+
+fn kick_for_restart(state: &GameState) -> anyhow::Result<()> {
+    state.resource_mut::<ScheduledTaskManager>().cancel(RESTART_COUNTDOWN_TASK);
+    let server = state.resource::<Server>();
+    let map = state.resource::<ClientMap>();
+    let mut world_manager = state.resource_mut::<WorldManager>();
+    let ecs = state.ecs().borrow();
+
+    tracing::info!("Restarting the server");
+    for client in server.clients().filter(|v| !v.is_disconnected()) {
+        if let Some(player) = map.try_get_mapping(client.id).and_then(|v| ecs.entity(v).ok()) {
+            save_player_data(&mut world_manager, client, player);
+        }
+        client.kick(RESTART_MESSAGE)?;
+    }
+    *state.resource_mut::<ShutdownState>() = ShutdownState::Disconnecting;
+    Ok(())
+}
+
+/// Once a restart has kicked every player, waits for each one to be
+/// removed and loaded chunks to be saved before stopping the tick loop.
+pub fn handle_server_restart(state: &GameState) -> anyhow::Result<()> {
+    let mut shutdown = state.resource_mut::<ShutdownState>();
+    match *shutdown {
+        ShutdownState::Running | ShutdownState::Restarting { .. } | ShutdownState::Restart => (),
+        // Chunks unloaded as players leave are
+        // saved before those still loaded.
+        ShutdownState::Disconnecting => {
+            if state.resource::<Server>().clients().next().is_none() {
+                state.resource_mut::<GameWorld>().save_all()?;
+                *shutdown = ShutdownState::Saving;
+            }
+        }
+        ShutdownState::Saving => {
+            if !state.resource::<GameWorld>().is_saving() {
+                *shutdown = ShutdownState::Restart;
+            }
+        }
+    }
+    Ok(())
+}
@@ -1,4 +1,5 @@
 pub mod login;
 pub mod world;
 pub mod packet;
-pub mod entity;
\ No newline at end of file
+pub mod entity;
+pub mod scoreboard;
\ No newline at end of file
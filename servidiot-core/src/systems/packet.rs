@@ -1,25 +1,80 @@
-use servidiot_ecs::{EntityRef, SystemExecutor};
+use std::{io::Cursor, path::Path, sync::Arc, time::{Duration, Instant}};
+
+use rand::Rng;
+use servidiot_anvil::{nbt::{entity::ItemSlot, player::PlayerData, tile_entity::TileEntitySign}, WorldManager};
+use servidiot_ecs::{Entity, EntityBuilder, EntityRef, SystemExecutor};
 use servidiot_network::{
-    io::packet::client::play::{self, ClientPlayPacket, ClientSettings},
-    server::{Client, Server},
+    io::{
+        packet::client::play::{
+            self, ClientPlayPacket, ClientSettings, ClientStatusType, DiggingStatus, EntityActionType, InteractionType,
+        },
+        Readable,
+    },
+    server::{id::NetworkID, Client, Server},
+};
+use servidiot_primitives::{
+    block::{self, BlockID},
+    item::{InventorySlot, ItemStack},
+    player::{Experience, Gamemode, GamemodeType},
+    position::{BlockPosition, CheckedBlockPosition, ChunkLocation, EntityLocation, Position},
 };
-use servidiot_primitives::position::{EntityLocation, Position};
+use uuid::Uuid;
+
+use crate::{
+    access::{BanList, Whitelist},
+    entity::{
+        container::{self, AnvilComponent, ContainerBlock, ContainerInventory, ContainerKind, CursorItem, OpenContainer},
+        health::HealthComponent,
+        item::{max_stack_size, ItemComponent, ItemEntity, ITEM_GRAVITY},
+        player::{
+            AntiCheatViolations, ChatCooldown, ClientBrandComponent, HeldItemSlot, MetadataDirty, MovementFlags,
+            Operator, PlayerAbilitiesComponent, PlayerMarker, SignEditor, ValidatedPosition,
+        },
+        projectile::ProjectileComponent,
+        riding::RidingComponent,
+        spawn_entity_at, EntityDispatch,
+    },
+    events::entity::{
+        BowDrawEvent, BowReleaseEvent, ContainerCloseEvent, ContainerOpenEvent, CreativeItemDropEvent, DismountEvent,
+        EntityAttackEvent, EntityInteractEvent, EntityMoveEvent, FishingRodUseEvent, ItemDropEvent, OperatorStatusEvent,
+        PlayerViewChangeEvent, RespawnEvent,
+    },
+    game::{ClientMap, EntityIdMap, GameState, ShutdownState},
+    systems::login,
+    world::{border::WorldBorder, protection::SpawnProtection, view::View, GameWorld},
+    Config,
+};
+
+/// How many movement violations within
+/// `VIOLATION_WINDOW` get a player kicked.
+const MAX_VIOLATIONS: usize = 3;
+const VIOLATION_WINDOW: Duration = Duration::from_secs(5);
 
-use crate::{game::{ClientMap, GameState}, events::entity::{EntityMoveEvent, PlayerViewChangeEvent}, world::view::View};
+/// The highest hotbar slot.
+const MAX_HOTBAR_SLOT: u8 = 8;
 
 pub fn register_systems(s: &mut SystemExecutor<GameState>) {
-    s.add_system(handle_packets);
+    s.add_system(handle_packets)
+        .add_system(handle_item_drop)
+        .add_system(handle_open_container)
+        .add_system(handle_close_container)
+        .add_system(handle_position_validation);
 }
 
 pub fn handle_packets(state: &GameState) -> anyhow::Result<()> {
-    let server = state.resources().get::<Server>();
-    let map = state.resources().get::<ClientMap>();
+    let server = state.resource::<Server>();
+    let map = state.resource::<ClientMap>();
+    let border = state.resource::<WorldBorder>();
+    let config = state.resource::<Arc<Config>>();
 
     for client in server.clients() {
         let entity = map.get_mapping(client.id);
         for packet in client.packets() {
             let ecs = state.ecs().borrow();
             let player_entity = ecs.entity(entity)?;
+            let mut chatted = false;
+            let mut brand = None;
+            let mut movement_changed = false;
             match packet {
                 ClientPlayPacket::Player(p) => {
                     let mut loc = player_entity.get::<&mut EntityLocation>().unwrap();
@@ -28,6 +83,16 @@ pub fn handle_packets(state: &GameState) -> anyhow::Result<()> {
                     handle_new_position(state, client, player_entity, pos, loc.position)?;
                 }
                 ClientPlayPacket::PlayerPosition(p) => {
+                    if !border.contains(p.x, p.z) {
+                        reject_position(client, player_entity)?;
+                        continue;
+                    }
+                    if !validate_teleport_destination(state, client, player_entity, p.x, p.feet_y, p.z)? {
+                        continue;
+                    }
+                    if let Some(mut validated) = player_entity.get::<&mut ValidatedPosition>() {
+                        validated.moves = validated.moves.saturating_add(1);
+                    }
                     let mut loc = player_entity.get::<&mut EntityLocation>().unwrap();
                     let pos = loc.position;
                     loc.position.on_ground = p.on_ground;
@@ -45,6 +110,16 @@ pub fn handle_packets(state: &GameState) -> anyhow::Result<()> {
                     handle_new_position(state, client, player_entity, pos, loc.position)?;
                 }
                 ClientPlayPacket::PlayerPositionAndLook(p) => {
+                    if !border.contains(p.x, p.z) {
+                        reject_position(client, player_entity)?;
+                        continue;
+                    }
+                    if !validate_teleport_destination(state, client, player_entity, p.x, p.feet_y, p.z)? {
+                        continue;
+                    }
+                    if let Some(mut validated) = player_entity.get::<&mut ValidatedPosition>() {
+                        validated.moves = validated.moves.saturating_add(1);
+                    }
                     let mut loc = player_entity.get::<&mut EntityLocation>().unwrap();
                     let pos = loc.position;
                     loc.position.on_ground = p.on_ground;
@@ -55,14 +130,1441 @@ pub fn handle_packets(state: &GameState) -> anyhow::Result<()> {
                     loc.position.z = p.z;
                     handle_new_position(state, client, player_entity, pos, loc.position)?;
                 }
+                ClientPlayPacket::PlayerInput(p) => {
+                    if p.unmount && player_entity.has::<RidingComponent>() {
+                        state.events().borrow().post_event(state, DismountEvent {
+                            entity
+                        })?;
+                    }
+                }
+                ClientPlayPacket::ClientSettings(mut settings) => {
+                    settings.view_distance = config.clamp_view_distance(settings.view_distance);
+                    let old_distance = std::mem::replace(
+                        &mut *player_entity.get::<&mut ClientSettings>().unwrap(),
+                        settings,
+                    ).view_distance;
+                    handle_view_distance_change(state, player_entity, old_distance)?;
+                }
+                ClientPlayPacket::HeldItemChange(p) => {
+                    handle_held_item_change(state, client, player_entity, p.slot)?;
+                }
+                ClientPlayPacket::PlayerBlockPlacement(p) => {
+                    handle_player_block_placement(state, client, player_entity, p)?;
+                }
+                ClientPlayPacket::PlayerDigging(p) => {
+                    handle_player_digging(state, client, player_entity, p)?;
+                }
+                ClientPlayPacket::ClientStatus(p) => {
+                    let dead = player_entity.get::<&HealthComponent>().is_some_and(|v| v.current <= 0.0);
+                    if p.ty == ClientStatusType::PerformRespawn && dead {
+                        state.events().borrow().post_event(state, RespawnEvent {
+                            entity
+                        })?;
+                    }
+                }
+                ClientPlayPacket::PlayerAbilities(p) => {
+                    handle_player_abilities(client, player_entity, p.flags)?;
+                }
+                ClientPlayPacket::CloseWindow(p) => {
+                    // Window 0 is the player's own inventory.
+                    if p.window_id != 0 {
+                        state.events().borrow().post_event(state, ContainerCloseEvent {
+                            player: entity,
+                            window_id: p.window_id as u8
+                        })?;
+                    }
+                }
+                ClientPlayPacket::EntityAction(p) => {
+                    movement_changed = handle_entity_action(client, player_entity, p);
+                }
+                ClientPlayPacket::ClickWindow(p) => {
+                    handle_click_window(state, client, player_entity, p)?;
+                }
+                ClientPlayPacket::UpdateSign(p) => {
+                    handle_update_sign(state, client, player_entity, p)?;
+                }
+                ClientPlayPacket::UseEntity(p) => {
+                    handle_use_entity(state, player_entity, p)?;
+                }
+                ClientPlayPacket::ChatMessage(p) => match p.message.strip_prefix('/') {
+                    Some(command) => handle_command(state, client, player_entity, command)?,
+                    None => chatted = handle_chat(&server, &config, client, player_entity, &p.message)?,
+                },
+                ClientPlayPacket::KeepAlive(p) => {
+                    client.receive_keepalive(p.id);
+                }
+                ClientPlayPacket::CreativeInventoryAction(p) => {
+                    handle_creative_inventory_action(state, client, player_entity, p)?;
+                }
+                ClientPlayPacket::PluginMessage(p) if p.channel == ITEM_NAME_CHANNEL => {
+                    handle_anvil(state, client, player_entity, &p.data)?;
+                }
+                ClientPlayPacket::PluginMessage(p) if p.channel == BOOK_EDIT_CHANNEL || p.channel == BOOK_SIGN_CHANNEL => {
+                    handle_book_edit(state, client, player_entity, &p.data, p.channel == BOOK_SIGN_CHANNEL)?;
+                }
+                ClientPlayPacket::PluginMessage(p) => {
+                    brand = handle_server_brand(client, &p.channel, &p.data);
+                }
                 _ => (),
             }
+            drop(ecs);
+            if chatted {
+                state.ecs().borrow_mut().insert_one(entity, ChatCooldown(Instant::now()))?;
+            }
+            if let Some(brand) = brand {
+                state.ecs().borrow_mut().insert_one(entity, brand)?;
+            }
+            if movement_changed {
+                state.ecs().borrow_mut().insert_one(entity, MetadataDirty)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Records a player starting or stopping crouching or
+/// sprinting. Returns whether either changed.
+///
+/// Walk speed is left alone: clients speed up
+/// sprinting players and slow crouching ones by
+/// themselves, so changing it would do so twice.
+fn handle_entity_action(client: &Client, player: EntityRef, p: play::EntityAction) -> bool {
+    if p.eid != client.id {
+        tracing::warn!("{} sent an action for another entity", client.profile.name);
+        return false;
+    }
+    let Some(mut movement) = player.get::<&mut MovementFlags>() else {
+        return false;
+    };
+    let old = *movement;
+    match p.action {
+        EntityActionType::Crouch => movement.sneaking = true,
+        EntityActionType::Uncrouch => movement.sneaking = false,
+        EntityActionType::StartSprinting => movement.sprinting = true,
+        EntityActionType::StopSprintingOrHorse => movement.sprinting = false,
+        _ => (),
+    }
+    movement.flags() != old.flags()
+}
+
+/// Records whether a player started or stopped flying,
+/// grounding them if they are not allowed to fly.
+fn handle_player_abilities(client: &Client, player: EntityRef, flags: i8) -> anyhow::Result<()> {
+    let mut abilities = player.get::<&mut PlayerAbilitiesComponent>().unwrap();
+    let flying = flags & PlayerAbilitiesComponent::FLYING_FLAG != 0;
+    if flying && !abilities.allow_flying {
+        tracing::warn!("{} tried to fly without being allowed to", client.profile.name);
+        abilities.flying = false;
+        return client.send_abilities(abilities.flags(), abilities.fly_speed, abilities.walk_speed);
+    }
+    abilities.flying = flying;
+    Ok(())
+}
+
+/// The plugin channel clients report their brand on.
+const BRAND_CHANNEL: &str = "MC|Brand";
+
+/// Reads the client brand out of a plugin message,
+/// if it was sent on the brand channel.
+///
+/// This protocol sends the brand as the raw UTF-8
+/// payload, without the length prefix used later.
+pub fn handle_server_brand(client: &Client, channel: &str, data: &[u8]) -> Option<ClientBrandComponent> {
+    if channel != BRAND_CHANNEL {
+        return None;
+    }
+    let brand = String::from_utf8_lossy(data).into_owned();
+    tracing::debug!("{} is using client brand {:?}", client.profile.name, brand);
+    Some(ClientBrandComponent(brand))
+}
+
+/// Block IDs of the standing and wall-mounted signs.
+const STANDING_SIGN: u16 = 63;
+const WALL_SIGN: u16 = 68;
+const SIGN_BLOCKS: [u16; 2] = [STANDING_SIGN, WALL_SIGN];
+/// The item ID of signs.
+const SIGN_ITEM: i16 = 323;
+/// The most characters a line of a sign may hold.
+const SIGN_LINE_LENGTH: usize = 15;
+/// How far, in blocks, players may be from a sign they write on.
+const SIGN_EDIT_DISTANCE: f64 = 6.0;
+
+/// Stores the text a player wrote on the sign they
+/// placed, and shows it to everyone who can see it.
+pub fn handle_update_sign(state: &GameState, client: &Client, player: EntityRef, p: play::UpdateSign) -> anyhow::Result<()> {
+    let position = BlockPosition::new(p.x, i32::from(p.y), p.z);
+    let location = player.get::<&EntityLocation>().unwrap().location;
+    let editing = player.get::<&mut SignEditor>().and_then(|mut v| v.0.take());
+    if editing != Some((position, location)) {
+        tracing::debug!("{} tried to write on a sign they did not place at {}", client.profile.name, position);
+        return Ok(());
+    }
+    let mut world = state.resource_mut::<GameWorld>();
+
+    let is_sign = world.block_at(location, position).is_some_and(|(v, _)| SIGN_BLOCKS.contains(&v));
+    if !is_sign {
+        tracing::debug!("{} tried to write on a sign that does not exist at {}", client.profile.name, position);
+        return Ok(());
+    }
+    let center = Position::new(f64::from(position.x) + 0.5, f64::from(position.y) + 0.5, f64::from(position.z) + 0.5, 0.0, 0.0, false);
+    if player.get::<&EntityLocation>().unwrap().position.distance_to(&center) > SIGN_EDIT_DISTANCE {
+        tracing::debug!("{} tried to write on a sign too far away at {}", client.profile.name, position);
+        return Ok(());
+    }
+
+    let lines = [p.line1, p.line2, p.line3, p.line4].map(|v| v.chars().take(SIGN_LINE_LENGTH).collect());
+    let sign = TileEntitySign::new(position.x, position.y, position.z, lines);
+
+    let chunk = ChunkLocation::new(position.chunk(), location);
+    for_chunk_viewers(state, &world, chunk, |viewer| viewer.send_sign(position, sign.lines()))?;
+    world.set_sign(chunk, sign);
+    Ok(())
+}
+
+/// Runs `f` for the client of every player viewing some chunk.
+fn for_chunk_viewers(state: &GameState, world: &GameWorld, chunk: ChunkLocation, mut f: impl FnMut(&Client) -> anyhow::Result<()>) -> anyhow::Result<()> {
+    let Some((_, _, viewers)) = world.get_chunk(chunk) else {
+        return Ok(());
+    };
+    let server = state.resource::<Server>();
+    let ecs = state.ecs().borrow();
+    for viewer in viewers {
+        if let Ok(id) = ecs.get::<&NetworkID>(*viewer) {
+            let client = server.get_client(*id)?;
+            if !client.is_disconnected() {
+                f(client)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The block ID of chests.
+const CHEST_BLOCK: u16 = 54;
+/// The block ID of anvils.
+const ANVIL_BLOCK: u16 = 145;
+/// The inventory type of chests in `OpenWindow`.
+const CHEST_INVENTORY_TYPE: u8 = 0;
+/// The inventory type of anvils in `OpenWindow`.
+const ANVIL_INVENTORY_TYPE: u8 = 8;
+/// The window ID chests are opened with. Players
+/// only ever have one container open at a time.
+const CONTAINER_WINDOW_ID: u8 = 1;
+/// The block action which opens or closes a chest's lid.
+const CHEST_LID_ACTION: u8 = 1;
+
+/// Opens the chests and anvils players click on, creating the
+/// inventory of chests opened for the first time. Each player
+/// using an anvil gets an inventory of their own.
+pub fn handle_open_container(state: &GameState) -> anyhow::Result<()> {
+    for e in state.events().borrow().deferred_events::<ContainerOpenEvent>() {
+        close_container(state, e.player)?;
+
+        let container = match e.kind {
+            ContainerKind::Chest => {
+                let found = {
+                    let ecs = state.ecs().borrow();
+                    let mut query = ecs
+                        .query::<&ContainerBlock>()
+                        .with::<&ContainerInventory>()
+                        .without::<&AnvilComponent>();
+                    let found = query.iter().find(|(_, block)| **block == e.container).map(|(entity, _)| entity);
+                    found
+                };
+                match found {
+                    Some(v) => v,
+                    None => state.ecs().borrow_mut().spawn((e.container, ContainerInventory::chest())),
+                }
+            }
+            ContainerKind::Anvil => state.ecs().borrow_mut().spawn((e.container, ContainerInventory::anvil(), AnvilComponent::default())),
+        };
+        let inventory_type = match e.kind {
+            ContainerKind::Chest => CHEST_INVENTORY_TYPE,
+            ContainerKind::Anvil => ANVIL_INVENTORY_TYPE,
+        };
+
+        let viewers = {
+            let mut ecs = state.ecs().borrow_mut();
+            ecs.insert_one(e.player, OpenContainer {
+                window_id: CONTAINER_WINDOW_ID,
+                container
+            })?;
+            let server = state.resource::<Server>();
+            let client = server.get_client(*ecs.get::<&NetworkID>(e.player)?)?;
+            let mut inventory = ecs.get::<&mut ContainerInventory>(container)?;
+            inventory.viewers.insert(e.player);
+            client.open_window(CONTAINER_WINDOW_ID, inventory_type, inventory.slots.len() as u8)?;
+            client.send_window_items(CONTAINER_WINDOW_ID, inventory.slots.clone())?;
+            inventory.viewers.len()
+        };
+        if e.kind == ContainerKind::Chest {
+            send_chest_lid(state, e.container, viewers)?;
+        }
+    }
+    Ok(())
+}
+
+/// Closes the containers players have stopped looking in.
+pub fn handle_close_container(state: &GameState) -> anyhow::Result<()> {
+    for e in state.events().borrow().deferred_events::<ContainerCloseEvent>() {
+        let window_id = state.ecs().borrow().get::<&OpenContainer>(e.player).map(|v| v.window_id);
+        if window_id.is_ok_and(|v| v == e.window_id) {
+            close_container(state, e.player)?;
+        }
+    }
+    Ok(())
+}
+
+/// Removes a player from the viewers of their open container,
+/// if they have one, and gives back what they had picked up.
+/// Anvils keep nothing, so the items put in them are given back
+/// too, and the player's anvil inventory is despawned.
+pub(crate) fn close_container(state: &GameState, player: servidiot_ecs::Entity) -> anyhow::Result<()> {
+    let (block, viewers, is_anvil, returned) = {
+        let mut ecs = state.ecs().borrow_mut();
+        let Ok(open) = ecs.remove_one::<OpenContainer>(player) else {
+            return Ok(());
+        };
+        let block = *ecs.get::<&ContainerBlock>(open.container)?;
+        let mut inventory = ecs.get::<&mut ContainerInventory>(open.container)?;
+        inventory.viewers.remove(&player);
+        let mut returned = vec![ecs
+            .get::<&mut CursorItem>(player)
+            .map_or(InventorySlot::Empty, |mut v| std::mem::replace(&mut v.0, InventorySlot::Empty))];
+
+        let is_anvil = ecs.get::<&AnvilComponent>(open.container).is_ok();
+        if is_anvil {
+            inventory.slots[ContainerInventory::ANVIL_OUTPUT] = InventorySlot::Empty;
+            returned.extend(inventory.slots.iter_mut().map(|v| std::mem::replace(v, InventorySlot::Empty)));
+        }
+        let viewers = inventory.viewers.len();
+        drop(inventory);
+        if is_anvil {
+            ecs.despawn(open.container)?;
+        }
+        (block, viewers, is_anvil, returned)
+    };
+    if !is_anvil {
+        send_chest_lid(state, block, viewers)?;
+    }
+    if viewers == 0 && !is_anvil {
+        let world = state.resource::<GameWorld>();
+        let chunk = ChunkLocation::new(block.position.chunk(), block.location);
+        let centre = block_centre(block.position);
+        for_chunk_viewers(state, &world, chunk, |client| {
+            client.play_sound(CHEST_CLOSE_SOUND, centre, 0.5, 1.0)
+        })?;
+    }
+
+    for stack in returned.into_iter().filter_map(|v| match v {
+        InventorySlot::Filled(stack) => Some(stack),
+        InventorySlot::Empty => None,
+    }) {
+        let dropped = {
+            let server = state.resource::<Server>();
+            let ecs = state.ecs().borrow();
+            let player = ecs.entity(player)?;
+            let client = server.get_client(*player.get::<&NetworkID>().unwrap())?;
+            (!give_item(state, client, player, stack.clone())?).then(|| item_throw(player))
+        };
+        if let Some((loc, projectile)) = dropped {
+            spawn_item(state, (stack, loc, projectile))?;
+        }
+    }
+    Ok(())
+}
+
+/// Whether some block holds a container players can open.
+pub(crate) fn is_container_block(block: BlockID) -> bool {
+    matches!(*block, CHEST_BLOCK | ANVIL_BLOCK)
+}
+
+/// How fast the items in a destroyed container
+/// may be flung in each direction, in blocks per tick.
+const CONTAINER_DROP_SPEED: f64 = 0.05;
+
+/// Closes a destroyed container block for the players looking inside,
+/// giving back what they had picked up, then despawns its inventory
+/// and drops what it held where it stood.
+pub(crate) fn break_container(state: &GameState, block: ContainerBlock) -> anyhow::Result<()> {
+    let containers = state
+        .ecs()
+        .borrow()
+        .query::<&ContainerBlock>()
+        .with::<&ContainerInventory>()
+        .iter()
+        .filter(|(_, v)| **v == block)
+        .map(|(entity, _)| entity)
+        .collect::<Vec<_>>();
+
+    let mut dropped = vec![];
+    for container in containers {
+        let viewers = state.ecs().borrow().get::<&ContainerInventory>(container)?.viewers.clone();
+        for viewer in viewers {
+            {
+                let server = state.resource::<Server>();
+                let ecs = state.ecs().borrow();
+                let client = server.get_client(*ecs.get::<&NetworkID>(viewer)?)?;
+                if !client.is_disconnected() {
+                    client.close_window(ecs.get::<&OpenContainer>(viewer)?.window_id)?;
+                }
+            }
+            close_container(state, viewer)?;
+        }
+
+        // Anvil inventories are despawned as their player closes them.
+        let mut ecs = state.ecs().borrow_mut();
+        if let Ok(mut inventory) = ecs.get::<&mut ContainerInventory>(container) {
+            dropped.extend(inventory.slots.iter_mut().filter_map(|v| match std::mem::replace(v, InventorySlot::Empty) {
+                InventorySlot::Filled(stack) => Some(stack),
+                InventorySlot::Empty => None,
+            }));
+        }
+        if ecs.contains(container) {
+            ecs.despawn(container)?;
+        }
+    }
+
+    let mut rng = rand::thread_rng();
+    for stack in dropped {
+        let mut position = block_centre(block.position);
+        position.x += rng.gen_range(-0.4..0.4);
+        position.z += rng.gen_range(-0.4..0.4);
+        let projectile = ProjectileComponent {
+            velocity_x: rng.gen_range(-CONTAINER_DROP_SPEED..CONTAINER_DROP_SPEED),
+            velocity_y: CONTAINER_DROP_SPEED * 4.0,
+            velocity_z: rng.gen_range(-CONTAINER_DROP_SPEED..CONTAINER_DROP_SPEED),
+            shooter: None,
+            gravity: ITEM_GRAVITY,
+        };
+        spawn_item(state, (stack, EntityLocation { position, location: block.location }, projectile))?;
+    }
+    Ok(())
+}
+
+/// Played when the last player looking inside a chest closes it.
+const CHEST_CLOSE_SOUND: &str = "random.chestclosed";
+
+fn block_centre(position: BlockPosition) -> Position {
+    Position::new(
+        f64::from(position.x) + 0.5,
+        f64::from(position.y) + 0.5,
+        f64::from(position.z) + 0.5,
+        0.0,
+        0.0,
+        false,
+    )
+}
+
+/// Where a slot of a container window is stored.
+enum ContainerSlot {
+    /// An index into the container's slots.
+    Container(usize),
+    /// A saved slot of the player's main inventory.
+    Player(i8),
+}
+
+/// The slot shown at `window_slot` of a window onto a container
+/// with `container_len` slots. The player's main inventory is
+/// shown below the container, with the hotbar last.
+fn container_slot(container_len: usize, window_slot: i16) -> Option<ContainerSlot> {
+    let slot = usize::try_from(window_slot).ok()?;
+    if slot < container_len {
+        return Some(ContainerSlot::Container(slot));
+    }
+    match slot - container_len {
+        v @ 0..=26 => Some(ContainerSlot::Player(v as i8 + 9)),
+        v @ 27..=35 => Some(ContainerSlot::Player(v as i8 - 27)),
+        _ => None,
+    }
+}
+
+/// The click mode of plain left and right clicks.
+const PLAIN_CLICK_MODE: i8 = 0;
+
+/// Moves items around the container window some player has open,
+/// showing changes to the container to everyone else viewing it.
+/// Only plain clicks are understood, and the window is resent to
+/// undo any other kind of click.
+fn handle_click_window(state: &GameState, client: &Client, player: EntityRef, p: play::ClickWindow) -> anyhow::Result<()> {
+    let Some((window_id, container)) = player.get::<&OpenContainer>().map(|v| (v.window_id, v.container)) else {
+        return Ok(());
+    };
+    if p.window_id != window_id as i8 {
+        return Ok(());
+    }
+
+    let server = state.resource::<Server>();
+    let ecs = state.ecs().borrow();
+    let mut inventory = ecs.get::<&mut ContainerInventory>(container)?;
+    let mut cursor = player.get::<&mut CursorItem>().unwrap();
+    let right_click = p.button == 1;
+
+    let target = (p.mode == PLAIN_CLICK_MODE).then(|| container_slot(inventory.slots.len(), p.slot)).flatten();
+    let anvil = ecs.get::<&AnvilComponent>(container).ok();
+    match target {
+        Some(ContainerSlot::Container(ContainerInventory::ANVIL_OUTPUT)) if anvil.is_some() => {
+            let anvil = anvil.unwrap();
+            let (output, cost) = container::anvil_output(&inventory.slots[ContainerInventory::ANVIL_INPUT], anvil.item_name.as_deref());
+            let creative = player.get::<&Gamemode>().is_some_and(|v| matches!(v.ty, GamemodeType::Creative));
+            let mut experience = player.get::<&mut Experience>();
+            let affordable = creative || experience.as_ref().is_some_and(|v| v.level >= cost);
+            if output.is_empty() || !cursor.0.is_empty() || !affordable {
+                return resend_window(client, window_id, &inventory, player, &cursor);
+            }
+            if let Some(experience) = experience.as_mut().filter(|_| !creative) {
+                experience.level -= cost;
+                client.send_experience(**experience)?;
+            }
+            cursor.0 = output;
+            inventory.slots[ContainerInventory::ANVIL_INPUT] = InventorySlot::Empty;
+            inventory.slots[ContainerInventory::ANVIL_OUTPUT] = InventorySlot::Empty;
+            client.send_set_slot(window_id as i8, ContainerInventory::ANVIL_INPUT as i16, InventorySlot::Empty)?;
+            client.send_window_property(window_id, ANVIL_COST_PROPERTY, 0)?;
+        }
+        Some(ContainerSlot::Container(index)) => {
+            container::click_slot(&mut cursor.0, &mut inventory.slots[index], right_click);
+            if let Some(anvil) = anvil {
+                update_anvil(client, window_id, &mut inventory, &anvil)?;
+            }
+            let changed = inventory.slots[index].clone();
+            for viewer in inventory.viewers.iter().filter(|v| **v != player.entity()) {
+                let viewer = ecs.entity(*viewer)?;
+                let (Some(id), Some(open)) = (viewer.get::<&NetworkID>(), viewer.get::<&OpenContainer>()) else {
+                    continue;
+                };
+                server.get_client(*id)?.send_set_slot(open.window_id as i8, p.slot, changed.clone())?;
+            }
+        }
+        Some(ContainerSlot::Player(slot)) => {
+            {
+                let Some(mut data) = player.get::<&mut PlayerData>() else {
+                    return Ok(());
+                };
+                let mut clicked = data
+                    .inventory
+                    .iter()
+                    .position(|v| v.slot == slot)
+                    .map_or(InventorySlot::Empty, |v| InventorySlot::Filled(data.inventory.remove(v).stack_data));
+                container::click_slot(&mut cursor.0, &mut clicked, right_click);
+                if let InventorySlot::Filled(stack_data) = clicked {
+                    data.inventory.push(ItemSlot { stack_data, slot });
+                }
+            }
+            if player.get::<&HeldItemSlot>().is_some_and(|v| v.0 as i8 == slot) {
+                send_held_item(state, client, player)?;
+            }
+        }
+        None => resend_window(client, window_id, &inventory, player, &cursor)?,
+    }
+    Ok(())
+}
+
+/// Sends a player the whole of the container window they have
+/// open, and what they have picked up, undoing their last click.
+fn resend_window(client: &Client, window_id: u8, inventory: &ContainerInventory, player: EntityRef, cursor: &CursorItem) -> anyhow::Result<()> {
+    let mut slots = inventory.slots.clone();
+    let data = player.get::<&PlayerData>();
+    slots.extend((slots.len()..slots.len() + 36).map(|v| {
+        let Some(ContainerSlot::Player(slot)) = container_slot(inventory.slots.len(), v as i16) else {
+            return InventorySlot::Empty;
+        };
+        data.as_ref()
+            .and_then(|data| data.inventory.iter().find(|v| v.slot == slot))
+            .map_or(InventorySlot::Empty, |v| InventorySlot::Filled(v.stack_data.clone()))
+    }));
+    client.send_window_items(window_id, slots)?;
+    // Slot -1 of window -1 is the cursor.
+    client.send_set_slot(-1, -1, cursor.0.clone())
+}
+
+/// The plugin channel anvils send the name typed into them on.
+const ITEM_NAME_CHANNEL: &str = "MC|ItemName";
+/// The longest name an anvil may give an item.
+const MAX_ITEM_NAME_LENGTH: usize = 35;
+/// The window property holding the cost, in levels, of using an anvil.
+const ANVIL_COST_PROPERTY: i16 = 0;
+
+/// Renames the item in the anvil some player has open
+/// to the name they typed, showing them the cost.
+pub fn handle_anvil(state: &GameState, client: &Client, player: EntityRef, data: &[u8]) -> anyhow::Result<()> {
+    let Some((window_id, container)) = player.get::<&OpenContainer>().map(|v| (v.window_id, v.container)) else {
+        return Ok(());
+    };
+    let ecs = state.ecs().borrow();
+    let Ok(mut anvil) = ecs.get::<&mut AnvilComponent>(container) else {
+        return Ok(());
+    };
+    let name = String::from_utf8_lossy(data);
+    if name.chars().count() > MAX_ITEM_NAME_LENGTH {
+        tracing::warn!("{} tried to name an item {:?}, which is too long", client.profile.name, name);
+        return Ok(());
+    }
+    anvil.item_name = Some(name.into_owned());
+    let mut inventory = ecs.get::<&mut ContainerInventory>(container)?;
+    update_anvil(client, window_id, &mut inventory, &anvil)
+}
+
+/// Shows the player using an anvil what it will make, and at what cost.
+fn update_anvil(client: &Client, window_id: u8, inventory: &mut ContainerInventory, anvil: &AnvilComponent) -> anyhow::Result<()> {
+    let (output, cost) = container::anvil_output(&inventory.slots[ContainerInventory::ANVIL_INPUT], anvil.item_name.as_deref());
+    inventory.slots[ContainerInventory::ANVIL_OUTPUT] = output.clone();
+    client.send_set_slot(window_id as i8, ContainerInventory::ANVIL_OUTPUT as i16, output)?;
+    client.send_window_property(window_id, ANVIL_COST_PROPERTY, i16::try_from(cost).unwrap_or(i16::MAX))
+}
+
+/// The plugin channel books being written are sent on.
+const BOOK_EDIT_CHANNEL: &str = "MC|BEdit";
+/// The plugin channel books being signed are sent on.
+const BOOK_SIGN_CHANNEL: &str = "MC|BSign";
+const WRITABLE_BOOK: i16 = 386;
+const WRITTEN_BOOK: i16 = 387;
+const MAX_BOOK_PAGES: usize = 50;
+const MAX_PAGE_LENGTH: usize = 256;
+const MAX_BOOK_TITLE_LENGTH: usize = 16;
+/// The window slot of the first hotbar slot.
+const HOTBAR_WINDOW_SLOT: i16 = 36;
+
+/// Writes the pages some player sent into the book and quill they
+/// are holding, or signs it, turning it into a written book. Books
+/// longer than any client could write get the player kicked.
+pub fn handle_book_edit(state: &GameState, client: &Client, player: EntityRef, data: &[u8], sign: bool) -> anyhow::Result<()> {
+    let InventorySlot::Filled(book) = InventorySlot::read_from(&mut Cursor::new(data))? else {
+        return Ok(());
+    };
+    let pages = book.book_pages();
+    let title = book.book_title().unwrap_or_default();
+    if pages.len() > MAX_BOOK_PAGES
+        || pages.iter().any(|v| v.chars().count() > MAX_PAGE_LENGTH)
+        || title.chars().count() > MAX_BOOK_TITLE_LENGTH
+    {
+        tracing::warn!("{} sent a book longer than allowed", client.profile.name);
+        return client.kick("Invalid book!");
+    }
+
+    let held = player.get::<&HeldItemSlot>().map_or(0, |v| v.0);
+    let stack = {
+        let Some(mut data) = player.get::<&mut PlayerData>() else {
+            return Ok(());
+        };
+        let Some(slot) = data.inventory.iter_mut().find(|v| v.slot == held as i8 && v.stack_data.id == WRITABLE_BOOK) else {
+            tracing::warn!("{} tried to write a book without holding one", client.profile.name);
+            return Ok(());
+        };
+        slot.stack_data.set_book_pages(&pages);
+        if sign {
+            slot.stack_data.id = WRITTEN_BOOK;
+            slot.stack_data.sign_book(title, &client.profile.name);
+        }
+        slot.stack_data.clone()
+    };
+    client.send_set_slot(0, HOTBAR_WINDOW_SLOT + i16::from(held), InventorySlot::Filled(stack))?;
+    if sign {
+        send_held_item(state, client, player)?;
+    }
+    Ok(())
+}
+
+/// Opens a chest's lid for everyone who can see it,
+/// or closes it once nobody is looking inside.
+fn send_chest_lid(state: &GameState, block: ContainerBlock, viewers: usize) -> anyhow::Result<()> {
+    let world = state.resource::<GameWorld>();
+    let chest = BlockID::new(CHEST_BLOCK).expect("valid block");
+    let viewers = viewers.min(usize::from(u8::MAX)) as u8;
+    let chunk = ChunkLocation::new(block.position.chunk(), block.location);
+    for_chunk_viewers(state, &world, chunk, |client| {
+        client.send_block_action(block.position, CHEST_LID_ACTION, viewers, chest)
+    })
+}
+
+/// Tells other systems when a player clicks on an
+/// entity. Clicks on unknown entities are ignored.
+pub fn handle_use_entity(state: &GameState, player: EntityRef, p: play::UseEntity) -> anyhow::Result<()> {
+    let Some(target) = state.resource::<EntityIdMap>().get(p.target) else {
+        return Ok(());
+    };
+    if target == player.entity() {
+        return Ok(());
+    }
+    let events = state.events().borrow();
+    match p.mouse {
+        InteractionType::Attack => events.post_event(state, EntityAttackEvent {
+            attacker: player.entity(),
+            target
+        })?,
+        interaction_type => events.post_event(state, EntityInteractEvent {
+            actor: player.entity(),
+            target,
+            interaction_type
+        })?,
+    }
+    Ok(())
+}
+
+/// Sends a player's message to everyone, unless they sent
+/// another too recently. Returns whether it was sent.
+fn handle_chat(server: &Server, config: &Config, client: &Client, player: EntityRef, message: &str) -> anyhow::Result<bool> {
+    let interval = if player.has::<Operator>() {
+        Duration::ZERO
+    } else {
+        config.min_chat_interval
+    };
+    if player.get::<&ChatCooldown>().is_some_and(|v| v.0.elapsed() < interval) {
+        client.send_message("You are sending messages too quickly!")?;
+        return Ok(false);
+    }
+
+    let line = format!("<{}> {}", client.profile.name, message);
+    tracing::info!("{}", line);
+    for other in server.clients() {
+        if !other.is_disconnected() {
+            other.send_message(&line)?;
+        }
+    }
+    Ok(true)
+}
+
+/// How long players are warned before the server restarts.
+const RESTART_COUNTDOWN_SECONDS: u64 = 10;
+
+/// Runs a command some player typed, without its leading `/`.
+/// Every command is for operators only.
+fn handle_command(state: &GameState, client: &Client, player: EntityRef, command: &str) -> anyhow::Result<()> {
+    tracing::info!("{} issued server command: /{}", client.profile.name, command);
+    if !player.has::<Operator>() {
+        return client.send_message("You do not have permission to use this command.");
+    }
+    let mut args = command.split_whitespace();
+    match args.next().unwrap_or_default() {
+        "restart" => {
+            if *state.resource::<ShutdownState>() != ShutdownState::Running {
+                return client.send_message("The server is already restarting.");
+            }
+            login::start_restart(state, RESTART_COUNTDOWN_SECONDS)
+        }
+        "tp" => handle_teleport_command(state, client, player, &args.collect::<Vec<_>>()),
+        "whitelist" => handle_whitelist_command(state, client, &args.collect::<Vec<_>>()),
+        "ban" => {
+            let Some(name) = args.next() else {
+                return client.send_message("Usage: /ban <player> [reason]");
+            };
+            let reason = args.collect::<Vec<_>>().join(" ");
+            handle_ban_command(state, client, name, Some(reason.as_str()).filter(|v| !v.is_empty()))
+        }
+        "pardon" => {
+            let &[name] = args.collect::<Vec<_>>().as_slice() else {
+                return client.send_message("Usage: /pardon <player>");
+            };
+            handle_pardon_command(state, client, name)
+        }
+        command @ ("op" | "deop") => {
+            let operator = command == "op";
+            let &[name] = args.collect::<Vec<_>>().as_slice() else {
+                return client.send_message(&format!("Usage: /{command} <player>"));
+            };
+            let Some((target, name)) = find_online_player(state, name) else {
+                return client.send_message(&format!("There is no player called {name} online."));
+            };
+            state.events().borrow().post_event(state, OperatorStatusEvent { player: target, operator })?;
+            client.send_message(&format!(
+                "{name} is {} an operator",
+                if operator { "now" } else { "no longer" }
+            ))
+        }
+        "who" => {
+            let &[name] = args.collect::<Vec<_>>().as_slice() else {
+                return client.send_message("Usage: /who <player>");
+            };
+            let Some((target, name)) = find_online_player(state, name) else {
+                return client.send_message(&format!("There is no player called {name} online."));
+            };
+            let brand = state.ecs().borrow().get::<&ClientBrandComponent>(target).map(|v| v.0.clone());
+            match brand {
+                Ok(brand) => client.send_message(&format!("{name} is using client brand {brand}")),
+                Err(_) => client.send_message(&format!("{name} has not sent a client brand")),
+            }
+        }
+        _ => client.send_message("Unknown command."),
+    }
+}
+
+/// Handles `/tp <player> <x> <y> <z>`, moving a player to some
+/// coordinates. Coordinates starting with `~` are relative to
+/// the position of the player who sent the command.
+fn handle_teleport_command(state: &GameState, client: &Client, player: EntityRef, args: &[&str]) -> anyhow::Result<()> {
+    let &[name, x, y, z] = args else {
+        return client.send_message("Usage: /tp <player> <x> <y> <z>");
+    };
+    let origin = player.get::<&EntityLocation>().unwrap().position;
+    let (Some(x), Some(y), Some(z)) = (
+        parse_coordinate(x, origin.x),
+        parse_coordinate(y, origin.y),
+        parse_coordinate(z, origin.z),
+    ) else {
+        return client.send_message("Coordinates must be numbers, or ~ followed by an optional number.");
+    };
+    if let Err(e) = CheckedBlockPosition::new(x.floor() as i32, y.floor() as i32, z.floor() as i32) {
+        return client.send_message(&format!("Cannot teleport there: {e}"));
+    }
+
+    let Some((target, name)) = find_online_player(state, name) else {
+        return client.send_message(&format!("There is no player called {name} online."));
+    };
+
+    let ecs = state.ecs().borrow();
+    let mut location = *ecs.get::<&EntityLocation>(target)?;
+    drop(ecs);
+    (location.position.x, location.position.y, location.position.z) = (x, y, z);
+    state.teleport_entity(target, location)?;
+    client.send_message(&format!("Teleported {name} to {x:.2}, {y:.2}, {z:.2}"))
+}
+
+/// Handles `/whitelist <on|off>` and `/whitelist <add|remove> <player>`,
+/// saving the whitelist after each change.
+fn handle_whitelist_command(state: &GameState, client: &Client, args: &[&str]) -> anyhow::Result<()> {
+    let mut whitelist = state.resource_mut::<Whitelist>();
+    let message = match args {
+        ["on"] => {
+            whitelist.enable();
+            "Turned on the whitelist".to_string()
+        }
+        ["off"] => {
+            whitelist.disable();
+            "Turned off the whitelist".to_string()
+        }
+        [action @ ("add" | "remove"), player] => {
+            let Some((id, name)) = find_player_id(state, player) else {
+                return client.send_message(&format!("There is no player called {player} online. Give offline players by UUID."));
+            };
+            let add = *action == "add";
+            let changed = if add { whitelist.add(id) } else { whitelist.remove(id) };
+            if !changed {
+                return client.send_message(&format!("{name} is {} whitelisted", if add { "already" } else { "not" }));
+            }
+            format!("{} {name} {} the whitelist", if add { "Added" } else { "Removed" }, if add { "to" } else { "from" })
+        }
+        _ => return client.send_message("Usage: /whitelist <on|off|add|remove> [player]"),
+    };
+    save_access_list(state, "whitelist", |dir| whitelist.save(dir));
+    client.send_message(&message)
+}
+
+/// Handles `/ban <player> [reason]`, kicking the player if they
+/// are online, and saving the ban list.
+fn handle_ban_command(state: &GameState, client: &Client, player: &str, reason: Option<&str>) -> anyhow::Result<()> {
+    let Some((id, name)) = find_player_id(state, player) else {
+        return client.send_message(&format!("There is no player called {player} online. Give offline players by UUID."));
+    };
+    let mut bans = state.resource_mut::<BanList>();
+    bans.ban(id, reason);
+    save_access_list(state, "ban list", |dir| bans.save(dir));
+
+    let server = state.resource::<Server>();
+    if let Some(banned) = server.clients().filter(|v| !v.is_disconnected()).find(|v| v.profile.id == id) {
+        let reason = bans.is_banned(id).unwrap_or_default();
+        banned.kick(&format!("You are banned from this server.\nReason: {reason}"))?;
+    }
+    tracing::info!("{} banned {}", client.profile.name, name);
+    client.send_message(&format!("Banned {name}"))
+}
+
+/// Handles `/pardon <player>`, saving the ban list.
+fn handle_pardon_command(state: &GameState, client: &Client, player: &str) -> anyhow::Result<()> {
+    let Some((id, name)) = find_player_id(state, player) else {
+        return client.send_message(&format!("There is no player called {player} online. Give offline players by UUID."));
+    };
+    let mut bans = state.resource_mut::<BanList>();
+    if !bans.unban(id) {
+        return client.send_message(&format!("{name} is not banned"));
+    }
+    save_access_list(state, "ban list", |dir| bans.save(dir));
+    client.send_message(&format!("Unbanned {name}"))
+}
+
+/// Saves some access list to the world directory, logging any failure.
+fn save_access_list(state: &GameState, name: &str, save: impl FnOnce(&Path) -> anyhow::Result<()>) {
+    let world_manager = state.resource::<WorldManager>();
+    if let Err(e) = save(world_manager.directory()) {
+        tracing::error!("Failed to save the {}: {:?}", name, e);
+    }
+}
+
+/// Finds the UUID of a player, given the name of an online
+/// player ignoring case, or the UUID of any player.
+fn find_player_id(state: &GameState, player: &str) -> Option<(Uuid, String)> {
+    let server = state.resource::<Server>();
+    let online = server
+        .clients()
+        .filter(|v| !v.is_disconnected())
+        .find(|v| v.profile.name.eq_ignore_ascii_case(player));
+    match online {
+        Some(client) => Some((client.profile.id, client.profile.name.clone())),
+        None => Uuid::parse_str(player).ok().map(|v| (v, v.to_string())),
+    }
+}
+
+/// Finds an online player by name, ignoring case,
+/// returning them with their name as it is spelled.
+fn find_online_player(state: &GameState, name: &str) -> Option<(Entity, String)> {
+    let server = state.resource::<Server>();
+    let map = state.resource::<ClientMap>();
+    let player = server
+        .clients()
+        .filter(|v| !v.is_disconnected())
+        .find(|v| v.profile.name.eq_ignore_ascii_case(name))
+        .and_then(|v| Some((map.try_get_mapping(v.id)?, v.profile.name.clone())));
+    player
+}
+
+/// Parses a command coordinate, which is relative
+/// to `origin` if it starts with `~`.
+fn parse_coordinate(arg: &str, origin: f64) -> Option<f64> {
+    let value = match arg.strip_prefix('~') {
+        Some("") => origin,
+        Some(offset) => origin + offset.parse::<f64>().ok()?,
+        None => arg.parse().ok()?,
+    };
+    value.is_finite().then_some(value)
+}
+
+/// Moves players back if they travelled further since their last
+/// validated position than the movement packets they sent allow,
+/// kicking repeat offenders.
+pub fn handle_position_validation(state: &GameState) -> anyhow::Result<()> {
+    let server = state.resource::<Server>();
+    let max_speed = state.resource::<Arc<Config>>().max_speed_blocks_per_tick;
+    let now = Instant::now();
+
+    let mut rejected = vec![];
+    {
+        let mut ecs = state.ecs().borrow_mut();
+        for (entity, (id, loc, validated, violations, abilities)) in ecs
+            .query_mut::<(&NetworkID, &mut EntityLocation, &mut ValidatedPosition, &mut AntiCheatViolations, &PlayerAbilitiesComponent)>()
+            .with::<&PlayerMarker>()
+        {
+            let (old, new) = (validated.position, loc.position);
+            let moves = std::mem::take(&mut validated.moves).max(1);
+            let allowed = max_speed * f64::from(moves);
+            let horizontal = (new.x - old.x).hypot(new.z - old.z);
+            if abilities.flying || horizontal <= allowed {
+                validated.position = new;
+                continue;
+            }
+
+            let client = server.get_client(*id)?;
+            tracing::warn!(
+                "{} moved {:.2} blocks in {} moves, more than {:.2}",
+                client.profile.name, horizontal, moves, allowed
+            );
+            if violations.record(now, VIOLATION_WINDOW) >= MAX_VIOLATIONS {
+                client.kick("You moved too quickly!")?;
+                continue;
+            }
+            loc.position = old;
+            client.set_position(old)?;
+            rejected.push((entity, *id, new, old));
+        }
+    }
+
+    let ecs = state.ecs().borrow();
+    for (entity, id, from, to) in rejected {
+        handle_new_position(state, server.get_client(id)?, ecs.entity(entity)?, from, to)?;
+    }
+    Ok(())
+}
+
+/// Moves a player's view to their new view distance.
+fn handle_view_distance_change(game: &GameState, player: EntityRef, old_distance: i8) -> anyhow::Result<()> {
+    let new_distance = player.get::<&ClientSettings>().unwrap().view_distance;
+    if new_distance == old_distance {
+        return Ok(());
+    }
+    let chunk = player.get::<&EntityLocation>().unwrap().position.chunk();
+    game.events().borrow().post_event(game, PlayerViewChangeEvent {
+        entity: player.entity(),
+        old_view: View::new(chunk, old_distance as u32),
+        new_view: View::new(chunk, new_distance as u32)
+    })
+}
+
+/// Switches a player's held hotbar slot, showing
+/// the newly held item to players nearby.
+fn handle_held_item_change(state: &GameState, client: &Client, player: EntityRef, slot: i16) -> anyhow::Result<()> {
+    let Some(slot) = u8::try_from(slot).ok().filter(|v| *v <= MAX_HOTBAR_SLOT) else {
+        tracing::warn!("{} switched to invalid hotbar slot {}", client.profile.name, slot);
+        return Ok(());
+    };
+    player.get::<&mut HeldItemSlot>().unwrap().0 = slot;
+    send_held_item(state, client, player)
+}
+
+/// Shows nearby players the item some player is holding.
+fn send_held_item(state: &GameState, client: &Client, player: EntityRef) -> anyhow::Result<()> {
+    let held = player.get::<&HeldItemSlot>().map_or(0, |v| v.0);
+    let item = player.get::<&PlayerData>().and_then(|data| {
+        data.inventory
+            .iter()
+            .find(|v| v.slot == held as i8)
+            .map(|v| InventorySlot::Filled(v.stack_data.clone()))
+    });
+
+    let server = state.resource::<Server>();
+    let world = state.resource::<GameWorld>();
+    let ecs = state.ecs().borrow();
+    let loc = *player.get::<&EntityLocation>().unwrap();
+    let view = View::new(loc.position.chunk(), 8);
+
+    state.for_all_entities_nearby(&ecs, &world, loc.location, view.chunks().into_iter(), |other| {
+        if other.entity() == player.entity() || !other.has::<PlayerMarker>() {
+            return Ok(());
+        }
+        let other_id = *other.get::<&NetworkID>().unwrap();
+        server.get_client(other_id)?.send_equipment(client.id, 0, item.clone().unwrap_or(InventorySlot::Empty))
+    })
+}
+
+/// The window slot creative-mode players throw items out of.
+const CREATIVE_THROW_SLOT: i16 = -1;
+
+/// Lets creative-mode players set any slot of
+/// their inventory, or throw items out of it.
+pub fn handle_creative_inventory_action(state: &GameState, client: &Client, player: EntityRef, p: play::CreativeInventoryAction) -> anyhow::Result<()> {
+    let creative = player.get::<&Gamemode>().is_some_and(|v| matches!(v.ty, GamemodeType::Creative));
+    if !player.has::<PlayerMarker>() || !creative {
+        tracing::warn!("{} used the creative inventory outside of creative mode", client.profile.name);
+        return Ok(());
+    }
+
+    if p.slot == CREATIVE_THROW_SLOT {
+        if let InventorySlot::Filled(stack) = p.item {
+            state.events().borrow().post_event(state, CreativeItemDropEvent {
+                entity: player.entity(),
+                stack
+            })?;
+        }
+        return Ok(());
+    }
+
+    let Some(slot) = inventory_slot(p.slot) else {
+        tracing::warn!("{} set invalid inventory slot {}", client.profile.name, p.slot);
+        return Ok(());
+    };
+    {
+        // Players without saved data have no inventory to update.
+        let Some(mut data) = player.get::<&mut PlayerData>() else {
+            return Ok(());
+        };
+        data.inventory.retain(|v| v.slot != slot);
+        if let InventorySlot::Filled(stack) = &p.item {
+            data.inventory.push(ItemSlot {
+                stack_data: stack.clone(),
+                slot,
+            });
+        }
+    }
+    client.send_set_slot(0, p.slot, p.item)?;
+
+    if player.get::<&HeldItemSlot>().is_some_and(|v| v.0 as i8 == slot) {
+        send_held_item(state, client, player)?;
+    }
+    Ok(())
+}
+
+/// Stops players placing blocks within spawn protection,
+/// lets them open chests, and places signs.
+fn handle_player_block_placement(state: &GameState, client: &Client, player: EntityRef, p: play::PlayerBlockPlacement) -> anyhow::Result<()> {
+    // The block placed against, offset by the face clicked.
+    let (dx, dy, dz) = match p.direction {
+        0 => (0, -1, 0),
+        1 => (0, 1, 0),
+        2 => (0, 0, -1),
+        3 => (0, 0, 1),
+        4 => (-1, 0, 0),
+        5 => (1, 0, 0),
+        // Using an item rather than placing a block.
+        _ => return handle_use_item(state, player),
+    };
+    let clicked = BlockPosition::new(p.x, i32::from(p.y), p.z);
+    let location = player.get::<&EntityLocation>().unwrap().location;
+    let container = {
+        let world = state.resource::<GameWorld>();
+        match world.block_at(location, clicked).map(|(v, _)| *v) {
+            Some(CHEST_BLOCK) => Some(ContainerKind::Chest),
+            Some(ANVIL_BLOCK) => Some(ContainerKind::Anvil),
+            _ => None,
+        }
+    };
+    if let Some(kind) = container {
+        if check_spawn_protection(state, client, player, clicked)? {
+            state.events().borrow().post_event(state, ContainerOpenEvent {
+                player: player.entity(),
+                container: ContainerBlock { position: clicked, location },
+                kind
+            })?;
+        }
+        return Ok(());
+    }
+
+    let placed = clicked.offset(dx, dy, dz);
+    if check_spawn_protection(state, client, player, placed)? {
+        place_sign(state, client, player, p.direction, placed)?;
+    }
+    Ok(())
+}
+
+/// Places a sign if some player is holding one, against the
+/// face `direction` of the block they clicked, then lets
+/// them write on it. Signs cannot hang from ceilings.
+fn place_sign(state: &GameState, client: &Client, player: EntityRef, direction: i8, position: BlockPosition) -> anyhow::Result<()> {
+    let held = player.get::<&HeldItemSlot>().map_or(0, |v| v.0);
+    let holds_sign = player.get::<&PlayerData>().is_some_and(|data| {
+        data.inventory
+            .iter()
+            .any(|v| v.slot == held as i8 && v.stack_data.id == SIGN_ITEM)
+    });
+    if !holds_sign || direction == 0 {
+        return Ok(());
+    }
+    let (block, meta) = if direction == 1 {
+        // Standing signs face one of 16 ways, towards the player.
+        let yaw = f64::from(player.get::<&EntityLocation>().unwrap().position.yaw);
+        (STANDING_SIGN, (((yaw + 180.0) * 16.0 / 360.0 + 0.5).floor() as i32 & 15) as u8)
+    } else {
+        // Wall signs face away from the block they hang on.
+        (WALL_SIGN, direction as u8)
+    };
+    let block = BlockID::new(block).expect("valid block");
+
+    let location = player.get::<&EntityLocation>().unwrap().location;
+    let mut world = state.resource_mut::<GameWorld>();
+    if !world.block_at(location, position).is_some_and(|(v, _)| *v == 0) {
+        return Ok(());
+    }
+    if world.set_block(location, position, block, meta).is_none() {
+        return Ok(());
+    }
+    let chunk = ChunkLocation::new(position.chunk(), location);
+    for_chunk_viewers(state, &world, chunk, |viewer| viewer.send_block_change(position, block, meta))?;
+    if let Some(mut editor) = player.get::<&mut SignEditor>() {
+        editor.0 = Some((position, location));
+    }
+    client.open_sign_editor(position)
+}
+
+/// The item ID of fishing rods.
+const FISHING_ROD: i16 = 346;
+/// The item ID of bows.
+const BOW: i16 = 261;
+
+/// Right-clicking the air with the held item.
+fn handle_use_item(state: &GameState, player: EntityRef) -> anyhow::Result<()> {
+    let held = player.get::<&HeldItemSlot>().map_or(0, |v| v.0);
+    let held_id = player.get::<&PlayerData>().and_then(|data| {
+        data.inventory
+            .iter()
+            .find(|v| v.slot == held as i8)
+            .map(|v| v.stack_data.id)
+    });
+    match held_id {
+        Some(FISHING_ROD) => state.events().borrow().post_event(state, FishingRodUseEvent {
+            player: player.entity()
+        })?,
+        Some(BOW) => state.events().borrow().post_event(state, BowDrawEvent {
+            player: player.entity()
+        })?,
+        _ => (),
+    }
+    Ok(())
+}
+
+/// Stops players breaking blocks within spawn protection,
+/// and lets them drop what they are holding.
+fn handle_player_digging(state: &GameState, client: &Client, player: EntityRef, p: play::PlayerDigging) -> anyhow::Result<()> {
+    match p.status {
+        DiggingStatus::Started | DiggingStatus::Finished => {
+            check_spawn_protection(state, client, player, BlockPosition::new(p.x, i32::from(p.y), p.z))?;
+        }
+        DiggingStatus::DropItem | DiggingStatus::DropItemStack => {
+            state.events().borrow().post_event(state, ItemDropEvent {
+                entity: player.entity(),
+                whole_stack: p.status == DiggingStatus::DropItemStack
+            })?;
+        }
+        DiggingStatus::ShootArrow => {
+            state.events().borrow().post_event(state, BowReleaseEvent {
+                player: player.entity()
+            })?;
+        }
+        _ => (),
+    }
+    Ok(())
+}
+
+/// How fast dropped items are thrown, in blocks per tick.
+const ITEM_THROW_SPEED: f64 = 0.3;
+/// How far above a player's feet items are dropped from.
+const ITEM_DROP_HEIGHT: f64 = 1.3;
+
+/// Total slots in a player's inventory window.
+const PLAYER_WINDOW_SLOTS: usize = 45;
+
+/// Throws items out of the hands of players who dropped them,
+/// and out of the inventory menu of creative-mode players.
+pub fn handle_item_drop(state: &GameState) -> anyhow::Result<()> {
+    let server = state.resource::<Server>();
+
+    for e in state.events().borrow().deferred_events::<ItemDropEvent>() {
+        let (dropped, held) = {
+            let ecs = state.ecs().borrow();
+            let Ok(player) = ecs.entity(e.entity) else {
+                continue;
+            };
+            let Some(data) = player.get::<&PlayerData>() else {
+                continue;
+            };
+            let held = player.get::<&HeldItemSlot>().map_or(0, |v| v.0) as i8;
+            let Some(slot) = data.inventory.iter().find(|v| v.slot == held) else {
+                continue;
+            };
+            let mut stack = slot.stack_data.clone();
+            if !e.whole_stack {
+                stack.count = 1;
+            }
+            let (loc, projectile) = item_throw(player);
+            ((stack, loc, projectile), held)
+        };
+
+        // The item is only taken once it is thrown, so
+        // none are lost where it cannot be spawned.
+        let count = dropped.0.count;
+        spawn_item(state, dropped)?;
+
+        let ecs = state.ecs().borrow();
+        let player = ecs.entity(e.entity)?;
+        let mut data = player.get::<&mut PlayerData>().unwrap();
+        let Some(index) = data.inventory.iter().position(|v| v.slot == held) else {
+            continue;
+        };
+        let slot = &mut data.inventory[index];
+        if slot.stack_data.count <= count {
+            data.inventory.remove(index);
+        } else {
+            slot.stack_data.count -= count;
         }
+
+        let client = server.get_client(*player.get::<&NetworkID>().unwrap())?;
+        client.send_window_items(0, window_slots(&data.inventory))?;
+        drop(data);
+        send_held_item(state, client, player)?;
     }
 
+    for e in state.events().borrow().deferred_events::<CreativeItemDropEvent>() {
+        let thrown = {
+            let ecs = state.ecs().borrow();
+            let Ok(player) = ecs.entity(e.entity) else {
+                continue;
+            };
+            item_throw(player)
+        };
+        spawn_item(state, (e.stack, thrown.0, thrown.1))?;
+    }
+    Ok(())
+}
+
+/// Where an item thrown by some player starts, and how it moves.
+fn item_throw(player: EntityRef) -> (EntityLocation, ProjectileComponent) {
+    let mut loc = *player.get::<&EntityLocation>().unwrap();
+    let (yaw, pitch) = (f64::from(loc.position.yaw).to_radians(), f64::from(loc.position.pitch).to_radians());
+    loc.position.y += ITEM_DROP_HEIGHT;
+    let projectile = ProjectileComponent {
+        velocity_x: -yaw.sin() * pitch.cos() * ITEM_THROW_SPEED,
+        velocity_y: -pitch.sin() * ITEM_THROW_SPEED,
+        velocity_z: yaw.cos() * pitch.cos() * ITEM_THROW_SPEED,
+        shooter: Some(player.entity()),
+        gravity: ITEM_GRAVITY,
+    };
+    (loc, projectile)
+}
+
+fn spawn_item(state: &GameState, (stack, loc, projectile): (ItemStack, EntityLocation, ProjectileComponent)) -> anyhow::Result<()> {
+    let mut builder = EntityBuilder::new();
+    builder.add(EntityDispatch::new(ItemEntity));
+    builder.add(projectile);
+    builder.add(ItemComponent(stack));
+    spawn_entity_at(state, builder, loc)?;
     Ok(())
 }
 
+/// Puts `stack` in the main inventory of some player, topping up
+/// stacks of the same item before filling empty slots from the
+/// hotbar up. Returns `false`, giving nothing, if it does not fit.
+pub(crate) fn give_item(state: &GameState, client: &Client, player: EntityRef, stack: ItemStack) -> anyhow::Result<bool> {
+    let Some(mut data) = player.get::<&mut PlayerData>() else {
+        return Ok(false);
+    };
+    let same_item = |v: &ItemSlot| {
+        v.stack_data.id == stack.id
+            && v.stack_data.meta == stack.meta
+            && v.stack_data.nbt_data == stack.nbt_data
+    };
+    let slot = if let Some(existing) = data
+        .inventory
+        .iter_mut()
+        .filter(|v| (0..=35).contains(&v.slot))
+        .find(|v| same_item(v) && v.stack_data.count + stack.count <= max_stack_size(stack.id))
+    {
+        existing.stack_data.count += stack.count;
+        existing.slot
+    } else if let Some(free) = (0..=35).find(|slot| data.inventory.iter().all(|v| v.slot != *slot)) {
+        data.inventory.push(ItemSlot { stack_data: stack, slot: free });
+        free
+    } else {
+        return Ok(false);
+    };
+
+    // Players who left are still given back what
+    // they held, to be saved with their inventory.
+    if client.is_disconnected() {
+        return Ok(true);
+    }
+    client.send_window_items(0, window_slots(&data.inventory))?;
+    drop(data);
+    if player.get::<&HeldItemSlot>().is_some_and(|v| v.0 as i8 == slot) {
+        send_held_item(state, client, player)?;
+    }
+    Ok(true)
+}
+
+/// Takes one of some item from the main inventory of a player,
+/// from the first stack of it found. Returns `false`, taking
+/// nothing, if they have none.
+pub(crate) fn take_item(state: &GameState, client: &Client, player: EntityRef, id: i16) -> anyhow::Result<bool> {
+    let Some(mut data) = player.get::<&mut PlayerData>() else {
+        return Ok(false);
+    };
+    let Some(index) = data
+        .inventory
+        .iter()
+        .position(|v| (0..=35).contains(&v.slot) && v.stack_data.id == id)
+    else {
+        return Ok(false);
+    };
+
+    let slot = data.inventory[index].slot;
+    if data.inventory[index].stack_data.count <= 1 {
+        data.inventory.remove(index);
+    } else {
+        data.inventory[index].stack_data.count -= 1;
+    }
+
+    // Players who left are still given back what
+    // they held, to be saved with their inventory.
+    if client.is_disconnected() {
+        return Ok(true);
+    }
+    client.send_window_items(0, window_slots(&data.inventory))?;
+    drop(data);
+    if player.get::<&HeldItemSlot>().is_some_and(|v| v.0 as i8 == slot) {
+        send_held_item(state, client, player)?;
+    }
+    Ok(true)
+}
+
+/// The saved inventory slot shown at some slot of a
+/// player's inventory window. The inverse of `window_slots`.
+fn inventory_slot(window_slot: i16) -> Option<i8> {
+    match window_slot {
+        // Armor, from helmet to boots.
+        v @ 5..=8 => Some(108 - v as i8),
+        // The main inventory.
+        v @ 9..=35 => Some(v as i8),
+        // The hotbar.
+        v @ 36..=44 => Some(v as i8 - 36),
+        // The crafting grid, which is not saved.
+        _ => None,
+    }
+}
+
+/// Lays saved inventory slots out as the
+/// slots of a player's inventory window.
+pub(crate) fn window_slots(inventory: &[ItemSlot]) -> Vec<InventorySlot> {
+    let mut slots = vec![InventorySlot::Empty; PLAYER_WINDOW_SLOTS];
+    for item in inventory {
+        let index = match item.slot {
+            // The hotbar.
+            v @ 0..=8 => 36 + v as usize,
+            // The rest of the main inventory.
+            v @ 9..=35 => v as usize,
+            // Armor, from boots to helmet.
+            v @ 100..=103 => 108 - v as usize,
+            _ => continue,
+        };
+        slots[index] = InventorySlot::Filled(item.stack_data.clone());
+    }
+    slots
+}
+
+/// Returns whether some player may change the block at `position`.
+/// If they may not, sends them the block as it was.
+fn check_spawn_protection(state: &GameState, client: &Client, player: EntityRef, position: BlockPosition) -> anyhow::Result<bool> {
+    let protection = state.resource::<SpawnProtection>();
+    let location = player.get::<&EntityLocation>().unwrap().location;
+    if protection.allows(location, position, player.has::<Operator>()) {
+        return Ok(true);
+    }
+    tracing::debug!("{} tried to change {} within spawn protection", client.profile.name, position);
+
+    let world = state.resource::<GameWorld>();
+    let (block, meta) = world.block_at(location, position).unwrap_or((BlockID::new(0).expect("air is valid"), 0));
+    client.send_block_change(position, block, meta)?;
+    Ok(false)
+}
+
+/// Moves a client back to the position we last accepted from it.
+fn reject_position(client: &Client, player: EntityRef) -> anyhow::Result<()> {
+    let position = player.get::<&EntityLocation>().unwrap().position;
+    client.set_position(position)
+}
+
+/// How far, in blocks, a player may move in one packet
+/// before their destination is checked for solid blocks.
+const MAX_UNCHECKED_MOVE: f64 = 1.0;
+
+/// Whether a player may move to `(x, feet_y, z)`. Moving more
+/// than a block into a solid block is a violation, and sends
+/// the player back to where they were.
+fn validate_teleport_destination(state: &GameState, client: &Client, player: EntityRef, x: f64, feet_y: f64, z: f64) -> anyhow::Result<bool> {
+    let loc = *player.get::<&EntityLocation>().unwrap();
+    let mut destination = loc.position;
+    destination.x = x;
+    destination.y = feet_y;
+    destination.z = z;
+    if loc.position.distance_to(&destination) <= MAX_UNCHECKED_MOVE {
+        return Ok(true);
+    }
+
+    let feet = destination.block();
+    let inside_block = {
+        let world = state.resource::<GameWorld>();
+        [feet, feet.offset(0, 1, 0)]
+            .into_iter()
+            .any(|v| world.block_at(loc.location, v).is_some_and(|(id, _)| block::is_solid(id)))
+    };
+    if !inside_block {
+        return Ok(true);
+    }
+
+    tracing::warn!("{} tried to move inside a solid block at {:?}", client.profile.name, feet);
+    let violations = player.get::<&mut AntiCheatViolations>().unwrap().record(Instant::now(), VIOLATION_WINDOW);
+    if violations >= MAX_VIOLATIONS {
+        client.kick("You moved into a block!")?;
+        return Ok(false);
+    }
+    reject_position(client, player)?;
+    Ok(false)
+}
+
 fn handle_new_position(game: &GameState, client: &Client, player: EntityRef, old_pos: Position, new_pos: Position) -> anyhow::Result<()> {
     client.set_client_known_position(new_pos);
 
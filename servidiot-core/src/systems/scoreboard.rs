@@ -0,0 +1,41 @@
+use servidiot_ecs::SystemExecutor;
+use servidiot_network::server::{id::NetworkID, Server};
+
+use crate::{
+    entity::player::PlayerMarker,
+    game::GameState,
+    scoreboard::{ScoreboardManager, ScoreboardSynced},
+};
+
+pub fn register_systems(s: &mut SystemExecutor<GameState>) {
+    s.add_system(sync_scoreboard);
+}
+
+/// Sends the scoreboard to new players,
+/// and scoreboard changes to everyone else.
+pub fn sync_scoreboard(state: &GameState) -> anyhow::Result<()> {
+    let server = state.resource::<Server>();
+    let mut scoreboard = state.resource_mut::<ScoreboardManager>();
+    let mut ecs = state.ecs().borrow_mut();
+
+    let mut newly_synced = vec![];
+    for (entity, (id, synced)) in ecs
+        .query::<(&NetworkID, Option<&ScoreboardSynced>)>()
+        .with::<&PlayerMarker>()
+        .iter()
+    {
+        let client = server.get_client(*id)?;
+        if synced.is_none() {
+            scoreboard.send_all(client)?;
+            newly_synced.push(entity);
+        } else if scoreboard.has_changes() {
+            scoreboard.send_changes(client)?;
+        }
+    }
+    scoreboard.clear_changes();
+
+    for entity in newly_synced {
+        ecs.insert_one(entity, ScoreboardSynced)?;
+    }
+    Ok(())
+}
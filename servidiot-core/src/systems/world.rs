@@ -1,15 +1,847 @@
-use servidiot_ecs::SystemExecutor;
-use servidiot_network::server::Server;
+use std::{collections::{HashMap, HashSet}, sync::Arc, time::Instant};
 
-use crate::{game::GameState, world::GameWorld};
+use rand::Rng;
+use servidiot_anvil::{nbt::{level::LevelData, player::PlayerData}, WorldManager};
+use servidiot_ecs::{EntityBuilder, SystemExecutor};
+use servidiot_network::{
+    io::packet::server::play::{BlockChangeRecord, ExplosionRecord},
+    server::{id::NetworkID, Server},
+};
+use servidiot_primitives::{
+    block::{self, BlockID},
+    chunk::{section::ChunkSection, ChunkBitmap},
+    item::ItemStack,
+    position::{BlockPosition, ChunkLocation, ChunkPosition, Direction, EntityLocation, Location, Position, MIN_BLOCK_Y},
+};
+
+use crate::{
+    entity::{container::ContainerBlock, item::{ItemComponent, ItemEntity, ITEM_GRAVITY}, player::{HeldItemSlot, PlayerMarker, PortalCooldown}, projectile::ProjectileComponent, spawn_entity_at, EntityDispatch},
+    events::world::{ExplosionEvent, FluidFlowEvent, PistonActivateEvent},
+    game::{ClientMap, GameState, TickCounter},
+    scheduler::{ScheduledTaskManager, TaskId},
+    systems::{login, packet},
+    world::{
+        generator::{
+            self, ChunkGenerationQueue, PopulationQueue, WorldGeneratorConfig,
+        },
+        weather::{WeatherState, WeatherSynced, WeatherSystem},
+        crops::{self, CropGrowthStage},
+        leaves::{self, LeafDecayQueue},
+        map,
+        portal,
+        AutosaveConfig, GameWorld,
+    },
+    Config,
+};
 
 pub fn register_systems(s: &mut SystemExecutor<GameState>) {
-    s.add_system(process_chunk_loads);
+    s.add_system(run_scheduled_tasks)
+        .add_system(process_chunk_loads)
+        .add_system(process_generation_queue)
+        .add_system(populate_structures)
+        .add_system(handle_explosion)
+        .add_system(process_leaf_decay)
+        .add_system(handle_crop_growth)
+        .add_system(handle_fluid_flow)
+        .add_system(handle_piston_activation)
+        .add_system(handle_portal_teleport)
+        .add_system(handle_weather)
+        .add_system(sync_weather);
+}
+
+/// How often players holding maps see them redrawn.
+const MAP_UPDATE_INTERVAL_TICKS: u64 = 20;
+
+pub fn schedule_tasks(tasks: &mut ScheduledTaskManager, autosave: &AutosaveConfig) {
+    tasks.schedule_repeating(autosave.interval_ticks, TaskId::Named("autosave"), handle_autosave);
+    tasks.schedule_repeating(MAP_UPDATE_INTERVAL_TICKS, TaskId::Named("update held maps"), update_held_maps);
+}
+
+/// Draws the chunk each player holding a map stands in onto
+/// it, showing them where on it they are. Maps are not saved,
+/// so they only show what their holders have walked through.
+pub fn update_held_maps(state: &GameState) -> anyhow::Result<()> {
+    let server = state.resource::<Server>();
+    let world = state.resource::<GameWorld>();
+    let ecs = state.ecs().borrow();
+    for (_, (id, held, data, location)) in ecs
+        .query::<(&NetworkID, &HeldItemSlot, &PlayerData, &EntityLocation)>()
+        .iter()
+    {
+        let client = server.get_client(*id)?;
+        if client.is_disconnected() {
+            continue;
+        }
+        let Some(held) = data
+            .inventory
+            .iter()
+            .find(|v| v.slot == held.0 as i8 && v.stack_data.id == map::MAP_ITEM)
+        else {
+            continue;
+        };
+        let position = location.position;
+        let block = position.block();
+        let center = map::map_center(block.x, block.z);
+        let chunk = position.chunk();
+        let Some((loaded, _, _)) = world.get_chunk(ChunkLocation::new(chunk, location.location)) else {
+            continue;
+        };
+        let Some((x, z)) = map::chunk_origin(chunk, center) else {
+            continue;
+        };
+        let region = map::render_chunk(loaded, x, z);
+        client.send_map_update(held.stack_data.meta, 0, region, &[map::player_icon(&position, center)])?;
+    }
+    Ok(())
+}
+
+/// Saves every loaded chunk and the data of
+/// every online player, telling players so.
+pub fn handle_autosave(state: &GameState) -> anyhow::Result<()> {
+    let started = Instant::now();
+    let server = state.resource::<Server>();
+    let map = state.resource::<ClientMap>();
+    let mut world_manager = state.resource_mut::<WorldManager>();
+    let ecs = state.ecs().borrow();
+
+    let chunks = state.resource_mut::<GameWorld>().save_ticketed()?;
+    let mut players = 0;
+    for client in server.clients().filter(|v| !v.is_disconnected()) {
+        if let Some(player) = map.try_get_mapping(client.id).and_then(|v| ecs.entity(v).ok()) {
+            login::save_player_data(&mut world_manager, client, player);
+            players += 1;
+        }
+    }
+    tracing::info!(
+        "Autosave queued {} chunks and saved {} players in {}ms",
+        chunks, players, started.elapsed().as_millis()
+    );
+    for client in server.clients().filter(|v| !v.is_disconnected()) {
+        client.send_message("The world was saved.")?;
+    }
+    Ok(())
+}
+
+/// Runs every scheduled task due this tick. Tasks
+/// may schedule more tasks, or cancel others.
+pub fn run_scheduled_tasks(state: &GameState) -> anyhow::Result<()> {
+    let tick = state.resource::<TickCounter>().current_tick();
+    let due = state.resource_mut::<ScheduledTaskManager>().take_due(tick);
+    for (id, task) in due {
+        if let Err(e) = task.run(state) {
+            tracing::error!("Scheduled task {:?} error: {:?}", id, e);
+        }
+    }
+    Ok(())
 }
 
 pub fn process_chunk_loads(state: &GameState) -> anyhow::Result<()> {
-    let mut world = state.resources().get_mut::<GameWorld>();
-    let server = state.resources().get::<Server>();
+    let mut world = state.resource_mut::<GameWorld>();
+    let server = state.resource::<Server>();
 
     world.process_loads(&server)
-}
\ No newline at end of file
+}
+
+/// Generates chunks missing on disk, at most
+/// `Config::max_generated_chunks_per_tick` each tick.
+pub fn process_generation_queue(state: &GameState) -> anyhow::Result<()> {
+    let mut world = state.resource_mut::<GameWorld>();
+    let mut queue = state.resource_mut::<ChunkGenerationQueue>();
+    let mut population = state.resource_mut::<PopulationQueue>();
+    let server = state.resource::<Server>();
+
+    for location in world.take_missing_chunks() {
+        queue.push(location);
+    }
+
+    let config = state.resource::<WorldGeneratorConfig>();
+    let max_per_tick = state.resource::<Arc<Config>>().max_generated_chunks_per_tick;
+    for _ in 0..max_per_tick {
+        let Some(location) = queue.pop() else {
+            break;
+        };
+        if world.is_loaded(location) {
+            continue;
+        }
+        world.add_chunk(location, config.generator.generate_chunk(location));
+        world.notify_loaded(&server, location)?;
+        population.push(location);
+    }
+    Ok(())
+}
+
+/// Runs each `ChunkPopulator` over generated chunks once all four
+/// of their neighbors are loaded, then resends them to players.
+pub fn populate_structures(state: &GameState) -> anyhow::Result<()> {
+    let mut world = state.resource_mut::<GameWorld>();
+    let mut queue = state.resource_mut::<PopulationQueue>();
+    let config = state.resource::<WorldGeneratorConfig>();
+    let server = state.resource::<Server>();
+    let ecs = state.ecs().borrow();
+
+    let ready = queue.take_ready(|location| {
+        !world.is_loaded(location)
+            || generator::neighbors(location.position)
+                .into_iter()
+                .all(|v| world.is_loaded(ChunkLocation::new(v, location.location)))
+    });
+    for location in ready {
+        let Some((chunk, neighbors)) = world.chunk_with_neighbors_mut(location) else {
+            continue;
+        };
+        for populator in &config.populators {
+            populator.populate(chunk, config.seed, neighbors)?;
+        }
+
+        let (chunk, _, viewers) = world.get_chunk(location).expect("checked");
+        for viewer in viewers {
+            if let Ok(id) = ecs.get::<&NetworkID>(*viewer) {
+                server.get_client(*id)?.send_chunk(chunk, ChunkBitmap::full())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The largest explosion radius, so that every destroyed
+/// block fits in an `ExplosionRecord`.
+const MAX_EXPLOSION_RADIUS: f32 = 16.0;
+
+const AIR: u16 = 0;
+const BEDROCK: u16 = 7;
+
+/// Entities are pushed by explosions up to
+/// this many radii away from the center.
+const EXPLOSION_PUSH_RADII: f64 = 2.0;
+
+/// How far, in chunks, players can see explosions from.
+const EXPLOSION_VIEW_DISTANCE: i32 = 8;
+
+/// Destroys the blocks caught in each explosion, shows it to players
+/// nearby, and pushes players and projectiles other than the
+/// exploding entity away.
+pub fn handle_explosion(state: &GameState) -> anyhow::Result<()> {
+    let server = state.resource::<Server>();
+    let mut world = state.resource_mut::<GameWorld>();
+    let mut leaves = state.resource_mut::<LeafDecayQueue>();
+    let mut ecs = state.ecs().borrow_mut();
+    let mut rng = rand::thread_rng();
+    let mut broken_containers = vec![];
+
+    for explosion in state.events().borrow().deferred_events::<ExplosionEvent>() {
+        let radius = explosion.radius.clamp(0.0, MAX_EXPLOSION_RADIUS);
+        // Clients offset records from the center truncated towards zero.
+        #[allow(clippy::cast_possible_truncation)]
+        let center = BlockPosition::new(explosion.center.x as i32, explosion.center.y as i32, explosion.center.z as i32);
+        let air = BlockID::new(AIR).expect("valid block");
+
+        let mut records = vec![];
+        let mut changes: HashMap<ChunkPosition, Vec<BlockChangeRecord>> = HashMap::new();
+        let r = radius.ceil() as i32;
+        for dx in -r..=r {
+            for dy in -r..=r {
+                for dz in -r..=r {
+                    if ((dx * dx + dy * dy + dz * dz) as f32) > radius * radius {
+                        continue;
+                    }
+                    let block = center.offset(dx, dy, dz);
+                    if let Some((destroyed, change)) = destroy_block(&mut world, explosion.location, block, air) {
+                        records.push(ExplosionRecord { dx: dx as i8, dy: dy as i8, dz: dz as i8 });
+                        changes.entry(block.chunk()).or_default().push(change);
+                        if leaves::is_log(destroyed) {
+                            queue_adjacent_leaves(&world, &mut leaves, &mut rng, explosion.location, block);
+                        }
+                        if packet::is_container_block(destroyed) {
+                            broken_containers.push(ContainerBlock { position: block, location: explosion.location });
+                        }
+                    }
+                }
+            }
+        }
+        for (chunk, changes) in &changes {
+            if let Some((chunk, ..)) = world.get_chunk_mut(ChunkLocation::new(*chunk, explosion.location)) {
+                let columns = changes.iter().map(|v| (v.x, v.z)).collect::<HashSet<_>>();
+                for (x, z) in columns {
+                    chunk.recalculate_column(usize::from(x), usize::from(z));
+                }
+            }
+        }
+
+        let push_radius = f64::from(radius) * EXPLOSION_PUSH_RADII;
+        let push = |loc: &EntityLocation| {
+            let (dx, dy, dz) = (
+                loc.position.x - explosion.center.x,
+                loc.position.y - explosion.center.y,
+                loc.position.z - explosion.center.z,
+            );
+            let distance = loc.position.distance_to(&explosion.center);
+            if loc.location != explosion.location || distance >= push_radius || distance == 0.0 {
+                return (0.0, 0.0, 0.0);
+            }
+            let strength = (1.0 - distance / push_radius) / distance;
+            (dx * strength, dy * strength, dz * strength)
+        };
+
+        for (entity, (loc, projectile)) in ecs.query_mut::<(&EntityLocation, &mut ProjectileComponent)>() {
+            if Some(entity) == explosion.entity {
+                continue;
+            }
+            let (x, y, z) = push(loc);
+            projectile.velocity_x += x;
+            projectile.velocity_y += y;
+            projectile.velocity_z += z;
+        }
+
+        let center_chunk = explosion.center.chunk();
+        for (_, (id, loc)) in ecs
+            .query::<(&NetworkID, &EntityLocation)>()
+            .with::<&PlayerMarker>()
+            .iter()
+        {
+            let chunk = loc.position.chunk();
+            if loc.location != explosion.location
+                || (chunk.x - center_chunk.x).abs() > EXPLOSION_VIEW_DISTANCE
+                || (chunk.z - center_chunk.z).abs() > EXPLOSION_VIEW_DISTANCE
+            {
+                continue;
+            }
+            let client = server.get_client(*id)?;
+            for (chunk, changes) in &changes {
+                client.send_block_changes(*chunk, changes.clone())?;
+            }
+            let (x, y, z) = push(loc);
+            client.send_explosion(explosion.center, radius, records.clone(), (x as f32, y as f32, z as f32))?;
+        }
+    }
+
+    drop((server, world, leaves, ecs));
+    for block in broken_containers {
+        packet::break_container(state, block)?;
+    }
+    Ok(())
+}
+
+/// Replaces a block with air, returning the old block and the
+/// change if there was a block there which explosions can destroy.
+fn destroy_block(world: &mut GameWorld, location: Location, block: BlockPosition, air: BlockID) -> Option<(BlockID, BlockChangeRecord)> {
+    let (destroyed, _) = world.block_at(location, block)?;
+    if matches!(*destroyed, AIR | BEDROCK) {
+        return None;
+    }
+    world.set_block(location, block, air, 0)?;
+    Some((destroyed, BlockChangeRecord {
+        x: (block.x & 15) as u8,
+        y: block.y as u8,
+        z: (block.z & 15) as u8,
+        block: air,
+        meta: 0,
+    }))
+}
+
+/// The least and most ticks before leaves
+/// which lost their log are checked.
+const LEAF_DECAY_DELAY_TICKS: std::ops::RangeInclusive<u32> = 20..=60;
+/// The most leaves checked each tick.
+const MAX_LEAF_CHECKS_PER_TICK: usize = 64;
+/// The chance decaying leaves drop a sapling.
+const SAPLING_CHANCE: f64 = 0.05;
+const SAPLING: i16 = 6;
+
+/// Queues the leaves next to `block` to check whether they
+/// are still near a log, after a random delay.
+fn queue_adjacent_leaves(world: &GameWorld, queue: &mut LeafDecayQueue, rng: &mut impl Rng, location: Location, block: BlockPosition) {
+    for id in 0..6 {
+        let direction = Direction::from_id(id).expect("valid direction");
+        let next = block.offset_towards(direction, 1);
+        if world.block_at(location, next).is_some_and(|(id, meta)| leaves::is_decaying_leaves(id, meta)) {
+            queue.push(next, location, rng.gen_range(LEAF_DECAY_DELAY_TICKS));
+        }
+    }
+}
+
+/// Breaks leaves which are no longer near a log, sometimes
+/// dropping a sapling. Their neighbors are then checked in turn.
+pub fn process_leaf_decay(state: &GameState) -> anyhow::Result<()> {
+    let mut drops = vec![];
+    {
+        let server = state.resource::<Server>();
+        let mut world = state.resource_mut::<GameWorld>();
+        let mut queue = state.resource_mut::<LeafDecayQueue>();
+        let air = BlockID::new(AIR).expect("valid block");
+        let mut rng = rand::thread_rng();
+
+        let mut changes: HashMap<ChunkLocation, Vec<BlockChangeRecord>> = HashMap::new();
+        for check in queue.tick(MAX_LEAF_CHECKS_PER_TICK) {
+            let (position, location) = (check.position, check.location);
+            // Leaves may have been changed since they were queued.
+            let Some((leaf, meta)) = world.block_at(location, position).filter(|(id, meta)| leaves::is_decaying_leaves(*id, *meta)) else {
+                continue;
+            };
+            if leaves::is_near_log(position, |v| world.block_at(location, v).map(|(id, _)| id)) {
+                continue;
+            }
+            if let Some(change) = set_block(&mut world, location, position, air, 0) {
+                changes.entry(ChunkLocation::new(position.chunk(), location)).or_default().push(change);
+            }
+            queue_adjacent_leaves(&world, &mut queue, &mut rng, location, position);
+            if rng.gen_bool(SAPLING_CHANCE) {
+                drops.push((position, location, leaves::sapling_meta(leaf, meta)));
+            }
+        }
+        send_block_changes(state, &server, &world, changes)?;
+    }
+
+    for (position, location, meta) in drops {
+        let mut builder = EntityBuilder::new();
+        builder.add(EntityDispatch::new(ItemEntity));
+        builder.add(ItemComponent(ItemStack { count: 1, meta, id: SAPLING, nbt_data: None }));
+        builder.add(ProjectileComponent {
+            velocity_x: 0.0,
+            velocity_y: 0.0,
+            velocity_z: 0.0,
+            shooter: None,
+            gravity: ITEM_GRAVITY,
+        });
+        let position = Position::new(
+            f64::from(position.x) + 0.5,
+            f64::from(position.y) + 0.5,
+            f64::from(position.z) + 0.5,
+            0.0,
+            0.0,
+            false,
+        );
+        spawn_entity_at(state, builder, EntityLocation { position, location })?;
+    }
+    Ok(())
+}
+
+/// How many random ticks each chunk section gets every tick.
+const RANDOM_TICKS_PER_SECTION: usize = 3;
+
+/// Gives crops picked by random ticks which stand on farmland, lit
+/// from above, a chance to grow. Sky light darkens with the time of day.
+pub fn handle_crop_growth(state: &GameState) -> anyhow::Result<()> {
+    let server = state.resource::<Server>();
+    let mut world = state.resource_mut::<GameWorld>();
+    let level = state.resource::<Option<LevelData>>();
+    let sky_darkening = level.as_ref().map_or(0, |v| crops::sky_darkening(v.day_time));
+    let mut rng = rand::thread_rng();
+
+    let mut ticked = vec![];
+    for (chunk_location, chunk) in world.loaded_chunks() {
+        for section in 0..ChunkSection::SECTIONS_PER_CHUNK {
+            if chunk.get_section(section as u8).is_none_or(|v| v.is_empty()) {
+                continue;
+            }
+            for _ in 0..RANDOM_TICKS_PER_SECTION {
+                let (x, z) = (rng.gen_range(0..16), rng.gen_range(0..16));
+                let y = section * ChunkSection::HEIGHT + rng.gen_range(0..ChunkSection::HEIGHT);
+                if chunk.block_type_at(x, y, z).is_some_and(crops::is_crop) {
+                    let position = BlockPosition::new(
+                        chunk_location.position.x * 16 + x as i32,
+                        y as i32,
+                        chunk_location.position.z * 16 + z as i32,
+                    );
+                    ticked.push((position, chunk_location.location));
+                }
+            }
+        }
+    }
+
+    let mut changes: HashMap<ChunkLocation, Vec<BlockChangeRecord>> = HashMap::new();
+    for (position, location) in ticked {
+        let Some((id, meta)) = world.block_at(location, position) else {
+            continue;
+        };
+        let Some((below, below_meta)) = world.block_at(location, position.offset(0, -1, 0)) else {
+            continue;
+        };
+        let Some(light) = world.light_at(location, position.offset(0, 1, 0), sky_darkening) else {
+            continue;
+        };
+        if !crops::can_grow(below, light) || rng.gen_range(0..crops::growth_odds(below_meta)) != 0 {
+            continue;
+        }
+        let Some(stage) = CropGrowthStage(meta).next() else {
+            continue;
+        };
+        if let Some(change) = set_block(&mut world, location, position, id, stage.0) {
+            changes.entry(ChunkLocation::new(position.chunk(), location)).or_default().push(change);
+        }
+    }
+    send_block_changes(state, &server, &world, changes)
+}
+
+/// How a fluid spreads.
+struct Fluid {
+    /// The block ID of the flowing fluid.
+    flowing: u16,
+    /// How many blocks the fluid spreads from its source.
+    spread: u8,
+    /// How much the meta of the fluid rises with each block.
+    meta_step: u8,
+}
+
+const WATER: Fluid = Fluid { flowing: 8, spread: 7, meta_step: 1 };
+const LAVA: Fluid = Fluid { flowing: 10, spread: 3, meta_step: 2 };
+/// Set on the meta of fluids falling straight down.
+const FALLING_FLUID: u8 = 8;
+
+impl Fluid {
+    fn of(block: BlockID) -> Option<Self> {
+        match *block {
+            8 | 9 => Some(WATER),
+            10 | 11 => Some(LAVA),
+            _ => None,
+        }
+    }
+}
+
+/// Spreads fluids one block each tick, falling straight
+/// down where they can and otherwise flowing sideways.
+pub fn handle_fluid_flow(state: &GameState) -> anyhow::Result<()> {
+    let server = state.resource::<Server>();
+    let mut world = state.resource_mut::<GameWorld>();
+
+    let mut next = vec![];
+    let mut changes: HashMap<ChunkLocation, Vec<BlockChangeRecord>> = HashMap::new();
+    for e in state.events().borrow().deferred_events::<FluidFlowEvent>() {
+        let Some(fluid) = Fluid::of(e.fluid_id) else {
+            continue;
+        };
+        let flowing = BlockID::new(fluid.flowing).expect("valid block");
+
+        let below = e.block.offset(0, -1, 0);
+        let targets = if can_flow_into(&world, e.location, below) {
+            // Falling fluid spreads out again from where it lands.
+            vec![(below, FALLING_FLUID, 0)]
+        } else if e.source_level < fluid.spread {
+            let level = e.source_level + 1;
+            e.block
+                .cardinal_neighbors()
+                .into_iter()
+                .filter(|v| can_flow_into(&world, e.location, *v))
+                .map(|v| (v, level * fluid.meta_step, level))
+                .collect()
+        } else {
+            vec![]
+        };
+
+        for (block, meta, source_level) in targets {
+            if let Some(change) = set_block(&mut world, e.location, block, flowing, meta) {
+                changes.entry(ChunkLocation::new(block.chunk(), e.location)).or_default().push(change);
+                next.push(FluidFlowEvent {
+                    block,
+                    location: e.location,
+                    fluid_id: flowing,
+                    source_level,
+                });
+            }
+        }
+    }
+
+    send_block_changes(state, &server, &world, changes)?;
+
+    let events = state.events().borrow();
+    for event in next {
+        events.post_event(state, event)?;
+    }
+    Ok(())
+}
+
+/// Whether fluid can flow into a block. Fluids wash away
+/// blocks which are not solid, but never other fluids.
+fn can_flow_into(world: &GameWorld, location: Location, block: BlockPosition) -> bool {
+    let Ok(y) = usize::try_from(block.y) else {
+        return false;
+    };
+    world
+        .get_chunk(ChunkLocation::new(block.chunk(), location))
+        .and_then(|v| v.0.block_type_at((block.x & 15) as usize, y, (block.z & 15) as usize))
+        .is_some_and(|v| !block::is_solid(v) && Fluid::of(v).is_none())
+}
+
+/// Sets the block at some position, returning the change.
+fn set_block(world: &mut GameWorld, location: Location, block: BlockPosition, id: BlockID, meta: u8) -> Option<BlockChangeRecord> {
+    world.set_block(location, block, id, meta)?;
+    Some(BlockChangeRecord {
+        x: (block.x & 15) as u8,
+        y: block.y as u8,
+        z: (block.z & 15) as u8,
+        block: id,
+        meta,
+    })
+}
+
+/// Sends block changes to the players viewing each chunk.
+fn send_block_changes(state: &GameState, server: &Server, world: &GameWorld, changes: HashMap<ChunkLocation, Vec<BlockChangeRecord>>) -> anyhow::Result<()> {
+    let ecs = state.ecs().borrow();
+    for (chunk, changes) in changes {
+        let Some((_, _, viewers)) = world.get_chunk(chunk) else {
+            continue;
+        };
+        for viewer in viewers {
+            if let Ok(id) = ecs.get::<&NetworkID>(*viewer) {
+                server.get_client(*id)?.send_block_changes(chunk.position, changes.clone())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+const PISTON: u16 = 33;
+const STICKY_PISTON: u16 = 29;
+const PISTON_HEAD: u16 = 34;
+/// Set on the meta of extended pistons, and of sticky piston heads.
+const PISTON_FLAG: u8 = 8;
+/// The most blocks a piston can push.
+const PISTON_PUSH_LIMIT: usize = 12;
+
+/// Extends and retracts pistons, moving the blocks in front of them.
+pub fn handle_piston_activation(state: &GameState) -> anyhow::Result<()> {
+    let server = state.resource::<Server>();
+    let mut world = state.resource_mut::<GameWorld>();
+    let air = BlockID::new(AIR).expect("valid block");
+
+    let mut changes: HashMap<ChunkLocation, Vec<BlockChangeRecord>> = HashMap::new();
+    for e in state.events().borrow().deferred_events::<PistonActivateEvent>() {
+        let base = e.head_pos.offset_towards(e.facing.opposite(), 1);
+        let Some((base_id, base_meta)) = world.block_at(e.location, base) else {
+            continue;
+        };
+        let sticky = match *base_id {
+            STICKY_PISTON => true,
+            PISTON => false,
+            _ => continue,
+        };
+        if (base_meta & PISTON_FLAG != 0) == e.extending {
+            continue;
+        }
+
+        // Blocks are set in order, so later writes win.
+        let mut writes = vec![];
+        if e.extending {
+            let Some(pushed) = push_chain(&world, e.location, e.head_pos, e.facing) else {
+                continue;
+            };
+            for (block, id, meta) in pushed.into_iter().rev() {
+                writes.push((block.offset_towards(e.facing, 1), id, meta));
+            }
+            let head_meta = e.facing.id() | if sticky { PISTON_FLAG } else { 0 };
+            writes.push((e.head_pos, BlockID::new(PISTON_HEAD).expect("valid block"), head_meta));
+            writes.push((base, base_id, e.facing.id() | PISTON_FLAG));
+        } else if world.block_at(e.location, e.head_pos).is_some_and(|(v, _)| *v == PISTON_HEAD) {
+            writes.push((e.head_pos, air, 0));
+            if sticky {
+                let pulled = e.head_pos.offset_towards(e.facing, 1);
+                if let Some((id, meta)) = world.block_at(e.location, pulled) {
+                    if block::is_solid(id) && can_move(id, meta) {
+                        writes.push((pulled, air, 0));
+                        writes.push((e.head_pos, id, meta));
+                    }
+                }
+            }
+            writes.push((base, base_id, e.facing.id()));
+        } else {
+            // The head is missing, so only the base retracts.
+            writes.push((base, base_id, e.facing.id()));
+        }
+
+        for (block, id, meta) in writes {
+            if let Some(change) = set_block(&mut world, e.location, block, id, meta) {
+                changes.entry(ChunkLocation::new(block.chunk(), e.location)).or_default().push(change);
+            }
+        }
+    }
+
+    send_block_changes(state, &server, &world, changes)
+}
+
+/// Whether pistons can move some block. Extended
+/// pistons are held in place by their heads.
+fn can_move(id: BlockID, meta: u8) -> bool {
+    let extended = matches!(*id, PISTON | STICKY_PISTON) && meta & PISTON_FLAG != 0;
+    block::is_movable(id) && !extended
+}
+
+/// The blocks a piston extending into `head_pos` pushes, nearest
+/// first. Returns `None` if the piston cannot extend.
+///
+/// Pushing stops at air, or at a block which is not solid,
+/// which the pushed blocks replace.
+fn push_chain(world: &GameWorld, location: Location, head_pos: BlockPosition, facing: Direction) -> Option<Vec<(BlockPosition, BlockID, u8)>> {
+    let mut pushed = vec![];
+    let mut block = head_pos;
+    loop {
+        // Unloaded chunks and the edges of the world stop pistons.
+        let (id, meta) = world.block_at(location, block)?;
+        if *id == AIR {
+            return Some(pushed);
+        }
+        if !can_move(id, meta) {
+            return None;
+        }
+        if !block::is_solid(id) {
+            return Some(pushed);
+        }
+        if pushed.len() == PISTON_PUSH_LIMIT {
+            return None;
+        }
+        pushed.push((block, id, meta));
+        block = block.offset_towards(facing, 1);
+    }
+}
+
+/// Counts the weather down to its next change,
+/// keeping the level's saved weather up to date.
+pub fn handle_weather(state: &GameState) -> anyhow::Result<()> {
+    let mut weather = state.resource_mut::<WeatherState>();
+    weather.tick(&mut rand::thread_rng());
+    if let Some(level) = state.resource_mut::<Option<LevelData>>().as_mut() {
+        weather.save(level);
+    }
+    Ok(())
+}
+
+/// Sends the weather to new players, and
+/// tells everyone else when it changes.
+pub fn sync_weather(state: &GameState) -> anyhow::Result<()> {
+    let server = state.resource::<Server>();
+    let weather = state.resource::<WeatherState>();
+    let mut system = state.resource_mut::<WeatherSystem>();
+    let mut ecs = state.ecs().borrow_mut();
+
+    let changes = system.take_changes(&weather);
+
+    let mut newly_synced = vec![];
+    for (entity, (id, synced)) in ecs
+        .query::<(&NetworkID, Option<&WeatherSynced>)>()
+        .with::<&PlayerMarker>()
+        .iter()
+    {
+        let client = server.get_client(*id)?;
+        let to_send = if synced.is_some() {
+            changes.clone()
+        } else {
+            newly_synced.push(entity);
+            WeatherSystem::initialize(&weather)
+        };
+        for change in to_send {
+            client.send_game_state(change)?;
+        }
+    }
+
+    for entity in newly_synced {
+        ecs.insert_one(entity, WeatherSynced)?;
+    }
+    Ok(())
+}
+
+/// Sends players who stand in a portal for
+/// `Config::portal_delay_ticks` to its dimension.
+pub fn handle_portal_teleport(state: &GameState) -> anyhow::Result<()> {
+    let mut started = vec![];
+    let mut left = vec![];
+    let mut arrived = vec![];
+    {
+        let server = state.resource::<Server>();
+        let mut world = state.resource_mut::<GameWorld>();
+        let delay = state.resource::<Arc<Config>>().portal_delay_ticks;
+        let ecs = state.ecs().borrow();
+
+        let mut changes: HashMap<ChunkLocation, Vec<BlockChangeRecord>> = HashMap::new();
+        for (entity, (location, cooldown)) in ecs
+            .query::<(&EntityLocation, Option<&mut PortalCooldown>)>()
+            .with::<&PlayerMarker>()
+            .iter()
+        {
+            let target = world
+                .block_at(location.location, location.position.block())
+                .and_then(|(block, _)| portal::portal_target(*block, location.location));
+            let (target, cooldown) = match (target, cooldown) {
+                (Some(target), Some(cooldown)) if cooldown.target_dimension == target.dimension => (target, cooldown),
+                (Some(target), _) => {
+                    started.push((entity, PortalCooldown {
+                        remaining_ticks: delay,
+                        target_dimension: target.dimension,
+                    }));
+                    continue;
+                }
+                (None, Some(_)) => {
+                    left.push(entity);
+                    continue;
+                }
+                (None, None) => continue,
+            };
+
+            cooldown.remaining_ticks = cooldown.remaining_ticks.saturating_sub(1);
+            if cooldown.remaining_ticks > 0 {
+                continue;
+            }
+            let mut destination = portal::portal_destination(*location, target);
+            let center = destination.position.block();
+
+            // Everywhere the arrival may be searched for or built
+            // must be loaded first, so players wait in the portal.
+            let around = center.chunk();
+            let mut loading = false;
+            for x in -1..=1 {
+                for z in -1..=1 {
+                    let chunk = ChunkLocation::new(ChunkPosition::new(around.x + x, around.z + z), target);
+                    if !world.is_loaded(chunk) {
+                        world.request_chunk(chunk)?;
+                        loading = true;
+                    }
+                }
+            }
+            if loading {
+                continue;
+            }
+
+            let max_y = portal::max_arrival_y(target);
+            let block_at = |v| world.block_at(target, v).map(|(block, _)| block);
+            let arrival = if target.dimension == Location::the_end().dimension {
+                None
+            } else {
+                portal::find_portal(center, max_y, block_at).or_else(|| {
+                    portal::find_safe_y(max_y, |y| block_at(BlockPosition::new(center.x, y, center.z)))
+                        .map(|y| BlockPosition::new(center.x, y, center.z))
+                })
+            };
+            let arrival = match arrival {
+                Some(v) => v,
+                None => {
+                    let feet = BlockPosition::new(center.x, center.y.clamp(MIN_BLOCK_Y + 1, max_y), center.z);
+                    for (block, id) in portal::platform(feet) {
+                        let id = BlockID::new(id).expect("valid block");
+                        if let Some(record) = set_block(&mut world, target, block, id, 0) {
+                            changes.entry(ChunkLocation::new(block.chunk(), target)).or_default().push(record);
+                        }
+                    }
+                    feet
+                }
+            };
+            destination.position.x = f64::from(arrival.x) + 0.5;
+            destination.position.y = f64::from(arrival.y);
+            destination.position.z = f64::from(arrival.z) + 0.5;
+
+            left.push(entity);
+            arrived.push((entity, destination));
+        }
+        send_block_changes(state, &server, &world, changes)?;
+    }
+
+    {
+        let mut ecs = state.ecs().borrow_mut();
+        for (entity, cooldown) in started {
+            ecs.insert_one(entity, cooldown)?;
+        }
+        for entity in left {
+            ecs.remove_one::<PortalCooldown>(entity)?;
+        }
+    }
+    for (entity, location) in arrived {
+        state.teleport_entity(entity, location)?;
+    }
+    Ok(())
+}
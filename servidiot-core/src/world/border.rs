@@ -0,0 +1,32 @@
+/// The world border. Protocol 5 clients cannot be shown it,
+/// so it is only enforced on the server.
+pub struct WorldBorder {
+    /// The X coordinate of the border's center.
+    pub center_x: f64,
+    /// The Z coordinate of the border's center.
+    pub center_z: f64,
+    /// The length of one side of the border, in blocks.
+    pub diameter: f64,
+}
+
+impl Default for WorldBorder {
+    fn default() -> Self {
+        Self::new(0.0, 0.0, 60_000_000.0)
+    }
+}
+
+impl WorldBorder {
+    pub fn new(center_x: f64, center_z: f64, diameter: f64) -> Self {
+        Self {
+            center_x,
+            center_z,
+            diameter,
+        }
+    }
+
+    /// Whether the position (x, z) lies within this border.
+    pub fn contains(&self, x: f64, z: f64) -> bool {
+        let radius = self.diameter / 2.0;
+        (x - self.center_x).abs() <= radius && (z - self.center_z).abs() <= radius
+    }
+}
@@ -0,0 +1,104 @@
+use std::f64::consts::PI;
+
+use servidiot_primitives::block::{BlockID, LightLevel};
+
+const WHEAT: u16 = 59;
+const FARMLAND: u16 = 60;
+const CARROTS: u16 = 141;
+const POTATOES: u16 = 142;
+
+/// The light level a crop needs to grow.
+pub const MIN_GROWTH_LIGHT: LightLevel = match LightLevel::new(9) {
+    Some(v) => v,
+    None => unreachable!(),
+};
+
+/// How many ticks a day lasts.
+const DAY_TICKS: i64 = 24000;
+/// The most levels sky light is darkened by at night.
+const MAX_SKY_DARKENING: f64 = 11.0;
+/// The odds of a random tick, times how well a crop grows,
+/// that it grows. Crops on wet farmland grow twice as well.
+const GROWTH_ODDS: f64 = 25.0;
+
+/// How far a crop has grown, stored as its block meta.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct CropGrowthStage(pub u8);
+
+impl CropGrowthStage {
+    /// A fully grown crop.
+    pub const MAX: Self = Self(7);
+
+    /// The stage after this one, or
+    /// `None` if the crop is fully grown.
+    pub fn next(self) -> Option<Self> {
+        (self < Self::MAX).then_some(Self(self.0 + 1))
+    }
+}
+
+/// Whether some block is a crop planted on farmland.
+pub fn is_crop(block: BlockID) -> bool {
+    matches!(*block, WHEAT | CARROTS | POTATOES)
+}
+
+/// Whether a crop above `below`, lit by `light`, may grow.
+pub fn can_grow(below: BlockID, light: LightLevel) -> bool {
+    *below == FARMLAND && light >= MIN_GROWTH_LIGHT
+}
+
+/// One in how many random ticks a crop on farmland
+/// with `farmland_meta` grows. Farmland with any moisture is wet.
+pub fn growth_odds(farmland_meta: u8) -> u32 {
+    let rate = if farmland_meta > 0 { 4.0 } else { 2.0 };
+    (GROWTH_ODDS / rate) as u32 + 1
+}
+
+/// How many levels sky light is darkened by at `day_time`,
+/// from none at noon to [`MAX_SKY_DARKENING`] at midnight.
+pub fn sky_darkening(day_time: i64) -> u8 {
+    let mut angle = day_time.rem_euclid(DAY_TICKS) as f64 / DAY_TICKS as f64 - 0.25;
+    if angle < 0.0 {
+        angle += 1.0;
+    }
+    let eased = 1.0 - ((angle * PI).cos() + 1.0) / 2.0;
+    let angle = angle + (eased - angle) / 3.0;
+    let darkness = 1.0 - ((angle * PI * 2.0).cos() * 2.0 + 0.5).clamp(0.0, 1.0);
+    (darkness * MAX_SKY_DARKENING) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use servidiot_primitives::block::{BlockID, LightLevel};
+
+    use super::{can_grow, growth_odds, sky_darkening, CropGrowthStage, FARMLAND, MIN_GROWTH_LIGHT};
+
+    #[test]
+    fn next_stage_test() {
+        assert_eq!(CropGrowthStage(0).next(), Some(CropGrowthStage(1)));
+        assert_eq!(CropGrowthStage(6).next(), Some(CropGrowthStage::MAX));
+        assert_eq!(CropGrowthStage::MAX.next(), None);
+    }
+
+    #[test]
+    fn can_grow_test() {
+        let farmland = BlockID::new(FARMLAND).unwrap();
+        assert!(can_grow(farmland, MIN_GROWTH_LIGHT));
+        assert!(can_grow(farmland, LightLevel::MAX));
+        assert!(!can_grow(farmland, MIN_GROWTH_LIGHT.attenuate(1)));
+        assert!(!can_grow(BlockID::new(3).unwrap(), LightLevel::MAX));
+    }
+
+    #[test]
+    fn growth_odds_test() {
+        assert_eq!(growth_odds(0), 13);
+        assert_eq!(growth_odds(7), 7);
+    }
+
+    #[test]
+    fn sky_darkening_test() {
+        assert_eq!(sky_darkening(6000), 0);
+        assert_eq!(sky_darkening(18000), 11);
+        assert_eq!(sky_darkening(18000 + 24000 * 3), 11);
+        assert!(sky_darkening(13000) > 0 && sky_darkening(13000) < 11);
+    }
+}
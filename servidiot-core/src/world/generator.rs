@@ -0,0 +1,237 @@
+use std::collections::{HashSet, VecDeque};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use servidiot_primitives::{
+    block::BlockID,
+    chunk::{section::ChunkSection, Chunk},
+    position::{ChunkLocation, ChunkPosition},
+};
+
+/// Generates chunks which are not yet present on disk.
+pub trait WorldGenerator {
+    fn generate_chunk(&self, location: ChunkLocation) -> Chunk;
+}
+
+/// Generates chunks made entirely of air.
+pub struct VoidGenerator;
+
+impl WorldGenerator for VoidGenerator {
+    fn generate_chunk(&self, location: ChunkLocation) -> Chunk {
+        Chunk::new(location.position)
+    }
+}
+
+/// The `generatorName` of superflat worlds.
+const FLAT_GENERATOR: &str = "flat";
+
+/// Generates a layer of bedrock
+/// under a single section of stone.
+pub struct FlatWorldGenerator;
+
+impl WorldGenerator for FlatWorldGenerator {
+    fn generate_chunk(&self, location: ChunkLocation) -> Chunk {
+        let mut section = ChunkSection::empty(0);
+        section.fill_blocks(STONE as u8);
+        let bedrock = BlockID::new(BEDROCK).expect("valid block");
+        for x in 0..ChunkSection::WIDTH {
+            for z in 0..ChunkSection::LENGTH {
+                section.set_block_type_at(x, 0, z, bedrock);
+            }
+        }
+
+        let mut chunk = Chunk::new(location.position);
+        chunk.set_section(0, section);
+        chunk.recalculate_heightmap();
+        chunk
+    }
+}
+
+/// Chunks waiting to be generated, oldest first.
+#[derive(Default)]
+pub struct ChunkGenerationQueue(VecDeque<ChunkLocation>);
+
+impl ChunkGenerationQueue {
+    /// Queues a chunk for generation.
+    /// Returns `false` if it was already queued.
+    pub fn push(&mut self, location: ChunkLocation) -> bool {
+        if self.0.contains(&location) {
+            return false;
+        }
+        self.0.push_back(location);
+        true
+    }
+
+    pub fn pop(&mut self) -> Option<ChunkLocation> {
+        self.0.pop_front()
+    }
+}
+
+/// Places features such as ores and trees into a
+/// chunk once it and its neighbors have been generated.
+pub trait ChunkPopulator: Send + Sync {
+    /// `neighbors` are the chunks to the north,
+    /// east, south and west, in that order.
+    fn populate(
+        &self,
+        chunk: &mut Chunk,
+        seed: i64,
+        neighbors: [Option<&Chunk>; 4],
+    ) -> anyhow::Result<()>;
+}
+
+/// How chunks missing on disk are generated, and
+/// the populators run over them once generated.
+pub struct WorldGeneratorConfig {
+    /// The world seed passed to each populator.
+    pub seed: i64,
+    pub generator: Box<dyn WorldGenerator>,
+    /// Run in order over each chunk.
+    pub populators: Vec<Box<dyn ChunkPopulator>>,
+}
+
+impl WorldGeneratorConfig {
+    pub fn new(seed: i64, generator: impl WorldGenerator + 'static) -> Self {
+        Self {
+            seed,
+            generator: Box::new(generator),
+            populators: vec![],
+        }
+    }
+
+    /// Generates chunks the way some `generatorName`
+    /// from `level.dat` does, where it is supported.
+    pub fn for_generator(seed: i64, generator_name: &str) -> Self {
+        if generator_name == FLAT_GENERATOR {
+            Self::new(seed, FlatWorldGenerator)
+        } else {
+            Self::new(seed, VoidGenerator)
+        }
+    }
+
+    pub fn with_populator(mut self, populator: impl ChunkPopulator + 'static) -> Self {
+        self.populators.push(Box::new(populator));
+        self
+    }
+}
+
+/// Generated chunks waiting on their neighbors to be populated.
+#[derive(Default)]
+pub struct PopulationQueue(HashSet<ChunkLocation>);
+
+impl PopulationQueue {
+    pub fn push(&mut self, location: ChunkLocation) {
+        self.0.insert(location);
+    }
+
+    /// Removes and returns every queued chunk
+    /// for which `ready` returns `true`.
+    pub fn take_ready(&mut self, mut ready: impl FnMut(ChunkLocation) -> bool) -> Vec<ChunkLocation> {
+        let taken = self.0.iter().copied().filter(|v| ready(*v)).collect::<Vec<_>>();
+        for location in &taken {
+            self.0.remove(location);
+        }
+        taken
+    }
+}
+
+/// The chunks to the north, east, south
+/// and west of `position`, in that order.
+pub fn neighbors(position: ChunkPosition) -> [ChunkPosition; 4] {
+    [
+        position.offset(0, -1),
+        position.offset(1, 0),
+        position.offset(0, 1),
+        position.offset(-1, 0),
+    ]
+}
+
+const STONE: u16 = 1;
+const BEDROCK: u16 = 7;
+const IRON_ORE: u16 = 15;
+
+/// Replaces stone with veins of ore.
+pub struct OrePopulator {
+    pub ore: BlockID,
+    pub veins_per_chunk: u32,
+    /// The most blocks of ore in one vein.
+    pub vein_size: u32,
+    /// The highest Y level veins may start at.
+    pub max_y: usize,
+}
+
+impl OrePopulator {
+    pub fn iron() -> Self {
+        Self {
+            ore: BlockID::new(IRON_ORE).expect("valid block"),
+            veins_per_chunk: 20,
+            vein_size: 8,
+            max_y: 64,
+        }
+    }
+
+    /// A generator seeded by the world seed and chunk position,
+    /// so a chunk is always populated the same way.
+    fn rng(seed: i64, position: ChunkPosition) -> StdRng {
+        let seed = seed
+            ^ i64::from(position.x).wrapping_mul(341_873_128_712)
+            ^ i64::from(position.z).wrapping_mul(132_897_987_541);
+        StdRng::seed_from_u64(seed as u64)
+    }
+}
+
+impl ChunkPopulator for OrePopulator {
+    fn populate(
+        &self,
+        chunk: &mut Chunk,
+        seed: i64,
+        _neighbors: [Option<&Chunk>; 4],
+    ) -> anyhow::Result<()> {
+        let mut rng = Self::rng(seed, chunk.position());
+        for _ in 0..self.veins_per_chunk {
+            let mut x = rng.gen_range(0..Chunk::WIDTH);
+            let mut y = rng.gen_range(0..=self.max_y);
+            let mut z = rng.gen_range(0..Chunk::LENGTH);
+            for _ in 0..self.vein_size {
+                if chunk.block_type_at(x, y, z).is_some_and(|v| *v == STONE) {
+                    chunk.set_block_type_at(x, y, z, self.ore);
+                }
+                x = step(&mut rng, x, Chunk::WIDTH);
+                y = step(&mut rng, y, Chunk::HEIGHT);
+                z = step(&mut rng, z, Chunk::LENGTH);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Moves `v` by at most one, staying below `max`.
+fn step(rng: &mut StdRng, v: usize, max: usize) -> usize {
+    match rng.gen_range(0..3) {
+        0 => v.saturating_sub(1),
+        1 => (v + 1).min(max - 1),
+        _ => v,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use servidiot_primitives::position::{ChunkLocation, ChunkPosition, Location};
+
+    use super::{WorldGeneratorConfig, BEDROCK, STONE};
+
+    #[test]
+    fn flat_generator_test() {
+        let location = ChunkLocation::new(ChunkPosition::new(3, -2), Location::overworld());
+        let chunk = WorldGeneratorConfig::for_generator(0, "flat").generator.generate_chunk(location);
+        assert_eq!(chunk.position(), location.position);
+        for (x, z) in [(0, 0), (7, 12), (15, 15)] {
+            assert_eq!(*chunk.block_type_at(x, 0, z).unwrap(), BEDROCK);
+            assert!((1..16).all(|y| *chunk.block_type_at(x, y, z).unwrap() == STONE));
+            assert_eq!(chunk.block_type_at(x, 16, z), None);
+            assert_eq!(chunk.heightmap()[x][z], 16);
+        }
+
+        let chunk = WorldGeneratorConfig::for_generator(0, "default").generator.generate_chunk(location);
+        assert_eq!(chunk.sections().count(), 0);
+    }
+}
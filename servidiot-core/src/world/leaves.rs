@@ -0,0 +1,183 @@
+use std::collections::{HashSet, VecDeque};
+
+use servidiot_primitives::{
+    block::BlockID,
+    position::{BlockPosition, Direction, Location},
+};
+
+const LOG: u16 = 17;
+const LOG2: u16 = 162;
+const LEAVES: u16 = 18;
+const LEAVES2: u16 = 161;
+/// Set on the meta of leaves placed by players, which never decay.
+const NO_DECAY_FLAG: u8 = 4;
+
+/// How far, in blocks through other leaves,
+/// leaves may be from a log before they decay.
+pub const LOG_REACH: u32 = 4;
+
+/// A leaf block due to be checked for a nearby log.
+pub struct LeafDecayCheck {
+    pub position: BlockPosition,
+    pub location: Location,
+    /// Ticks until the check.
+    pub delay: u32,
+}
+
+/// Leaves which may have lost the logs holding them up.
+#[derive(Default)]
+pub struct LeafDecayQueue {
+    checks: VecDeque<LeafDecayCheck>,
+    queued: HashSet<(BlockPosition, Location)>,
+}
+
+impl LeafDecayQueue {
+    /// Queues a leaf block to be checked in `delay` ticks.
+    /// Returns `false` if it was already queued.
+    pub fn push(&mut self, position: BlockPosition, location: Location, delay: u32) -> bool {
+        if !self.queued.insert((position, location)) {
+            return false;
+        }
+        self.checks.push_back(LeafDecayCheck { position, location, delay });
+        true
+    }
+
+    /// Counts every check down by a tick, then takes
+    /// at most `max` of those which are due, oldest first.
+    pub fn tick(&mut self, max: usize) -> Vec<LeafDecayCheck> {
+        let mut due = vec![];
+        let mut waiting = VecDeque::with_capacity(self.checks.len());
+        for mut check in self.checks.drain(..) {
+            check.delay = check.delay.saturating_sub(1);
+            if check.delay == 0 && due.len() < max {
+                self.queued.remove(&(check.position, check.location));
+                due.push(check);
+            } else {
+                waiting.push_back(check);
+            }
+        }
+        self.checks = waiting;
+        due
+    }
+}
+
+pub fn is_log(block: BlockID) -> bool {
+    matches!(*block, LOG | LOG2)
+}
+
+/// Whether some block is leaves which can decay.
+pub fn is_decaying_leaves(block: BlockID, meta: u8) -> bool {
+    matches!(*block, LEAVES | LEAVES2) && meta & NO_DECAY_FLAG == 0
+}
+
+/// The meta of the sapling some leaves drop. The lowest two bits
+/// of leaf meta give the wood type, and the types of `LEAVES2`
+/// follow those of `LEAVES` among saplings.
+pub fn sapling_meta(block: BlockID, meta: u8) -> i16 {
+    let wood = i16::from(meta & 3);
+    if *block == LEAVES2 {
+        wood + 4
+    } else {
+        wood
+    }
+}
+
+/// Whether a log can be reached from `start` within [`LOG_REACH`]
+/// steps through leaves. `block_at` gives the block at some position,
+/// or `None` where the world is not loaded.
+pub fn is_near_log(start: BlockPosition, block_at: impl Fn(BlockPosition) -> Option<BlockID>) -> bool {
+    let mut visited = HashSet::from([start]);
+    let mut frontier = VecDeque::from([(start, 0)]);
+    while let Some((position, distance)) = frontier.pop_front() {
+        if distance == LOG_REACH {
+            continue;
+        }
+        for direction in DIRECTIONS {
+            let next = position.offset_towards(direction, 1);
+            if !visited.insert(next) {
+                continue;
+            }
+            match block_at(next) {
+                Some(v) if is_log(v) => return true,
+                Some(v) if matches!(*v, LEAVES | LEAVES2) => frontier.push_back((next, distance + 1)),
+                // Leaves at the edge of the loaded
+                // world are kept until it loads.
+                None => return true,
+                _ => (),
+            }
+        }
+    }
+    false
+}
+
+const DIRECTIONS: [Direction; 6] = [
+    Direction::Down,
+    Direction::Up,
+    Direction::North,
+    Direction::South,
+    Direction::West,
+    Direction::East,
+];
+
+#[cfg(test)]
+mod tests {
+    use servidiot_primitives::{
+        block::BlockID,
+        position::{BlockPosition, Location},
+    };
+
+    use super::{is_near_log, sapling_meta, LeafDecayQueue, LEAVES, LEAVES2, LOG};
+
+    fn id(v: u16) -> BlockID {
+        BlockID::new(v).unwrap()
+    }
+
+    #[test]
+    fn decay_queue_tick_test() {
+        let mut queue = LeafDecayQueue::default();
+        let (a, b, c) = (BlockPosition::new(0, 64, 0), BlockPosition::new(1, 64, 0), BlockPosition::new(2, 64, 0));
+        assert!(queue.push(a, Location::overworld(), 2));
+        assert!(queue.push(b, Location::overworld(), 1));
+        assert!(queue.push(c, Location::overworld(), 1));
+        assert!(!queue.push(a, Location::overworld(), 1));
+
+        // Only `max` due checks are taken, and the rest wait a tick.
+        let due = queue.tick(1);
+        assert_eq!(due.iter().map(|v| v.position).collect::<Vec<_>>(), [b]);
+        let due = queue.tick(8);
+        assert_eq!(due.iter().map(|v| v.position).collect::<Vec<_>>(), [a, c]);
+        assert!(queue.tick(8).is_empty());
+
+        // Taken checks may be queued again.
+        assert!(queue.push(a, Location::overworld(), 1));
+    }
+
+    #[test]
+    fn is_near_log_test() {
+        let start = BlockPosition::new(0, 64, 0);
+        // A row of leaves leading to a log at some distance.
+        let row = |log_x: i32| {
+            move |v: BlockPosition| {
+                Some(id(match (v.x, v.y, v.z) {
+                    (x, 64, 0) if x == log_x => LOG,
+                    (0..=10, 64, 0) => LEAVES,
+                    _ => 0,
+                }))
+            }
+        };
+        assert!(is_near_log(start, row(4)));
+        assert!(!is_near_log(start, row(5)));
+        assert!(!is_near_log(start, |_| Some(id(0))));
+        // Leaves next to unloaded chunks are kept.
+        assert!(is_near_log(start, |v| (v.x < 1).then(|| id(0))));
+    }
+
+    #[test]
+    fn sapling_meta_test() {
+        assert_eq!(sapling_meta(id(LEAVES), 2), 2);
+        // Players placed these, and they have been checked.
+        assert_eq!(sapling_meta(id(LEAVES), 4 | 8 | 3), 3);
+        assert_eq!(sapling_meta(id(LEAVES2), 1), 5);
+        assert_eq!(sapling_meta(id(LEAVES2), 8), 4);
+    }
+}
@@ -1,39 +1,88 @@
 use std::{collections::HashMap, path::PathBuf, thread::spawn};
 
-use servidiot_anvil::{WorldManager, region::{RegionManager, RegionManagerError, file::ChunkError, nbt::ChunkRoot}};
+use nbt::Value;
+use servidiot_anvil::{WorldManager, nbt::tile_entity::TileEntitySign, region::{RegionManager, RegionManagerError, file::ChunkError, nbt::{ByteArray, ChunkRoot, IntArray, Level, Section, TileTick}}};
 use servidiot_primitives::{position::{RegionPosition, ChunkLocation, ChunkPosition}, chunk::{Chunk, section::ChunkSection}};
 
 use super::TicketCount;
 
 pub enum WorldLoaderCommand {
     LoadChunk(ChunkLocation),
-    SaveChunk(ChunkLocation, ChunkRoot)
+    /// Unloads a chunk, writing it first if it is given.
+    SaveChunk(ChunkLocation, Option<ChunkRoot>),
+    /// Saves a chunk which stays loaded.
+    WriteChunk(ChunkLocation, ChunkRoot),
+    /// Writes every cached region to disk, then
+    /// drops the sender once all earlier commands ran.
+    Flush(flume::Sender<()>),
+}
+
+/// A chunk read from disk, with its signs.
+pub type LoadedChunk = (Chunk, Vec<TileEntitySign>, SavedChunkData, ChunkLocation);
+
+/// The parts of a saved chunk the server does not use yet, kept
+/// while it is loaded so saving it writes them back unchanged.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SavedChunkData {
+    pub entities: Vec<Value>,
+    /// Every tile entity but signs, which are kept apart.
+    pub tile_entities: Vec<Value>,
+    pub tile_ticks: Option<Vec<TileTick>>,
+    pub inhabited_time: i64,
+}
+
+impl SavedChunkData {
+    /// Takes this data out of a saved chunk,
+    /// along with the signs in it.
+    pub fn split(level: &Level) -> (Self, Vec<TileEntitySign>) {
+        let mut signs = vec![];
+        let mut tile_entities = vec![];
+        for value in &level.tile_entities {
+            match TileEntitySign::from_value(value) {
+                Some(sign) => signs.push(sign),
+                None => tile_entities.push(value.clone()),
+            }
+        }
+        let data = Self {
+            entities: level.entities.clone(),
+            tile_entities,
+            tile_ticks: level.tile_ticks.clone(),
+            inhabited_time: level.inhabited_time,
+        };
+        (data, signs)
+    }
 }
 
 pub struct WorldLoader {
     world_manager: WorldManager,
     dimensions: HashMap<i32, (RegionManager, HashMap<RegionPosition, TicketCount>)>,
 
-    loaded_channel: flume::Sender<(Chunk, ChunkLocation)>,
+    loaded_channel: flume::Sender<LoadedChunk>,
+    missing_channel: flume::Sender<ChunkLocation>,
     command_recv: flume::Receiver<WorldLoaderCommand>
 }
 
 impl WorldLoader {
-    pub fn create(folder: PathBuf) -> (flume::Sender<WorldLoaderCommand>, flume::Receiver<(Chunk, ChunkLocation)>) {
+    /// Returns a command sender, a receiver for loaded chunks
+    /// and their signs, and a receiver for chunks that need
+    /// to be generated.
+    pub fn create(folder: PathBuf) -> (flume::Sender<WorldLoaderCommand>, flume::Receiver<LoadedChunk>, flume::Receiver<ChunkLocation>) {
         let (command_send, command_recv) = flume::unbounded();
         let (chunk_send, chunk_recv) = flume::unbounded();
+        let (missing_send, missing_recv) = flume::unbounded();
 
         let mut s = Self {
             world_manager: WorldManager::open(folder),
             dimensions: Default::default(),
             loaded_channel: chunk_send,
+            missing_channel: missing_send,
             command_recv
         };
         rayon::spawn(move || {
             s.run();
         });
 
-        (command_send, chunk_recv)
+        (command_send, chunk_recv, missing_recv)
     } 
     fn get_dimension(
         &mut self,
@@ -52,12 +101,13 @@ impl WorldLoader {
         match mgr.load_chunk(position.position) {
             Ok(v) => {
                 let chunk = chunk_root_to_chunk(&v.0);
+                let (data, signs) = SavedChunkData::split(&v.0.level);
                 self.increment_ticket(position);
-                let _ = self.loaded_channel.send((chunk, position));
+                let _ = self.loaded_channel.send((chunk, signs, data, position));
                 Ok(())
             }
             Err(RegionManagerError::ChunkError(ChunkError::ChunkNotPresent(_))) => {
-                // need to generate it
+                let _ = self.missing_channel.send(position);
                 Ok(())
             }
             Err(e) => Err(e.into()),
@@ -70,10 +120,14 @@ impl WorldLoader {
         dim.1.entry(position.position.region()).or_default().increment(); 
     }
 
-    fn unload_chunk(&mut self, position: ChunkLocation, data: ChunkRoot) -> anyhow::Result<()> {
-        let dim = self.dimensions.get_mut(&position.location.dimension).expect("assumed loaded");
+    fn unload_chunk(&mut self, position: ChunkLocation, data: Option<ChunkRoot>) -> anyhow::Result<()> {
+        // Generated chunks may be saved to a dimension
+        // with no regions loaded.
+        let dim = self.get_dimension(position.location.dimension);
         let region = position.position.region();
-        dim.0.save_chunk(position.position, data)?;
+        if let Some(data) = data {
+            dim.0.save_chunk(position.position, data)?;
+        }
         if let Some(ticket) = dim.1.get_mut(&region) {
             if ticket.decrement() {
                 dim.1.remove(&region);
@@ -87,6 +141,19 @@ impl WorldLoader {
         Ok(())
     }
 
+    fn write_chunk(&mut self, position: ChunkLocation, data: ChunkRoot) -> anyhow::Result<()> {
+        let mgr = &mut self.get_dimension(position.location.dimension).0;
+        mgr.save_chunk(position.position, data)?;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> anyhow::Result<()> {
+        for (mgr, _) in self.dimensions.values_mut() {
+            mgr.flush_cache()?;
+        }
+        Ok(())
+    }
+
     fn run(mut self) {
 
         while let Ok(command) = self.command_recv.recv() {
@@ -96,6 +163,15 @@ impl WorldLoader {
                 },
                 WorldLoaderCommand::SaveChunk(pos, data) => if let Err(e) = self.unload_chunk(pos, data) {
                     tracing::error!("Chunk save failure: {:?}", e)
+                },
+                WorldLoaderCommand::WriteChunk(pos, data) => if let Err(e) = self.write_chunk(pos, data) {
+                    tracing::error!("Chunk save failure: {:?}", e)
+                },
+                WorldLoaderCommand::Flush(done) => {
+                    if let Err(e) = self.flush() {
+                        tracing::error!("Region flush failure: {:?}", e)
+                    }
+                    drop(done);
                 }
             }
         }
@@ -107,6 +183,10 @@ impl WorldLoader {
 fn chunk_root_to_chunk(c: &ChunkRoot) -> Chunk {
     let mut chunk = Chunk::new(ChunkPosition::new(c.level.x_position, c.level.z_position));
 
+    for (i, height) in c.level.heightmap.iter().enumerate() {
+        chunk.heightmap_mut()[i % 16][i / 16] = (*height).clamp(0, u8::MAX.into()) as u8;
+    }
+
     for section in &c.level.sections {
         let mut new_sec = ChunkSection::empty(0);
         new_sec.block_light = section.block_light.clone();
@@ -117,5 +197,123 @@ fn chunk_root_to_chunk(c: &ChunkRoot) -> Chunk {
 
         chunk.set_section(section.y_index as u8, new_sec)
     }
+    if let Some(biomes) = &c.level.biomes {
+        for (i, biome) in biomes.as_u8_array().iter().enumerate().take(256) {
+            chunk.biomes_mut()[i % 16][i / 16] = *biome;
+        }
+    }
     chunk
 }
+
+/// Converts a chunk, with the signs in it and whatever else
+/// was loaded along with it, into its saved form.
+pub fn chunk_to_chunk_root(chunk: &Chunk, signs: &[TileEntitySign], data: &SavedChunkData) -> ChunkRoot {
+    let mut heightmap = vec![0; 256];
+    let mut biomes = vec![0; 256];
+    for (i, (height, biome)) in heightmap.iter_mut().zip(&mut biomes).enumerate() {
+        *height = i32::from(chunk.heightmap()[i % 16][i / 16]);
+        *biome = chunk.biomes()[i % 16][i / 16] as i8;
+    }
+
+    // Sections loaded from disk do not know their own index.
+    let sections = (0..ChunkSection::SECTIONS_PER_CHUNK as u8)
+        .filter_map(|i| Some((i, chunk.get_section(i).filter(|v| !v.is_empty())?)))
+        .map(|(i, v)| Section {
+            y_index: i as i8,
+            blocks: ByteArray(v.block_types.iter().map(|v| *v as i8).collect()),
+            additional: v.block_types_add.clone(),
+            data: v.block_meta.clone(),
+            block_light: v.block_light.clone(),
+            sky_light: v.skylight.clone(),
+        })
+        .collect();
+
+    ChunkRoot {
+        level: Level {
+            x_position: chunk.position().x,
+            z_position: chunk.position().z,
+            last_update: 0,
+            light_populated: Some(true),
+            // Chunks are populated as soon as they are generated.
+            terrain_populated: true,
+            version: Some(1),
+            inhabited_time: data.inhabited_time,
+            biomes: Some(ByteArray(biomes)),
+            heightmap: IntArray(heightmap),
+            sections,
+            entities: data.entities.clone(),
+            tile_entities: data.tile_entities.iter().cloned().chain(signs.iter().map(TileEntitySign::to_value)).collect(),
+            tile_ticks: data.tile_ticks.clone(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nbt::{Map, Value};
+    use servidiot_anvil::{
+        nbt::tile_entity::TileEntitySign,
+        region::nbt::{ByteArray, ChunkRoot, IntArray, Level, Section, TileTick},
+    };
+    use servidiot_primitives::nibble_vec::NibbleVec;
+
+    use super::{chunk_root_to_chunk, chunk_to_chunk_root, SavedChunkData};
+
+    fn compound(id: &str) -> Value {
+        let mut map = Map::new();
+        map.insert("id".to_string(), Value::String(id.to_string()));
+        Value::Compound(map)
+    }
+
+    #[test]
+    fn chunk_round_trip_test() {
+        let mut blocks = vec![0; 4096];
+        blocks[..256].fill(1);
+        let sign = TileEntitySign::new(3, 20, 5, ["a".into(), "b".into(), String::new(), "d".into()]);
+        let original = ChunkRoot {
+            level: Level {
+                x_position: 2,
+                z_position: -1,
+                last_update: 0,
+                light_populated: Some(true),
+                terrain_populated: true,
+                version: Some(1),
+                inhabited_time: 1234,
+                biomes: Some(ByteArray(vec![4; 256])),
+                heightmap: IntArray(vec![17; 256]),
+                sections: vec![Section {
+                    y_index: 1,
+                    blocks: ByteArray(blocks),
+                    additional: None,
+                    data: NibbleVec::fill(2, 4096),
+                    block_light: NibbleVec::fill(0, 4096),
+                    sky_light: NibbleVec::fill(15, 4096),
+                }],
+                entities: vec![compound("Pig")],
+                tile_entities: vec![compound("Chest"), compound("Furnace"), sign.to_value()],
+                tile_ticks: Some(vec![TileTick { block_id: 8, ticks_until: 3, ordering: 0, x: 40, y: 60, z: -10 }]),
+            },
+        };
+
+        let chunk = chunk_root_to_chunk(&original);
+        let (data, signs) = SavedChunkData::split(&original.level);
+        assert_eq!(signs, [sign]);
+        let saved = chunk_to_chunk_root(&chunk, &signs, &data);
+
+        assert_eq!(saved.level.x_position, original.level.x_position);
+        assert_eq!(saved.level.z_position, original.level.z_position);
+        assert_eq!(saved.level.inhabited_time, original.level.inhabited_time);
+        assert_eq!(saved.level.entities, original.level.entities);
+        assert_eq!(saved.level.tile_entities, original.level.tile_entities);
+        assert_eq!(saved.level.tile_ticks, original.level.tile_ticks);
+        assert_eq!(saved.level.heightmap.0, original.level.heightmap.0);
+        assert_eq!(saved.level.biomes.map(|v| v.0), original.level.biomes.map(|v| v.0));
+        assert_eq!(saved.level.sections.len(), 1);
+        let (section, expected) = (&saved.level.sections[0], &original.level.sections[0]);
+        assert_eq!(section.y_index, expected.y_index);
+        assert_eq!(section.blocks.0, expected.blocks.0);
+        assert_eq!(section.data, expected.data);
+        assert_eq!(section.block_light, expected.block_light);
+        assert_eq!(section.sky_light, expected.sky_light);
+    }
+}
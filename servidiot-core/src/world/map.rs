@@ -0,0 +1,122 @@
+use servidiot_network::io::packet::server::play::{MapIcon, MapRegion};
+use servidiot_primitives::{
+    chunk::Chunk,
+    position::{ChunkPosition, Position},
+};
+
+/// The item ID of a filled map, whose damage is its map ID.
+pub const MAP_ITEM: i16 = 358;
+/// How many blocks across the narrowest map draws.
+const MAP_SIZE: i32 = 128;
+
+/// Map colors are a base color times four, plus a shade.
+const SHADE_DARK: u8 = 0;
+const SHADE_NORMAL: u8 = 1;
+const SHADE_LIGHT: u8 = 2;
+
+/// The center of the area a map drawn at (`x`, `z`) shows.
+/// Maps show areas on a grid, so that nearby maps line up.
+pub fn map_center(x: i32, z: i32) -> (i32, i32) {
+    let center = |v: i32| (v + MAP_SIZE / 2).div_euclid(MAP_SIZE) * MAP_SIZE;
+    (center(x), center(z))
+}
+
+/// Where some chunk is drawn on the map centered
+/// at `center`, or `None` if the map does not show it.
+pub fn chunk_origin(chunk: ChunkPosition, center: (i32, i32)) -> Option<(u8, u8)> {
+    let origin = |chunk: i32, center: i32| u8::try_from(chunk * 16 - (center - MAP_SIZE / 2)).ok().filter(|v| i32::from(*v) < MAP_SIZE);
+    Some((origin(chunk.x, center.0)?, origin(chunk.z, center.1)?))
+}
+
+/// The icon of a player at `position` on
+/// the map centered at `center`, facing their way.
+pub fn player_icon(position: &Position, center: (i32, i32)) -> MapIcon {
+    let offset = |v: f64, center: i32| ((v - f64::from(center)) * 2.0).clamp(-128.0, 127.0) as i8;
+    let direction = (f64::from(position.yaw) * 16.0 / 360.0 + 0.5).floor() as i32 & 15;
+    MapIcon {
+        type_and_direction: (direction as u8) << 4,
+        x: offset(position.x, center.0),
+        z: offset(position.z, center.1),
+    }
+}
+
+/// Renders a top-down view of a chunk from its heightmap,
+/// as a 16 by 16 region starting at (`x`, `z`) on the map.
+///
+/// Each column is shaded against the one north of it, and
+/// only a handful of common blocks have their own color.
+pub fn render_chunk(chunk: &Chunk, x: u8, z: u8) -> MapRegion {
+    let heightmap = chunk.heightmap();
+    let mut colors = Vec::with_capacity(Chunk::WIDTH * Chunk::LENGTH);
+    for block_z in 0..Chunk::WIDTH {
+        for (block_x, column) in heightmap.iter().enumerate() {
+            let height = column[block_z];
+            let top = usize::from(height.saturating_sub(1));
+            let base = chunk
+                .block_type_at(block_x, top, block_z)
+                .map_or(0, |v| base_color(*v));
+            if base == 0 {
+                colors.push(0);
+                continue;
+            }
+            let shade = match block_z.checked_sub(1).map(|v| column[v]) {
+                Some(north) if height > north => SHADE_LIGHT,
+                Some(north) if height < north => SHADE_DARK,
+                _ => SHADE_NORMAL,
+            };
+            colors.push(base * 4 + shade);
+        }
+    }
+    MapRegion {
+        x,
+        z,
+        columns: Chunk::LENGTH as u8,
+        rows: Chunk::WIDTH as u8,
+        colors,
+    }
+}
+
+/// The base map color of some block type,
+/// or 0 for blocks which are not drawn.
+fn base_color(block: u16) -> u8 {
+    match block {
+        0 => 0,
+        2 => 1,                  // grass
+        12 | 24 => 2,            // sand, sandstone
+        35 => 3,                 // wool
+        10 | 11 => 4,            // lava
+        79 => 5,                 // ice
+        18 => 7,                 // leaves
+        78 | 80 => 8,            // snow
+        82 => 9,                 // clay
+        3 => 10,                 // dirt
+        8 | 9 => 12,             // water
+        5 | 17 => 13,            // planks, logs
+        _ => 11,                 // stone, and anything else
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use servidiot_primitives::position::{ChunkPosition, Position};
+
+    use super::{chunk_origin, map_center, player_icon};
+
+    #[test]
+    fn chunk_origin_test() {
+        assert_eq!(map_center(0, 63), (0, 0));
+        assert_eq!(map_center(64, -65), (128, -128));
+
+        assert_eq!(chunk_origin(ChunkPosition::new(0, 0), (0, 0)), Some((64, 64)));
+        assert_eq!(chunk_origin(ChunkPosition::new(-4, 3), (0, 0)), Some((0, 112)));
+        assert_eq!(chunk_origin(ChunkPosition::new(4, 0), (0, 0)), None);
+        assert_eq!(chunk_origin(ChunkPosition::new(-5, 0), (0, 0)), None);
+    }
+
+    #[test]
+    fn player_icon_test() {
+        let icon = player_icon(&Position::new(10.0, 64.0, -100.0, 90.0, 0.0, true), (0, 0));
+        assert_eq!((icon.x, icon.z), (20, -128));
+        assert_eq!(icon.type_and_direction, 4 << 4);
+    }
+}
@@ -3,18 +3,28 @@ use std::{
     path::PathBuf,
 };
 
+use servidiot_anvil::nbt::tile_entity::TileEntitySign;
 use servidiot_ecs::Entity;
 use servidiot_network::server::{id::NetworkID, Client, Server};
 use servidiot_primitives::{
+    block::{BlockID, LightLevel},
     chunk::{Chunk, ChunkBitmap},
-    position::ChunkLocation,
+    position::{BlockPosition, ChunkLocation, Location},
 };
 
 
-use self::loader::{WorldLoader, WorldLoaderCommand};
+use self::loader::{chunk_to_chunk_root, LoadedChunk, SavedChunkData, WorldLoader, WorldLoaderCommand};
 
+pub mod border;
+pub mod crops;
+pub mod generator;
+pub mod leaves;
+pub mod map;
 mod loader;
+pub mod portal;
+pub mod protection;
 pub mod view;
+pub mod weather;
 
 #[derive(Default)]
 pub struct TicketCount(pub usize);
@@ -30,26 +40,62 @@ impl TicketCount {
     }
 }
 
+/// How often the world is saved while the server runs.
+pub struct AutosaveConfig {
+    pub interval_ticks: u64,
+}
+
+impl Default for AutosaveConfig {
+    fn default() -> Self {
+        // Five minutes at 20 ticks per second.
+        Self { interval_ticks: 6000 }
+    }
+}
+
 pub struct GameWorld {
     loading_requests: HashMap<ChunkLocation, HashMap<NetworkID, Entity>>,
 
     command_sender: flume::Sender<WorldLoaderCommand>,
-    chunk_recv: flume::Receiver<(Chunk, ChunkLocation)>,
+    chunk_recv: flume::Receiver<LoadedChunk>,
+    missing_recv: flume::Receiver<ChunkLocation>,
 
     chunks: HashMap<ChunkLocation, (Chunk, TicketCount, HashSet<Entity>)>, 
+    /// The chunk each entity other than a
+    /// player is recorded as being within.
+    entity_chunks: HashMap<Entity, ChunkLocation>,
+    /// The signs within each loaded chunk.
+    signs: HashMap<ChunkLocation, Vec<TileEntitySign>>,
+    /// What each loaded chunk was saved with that
+    /// the server does not use, written back on save.
+    saved_data: HashMap<ChunkLocation, SavedChunkData>,
+    /// Loaded chunks changed since they were last saved.
+    dirty: HashSet<ChunkLocation>,
+    /// Disconnects once the last `save_all` is on disk.
+    saving: Option<flume::Receiver<()>>,
 }
 
 impl GameWorld {
     pub fn new(folder: PathBuf) -> Self {
-        let (loaded, recv) = WorldLoader::create(folder);
+        let (loaded, recv, missing) = WorldLoader::create(folder);
         Self {
             loading_requests: Default::default(),
             chunks: Default::default(),
+            entity_chunks: Default::default(),
+            signs: Default::default(),
+            saved_data: Default::default(),
+            dirty: Default::default(),
             command_sender: loaded,
             chunk_recv: recv,
+            missing_recv: missing,
+            saving: None,
         }
     }
 
+    /// Every loaded chunk.
+    pub fn loaded_chunks(&self) -> impl Iterator<Item = (ChunkLocation, &Chunk)> {
+        self.chunks.iter().map(|(loc, (chunk, _, _))| (*loc, chunk))
+    }
+
     /// Returns `None` if the chunk is not loaded.
     pub fn get_chunk(&self, loc: ChunkLocation) -> Option<&(Chunk, TicketCount, HashSet<Entity>)> {
         self.chunks.get(&loc)
@@ -63,13 +109,99 @@ impl GameWorld {
         self.chunks.get_mut(&loc)
     }
 
-    fn add_chunk(&mut self, position: ChunkLocation, chunk: Chunk) {
+    /// Returns a loaded chunk along with its loaded neighbors,
+    /// in the order given by `generator::neighbors`.
+    pub fn chunk_with_neighbors_mut(
+        &mut self,
+        loc: ChunkLocation,
+    ) -> Option<(&mut Chunk, [Option<&Chunk>; 4])> {
+        let [n, e, s, w] =
+            generator::neighbors(loc.position).map(|v| ChunkLocation::new(v, loc.location));
+        let [chunk, n, e, s, w] = self.chunks.get_disjoint_mut([&loc, &n, &e, &s, &w]);
+        let neighbors = [n, e, s, w].map(|v| v.map(|v| &v.0));
+        let chunk = &mut chunk?.0;
+        self.dirty.insert(loc);
+        Some((chunk, neighbors))
+    }
+
+    /// Adds a chunk which is not on disk yet, such as
+    /// a newly generated one, so it is saved later.
+    pub fn add_chunk(&mut self, position: ChunkLocation, chunk: Chunk) {
+        self.insert_chunk(position, chunk);
+        self.dirty.insert(position);
+    }
+
+    fn insert_chunk(&mut self, position: ChunkLocation, chunk: Chunk) {
         self.chunks.insert(
             position,
             (chunk, TicketCount(0), Default::default()),
         );
     }
 
+    /// Marks a loaded chunk as changed, so it is
+    /// written the next time the world is saved.
+    pub fn mark_dirty(&mut self, chunk: ChunkLocation) {
+        if self.is_loaded(chunk) {
+            self.dirty.insert(chunk);
+        }
+    }
+
+    /// The block ID and meta at some position,
+    /// or `None` if its chunk is not loaded.
+    pub fn block_at(&self, location: Location, position: BlockPosition) -> Option<(BlockID, u8)> {
+        let y = usize::try_from(position.y).ok()?;
+        let (x, z) = ((position.x & 15) as usize, (position.z & 15) as usize);
+        let chunk = &self.get_chunk(ChunkLocation::new(position.chunk(), location))?.0;
+        Some((chunk.block_type_at(x, y, z)?, chunk.block_meta_at(x, y, z)?))
+    }
+
+    /// The brighter of the sky light, darkened by `sky_darkening`, and
+    /// block light at some position, or `None` if its chunk is not loaded.
+    pub fn light_at(&self, location: Location, position: BlockPosition, sky_darkening: u8) -> Option<LightLevel> {
+        let y = usize::try_from(position.y).ok()?;
+        let (x, z) = ((position.x & 15) as usize, (position.z & 15) as usize);
+        let chunk = &self.get_chunk(ChunkLocation::new(position.chunk(), location))?.0;
+        Some(chunk.sky_light_at(x, y, z)?.attenuate(sky_darkening).max(chunk.block_light_at(x, y, z)?))
+    }
+
+    /// Sets the block at some position. Returns
+    /// `None` if its chunk is not loaded.
+    pub fn set_block(&mut self, location: Location, position: BlockPosition, id: BlockID, meta: u8) -> Option<()> {
+        let y = usize::try_from(position.y).ok()?;
+        let (x, z) = ((position.x & 15) as usize, (position.z & 15) as usize);
+        let chunk_location = ChunkLocation::new(position.chunk(), location);
+        let chunk = &mut self.get_chunk_mut(chunk_location)?.0;
+        chunk.set_block_type_at(x, y, z, id)?;
+        chunk.set_block_meta_at(x, y, z, meta)?;
+        self.dirty.insert(chunk_location);
+        Some(())
+    }
+
+    /// The signs within some chunk. Empty
+    /// if the chunk is not loaded.
+    pub fn signs(&self, chunk: ChunkLocation) -> &[TileEntitySign] {
+        self.signs.get(&chunk).map_or(&[], |v| v)
+    }
+
+    /// Adds a sign to a loaded chunk, replacing any
+    /// sign already at the same position.
+    pub fn set_sign(&mut self, chunk: ChunkLocation, sign: TileEntitySign) {
+        self.mark_dirty(chunk);
+        let signs = self.signs.entry(chunk).or_default();
+        match signs.iter_mut().find(|v| (v.x, v.y, v.z) == (sign.x, sign.y, sign.z)) {
+            Some(existing) => *existing = sign,
+            None => signs.push(sign),
+        }
+    }
+
+    /// Sends a player the text of every sign in some chunk.
+    fn send_signs(&self, player: &Client, chunk: ChunkLocation) -> anyhow::Result<()> {
+        for sign in self.signs(chunk) {
+            player.send_sign(BlockPosition::new(sign.x, sign.y, sign.z), sign.lines())?;
+        }
+        Ok(())
+    }
+
     fn add_ticket_for_loaded(&mut self, chunk: ChunkLocation) {
         let chunk_data = self
             .get_chunk_mut(chunk)
@@ -94,15 +226,99 @@ impl GameWorld {
         self.get_chunk(chunk).is_some()
     }
 
+    /// Records an entity other than a player as being within some
+    /// chunk, so players who come to view the chunk are sent it.
+    /// Returns `false` if the chunk is not loaded, in which case
+    /// the entity is not within any chunk until it is recorded again.
+    pub fn set_entity_chunk(&mut self, entity: Entity, chunk: ChunkLocation) -> bool {
+        self.remove_entity(entity);
+        let Some(data) = self.chunks.get_mut(&chunk) else {
+            return false;
+        };
+        data.2.insert(entity);
+        self.entity_chunks.insert(entity, chunk);
+        true
+    }
+
+    /// Removes an entity from the chunk `set_entity_chunk`
+    /// recorded it in, such as once it is despawned.
+    pub fn remove_entity(&mut self, entity: Entity) {
+        if let Some(chunk) = self.entity_chunks.remove(&entity) {
+            if let Some(data) = self.chunks.get_mut(&chunk) {
+                data.2.remove(&entity);
+            }
+        }
+    }
+
+    /// Loads a chunk without a ticket, such as to look through it
+    /// before anyone views it. Does nothing if it is loaded or loading.
+    pub fn request_chunk(&mut self, chunk: ChunkLocation) -> anyhow::Result<()> {
+        if self.is_loaded(chunk) || self.loading_requests.contains_key(&chunk) {
+            return Ok(());
+        }
+        self.loading_requests.insert(chunk, HashMap::new());
+        self.command_sender.send(WorldLoaderCommand::LoadChunk(chunk))?;
+        Ok(())
+    }
+
 
+    /// Unloads a chunk, writing it first if it changed.
     fn save_chunk(&mut self, chunk: ChunkLocation) -> anyhow::Result<()> {
+        let signs = self.signs.remove(&chunk).unwrap_or_default();
+        let data = self.saved_data.remove(&chunk).unwrap_or_default();
+        let dirty = self.dirty.remove(&chunk);
         if let Some(c) = self.chunks.remove(&chunk) {
-            tracing::error!("Chunk saving not done");
-            //self.command_sender.send(WorldLoaderCommand::SaveChunk(chunk, todo!()))?;
+            for entity in &c.2 {
+                if self.entity_chunks.get(entity) == Some(&chunk) {
+                    self.entity_chunks.remove(entity);
+                }
+            }
+            let root = dirty.then(|| chunk_to_chunk_root(&c.0, &signs, &data));
+            self.command_sender.send(WorldLoaderCommand::SaveChunk(chunk, root))?;
         }
         Ok(())
     }
 
+    /// Saves every changed chunk, keeping them loaded, and returns how
+    /// many were queued. `is_saving` tells when they are all on disk.
+    pub fn save_all(&mut self) -> anyhow::Result<usize> {
+        self.save_where(|_| true)
+    }
+
+    /// Saves every changed chunk which some player
+    /// or other ticket keeps loaded, as `save_all` does.
+    pub fn save_ticketed(&mut self) -> anyhow::Result<usize> {
+        self.save_where(|ticket| ticket.0 > 0)
+    }
+
+    fn save_where(&mut self, filter: impl Fn(&TicketCount) -> bool) -> anyhow::Result<usize> {
+        let generated = SavedChunkData::default();
+        let mut saved = 0;
+        for location in self.dirty.iter().copied().collect::<Vec<_>>() {
+            let Some((chunk, ticket, _)) = self.chunks.get(&location) else {
+                continue;
+            };
+            if !filter(ticket) {
+                continue;
+            }
+            let data = self.saved_data.get(&location).unwrap_or(&generated);
+            let root = chunk_to_chunk_root(chunk, self.signs(location), data);
+            self.command_sender.send(WorldLoaderCommand::WriteChunk(location, root))?;
+            self.dirty.remove(&location);
+            saved += 1;
+        }
+        let (done, saving) = flume::bounded(1);
+        self.command_sender.send(WorldLoaderCommand::Flush(done))?;
+        self.saving = Some(saving);
+        Ok(saved)
+    }
+
+    /// Whether chunks queued by `save_all`
+    /// are still being written.
+    pub fn is_saving(&self) -> bool {
+        self.saving.as_ref().is_some_and(|v| !v.is_disconnected())
+    }
+
     pub fn remove_ticket(&mut self, chunk: ChunkLocation, entity: Option<Entity>) -> anyhow::Result<()> {
 
         let chunk_data = self
@@ -137,12 +353,14 @@ impl GameWorld {
         Ok(())
     }
 
-    fn load_for_client(
+    /// Marks a loaded chunk as known to some player,
+    /// without sending it.
+    fn mark_known_by_client(
         &mut self,
         player: &Client,
         player_entity: Entity,
         chunk: ChunkLocation,
-    ) -> anyhow::Result<()> {
+    ) -> &Chunk {
         let chunk_data = self
             .get_chunk_mut(chunk)
             .expect("Should be loaded when this is called");
@@ -152,9 +370,28 @@ impl GameWorld {
             player.client_waiting_chunks.lock().remove(&chunk);
             player.client_known_chunks.lock().insert(chunk.position);
         }
-        player.send_chunk(&chunk_data.0, ChunkBitmap::full())?;
+        &chunk_data.0
+    }
 
-        Ok(())
+    /// Returns `false` if the chunk is
+    /// not yet loaded, and was requested.
+    fn request_for_client(
+        &mut self,
+        player: &Client,
+        player_entity: Entity,
+        chunk: ChunkLocation,
+    ) -> anyhow::Result<bool> {
+        if self.add_ticket(chunk)? {
+            return Ok(true);
+        }
+        {
+            player.client_waiting_chunks.lock().insert(chunk);
+        }
+        self.loading_requests
+            .entry(chunk)
+            .or_default()
+            .insert(player.id, player_entity);
+        Ok(false)
     }
 
     pub fn add_player_to_chunk(
@@ -163,16 +400,52 @@ impl GameWorld {
         player_entity: Entity,
         chunk: ChunkLocation,
     ) -> anyhow::Result<()> {
-        if self.add_ticket(chunk)? {
-            self.load_for_client(player, player_entity, chunk)?;
-        } else {
-            {
-                player.client_waiting_chunks.lock().insert(chunk);
+        if self.request_for_client(player, player_entity, chunk)? {
+            let data = self.mark_known_by_client(player, player_entity, chunk);
+            player.send_chunk(data, ChunkBitmap::full())?;
+            self.send_signs(player, chunk)?;
+        }
+        Ok(())
+    }
+
+    /// Adds a player to many chunks at once, sending
+    /// those already loaded in bulk.
+    pub fn add_player_to_chunks(
+        &mut self,
+        player: &Client,
+        player_entity: Entity,
+        chunks: impl IntoIterator<Item = ChunkLocation>,
+    ) -> anyhow::Result<()> {
+        let mut loaded = vec![];
+        for chunk in chunks {
+            if self.request_for_client(player, player_entity, chunk)? {
+                self.mark_known_by_client(player, player_entity, chunk);
+                loaded.push(chunk);
             }
-            self.loading_requests
-                .entry(chunk)
-                .or_default()
-                .insert(player.id, player_entity);
+        }
+        let to_send = loaded
+            .iter()
+            .map(|v| (&self.chunks[v].0, ChunkBitmap::full()))
+            .collect::<Vec<_>>();
+        player.send_chunks_bulk(&to_send)?;
+        for chunk in loaded {
+            self.send_signs(player, chunk)?;
+        }
+        Ok(())
+    }
+
+    /// Sends a player every chunk it already knows
+    /// in some dimension again, such as after respawning.
+    pub fn resend_known_chunks(&self, player: &Client, location: Location) -> anyhow::Result<()> {
+        let known = player.client_known_chunks.lock().clone();
+        let to_send = known
+            .iter()
+            .filter_map(|v| self.get_chunk(ChunkLocation::new(*v, location)))
+            .map(|v| (&v.0, ChunkBitmap::full()))
+            .collect::<Vec<_>>();
+        player.send_chunks_bulk(&to_send)?;
+        for chunk in known {
+            self.send_signs(player, ChunkLocation::new(chunk, location))?;
         }
         Ok(())
     }
@@ -187,15 +460,67 @@ impl GameWorld {
     }
 
     pub fn process_loads(&mut self, server: &Server) -> anyhow::Result<()> {
-        while let Ok((chunk, location)) = self.chunk_recv.try_recv() {
-            self.add_chunk(location, chunk);
-            if let Some(requests) = self.loading_requests.remove(&location) {
-                for req in requests {
-                    let cl = server.get_client(req.0)?;
-                    self.add_player_to_chunk(cl, req.1, location)?;
-                }
+        while let Ok((chunk, signs, data, location)) = self.chunk_recv.try_recv() {
+            self.insert_chunk(location, chunk);
+            self.signs.insert(location, signs);
+            self.saved_data.insert(location, data);
+            self.notify_loaded(server, location)?;
+        }
+        Ok(())
+    }
+
+    /// Sends a newly added chunk to every
+    /// player waiting for it to load.
+    pub fn notify_loaded(&mut self, server: &Server, location: ChunkLocation) -> anyhow::Result<()> {
+        if let Some(requests) = self.loading_requests.remove(&location) {
+            for req in requests {
+                let cl = server.get_client(req.0)?;
+                self.add_player_to_chunk(cl, req.1, location)?;
             }
         }
         Ok(())
     }
+
+    /// Chunks the loader found missing
+    /// on disk since the last call.
+    pub fn take_missing_chunks(&mut self) -> impl Iterator<Item = ChunkLocation> + '_ {
+        self.missing_recv.try_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use servidiot_primitives::{
+        chunk::Chunk,
+        position::{ChunkLocation, ChunkPosition, Location},
+    };
+
+    use super::GameWorld;
+
+    #[test]
+    fn entity_chunks_test() {
+        let mut world = GameWorld::new(std::env::temp_dir().join(format!("servidiot-entity-chunks-{}", std::process::id())));
+        let mut ecs = servidiot_ecs::World::new();
+        let entity = ecs.spawn(());
+        let [first, second, unloaded] = [0, 1, 2].map(|x| ChunkLocation::new(ChunkPosition::new(x, 0), Location::overworld()));
+        for chunk in [first, second] {
+            world.add_chunk(chunk, Chunk::new(chunk.position));
+        }
+        let entities = |world: &GameWorld, chunk| world.get_chunk(chunk).unwrap().2.iter().copied().collect::<Vec<_>>();
+
+        // Players who come to view a chunk are sent what is in its set.
+        assert!(world.set_entity_chunk(entity, first));
+        assert_eq!(entities(&world, first), [entity]);
+
+        assert!(world.set_entity_chunk(entity, second));
+        assert!(entities(&world, first).is_empty());
+        assert_eq!(entities(&world, second), [entity]);
+
+        assert!(!world.set_entity_chunk(entity, unloaded));
+        assert!(entities(&world, second).is_empty());
+
+        world.set_entity_chunk(entity, first);
+        world.remove_entity(entity);
+        assert!(entities(&world, first).is_empty());
+    }
 }
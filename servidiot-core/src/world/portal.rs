@@ -0,0 +1,182 @@
+use servidiot_primitives::{
+    block::{self, BlockID},
+    position::{BlockPosition, EntityLocation, Location, MAX_BLOCK_Y, MIN_BLOCK_Y},
+};
+
+const NETHER_PORTAL: u16 = 90;
+const END_PORTAL: u16 = 119;
+const AIR: u16 = 0;
+const OBSIDIAN: u16 = 49;
+/// How many overworld blocks each nether block spans.
+const NETHER_SCALE: f64 = 8.0;
+/// The lowest block of the bedrock roof of the nether.
+const NETHER_ROOF_Y: i32 = 127;
+
+/// Where players arrive in the end, atop its obsidian platform.
+pub const END_SPAWN: BlockPosition = BlockPosition { x: 100, y: 49, z: 0 };
+
+/// How far from where it leads, in blocks horizontally,
+/// a portal is looked for to arrive at instead.
+pub const PORTAL_SEARCH_RADIUS: i32 = 16;
+
+/// The dimension a portal at `block` takes an entity in `from` to.
+/// Going through a portal in its own dimension leads back to the overworld.
+pub fn portal_target(block: u16, from: Location) -> Option<Location> {
+    let target = match block {
+        NETHER_PORTAL => Location::nether(),
+        END_PORTAL => Location::the_end(),
+        _ => return None,
+    };
+    if from.dimension == target.dimension {
+        Some(Location::new(from.world, Location::overworld().dimension))
+    } else {
+        Some(Location::new(from.world, target.dimension))
+    }
+}
+
+/// Where `from` leads in `to`: onto the end platform, or the same place
+/// scaled by [`NETHER_SCALE`] into and out of the nether. The height
+/// is kept, to be replaced by wherever is safe to arrive.
+pub fn portal_destination(from: EntityLocation, to: Location) -> EntityLocation {
+    let nether = Location::nether().dimension;
+    let mut destination = from.with_location(to);
+    if to.dimension == Location::the_end().dimension {
+        destination.position.x = f64::from(END_SPAWN.x) + 0.5;
+        destination.position.y = f64::from(END_SPAWN.y);
+        destination.position.z = f64::from(END_SPAWN.z) + 0.5;
+        return destination;
+    }
+    let scale = if to.dimension == nether {
+        1.0 / NETHER_SCALE
+    } else if from.location.dimension == nether {
+        NETHER_SCALE
+    } else {
+        1.0
+    };
+    destination.position.x *= scale;
+    destination.position.z *= scale;
+    destination
+}
+
+/// The highest an entity arriving in some dimension
+/// may stand, keeping nether arrivals under its roof.
+pub fn max_arrival_y(location: Location) -> i32 {
+    if location.dimension == Location::nether().dimension {
+        NETHER_ROOF_Y - 2
+    } else {
+        MAX_BLOCK_Y - 1
+    }
+}
+
+/// The bottom block of the nether portal nearest to `center`, within
+/// [`PORTAL_SEARCH_RADIUS`] of it horizontally and at most `max_y` high.
+/// `block_at` gives the block at some position, or `None` where the
+/// world is not loaded.
+pub fn find_portal(center: BlockPosition, max_y: i32, block_at: impl Fn(BlockPosition) -> Option<BlockID>) -> Option<BlockPosition> {
+    let is_portal = |position| block_at(position).is_some_and(|v| *v == NETHER_PORTAL);
+    let mut nearest: Option<(i64, BlockPosition)> = None;
+    for x in center.x - PORTAL_SEARCH_RADIUS..=center.x + PORTAL_SEARCH_RADIUS {
+        for z in center.z - PORTAL_SEARCH_RADIUS..=center.z + PORTAL_SEARCH_RADIUS {
+            for y in MIN_BLOCK_Y + 1..=max_y {
+                let position = BlockPosition::new(x, y, z);
+                if !is_portal(position) || is_portal(BlockPosition::new(x, y - 1, z)) {
+                    continue;
+                }
+                let distance = [x - center.x, y - center.y, z - center.z]
+                    .into_iter()
+                    .map(|v| i64::from(v).pow(2))
+                    .sum();
+                if nearest.is_none_or(|(v, _)| distance < v) {
+                    nearest = Some((distance, position));
+                }
+            }
+        }
+    }
+    nearest.map(|(_, v)| v)
+}
+
+/// The highest height at most `max_y` in some column an entity can stand
+/// at, with two blocks of air over a solid block. `block_at` gives the
+/// block at some height, or `None` where the world is not loaded.
+pub fn find_safe_y(max_y: i32, block_at: impl Fn(i32) -> Option<BlockID>) -> Option<i32> {
+    let is_air = |y| block_at(y).is_some_and(|v| *v == AIR);
+    (MIN_BLOCK_Y + 1..=max_y)
+        .rev()
+        .find(|y| is_air(*y) && is_air(y + 1) && block_at(y - 1).is_some_and(block::is_solid))
+}
+
+/// The blocks of a platform for an entity to arrive on with its
+/// feet at `feet`: a 5x5 floor of obsidian, cleared three blocks up.
+pub fn platform(feet: BlockPosition) -> impl Iterator<Item = (BlockPosition, u16)> {
+    (-2..=2).flat_map(move |x| {
+        (-2..=2).flat_map(move |z| {
+            (-1..=2).map(move |y| {
+                let block = if y < 0 { OBSIDIAN } else { AIR };
+                (BlockPosition::new(feet.x + x, feet.y + y, feet.z + z), block)
+            })
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use servidiot_primitives::{
+        block::BlockID,
+        position::{BlockPosition, EntityLocation, Location, Position},
+    };
+
+    use super::{find_portal, find_safe_y, platform, portal_destination, portal_target, END_PORTAL, NETHER_PORTAL, OBSIDIAN};
+
+    fn id(v: u16) -> BlockID {
+        BlockID::new(v).unwrap()
+    }
+
+    #[test]
+    fn portal_destination_test() {
+        let from = EntityLocation {
+            position: Position::new(80.0, 70.0, -160.0, 0.0, 0.0, false),
+            location: Location::overworld(),
+        };
+        let nether = portal_target(NETHER_PORTAL, from.location).unwrap();
+        assert_eq!(nether, Location::nether());
+        let arrival = portal_destination(from, nether).position;
+        assert_eq!((arrival.x, arrival.z), (10.0, -20.0));
+
+        let end = portal_target(END_PORTAL, from.location).unwrap();
+        let arrival = portal_destination(from, end).position;
+        assert_eq!((arrival.x, arrival.y, arrival.z), (100.5, 49.0, 0.5));
+        assert_eq!(portal_target(END_PORTAL, end), Some(Location::overworld()));
+    }
+
+    #[test]
+    fn find_safe_y_test() {
+        // Stone up to 10, a cave from 20 to 21, and a roof from 22 to 30.
+        let column = |y: i32| match y {
+            0..=10 | 19 | 22..=30 => Some(id(1)),
+            _ if y > 255 => None,
+            _ => Some(id(0)),
+        };
+        assert_eq!(find_safe_y(100, column), Some(31));
+        assert_eq!(find_safe_y(30, column), Some(20));
+        assert_eq!(find_safe_y(19, column), Some(11));
+        assert_eq!(find_safe_y(100, |_| Some(id(0))), None);
+    }
+
+    #[test]
+    fn find_portal_test() {
+        let portals = [BlockPosition::new(5, 64, 5), BlockPosition::new(5, 65, 5), BlockPosition::new(-10, 40, 0)];
+        let block_at = |v| Some(id(if portals.contains(&v) { NETHER_PORTAL } else { 0 }));
+        assert_eq!(find_portal(BlockPosition::new(0, 64, 0), 126, block_at), Some(BlockPosition::new(5, 64, 5)));
+        assert_eq!(find_portal(BlockPosition::new(-8, 40, 0), 126, block_at), Some(BlockPosition::new(-10, 40, 0)));
+        assert_eq!(find_portal(BlockPosition::new(40, 64, 0), 126, block_at), None);
+    }
+
+    #[test]
+    fn platform_test() {
+        let blocks = platform(BlockPosition::new(100, 49, 0)).collect::<Vec<_>>();
+        assert_eq!(blocks.len(), 100);
+        assert!(blocks.contains(&(BlockPosition::new(98, 48, -2), OBSIDIAN)));
+        assert!(blocks.contains(&(BlockPosition::new(102, 51, 2), 0)));
+        assert!(blocks.iter().all(|(v, block)| (*block == OBSIDIAN) == (v.y == 48)));
+    }
+}
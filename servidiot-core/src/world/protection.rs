@@ -0,0 +1,42 @@
+use servidiot_anvil::nbt::level::LevelData;
+use servidiot_primitives::position::{BlockPosition, Location};
+
+/// The area around the world spawn which
+/// players may not change.
+pub struct SpawnProtection {
+    pub center: BlockPosition,
+    /// How far from `center`, in blocks, is protected.
+    /// Zero disables protection.
+    pub radius: u32,
+    /// Whether operators may change protected blocks.
+    pub operator_bypass: bool,
+}
+
+impl SpawnProtection {
+    /// Protects `radius` blocks around the spawn of some level.
+    /// Levels without a level.dat spawn at the origin.
+    pub fn new(level: Option<&LevelData>, radius: u32) -> Self {
+        let center = level.map_or(BlockPosition::new(0, 0, 0), |v| {
+            BlockPosition::new(v.spawn_x, v.spawn_y, v.spawn_z)
+        });
+        Self {
+            center,
+            radius,
+            operator_bypass: true,
+        }
+    }
+
+    /// Whether the block at `position` is protected. Only
+    /// the overworld is protected, and height is ignored.
+    pub fn protects(&self, location: Location, position: BlockPosition) -> bool {
+        let flat = BlockPosition::new(position.x, self.center.y, position.z);
+        location == Location::overworld()
+            && flat.distance_squared(&self.center) <= i64::from(self.radius).pow(2)
+            && self.radius != 0
+    }
+
+    /// Whether a player may change the block at `position`.
+    pub fn allows(&self, location: Location, position: BlockPosition, operator: bool) -> bool {
+        (operator && self.operator_bypass) || !self.protects(location, position)
+    }
+}
@@ -0,0 +1,105 @@
+use std::ops::RangeInclusive;
+
+use rand::Rng;
+use servidiot_anvil::nbt::level::LevelData;
+use servidiot_network::io::packet::server::play::GameStateChange;
+
+/// The least and most ticks before rain or thunder toggles.
+const WEATHER_DURATION_TICKS: RangeInclusive<i32> = 6000..=18000;
+
+/// The weather, counting down to its next change.
+pub struct WeatherState {
+    pub raining: bool,
+    pub thundering: bool,
+    /// Ticks until `raining` toggles.
+    pub rain_timer: i32,
+    /// Ticks until `thundering` toggles.
+    pub thunder_timer: i32,
+}
+
+impl WeatherState {
+    /// The weather saved in some level. Levels without a level.dat
+    /// start clear, and timers which already ran out start afresh.
+    pub fn from_level(level: Option<&LevelData>, rng: &mut impl Rng) -> Self {
+        let mut timer = |saved: Option<i32>| match saved {
+            Some(v) if v > 0 => v,
+            _ => rng.gen_range(WEATHER_DURATION_TICKS),
+        };
+        Self {
+            raining: level.is_some_and(|v| v.raining),
+            thundering: level.is_some_and(|v| v.thundering),
+            rain_timer: timer(level.map(|v| v.rain_time)),
+            thunder_timer: timer(level.map(|v| v.thunder_time)),
+        }
+    }
+
+    /// Counts both timers down by a tick, toggling the
+    /// weather whose timer ran out and starting it afresh.
+    pub fn tick(&mut self, rng: &mut impl Rng) {
+        for (timer, weather) in [
+            (&mut self.rain_timer, &mut self.raining),
+            (&mut self.thunder_timer, &mut self.thundering),
+        ] {
+            *timer -= 1;
+            if *timer <= 0 {
+                *weather = !*weather;
+                *timer = rng.gen_range(WEATHER_DURATION_TICKS);
+            }
+        }
+    }
+
+    /// Saves the weather into some level.
+    pub fn save(&self, level: &mut LevelData) {
+        level.raining = self.raining;
+        level.thundering = self.thundering;
+        level.rain_time = self.rain_timer;
+        level.thunder_time = self.thunder_timer;
+    }
+}
+
+/// Watches the level's weather for changes to send to players.
+#[derive(Default)]
+pub struct WeatherSystem {
+    /// Whether players were last told it is raining.
+    synced_raining: Option<bool>,
+    /// Whether players were last told it is thundering.
+    synced_thundering: Option<bool>,
+}
+
+/// Marks a player as having been sent the weather.
+pub struct WeatherSynced;
+
+impl WeatherSystem {
+    /// The changes to send to players who have not been sent the weather.
+    pub fn initialize(weather: &WeatherState) -> Vec<GameStateChange> {
+        let mut changes = vec![];
+        if weather.raining {
+            changes.push(GameStateChange::BeginRaining);
+        }
+        if weather.thundering {
+            changes.push(thunder_change(true));
+        }
+        changes
+    }
+
+    /// Returns the changes to send to players if
+    /// `weather` differs from what they were last sent.
+    pub fn take_changes(&mut self, weather: &WeatherState) -> Vec<GameStateChange> {
+        let mut changes = vec![];
+        match (self.synced_raining.replace(weather.raining), weather.raining) {
+            (Some(false), true) => changes.push(GameStateChange::BeginRaining),
+            (Some(true), false) => changes.push(GameStateChange::EndRaining),
+            _ => (),
+        }
+        match self.synced_thundering.replace(weather.thundering) {
+            Some(old) if old != weather.thundering => changes.push(thunder_change(weather.thundering)),
+            _ => (),
+        }
+        changes
+    }
+}
+
+/// Thunder is shown by fading the sky darker or lighter.
+fn thunder_change(thundering: bool) -> GameStateChange {
+    GameStateChange::FadeTime(if thundering { 1.0 } else { 0.0 })
+}
@@ -3,9 +3,33 @@
 type SystemFn<State> =
     dyn Fn(&State) -> anyhow::Result<()>;
 
-pub struct SystemExecutor<State> {
+/// A run of systems executed together, in the order they were added.
+struct SystemGroup<State> {
+    /// `None` for systems added outside any group.
+    name: Option<&'static str>,
+    enabled: bool,
     systems: Vec<Box<SystemFn<State>>>,
 }
+
+impl<State> SystemGroup<State> {
+    fn new(name: Option<&'static str>) -> Self {
+        Self { name, enabled: true, systems: vec![] }
+    }
+
+    fn run(&self, state: &State) {
+        for sys in &self.systems {
+            if let Err(e) = sys(state) {
+                tracing::error!("System error: {:?}", e);
+            }
+        }
+    }
+}
+
+pub struct SystemExecutor<State> {
+    /// Run one after another. Systems are
+    /// always added to the last group.
+    groups: Vec<SystemGroup<State>>,
+}
 impl<State> Default for SystemExecutor<State> {
     fn default() -> Self {
         Self::new()
@@ -14,22 +38,114 @@ impl<State> Default for SystemExecutor<State> {
 
 impl<State> SystemExecutor<State> {
     pub fn new() -> Self {
-        Self { systems: vec![] }
+        Self { groups: vec![SystemGroup::new(None)] }
     }
 
     pub fn add_system(
         &mut self,
         s: impl Fn(&State) -> anyhow::Result<()> + 'static,
     ) -> &mut Self {
-        self.systems.push(Box::new(s));
+        self.groups.last_mut().expect("always one group").systems.push(Box::new(s));
+        self
+    }
+
+    /// Starts a named group, which systems are added to until
+    /// `end_group`. Groups run in the order they were begun.
+    ///
+    /// # Panics
+    /// Panics if a group called `name` already exists.
+    pub fn begin_group(&mut self, name: &'static str) -> &mut Self {
+        assert!(self.group(name).is_none(), "system group {name} already exists");
+        self.groups.push(SystemGroup::new(Some(name)));
         self
     }
 
+    /// Ends the current group. Systems added
+    /// afterwards run after it, outside any group.
+    pub fn end_group(&mut self) -> &mut Self {
+        self.groups.push(SystemGroup::new(None));
+        self
+    }
+
+    /// Stops or resumes `run_systems` running some group.
+    /// Returns `false` if there is no such group.
+    pub fn set_group_enabled(&mut self, name: &'static str, enabled: bool) -> bool {
+        let Some(group) = self.groups.iter_mut().find(|v| v.name == Some(name)) else {
+            return false;
+        };
+        group.enabled = enabled;
+        true
+    }
+
+    /// Runs the systems of one group, even if it is disabled.
+    /// Returns `false` if there is no such group.
+    pub fn run_group(&self, name: &'static str, state: &State) -> bool {
+        let Some(group) = self.group(name) else {
+            return false;
+        };
+        group.run(state);
+        true
+    }
+
+    /// Runs every enabled group in turn.
     pub fn run_systems(&self, state: &State)  {
-        for sys in &self.systems {
-            if let Err(e) = sys(state) {
-                tracing::error!("System error: {:?}", e);
-            }
+        for group in self.groups.iter().filter(|v| v.enabled) {
+            group.run(state);
         }
     }
+
+    fn group(&self, name: &'static str) -> Option<&SystemGroup<State>> {
+        self.groups.iter().find(|v| v.name == Some(name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::SystemExecutor;
+
+    fn log(name: &'static str) -> impl Fn(&RefCell<Vec<&'static str>>) -> anyhow::Result<()> {
+        move |state| {
+            state.borrow_mut().push(name);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn system_groups_test() {
+        let mut systems = SystemExecutor::new();
+        systems
+            .add_system(log("first"))
+            .begin_group("input")
+            .add_system(log("input"))
+            .end_group()
+            .begin_group("physics")
+            .add_system(log("physics a"))
+            .add_system(log("physics b"))
+            .end_group()
+            .add_system(log("last"));
+
+        let state = RefCell::new(vec![]);
+        systems.run_systems(&state);
+        assert_eq!(*state.borrow(), ["first", "input", "physics a", "physics b", "last"]);
+
+        state.borrow_mut().clear();
+        assert!(systems.set_group_enabled("input", false));
+        systems.run_systems(&state);
+        assert_eq!(*state.borrow(), ["first", "physics a", "physics b", "last"]);
+
+        state.borrow_mut().clear();
+        assert!(systems.run_group("input", &state));
+        assert!(!systems.run_group("network", &state));
+        assert!(!systems.set_group_enabled("network", true));
+        assert_eq!(*state.borrow(), ["input"]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn duplicate_group_test() {
+        let mut systems = SystemExecutor::<()>::new();
+        systems.begin_group("input").end_group().begin_group("input");
+    }
 }
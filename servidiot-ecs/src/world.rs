@@ -1 +1,55 @@
-pub use hecs::World;
\ No newline at end of file
+use hecs::{Component, Entity, NoSuchEntity, World};
+
+/// Convenience methods on the ECS world.
+pub trait EcsExt {
+    /// Whether `entity` has a component of type `T`.
+    /// Returns `false` if the entity does not exist.
+    fn has_component<T: Component>(&self, entity: Entity) -> bool;
+
+    /// Whether `entity` has a component of type `T`,
+    /// or an error if the entity does not exist.
+    fn has_component_strict<T: Component>(&self, entity: Entity) -> Result<bool, NoSuchEntity>;
+
+    /// The number of entities with a component of type `T`.
+    fn count<T: Component>(&self) -> usize;
+}
+
+impl EcsExt for World {
+    fn has_component<T: Component>(&self, entity: Entity) -> bool {
+        self.has_component_strict::<T>(entity).unwrap_or(false)
+    }
+
+    fn has_component_strict<T: Component>(&self, entity: Entity) -> Result<bool, NoSuchEntity> {
+        Ok(self.entity(entity)?.has::<T>())
+    }
+
+    fn count<T: Component>(&self) -> usize {
+        self.query::<()>().with::<&T>().iter().count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hecs::World;
+
+    use super::EcsExt;
+
+    #[test]
+    fn component_helpers() {
+        let mut world = World::new();
+        let a = world.spawn((1u32, 'a'));
+        let b = world.spawn((2u32,));
+        let gone = world.spawn(('c',));
+        world.despawn(gone).unwrap();
+
+        assert!(world.has_component::<char>(a));
+        assert!(!world.has_component::<char>(b));
+        assert!(!world.has_component::<char>(gone));
+        assert_eq!(world.has_component_strict::<u32>(b), Ok(true));
+        assert!(world.has_component_strict::<u32>(gone).is_err());
+
+        assert_eq!(world.count::<u32>(), 2);
+        assert_eq!(world.count::<char>(), 1);
+        assert_eq!(world.count::<u8>(), 0);
+    }
+}
@@ -6,6 +6,7 @@ use servidiot_yggdrasil::authenticate::Profile;
 use crate::io::packet::{server::play::ServerPlayPacket, client::play::ClientPlayPacket};
 
 pub mod listener;
+pub mod status;
 pub mod worker;
 
 /// A new player connecting to the game.
@@ -21,5 +22,15 @@ pub struct NewPlayer {
 
 /// The server state.
 pub struct ServerState {
-    pub rsa_key: RsaPrivateKey
-}
\ No newline at end of file
+    pub rsa_key: RsaPrivateKey,
+    /// How many packets a client may send each
+    /// second before being disconnected.
+    pub max_packets_per_second: u32,
+    /// The server icon shown in the server list,
+    /// made by `ServerListEntry::favicon_from_png_file`.
+    pub favicon: Option<String>,
+}
+
+impl ServerState {
+    pub const DEFAULT_MAX_PACKETS_PER_SECOND: u32 = 500;
+}
@@ -0,0 +1,82 @@
+use std::path::Path;
+
+use anyhow::bail;
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+/// How this server appears in the server list.
+#[derive(Debug, Default, Clone)]
+pub struct ServerListEntry {
+    /// The server icon, as a PNG data URI.
+    pub favicon: Option<String>,
+}
+
+/// The signature every PNG file starts with.
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+/// The width and height server icons must have.
+const FAVICON_SIZE: u32 = 64;
+
+impl ServerListEntry {
+    /// Reads a server icon from a PNG file, encoding it
+    /// as a data URI for the status response.
+    ///
+    /// Clients only display icons exactly
+    /// 64×64 pixels, so other sizes are an error.
+    pub fn favicon_from_png_file(path: &Path) -> anyhow::Result<String> {
+        let data = std::fs::read(path)?;
+        let (width, height) = png_dimensions(&data)?;
+        if (width, height) != (FAVICON_SIZE, FAVICON_SIZE) {
+            bail!(
+                "server icon {} is {}x{}, but must be {}x{}",
+                path.display(), width, height, FAVICON_SIZE, FAVICON_SIZE
+            );
+        }
+        Ok(format!("data:image/png;base64,{}", STANDARD.encode(data)))
+    }
+}
+
+/// Reads the width and height from the header of a PNG file.
+fn png_dimensions(data: &[u8]) -> anyhow::Result<(u32, u32)> {
+    // The signature is followed by the length and
+    // type of the IHDR chunk, then the dimensions.
+    if data.len() < 24 || !data.starts_with(PNG_SIGNATURE) || &data[12..16] != b"IHDR" {
+        bail!("not a PNG file");
+    }
+    let width = u32::from_be_bytes(data[16..20].try_into().unwrap());
+    let height = u32::from_be_bytes(data[20..24].try_into().unwrap());
+    Ok((width, height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{png_dimensions, ServerListEntry, PNG_SIGNATURE};
+
+    fn png_header(width: u32, height: u32) -> Vec<u8> {
+        let mut data = PNG_SIGNATURE.to_vec();
+        data.extend(13u32.to_be_bytes());
+        data.extend(b"IHDR");
+        data.extend(width.to_be_bytes());
+        data.extend(height.to_be_bytes());
+        data
+    }
+
+    #[test]
+    fn png_dimensions_test() {
+        assert_eq!(png_dimensions(&png_header(64, 32)).unwrap(), (64, 32));
+        assert!(png_dimensions(b"GIF89a").is_err());
+        assert!(png_dimensions(&png_header(64, 64)[..20]).is_err());
+    }
+
+    #[test]
+    fn favicon_test() {
+        let path = std::env::temp_dir().join(format!("servidiot-favicon-{}.png", std::process::id()));
+
+        std::fs::write(&path, png_header(64, 64)).unwrap();
+        let favicon = ServerListEntry::favicon_from_png_file(&path).unwrap();
+        assert!(favicon.starts_with("data:image/png;base64,iVBORw0KGgo"));
+
+        std::fs::write(&path, png_header(128, 128)).unwrap();
+        assert!(ServerListEntry::favicon_from_png_file(&path).is_err());
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -1,4 +1,4 @@
-use std::{io, net::SocketAddr, sync::Arc};
+use std::{io, net::SocketAddr, sync::Arc, time::Instant};
 
 use anyhow::bail;
 use tokio::{
@@ -68,7 +68,7 @@ impl Worker {
                     self.new_player_sender.send_async(new_player).await?;
 
                     tokio::select! {
-                        x = self.reader.run(send2) => {
+                        x = self.reader.run(send2, self.server_state.max_packets_per_second) => {
                             let err = x.unwrap_err();
                             log::info!("{:?} disconnected: {:?}", profile.name, err);
                         },
@@ -117,13 +117,20 @@ pub struct Reader {
 }
 
 impl Reader {
-    /// Run this reader.
+    /// Run this reader, disconnecting if more than
+    /// `max_packets_per_second` packets arrive each second.
     pub async fn run<P: Readable + Send + Sync + 'static>(
         mut self,
         sender: flume::Sender<P>,
+        max_packets_per_second: u32,
     ) -> anyhow::Result<!> {
+        let mut bucket = TokenBucket::new(max_packets_per_second);
         loop {
             let v = self.read().await?;
+            if !bucket.try_take(Instant::now()) {
+                log::warn!("Packet rate limit of {max_packets_per_second}/s exceeded");
+                bail!("rate limit exceeded");
+            }
             sender.send_async(v).await?;
         }
     }
@@ -146,6 +153,37 @@ impl Reader {
     }
 }
 
+/// Limits how often some action may happen,
+/// refilling `rate` tokens each second.
+struct TokenBucket {
+    rate: f64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: u32) -> Self {
+        Self {
+            rate: rate as f64,
+            tokens: rate as f64,
+            last_refill: Instant::now(),
+        }
+    }
+
+    /// Takes a token, returning `false` if none are left.
+    fn try_take(&mut self, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.rate);
+        self.last_refill = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
 /// A reader half of a connection.
 pub struct Writer {
     writer: OwnedWriteHalf,
@@ -174,3 +212,32 @@ impl Writer {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::TokenBucket;
+
+    #[test]
+    fn token_bucket_test() {
+        let mut bucket = TokenBucket::new(10);
+        let now = bucket.last_refill;
+        for _ in 0..10 {
+            assert!(bucket.try_take(now));
+        }
+        assert!(!bucket.try_take(now));
+
+        let later = now + Duration::from_millis(200);
+        assert!(bucket.try_take(later));
+        assert!(bucket.try_take(later));
+        assert!(!bucket.try_take(later));
+
+        // never refills past the rate
+        let much_later = later + Duration::from_secs(60);
+        for _ in 0..10 {
+            assert!(bucket.try_take(much_later));
+        }
+        assert!(!bucket.try_take(much_later));
+    }
+}
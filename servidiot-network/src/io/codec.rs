@@ -63,10 +63,10 @@ impl MinecraftCodec {
         let mut cursor = Cursor::new(self.received_buf.as_slice());
         if let Ok(v) = VarInt::read_from(&mut cursor) {
             let packet_length: usize = v.0.try_into()?;
-            if cursor.remaining_slice().len() >= packet_length {
+            if cursor.split().1.len() >= packet_length {
                 // we have enough data
 
-                let varint_length = self.received_buf.len() - cursor.remaining_slice().len();
+                let varint_length = self.received_buf.len() - cursor.split().1.len();
 
                 // read the packet
                 let packet = P::read_from(&mut cursor)?;
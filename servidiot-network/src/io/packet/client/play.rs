@@ -13,6 +13,9 @@ bitflags::bitflags! {
 }
 
 def_packets! {
+    ChatMessage {
+        message: String
+    },
     ClientSettings {
         locale: String,
         view_distance: i8,
@@ -72,6 +75,14 @@ def_packets! {
     CloseWindow {
         window_id: i8
     },
+    ClickWindow {
+        window_id: i8,
+        slot: i16,
+        button: i8,
+        action_number: i16,
+        mode: i8,
+        clicked_item: InventorySlot
+    },
     PlayerBlockPlacement {
         x: i32,
         y: u8,
@@ -95,10 +106,32 @@ def_packets! {
     },
     HeldItemChange {
         slot: i16
+    },
+    UpdateSign {
+        x: i32,
+        y: i16,
+        z: i32,
+        line1: String,
+        line2: String,
+        line3: String,
+        line4: String
+    },
+    UseEntity {
+        target: NetworkID,
+        mouse: InteractionType
+    },
+    PlayerInput {
+        sideways: f32,
+        forward: f32,
+        jump: bool,
+        unmount: bool
     }
 }
 
 packet_enum!(ClientPlayPacket {
+    ChatMessage = 0x01,
+    UseEntity = 0x02,
+    UpdateSign = 0x12,
     ClientSettings = 0x15,
     PluginMessage = 0x17,
     Player = 0x03,
@@ -111,10 +144,12 @@ packet_enum!(ClientPlayPacket {
     Animation = 0x0A,
     ClientStatus = 0x16,
     CloseWindow = 0x0D,
+    ClickWindow = 0x0E,
     PlayerBlockPlacement = 0x08,
     PlayerDigging = 0x07,
     CreativeInventoryAction = 0x10,
-    HeldItemChange = 0x09
+    HeldItemChange = 0x09,
+    PlayerInput = 0x0C
 });
 
 def_user_enum! {
@@ -145,6 +180,14 @@ def_user_enum! {
 }
 
 
+def_user_enum! {
+    InteractionType (i8) {
+        Interact = 0,
+        Attack = 1
+    }
+}
+
+
 def_user_enum! {
     ClientStatusType (i8) {
         PerformRespawn = 0,
@@ -157,8 +200,8 @@ def_user_enum! {
 def_user_enum! {
     DiggingStatus (i8) {
         Started = 0,
-        Cancelled = 0,
-        Finished = 0,
+        Cancelled = 1,
+        Finished = 2,
         DropItemStack = 3,
         DropItem = 4,
         ShootArrow = 5
@@ -1,6 +1,6 @@
 use anyhow::bail;
 use miniz_oxide::deflate::compress_to_vec_zlib;
-use servidiot_primitives::{chunk::ChunkBitmap, metadata::Metadata, nibble_vec::NibbleVec, number::{FixedPoint, RotationFraction360}, player::Gamemode, position::ChunkPosition};
+use servidiot_primitives::{block::BlockID, chunk::ChunkBitmap, item::InventorySlot, metadata::Metadata, nibble_vec::NibbleVec, number::{FixedPoint, RotationFraction360}, player::Gamemode, position::ChunkPosition};
 
 use crate::io::{
     packet::{def_packets, packet_enum},
@@ -8,6 +8,15 @@ use crate::io::{
 };
 
 def_packets! {
+    ChatMessage {
+        json: String
+    },
+    Respawn {
+        dimension: i32,
+        difficulty: u8,
+        gamemode: Gamemode,
+        level_type: String
+    },
     PlayerPositionAndLook {
         x: f64,
         y: f64,
@@ -59,30 +68,564 @@ def_packets! {
         current_item: i16,
         metadata: Metadata
     },
+    SpawnObject {
+        entity_id: VarInt,
+        kind: i8,
+        x: i32,
+        y: i32,
+        z: i32,
+        pitch: RotationFraction360,
+        yaw: RotationFraction360,
+        data: ObjectData
+    },
+    SpawnExperienceOrb {
+        entity_id: VarInt,
+        x: i32,
+        y: i32,
+        z: i32,
+        count: i16
+    },
+    CollectItem {
+        collected_entity_id: i32,
+        collector_entity_id: i32
+    },
+    EntityMetadata {
+        entity_id: i32,
+        metadata: Metadata
+    },
+    CloseWindow {
+        window_id: u8
+    },
+    OpenWindow {
+        window_id: u8,
+        inventory_type: u8,
+        window_title: String,
+        slot_count: u8,
+        use_provided_title: bool
+    },
+    WindowItems {
+        window_id: u8,
+        slots: LengthPrefixedVec<i16, InventorySlot>
+    },
+    WindowProperty {
+        window_id: u8,
+        property: i16,
+        value: i16
+    },
+    SetSlot {
+        window_id: i8,
+        slot: i16,
+        slot_data: InventorySlot
+    },
+    EntityEquipment {
+        entity_id: i32,
+        slot: i16,
+        item: InventorySlot
+    },
+    EntityVelocity {
+        entity_id: i32,
+        velocity_x: i16,
+        velocity_y: i16,
+        velocity_z: i16
+    },
     DestroyEntities {
         list: LengthPrefixedVec<u8, i32>
     },
+    EntityLookAndRelativeMove {
+        eid: i32,
+        dx: i8,
+        dy: i8,
+        dz: i8,
+        yaw: RotationFraction360,
+        pitch: RotationFraction360
+    },
+    EntityHeadLook {
+        entity_id: i32,
+        head_yaw: RotationFraction360
+    },
+    EntityStatus {
+        entity_id: i32,
+        status: EntityStatusKind
+    },
     EntityTeleport {
         eid: i32,
-        x: FixedPoint,
-        y: FixedPoint,
-        z: FixedPoint,
+        x: i32,
+        y: i32,
+        z: i32,
         yaw: RotationFraction360,
         pitch: RotationFraction360
+    },
+    BlockChange {
+        x: i32,
+        y: u8,
+        z: i32,
+        block_id: VarInt,
+        block_meta: u8
+    },
+    UpdateSign {
+        x: i32,
+        y: i16,
+        z: i32,
+        line1: String,
+        line2: String,
+        line3: String,
+        line4: String
+    },
+    SignEditorOpen {
+        x: i32,
+        y: i32,
+        z: i32
+    },
+    BlockAction {
+        x: i32,
+        y: i16,
+        z: i32,
+        action_id: u8,
+        action_param: u8,
+        block_type: VarInt
+    },
+    MultiBlockChange {
+        chunk_x: i32,
+        chunk_z: i32,
+        records: BlockChangeRecords
+    },
+    Explosion {
+        x: f32,
+        y: f32,
+        z: f32,
+        radius: f32,
+        records: LengthPrefixedVec<i32, ExplosionRecord>,
+        velocity_x: f32,
+        velocity_y: f32,
+        velocity_z: f32
+    },
+    PlayerListItem {
+        player_name: String,
+        online: bool,
+        ping: i16
+    },
+    NamedSoundEffect {
+        sound_name: String,
+        x: i32,
+        y: i32,
+        z: i32,
+        volume: f32,
+        pitch: u8
+    },
+    ChangeGameState {
+        reason: u8,
+        value: f32
+    },
+    AttachEntity {
+        entity_id: i32,
+        vehicle_id: i32,
+        leash: bool
+    },
+    EntityEffect {
+        entity_id: i32,
+        effect_id: i8,
+        amplifier: i8,
+        duration: i16
+    },
+    RemoveEntityEffect {
+        entity_id: i32,
+        effect_id: i8
+    },
+    UpdateHealth {
+        health: f32,
+        food: i16,
+        food_saturation: f32
+    },
+    PlayerAbilities {
+        flags: i8,
+        flying_speed: f32,
+        walking_speed: f32
+    },
+    SetExperience {
+        bar: f32,
+        level: i16,
+        total_exp: i16
+    },
+    Disconnect {
+        reason: String
+    },
+    PluginMessage {
+        channel: String,
+        data: LengthPrefixedVec<i16, u8>
+    },
+    MapData {
+        item_damage: VarInt,
+        data: LengthPrefixedVec<i16, u8>
+    },
+    ScoreboardObjective {
+        name: String,
+        display_name: String,
+        action: i8
+    },
+    ScoreboardDisplay {
+        position: i8,
+        score_name: String
+    }
+}
+
+impl ScoreboardObjective {
+    pub const CREATE: i8 = 0;
+    pub const REMOVE: i8 = 1;
+    pub const UPDATE: i8 = 2;
+}
+
+impl ScoreboardScore {
+    pub const UPDATE: i8 = 0;
+    pub const REMOVE: i8 = 1;
+}
+
+impl ScoreboardDisplay {
+    pub const LIST: i8 = 0;
+    pub const SIDEBAR: i8 = 1;
+    pub const BELOW_NAME: i8 = 2;
+}
+
+impl MapData {
+    /// Sets the colors of one column of the map,
+    /// starting `y` pixels down.
+    pub fn column(map_id: i16, x: u8, y: u8, colors: &[u8]) -> Self {
+        let mut data = vec![0, x, y];
+        data.extend_from_slice(colors);
+        Self::new(map_id, data)
+    }
+
+    /// Replaces every icon shown on the map.
+    pub fn icons(map_id: i16, icons: &[MapIcon]) -> Self {
+        let mut data = vec![1];
+        for icon in icons {
+            data.extend_from_slice(&[icon.type_and_direction, icon.x as u8, icon.z as u8]);
+        }
+        Self::new(map_id, data)
+    }
+
+    /// Sets the scale of the map, from 0 to 4.
+    pub fn scale(map_id: i16, scale: i8) -> Self {
+        Self::new(map_id, vec![2, scale as u8])
+    }
+
+    fn new(map_id: i16, data: Vec<u8>) -> Self {
+        Self {
+            item_damage: VarInt(map_id.into()),
+            data: LengthPrefixedVec::new(data),
+        }
+    }
+}
+
+/// The data sent with a spawned object. Its meaning depends
+/// on the object type. The velocity, in 8000ths of a block
+/// per tick, is only sent when `data` is positive.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ObjectData {
+    pub data: i32,
+    pub velocity: (i16, i16, i16),
+}
+
+impl Writable for ObjectData {
+    fn write_to(&self, target: &mut Vec<u8>) -> anyhow::Result<()> {
+        self.data.write_to(target)?;
+        if self.data > 0 {
+            self.velocity.0.write_to(target)?;
+            self.velocity.1.write_to(target)?;
+            self.velocity.2.write_to(target)?;
+        }
+        Ok(())
+    }
+}
+
+impl Readable for ObjectData {
+    fn read_from(data: &mut std::io::Cursor<&[u8]>) -> anyhow::Result<Self> {
+        let object_data = i32::read_from(data)?;
+        let velocity = if object_data > 0 {
+            (i16::read_from(data)?, i16::read_from(data)?, i16::read_from(data)?)
+        } else {
+            (0, 0, 0)
+        };
+        Ok(Self {
+            data: object_data,
+            velocity,
+        })
+    }
+}
+
+/// A block destroyed by an explosion,
+/// relative to the explosion's center.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExplosionRecord {
+    pub dx: i8,
+    pub dy: i8,
+    pub dz: i8,
+}
+
+impl Writable for ExplosionRecord {
+    fn write_to(&self, target: &mut Vec<u8>) -> anyhow::Result<()> {
+        self.dx.write_to(target)?;
+        self.dy.write_to(target)?;
+        self.dz.write_to(target)
+    }
+}
+
+impl Readable for ExplosionRecord {
+    fn read_from(data: &mut std::io::Cursor<&[u8]>) -> anyhow::Result<Self> {
+        Ok(Self {
+            dx: i8::read_from(data)?,
+            dy: i8::read_from(data)?,
+            dz: i8::read_from(data)?,
+        })
+    }
+}
+
+/// An animation or effect played on an entity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntityStatusKind {
+    HurtAnimation,
+    DeathAnimation,
+    WolfTaming,
+    WolfTamed,
+    WolfShaking,
+    EatingAccepted,
+    SheepEating,
+    GotInteractedWith,
+}
+
+impl EntityStatusKind {
+    pub const fn id(self) -> i8 {
+        match self {
+            Self::HurtAnimation => 2,
+            Self::DeathAnimation => 3,
+            Self::WolfTaming => 6,
+            Self::WolfTamed => 7,
+            Self::WolfShaking => 8,
+            Self::EatingAccepted => 9,
+            Self::SheepEating => 10,
+            Self::GotInteractedWith => 18,
+        }
+    }
+}
+
+impl Writable for EntityStatusKind {
+    fn write_to(&self, target: &mut Vec<u8>) -> anyhow::Result<()> {
+        self.id().write_to(target)
+    }
+}
+
+impl Readable for EntityStatusKind {
+    fn read_from(data: &mut std::io::Cursor<&[u8]>) -> anyhow::Result<Self> {
+        Ok(match i8::read_from(data)? {
+            2 => Self::HurtAnimation,
+            3 => Self::DeathAnimation,
+            6 => Self::WolfTaming,
+            7 => Self::WolfTamed,
+            8 => Self::WolfShaking,
+            9 => Self::EatingAccepted,
+            10 => Self::SheepEating,
+            18 => Self::GotInteractedWith,
+            n => bail!("unknown entity status {n}"),
+        })
+    }
+}
+
+/// A block changed within a chunk.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlockChangeRecord {
+    /// From 0 to 15.
+    pub x: u8,
+    pub y: u8,
+    /// From 0 to 15.
+    pub z: u8,
+    pub block: BlockID,
+    pub meta: u8,
+}
+
+/// The block changes of a `MultiBlockChange`,
+/// prefixed by both their count and size in bytes.
+#[derive(Debug, Clone, Default)]
+pub struct BlockChangeRecords(pub Vec<BlockChangeRecord>);
+
+impl Writable for BlockChangeRecords {
+    fn write_to(&self, target: &mut Vec<u8>) -> anyhow::Result<()> {
+        let count = i16::try_from(self.0.len())?;
+        count.write_to(target)?;
+        (i32::from(count) * 4).write_to(target)?;
+        for change in &self.0 {
+            let record = u32::from(change.x & 0xF) << 28
+                | u32::from(change.z & 0xF) << 24
+                | u32::from(change.y) << 16
+                | u32::from(*change.block) << 4
+                | u32::from(change.meta & 0xF);
+            record.write_to(target)?;
+        }
+        Ok(())
+    }
+}
+
+impl Readable for BlockChangeRecords {
+    fn read_from(data: &mut std::io::Cursor<&[u8]>) -> anyhow::Result<Self> {
+        let count = i16::read_from(data)?;
+        i32::read_from(data)?;
+        let mut changes = Vec::with_capacity(count.max(0) as usize);
+        for _ in 0..count {
+            let record = u32::read_from(data)?;
+            changes.push(BlockChangeRecord {
+                x: (record >> 28) as u8,
+                z: (record >> 24 & 0xF) as u8,
+                y: (record >> 16) as u8,
+                block: BlockID::new((record >> 4 & 0xFFF) as u16).expect("masked"),
+                meta: (record & 0xF) as u8,
+            });
+        }
+        Ok(Self(changes))
     }
 }
 
+/// A change to some client-side game state.
+#[derive(Debug, Clone, Copy)]
+pub enum GameStateChange {
+    InvalidBed,
+    EndRaining,
+    BeginRaining,
+    ChangeGameMode(Gamemode),
+    EnterCredits,
+    DemoMessage(i32),
+    ArrowHitPlayer,
+    FadeValue(f32),
+    FadeTime(f32),
+}
+
+impl GameStateChange {
+    /// The reason and value sent in `ChangeGameState`.
+    pub fn reason_and_value(self) -> (u8, f32) {
+        match self {
+            Self::InvalidBed => (0, 0.0),
+            Self::EndRaining => (1, 0.0),
+            Self::BeginRaining => (2, 0.0),
+            Self::ChangeGameMode(gamemode) => (3, Gamemode::new(gamemode.ty, false).encode().into()),
+            Self::EnterCredits => (4, 0.0),
+            Self::DemoMessage(message) => (5, message as f32),
+            Self::ArrowHitPlayer => (6, 0.0),
+            Self::FadeValue(value) => (7, value),
+            Self::FadeTime(time) => (8, time),
+        }
+    }
+}
+
+impl From<GameStateChange> for ChangeGameState {
+    fn from(change: GameStateChange) -> Self {
+        let (reason, value) = change.reason_and_value();
+        Self { reason, value }
+    }
+}
+
+/// An icon shown on a map.
+#[derive(Debug, Clone, Copy)]
+pub struct MapIcon {
+    /// The direction in the upper four
+    /// bits, and the type in the lower four.
+    pub type_and_direction: u8,
+    pub x: i8,
+    pub z: i8,
+}
+
+/// A rectangle of colors on a map.
+#[derive(Debug, Clone)]
+pub struct MapRegion {
+    /// The leftmost column.
+    pub x: u8,
+    /// The topmost row.
+    pub z: u8,
+    pub columns: u8,
+    pub rows: u8,
+    /// One color per pixel, row by row.
+    pub colors: Vec<u8>,
+}
+
 packet_enum!(ServerPlayPacket {
     KeepAlive = 0x00,
     PlayerPositionAndLook = 0x08,
     JoinGame = 0x01,
+    ChatMessage = 0x02,
+    Respawn = 0x07,
     ChunkData = 0x21,
     MapChunkBulk = 0x26,
     SpawnPlayer = 0x0C,
+    SpawnObject = 0x0E,
+    SpawnExperienceOrb = 0x11,
+    CollectItem = 0x0D,
+    EntityMetadata = 0x1C,
+    OpenWindow = 0x2D,
+    CloseWindow = 0x2E,
+    WindowItems = 0x30,
+    SetSlot = 0x2F,
+    WindowProperty = 0x31,
+    EntityEquipment = 0x04,
+    EntityVelocity = 0x12,
     DestroyEntities = 0x13,
-    EntityTeleport = 0x18
+    EntityLookAndRelativeMove = 0x17,
+    EntityTeleport = 0x18,
+    EntityHeadLook = 0x19,
+    EntityStatus = 0x1A,
+    AttachEntity = 0x1B,
+    EntityEffect = 0x1D,
+    RemoveEntityEffect = 0x1E,
+    MultiBlockChange = 0x22,
+    BlockChange = 0x23,
+    BlockAction = 0x24,
+    UpdateSign = 0x33,
+    SignEditorOpen = 0x36,
+    Explosion = 0x27,
+    NamedSoundEffect = 0x29,
+    PlayerListItem = 0x38,
+    ChangeGameState = 0x2B,
+    UpdateHealth = 0x06,
+    SetExperience = 0x1F,
+    PlayerAbilities = 0x39,
+    Disconnect = 0x40,
+    PluginMessage = 0x3F,
+    MapData = 0x34,
+    ScoreboardObjective = 0x3B,
+    ScoreboardScore = 0x3C,
+    ScoreboardDisplay = 0x3D
 });
 
+/// A score on some objective. Removing a score
+/// sends only the player's name and the action.
+#[derive(Debug)]
+pub struct ScoreboardScore {
+    pub item_name: String,
+    pub action: i8,
+    pub score_name: String,
+    pub value: i32,
+}
+
+impl Writable for ScoreboardScore {
+    fn write_to(&self, target: &mut Vec<u8>) -> anyhow::Result<()> {
+        self.item_name.write_to(target)?;
+        self.action.write_to(target)?;
+        if self.action != Self::REMOVE {
+            self.score_name.write_to(target)?;
+            self.value.write_to(target)?;
+        }
+        Ok(())
+    }
+}
+
+impl Readable for ScoreboardScore {
+    fn read_from(data: &mut std::io::Cursor<&[u8]>) -> anyhow::Result<Self> {
+        let item_name = String::read_from(data)?;
+        let action = i8::read_from(data)?;
+        let (score_name, value) = if action == Self::REMOVE {
+            (String::new(), 0)
+        } else {
+            (String::read_from(data)?, i32::read_from(data)?)
+        };
+        Ok(Self { item_name, action, score_name, value })
+    }
+}
+
 #[derive(Debug)]
 pub struct MapChunkBulk {
     pub chunk_column_count: i16,
@@ -196,3 +739,44 @@ impl Readable for NetChunk {
         panic!("unsupported")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{ScoreboardObjective, ScoreboardScore};
+    use crate::io::Writable;
+
+    #[test]
+    fn scoreboard_layout_test() {
+        let mut objective = vec![];
+        ScoreboardObjective {
+            name: "a".into(),
+            display_name: "b".into(),
+            action: ScoreboardObjective::REMOVE,
+        }
+        .write_to(&mut objective)
+        .unwrap();
+        assert_eq!(objective, [1, b'a', 1, b'b', 1]);
+
+        let mut removed = vec![];
+        ScoreboardScore {
+            item_name: "p".into(),
+            action: ScoreboardScore::REMOVE,
+            score_name: "a".into(),
+            value: 3,
+        }
+        .write_to(&mut removed)
+        .unwrap();
+        assert_eq!(removed, [1, b'p', 1]);
+
+        let mut updated = vec![];
+        ScoreboardScore {
+            item_name: "p".into(),
+            action: ScoreboardScore::UPDATE,
+            score_name: "a".into(),
+            value: 3,
+        }
+        .write_to(&mut updated)
+        .unwrap();
+        assert_eq!(updated, [1, b'p', 0, 1, b'a', 0, 0, 0, 3]);
+    }
+}
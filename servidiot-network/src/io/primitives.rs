@@ -394,7 +394,7 @@ impl Writable for Metadata {
                 MetadataItem::Int(v) => v.write_to(target)?,
                 MetadataItem::Float(v) => v.write_to(target)?,
                 MetadataItem::String(v) => v.write_to(target)?,
-                MetadataItem::Slot => todo!(),
+                MetadataItem::Slot(v) => v.write_to(target)?,
                 MetadataItem::Position(v) => {
                     v.x.write_to(target)?;
                     v.y.write_to(target)?;
@@ -434,7 +434,9 @@ impl Readable for Metadata {
                 v if v == MetadataTypeKey::String as u8 => {
                     values.insert(key_value, MetadataItem::String(String::read_from(data)?))
                 }
-                v if v == MetadataTypeKey::Slot as u8 => todo!(),
+                v if v == MetadataTypeKey::Slot as u8 => {
+                    values.insert(key_value, MetadataItem::Slot(InventorySlot::read_from(data)?))
+                }
                 v if v == MetadataTypeKey::Position as u8 => values.insert(
                     key_value,
                     MetadataItem::Position(BlockPosition::new(
@@ -1,4 +1,4 @@
-#![feature(cursor_remaining, never_type)]
+#![feature(cursor_split, never_type)]
 //! Minecraft networking.
 pub mod io;
 pub mod connection;
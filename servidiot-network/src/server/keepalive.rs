@@ -0,0 +1,56 @@
+use std::time::{Duration, Instant};
+
+/// Keep-alives sent to a client which
+/// it has not yet responded to.
+#[derive(Debug, Default)]
+pub struct KeepAliveTracker {
+    pending: Vec<(i32, Instant)>,
+}
+
+impl KeepAliveTracker {
+    /// Records that a keep-alive was just sent.
+    pub fn sent(&mut self, id: i32) {
+        self.pending.push((id, Instant::now()));
+    }
+
+    /// Records the response to a keep-alive, returning
+    /// how long ago it was sent. Keep-alives sent before
+    /// it are assumed lost and forgotten.
+    ///
+    /// Returns `None` if no keep-alive with this ID is pending.
+    pub fn received(&mut self, id: i32) -> Option<Duration> {
+        let index = self.pending.iter().position(|(v, _)| *v == id)?;
+        let (_, sent_at) = self.pending[index];
+        self.pending.drain(..=index);
+        Some(sent_at.elapsed())
+    }
+
+    /// The number of keep-alives awaiting a response.
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::KeepAliveTracker;
+
+    #[test]
+    fn keepalive_tracker_test() {
+        let mut tracker = KeepAliveTracker::default();
+        assert!(tracker.received(1).is_none());
+
+        tracker.sent(1);
+        tracker.sent(2);
+        tracker.sent(3);
+        assert!(tracker.received(4).is_none());
+        assert_eq!(tracker.pending(), 3);
+
+        assert!(tracker.received(2).is_some());
+        // The first keep-alive was never answered.
+        assert_eq!(tracker.pending(), 1);
+        assert!(tracker.received(1).is_none());
+        assert!(tracker.received(3).is_some());
+        assert!(tracker.received(3).is_none());
+    }
+}
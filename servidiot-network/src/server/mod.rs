@@ -1,5 +1,5 @@
 use std::{
-    sync::{Arc, atomic::{AtomicBool, Ordering}},
+    sync::{Arc, atomic::{AtomicBool, AtomicU32, Ordering}},
     time::{Duration, Instant},
 };
 
@@ -11,7 +11,7 @@ use fnv::FnvHashMap;
 use parking_lot::Mutex;
 use rsa::{pss, RsaPrivateKey};
 use servidiot_primitives::{
-    chunk::{section::ChunkSection, Chunk, ChunkBitmap}, metadata::Metadata, nibble_vec::NibbleVec, number::{FixedPoint, RotationFraction360}, player::Gamemode, position::{ChunkPosition, Position, ChunkLocation}
+    block::BlockID, chunk::{section::ChunkSection, Chunk, ChunkBitmap}, item::InventorySlot, metadata::Metadata, nibble_vec::NibbleVec, number::{FixedPoint, RotationFraction360}, player::{Experience, Gamemode}, position::{BlockPosition, ChunkPosition, Position, ChunkLocation}
 };
 use servidiot_yggdrasil::authenticate::Profile;
 use tokio::net::ToSocketAddrs;
@@ -21,14 +21,15 @@ use crate::{
     io::{packet::{
         client::play::ClientPlayPacket,
         server::play::{
-            ChunkData, DestroyEntities, EntityTeleport, JoinGame, KeepAlive, NetChunk, NetChunkData, PlayerPositionAndLook, ServerPlayPacket, SpawnPlayer
+            AttachEntity, BlockAction, BlockChange, ChatMessage, BlockChangeRecord, BlockChangeRecords, ChangeGameState, ChunkData, CloseWindow, CollectItem, DestroyEntities, EntityEquipment, MapChunkBulk, Disconnect, EntityHeadLook, EntityLookAndRelativeMove, EntityMetadata, EntityEffect, EntityStatus, EntityStatusKind, EntityTeleport, EntityVelocity, Explosion, ExplosionRecord, GameStateChange, JoinGame, KeepAlive, MapData, MapIcon, MapRegion, MultiBlockChange, NamedSoundEffect, ObjectData, PluginMessage, NetChunk, NetChunkData, OpenWindow, PlayerAbilities, PlayerListItem, PlayerPositionAndLook, RemoveEntityEffect, Respawn, ScoreboardDisplay, ScoreboardObjective, ScoreboardScore, ServerPlayPacket, SetExperience, SetSlot, SignEditorOpen, SpawnExperienceOrb, SpawnObject, SpawnPlayer, UpdateHealth, UpdateSign, WindowItems, WindowProperty
         },
     }, VarInt, LengthPrefixedVec},
 };
 
-use self::id::NetworkID;
+use self::{id::NetworkID, keepalive::KeepAliveTracker};
 
 pub mod id;
+pub mod keepalive;
 
 /// A minecraft network server.
 pub struct Server {
@@ -44,12 +45,14 @@ impl Server {
     }
 
     /// Bind this server to an address.
-    pub async fn bind<A: ToSocketAddrs>(addr: A) -> anyhow::Result<Self> {
+    pub async fn bind<A: ToSocketAddrs>(addr: A, max_packets_per_second: u32, favicon: Option<String>) -> anyhow::Result<Self> {
         let (send, recv) = flume::unbounded();
         let mut rng = rand::thread_rng();
         let bits = 1024;
         let server_state = ServerState {
             rsa_key: RsaPrivateKey::new(&mut rng, bits).unwrap(),
+            max_packets_per_second,
+            favicon,
         };
         let server_state = Arc::new(server_state);
         let listener = Listener::bind(addr, send, server_state.clone()).await?;
@@ -78,6 +81,8 @@ impl Server {
                     client_known_entities: Mutex::new(HashSet::default()),
                     client_waiting_chunks: Mutex::new(HashSet::default()),
                     last_keepalive_time: Mutex::new(Instant::now()),
+                    keepalive_tracker: Mutex::new(KeepAliveTracker::default()),
+                    ping_ms: AtomicU32::new(0),
                     client_known_position: Mutex::new(None),
                 },
             );
@@ -109,6 +114,10 @@ pub struct Client {
     pub disconnected: AtomicBool,
     /// The last time we sent a keepalive.
     pub last_keepalive_time: Mutex<Instant>,
+    /// The keepalives this client has not yet responded to.
+    pub keepalive_tracker: Mutex<KeepAliveTracker>,
+    /// The round trip time of the last keepalive responded to.
+    ping_ms: AtomicU32,
     /// The position the client thinks we are at.
     pub client_known_position: Mutex<Option<Position>>,
     /// The chunks the client has been sent.
@@ -125,6 +134,8 @@ pub struct Client {
 
 impl Client {
     pub const KEEPALIVE_TIME: Duration = Duration::from_secs(15);
+    /// The most chunks sent in one `MapChunkBulk` packet.
+    pub const CHUNKS_PER_BULK: usize = 10;
 
 
     /// Recieved packets iterator.
@@ -169,6 +180,7 @@ impl Client {
         let mut last_keepalive_time = self.last_keepalive_time.lock();
         if last_keepalive_time.elapsed() > Self::KEEPALIVE_TIME {
             *last_keepalive_time = Instant::now();
+            self.keepalive_tracker.lock().sent(id);
             self.send_packet(ServerPlayPacket::KeepAlive(KeepAlive { id }))?;
             Ok(true)
         } else {
@@ -176,6 +188,30 @@ impl Client {
         }
     }
 
+    /// Handles this client responding to a keep-alive,
+    /// updating its ping. Unknown IDs are ignored.
+    pub fn receive_keepalive(&self, id: i32) {
+        if let Some(elapsed) = self.keepalive_tracker.lock().received(id) {
+            self.ping_ms.store(elapsed.as_millis().saturating_as(), Ordering::SeqCst);
+        }
+    }
+
+    /// This client's latency, in milliseconds, as of
+    /// the last keep-alive it responded to.
+    pub fn ping_ms(&self) -> u32 {
+        self.ping_ms.load(Ordering::SeqCst)
+    }
+
+    /// Adds or updates a player in the tab list, or
+    /// removes them if they are no longer `online`.
+    pub fn send_player_list_item(&self, player_name: &str, online: bool, ping_ms: u32) -> anyhow::Result<()> {
+        self.send_packet(ServerPlayPacket::PlayerListItem(PlayerListItem {
+            player_name: player_name.to_string(),
+            online,
+            ping: ping_ms.saturating_as(),
+        }))
+    }
+
     pub fn is_disconnected(&self) -> bool {
         self.disconnected.load(Ordering::SeqCst) || self.sender.is_disconnected() || self.receiver.is_disconnected()
     }
@@ -199,6 +235,22 @@ impl Client {
         }))
     }
 
+    /// Respawns this client's player in `dimension`.
+    pub fn respawn(
+        &self,
+        gamemode: Gamemode,
+        dimension: i32,
+        difficulty: u8,
+        level_type: String,
+    ) -> anyhow::Result<()> {
+        self.send_packet(ServerPlayPacket::Respawn(Respawn {
+            dimension,
+            difficulty,
+            gamemode,
+            level_type,
+        }))
+    }
+
     /// Convert a chunk to a network chunk.
     fn chunk_to_net(
         chunk: &Chunk,
@@ -206,8 +258,9 @@ impl Client {
         compressed: bool,
     ) -> (NetChunkData, ChunkBitmap) {
         let mut primary_bit_map = to_send;
+        let present = chunk.bitmap();
         for n in 0..ChunkSection::SECTIONS_PER_CHUNK {
-            if chunk.get_section(n as u8).is_none() && primary_bit_map.get(n).unwrap_or(false) {
+            if !present.get(n).unwrap_or(false) && primary_bit_map.get(n).unwrap_or(false) {
                 //log::debug!("Before: {0000000000000000:b}", primary_bit_map.0);
                 primary_bit_map.set(n, false);
                 //log::debug!("After: {0000000000000000:b}", primary_bit_map.0);
@@ -227,15 +280,9 @@ impl Client {
             }
             //log::info!("DOING {:?}", section.section_id);
             block_types.extend_from_slice(&section.block_types);
-            block_meta
-                .backing_mut()
-                .extend_from_slice(section.block_meta.get_backing());
-            block_light
-                .backing_mut()
-                .extend_from_slice(section.block_light.get_backing());
-            block_sky_light
-                .backing_mut()
-                .extend_from_slice(section.skylight.get_backing());
+            block_meta.extend_backing(section.block_meta.get_backing());
+            block_light.extend_backing(section.block_light.get_backing());
+            block_sky_light.extend_backing(section.skylight.get_backing());
 
             for _ in 0..(block_types.len() / 2) {
                 add_array.push(0);
@@ -277,6 +324,30 @@ impl Client {
         }))
     }
 
+    /// Send many chunks to this client, batched
+    /// into as few packets as possible.
+    pub fn send_chunks_bulk(&self, chunks: &[(&Chunk, ChunkBitmap)]) -> anyhow::Result<()> {
+        for batch in chunks.chunks(Self::CHUNKS_PER_BULK) {
+            if let [(chunk, to_send)] = batch {
+                self.send_chunk(chunk, *to_send)?;
+                continue;
+            }
+            let data = batch
+                .iter()
+                .map(|(chunk, to_send)| {
+                    let (chunk_data, primary_bit_map) = Self::chunk_to_net(chunk, *to_send, false);
+                    (chunk.position(), chunk_data, primary_bit_map, ChunkBitmap::empty())
+                })
+                .collect::<Vec<_>>();
+            self.send_packet(ServerPlayPacket::MapChunkBulk(MapChunkBulk {
+                chunk_column_count: data.len().try_into()?,
+                sky_light_sent: true,
+                data,
+            }))?;
+        }
+        Ok(())
+    }
+
     /// Unload a chunk for this client.
     pub fn unload_chunk(&self, position: ChunkPosition) -> anyhow::Result<()> {
         self.send_packet(ServerPlayPacket::ChunkData(ChunkData {
@@ -309,6 +380,110 @@ impl Client {
         }))
     }
 
+    /// Send a non-living entity, such as a dropped item, to the client.
+    /// `velocity` is in blocks per tick.
+    pub fn send_object(&self, id: NetworkID, kind: i8, position: Position, data: i32, velocity: (f64, f64, f64)) -> anyhow::Result<()> {
+        self.client_known_entities.lock().insert(id);
+        let speed = |v: f64| (v * 8000.0).saturating_as();
+        self.send_packet(ServerPlayPacket::SpawnObject(SpawnObject {
+            entity_id: VarInt(id.0),
+            kind,
+            x: fixed_point(position.x),
+            y: fixed_point(position.y),
+            z: fixed_point(position.z),
+            pitch: RotationFraction360(position.pitch),
+            yaw: RotationFraction360(position.yaw),
+            data: ObjectData {
+                data,
+                velocity: (speed(velocity.0), speed(velocity.1), speed(velocity.2)),
+            },
+        }))
+    }
+
+    /// Set how fast some entity is moving, in blocks per tick.
+    pub fn send_entity_velocity(&self, id: NetworkID, velocity: (f64, f64, f64)) -> anyhow::Result<()> {
+        let speed = |v: f64| (v * 8000.0).saturating_as();
+        self.send_packet(ServerPlayPacket::EntityVelocity(EntityVelocity {
+            entity_id: id.0,
+            velocity_x: speed(velocity.0),
+            velocity_y: speed(velocity.1),
+            velocity_z: speed(velocity.2),
+        }))
+    }
+
+    /// Spawn an experience orb worth `count` points.
+    pub fn send_experience_orb(&self, id: NetworkID, position: Position, count: i16) -> anyhow::Result<()> {
+        self.client_known_entities.lock().insert(id);
+        self.send_packet(ServerPlayPacket::SpawnExperienceOrb(SpawnExperienceOrb {
+            entity_id: VarInt(id.0),
+            x: fixed_point(position.x),
+            y: fixed_point(position.y),
+            z: fixed_point(position.z),
+            count,
+        }))
+    }
+
+    /// Show some entity picking up an item or experience orb.
+    /// The collected entity must still be destroyed afterwards.
+    pub fn send_collect_item(&self, collected: NetworkID, collector: NetworkID) -> anyhow::Result<()> {
+        self.send_packet(ServerPlayPacket::CollectItem(CollectItem {
+            collected_entity_id: collected.0,
+            collector_entity_id: collector.0,
+        }))
+    }
+
+    /// Update the metadata of some entity.
+    pub fn send_metadata(&self, id: NetworkID, metadata: Metadata) -> anyhow::Result<()> {
+        self.send_packet(ServerPlayPacket::EntityMetadata(EntityMetadata {
+            entity_id: id.0,
+            metadata,
+        }))
+    }
+
+    /// Open a window showing some inventory, titled
+    /// with the name the client gives its type.
+    pub fn open_window(&self, window_id: u8, inventory_type: u8, slot_count: u8) -> anyhow::Result<()> {
+        self.send_packet(ServerPlayPacket::OpenWindow(OpenWindow {
+            window_id,
+            inventory_type,
+            window_title: String::new(),
+            slot_count,
+            use_provided_title: false,
+        }))
+    }
+
+    /// Close some window, such as when its container is destroyed.
+    pub fn close_window(&self, window_id: u8) -> anyhow::Result<()> {
+        self.send_packet(ServerPlayPacket::CloseWindow(CloseWindow { window_id }))
+    }
+
+    /// Replace every slot of some window.
+    pub fn send_window_items(&self, window_id: u8, slots: Vec<InventorySlot>) -> anyhow::Result<()> {
+        self.send_packet(ServerPlayPacket::WindowItems(WindowItems {
+            window_id,
+            slots: LengthPrefixedVec::new(slots),
+        }))
+    }
+
+    /// Updates a property of some window, such
+    /// as the cost of repairs in an anvil.
+    pub fn send_window_property(&self, window_id: u8, property: i16, value: i16) -> anyhow::Result<()> {
+        self.send_packet(ServerPlayPacket::WindowProperty(WindowProperty {
+            window_id,
+            property,
+            value,
+        }))
+    }
+
+    /// Sets a single slot of some window.
+    pub fn send_set_slot(&self, window_id: i8, slot: i16, slot_data: InventorySlot) -> anyhow::Result<()> {
+        self.send_packet(ServerPlayPacket::SetSlot(SetSlot {
+            window_id,
+            slot,
+            slot_data,
+        }))
+    }
+
     pub fn unload_entities(&self, ids: &[NetworkID]) -> anyhow::Result<()> {
         {
             let mut known = self.client_known_entities.lock();
@@ -321,14 +496,309 @@ impl Client {
         }))
     }
 
+    /// Teleport some entity to an absolute position.
     pub fn send_position(&self, id: NetworkID, position: Position) -> anyhow::Result<()> {
         self.send_packet(ServerPlayPacket::EntityTeleport(EntityTeleport {
             eid: id.0,
-            x: position.x.saturating_as(),
-            y: position.y.saturating_as(),
-            z: position.z.saturating_as(),
+            x: fixed_point(position.x),
+            y: fixed_point(position.y),
+            z: fixed_point(position.z),
             yaw: RotationFraction360(position.yaw),
-            pitch: RotationFraction360(position.yaw),
+            pitch: RotationFraction360(position.pitch),
+        }))
+    }
+
+    /// Move some entity relative to its old position.
+    /// Falls back to a teleport if the move is too
+    /// large to be sent as a relative one.
+    pub fn send_relative_move(&self, id: NetworkID, old: Position, new: Position) -> anyhow::Result<()> {
+        let delta = |old: f64, new: f64| i8::try_from(fixed_point(new) - fixed_point(old));
+        let (Ok(dx), Ok(dy), Ok(dz)) = (delta(old.x, new.x), delta(old.y, new.y), delta(old.z, new.z)) else {
+            return self.send_position(id, new);
+        };
+        self.send_packet(ServerPlayPacket::EntityLookAndRelativeMove(EntityLookAndRelativeMove {
+            eid: id.0,
+            dx,
+            dy,
+            dz,
+            yaw: RotationFraction360(new.yaw),
+            pitch: RotationFraction360(new.pitch),
+        }))
+    }
+
+    /// Show what some entity is holding or wearing.
+    /// Slot 0 is the held item, and 1 to 4 are armor.
+    pub fn send_equipment(&self, id: NetworkID, slot: i16, item: InventorySlot) -> anyhow::Result<()> {
+        self.send_packet(ServerPlayPacket::EntityEquipment(EntityEquipment {
+            entity_id: id.0,
+            slot,
+            item,
+        }))
+    }
+
+    /// Turn only the head of some entity.
+    pub fn send_head_look(&self, id: NetworkID, yaw: f32) -> anyhow::Result<()> {
+        self.send_packet(ServerPlayPacket::EntityHeadLook(EntityHeadLook {
+            entity_id: id.0,
+            head_yaw: RotationFraction360(yaw),
+        }))
+    }
+
+    /// Play an animation or effect on some entity.
+    pub fn send_entity_status(&self, id: NetworkID, status: EntityStatusKind) -> anyhow::Result<()> {
+        self.send_packet(ServerPlayPacket::EntityStatus(EntityStatus {
+            entity_id: id.0,
+            status,
+        }))
+    }
+
+    /// Seat some entity on a vehicle, or
+    /// dismount it if `vehicle` is `None`.
+    pub fn send_attach_entity(&self, id: NetworkID, vehicle: Option<NetworkID>) -> anyhow::Result<()> {
+        self.send_packet(ServerPlayPacket::AttachEntity(AttachEntity {
+            entity_id: id.0,
+            vehicle_id: vehicle.map_or(-1, |v| v.0),
+            leash: false,
+        }))
+    }
+
+    /// Show a potion effect on some entity. `duration` is in
+    /// ticks, and is capped at what the packet can carry.
+    pub fn send_entity_effect(&self, id: NetworkID, effect_id: i8, amplifier: i8, duration: i32) -> anyhow::Result<()> {
+        self.send_packet(ServerPlayPacket::EntityEffect(EntityEffect {
+            entity_id: id.0,
+            effect_id,
+            amplifier,
+            duration: duration.saturating_as(),
+        }))
+    }
+
+    /// Stop showing a potion effect on some entity.
+    pub fn send_remove_entity_effect(&self, id: NetworkID, effect_id: i8) -> anyhow::Result<()> {
+        self.send_packet(ServerPlayPacket::RemoveEntityEffect(RemoveEntityEffect {
+            entity_id: id.0,
+            effect_id,
+        }))
+    }
+
+    /// Change a single block. Does nothing
+    /// if `position` is outside the world.
+    pub fn send_block_change(&self, position: BlockPosition, block: BlockID, meta: u8) -> anyhow::Result<()> {
+        let Ok(y) = u8::try_from(position.y) else {
+            return Ok(());
+        };
+        self.send_packet(ServerPlayPacket::BlockChange(BlockChange {
+            x: position.x,
+            y,
+            z: position.z,
+            block_id: VarInt(i32::from(*block)),
+            block_meta: meta & 0xF,
+        }))
+    }
+
+    /// Let this client write on the sign at `position`.
+    pub fn open_sign_editor(&self, position: BlockPosition) -> anyhow::Result<()> {
+        self.send_packet(ServerPlayPacket::SignEditorOpen(SignEditorOpen {
+            x: position.x,
+            y: position.y,
+            z: position.z,
+        }))
+    }
+
+    /// Set the text of the sign at `position`.
+    pub fn send_sign(&self, position: BlockPosition, lines: [&str; 4]) -> anyhow::Result<()> {
+        let Ok(y) = i16::try_from(position.y) else {
+            return Ok(());
+        };
+        let [line1, line2, line3, line4] = lines.map(str::to_string);
+        self.send_packet(ServerPlayPacket::UpdateSign(UpdateSign {
+            x: position.x,
+            y,
+            z: position.z,
+            line1,
+            line2,
+            line3,
+            line4,
+        }))
+    }
+
+    /// Play some action on a block, such as a chest
+    /// opening. Does nothing if `position` is outside the world.
+    pub fn send_block_action(&self, position: BlockPosition, action_id: u8, action_param: u8, block: BlockID) -> anyhow::Result<()> {
+        let Ok(y) = i16::try_from(position.y) else {
+            return Ok(());
+        };
+        self.send_packet(ServerPlayPacket::BlockAction(BlockAction {
+            x: position.x,
+            y,
+            z: position.z,
+            action_id,
+            action_param,
+            block_type: VarInt(i32::from(*block)),
+        }))
+    }
+
+    /// Change many blocks within one chunk.
+    pub fn send_block_changes(&self, chunk: ChunkPosition, changes: Vec<BlockChangeRecord>) -> anyhow::Result<()> {
+        self.send_packet(ServerPlayPacket::MultiBlockChange(MultiBlockChange {
+            chunk_x: chunk.x,
+            chunk_z: chunk.z,
+            records: BlockChangeRecords(changes),
+        }))
+    }
+
+    /// Show an explosion, destroying `records` client-side
+    /// and pushing this client by `velocity`.
+    pub fn send_explosion(
+        &self,
+        center: Position,
+        radius: f32,
+        records: Vec<ExplosionRecord>,
+        velocity: (f32, f32, f32),
+    ) -> anyhow::Result<()> {
+        self.send_packet(ServerPlayPacket::Explosion(Explosion {
+            x: center.x as f32,
+            y: center.y as f32,
+            z: center.z as f32,
+            radius,
+            records: LengthPrefixedVec::new(records),
+            velocity_x: velocity.0,
+            velocity_y: velocity.1,
+            velocity_z: velocity.2,
+        }))
+    }
+
+    /// Play a sound at `position`. A `pitch`
+    /// of 1.0 plays the sound unchanged.
+    pub fn play_sound(&self, sound_name: &str, position: Position, volume: f32, pitch: f32) -> anyhow::Result<()> {
+        // Positions are sent in eighths of a block,
+        // and pitch in 63rds of the original pitch.
+        self.send_packet(ServerPlayPacket::NamedSoundEffect(NamedSoundEffect {
+            sound_name: sound_name.to_string(),
+            x: (position.x * 8.0).saturating_as(),
+            y: (position.y * 8.0).saturating_as(),
+            z: (position.z * 8.0).saturating_as(),
+            volume,
+            pitch: (pitch * 63.0).saturating_as(),
+        }))
+    }
+
+    /// Change some client-side game state,
+    /// such as the weather or game mode.
+    pub fn send_game_state(&self, change: GameStateChange) -> anyhow::Result<()> {
+        self.send_packet(ServerPlayPacket::ChangeGameState(ChangeGameState::from(change)))
+    }
+
+    /// Update this client's health and food bars.
+    pub fn send_health_update(&self, health: f32, food: i32, saturation: f32) -> anyhow::Result<()> {
+        self.send_packet(ServerPlayPacket::UpdateHealth(UpdateHealth {
+            health,
+            food: food.saturating_as(),
+            food_saturation: saturation,
+        }))
+    }
+
+    /// Tell this client what its player may do,
+    /// such as fly, and how fast it moves.
+    pub fn send_abilities(&self, flags: i8, flying_speed: f32, walking_speed: f32) -> anyhow::Result<()> {
+        self.send_packet(ServerPlayPacket::PlayerAbilities(PlayerAbilities {
+            flags,
+            flying_speed,
+            walking_speed,
+        }))
+    }
+
+    /// Update this client's experience bar.
+    pub fn send_experience(&self, experience: Experience) -> anyhow::Result<()> {
+        self.send_packet(ServerPlayPacket::SetExperience(SetExperience {
+            bar: experience.progress,
+            level: experience.level.saturating_as(),
+            total_exp: experience.total_points.saturating_as(),
+        }))
+    }
+
+    /// Update part of a map item. This protocol sends map
+    /// colors a column at a time, so this is several packets.
+    /// Columns past the edge of the map are left out.
+    pub fn send_map_update(&self, map_id: i16, scale: i8, region: MapRegion, icons: &[MapIcon]) -> anyhow::Result<()> {
+        let (columns, rows) = (usize::from(region.columns), usize::from(region.rows));
+        anyhow::ensure!(
+            region.colors.len() == columns * rows,
+            "map region of {columns}x{rows} has {} colors",
+            region.colors.len()
+        );
+        self.send_packet(ServerPlayPacket::MapData(MapData::scale(map_id, scale)))?;
+        self.send_packet(ServerPlayPacket::MapData(MapData::icons(map_id, icons)))?;
+        for column in 0..region.columns {
+            let Some(x) = region.x.checked_add(column) else {
+                break;
+            };
+            let colors = (0..rows)
+                .map(|row| region.colors[row * columns + usize::from(column)])
+                .collect::<Vec<_>>();
+            self.send_packet(ServerPlayPacket::MapData(MapData::column(map_id, x, region.z, &colors)))?;
+        }
+        Ok(())
+    }
+
+    /// Send a message on some plugin channel.
+    pub fn send_plugin_message(&self, channel: &str, data: &[u8]) -> anyhow::Result<()> {
+        self.send_packet(ServerPlayPacket::PluginMessage(PluginMessage {
+            channel: channel.to_string(),
+            data: LengthPrefixedVec::new(data.to_vec()),
+        }))
+    }
+
+    /// Ask this client to download a resource pack from `url`.
+    ///
+    /// This protocol sends resource packs over the `MC|RPack`
+    /// plugin channel, which carries no hash and has no status
+    /// response, so `hash` is currently unused.
+    pub fn send_resource_pack(&self, url: &str, _hash: &str) -> anyhow::Result<()> {
+        self.send_plugin_message("MC|RPack", url.as_bytes())
+    }
+
+    /// Kick this client with a plain-text reason. The client is
+    /// marked as disconnected, and cleaned up on the next tick.
+    pub fn kick(&self, reason: &str) -> anyhow::Result<()> {
+        let result = self.send_packet(ServerPlayPacket::Disconnect(Disconnect {
+            reason: text_component(reason),
+        }));
+        self.disconnected.store(true, Ordering::SeqCst);
+        result
+    }
+
+    /// Show a line of plain text in this client's chat.
+    pub fn send_message(&self, text: &str) -> anyhow::Result<()> {
+        self.send_packet(ServerPlayPacket::ChatMessage(ChatMessage {
+            json: text_component(text),
+        }))
+    }
+
+    /// Create, remove or update a scoreboard objective.
+    pub fn send_objective(&self, name: &str, action: i8, display_name: &str) -> anyhow::Result<()> {
+        self.send_packet(ServerPlayPacket::ScoreboardObjective(ScoreboardObjective {
+            name: name.to_string(),
+            display_name: display_name.to_string(),
+            action,
+        }))
+    }
+
+    /// Update or remove a score on some objective.
+    pub fn send_score(&self, item_name: &str, action: i8, score_name: &str, value: i32) -> anyhow::Result<()> {
+        self.send_packet(ServerPlayPacket::ScoreboardScore(ScoreboardScore {
+            item_name: item_name.to_string(),
+            action,
+            score_name: score_name.to_string(),
+            value,
+        }))
+    }
+
+    /// Display an objective at some position.
+    pub fn send_scoreboard_display(&self, position: i8, score_name: &str) -> anyhow::Result<()> {
+        self.send_packet(ServerPlayPacket::ScoreboardDisplay(ScoreboardDisplay {
+            position,
+            score_name: score_name.to_string(),
         }))
     }
 
@@ -337,3 +807,37 @@ impl Client {
         Ok(())
     }
 }
+
+/// Encodes a coordinate as a fixed-point
+/// integer, in 32nds of a block.
+fn fixed_point(v: f64) -> i32 {
+    (v * 32.0).floor().saturating_as()
+}
+
+/// Creates a JSON chat component containing only `text`.
+fn text_component(text: &str) -> String {
+    serde_json::json!({ "text": text }).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fixed_point, text_component};
+
+    #[test]
+    fn fixed_point_test() {
+        assert_eq!(fixed_point(1.5), 48);
+        assert_eq!(fixed_point(-0.5), -16);
+        assert_eq!(fixed_point(-0.01), -1);
+        assert_eq!(fixed_point(f64::MAX), i32::MAX);
+    }
+
+    #[test]
+    fn text_component_test() {
+        assert_eq!(text_component("Kicked"), r#"{"text":"Kicked"}"#);
+        assert_eq!(
+            text_component("say \"hi\"\\\n"),
+            r#"{"text":"say \"hi\"\\\n"}"#
+        );
+        assert_eq!(text_component("\u{1}"), r#"{"text":"\u0001"}"#);
+    }
+}
@@ -59,17 +59,356 @@ impl BlockID {
     }
 }
 
+/// A sky or block light level. Goes
+/// from `0` to `15`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct LightLevel(u8);
+
+impl LightLevel {
+    /// Complete darkness.
+    pub const ZERO: Self = Self(0);
+    /// The brightest possible light.
+    pub const MAX: Self = Self(15);
+
+    /// Creates a new `LightLevel`.
+    /// If `level` is greater than 15,
+    /// returns `None`.
+    pub const fn new(level: u8) -> Option<Self> {
+        if level > 15 {
+            None
+        } else {
+            Some(Self(level))
+        }
+    }
+
+    /// The light level after travelling through
+    /// `amount` levels of falloff, stopping at zero.
+    #[must_use]
+    pub const fn attenuate(self, amount: u8) -> Self {
+        Self(self.0.saturating_sub(amount))
+    }
+}
+
+impl From<LightLevel> for u8 {
+    fn from(level: LightLevel) -> Self {
+        level.0
+    }
+}
 
 pub trait BlockType {
     const VALID_BLOCK_TYPES: &'static [BlockID];
 }
 
+/// Material properties of a block type.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BlockInfo {
+    /// The block's name, without the `minecraft:` namespace.
+    pub name: &'static str,
+    /// Whether entities collide with the block.
+    pub solid: bool,
+    /// Whether the block can be seen through.
+    pub transparent: bool,
+    /// The light level the block gives off, from `0` to `15`.
+    pub emits_light: u8,
+    /// Whether the block breaks when the block it rests on is removed.
+    pub requires_support: bool,
+    /// Whether pistons can push or pull the block.
+    pub movable: bool,
+}
+
+impl BlockInfo {
+    /// A full, opaque block.
+    const fn full(name: &'static str) -> Self {
+        Self {
+            name,
+            solid: true,
+            transparent: false,
+            emits_light: 0,
+            requires_support: false,
+            movable: true,
+        }
+    }
+
+    /// A block which can be collided with, but
+    /// which does not fill its whole space.
+    const fn partial(name: &'static str) -> Self {
+        Self {
+            transparent: true,
+            ..Self::full(name)
+        }
+    }
+
+    /// A block which entities pass through.
+    const fn passable(name: &'static str) -> Self {
+        Self {
+            solid: false,
+            ..Self::partial(name)
+        }
+    }
+
+    /// A liquid which cannot be seen through.
+    const fn fluid(name: &'static str) -> Self {
+        Self {
+            solid: false,
+            ..Self::full(name)
+        }
+    }
+
+    const fn light(self, emits_light: u8) -> Self {
+        Self { emits_light, ..self }
+    }
+
+    const fn supported(self) -> Self {
+        Self {
+            requires_support: true,
+            ..self
+        }
+    }
+
+    const fn immovable(self) -> Self {
+        Self {
+            movable: false,
+            ..self
+        }
+    }
+}
+
+/// The vanilla block types, indexed by block ID.
+/// IDs 165 to 169 are unused in 1.7.
+pub static BLOCK_REGISTRY: [Option<BlockInfo>; 256] = block_registry();
+
+const fn block_registry() -> [Option<BlockInfo>; 256] {
+    let mut registry = [None; 256];
+    registry[0] = Some(BlockInfo::passable("air"));
+    registry[1] = Some(BlockInfo::full("stone"));
+    registry[2] = Some(BlockInfo::full("grass"));
+    registry[3] = Some(BlockInfo::full("dirt"));
+    registry[4] = Some(BlockInfo::full("cobblestone"));
+    registry[5] = Some(BlockInfo::full("planks"));
+    registry[6] = Some(BlockInfo::passable("sapling").supported());
+    registry[7] = Some(BlockInfo::full("bedrock").immovable());
+    registry[8] = Some(BlockInfo::passable("flowing_water"));
+    registry[9] = Some(BlockInfo::passable("water"));
+    registry[10] = Some(BlockInfo::fluid("flowing_lava").light(15));
+    registry[11] = Some(BlockInfo::fluid("lava").light(15));
+    registry[12] = Some(BlockInfo::full("sand"));
+    registry[13] = Some(BlockInfo::full("gravel"));
+    registry[14] = Some(BlockInfo::full("gold_ore"));
+    registry[15] = Some(BlockInfo::full("iron_ore"));
+    registry[16] = Some(BlockInfo::full("coal_ore"));
+    registry[17] = Some(BlockInfo::full("log"));
+    registry[18] = Some(BlockInfo::partial("leaves"));
+    registry[19] = Some(BlockInfo::full("sponge"));
+    registry[20] = Some(BlockInfo::partial("glass"));
+    registry[21] = Some(BlockInfo::full("lapis_ore"));
+    registry[22] = Some(BlockInfo::full("lapis_block"));
+    registry[23] = Some(BlockInfo::full("dispenser").immovable());
+    registry[24] = Some(BlockInfo::full("sandstone"));
+    registry[25] = Some(BlockInfo::full("noteblock").immovable());
+    registry[26] = Some(BlockInfo::partial("bed").supported());
+    registry[27] = Some(BlockInfo::passable("golden_rail").supported());
+    registry[28] = Some(BlockInfo::passable("detector_rail").supported());
+    registry[29] = Some(BlockInfo::full("sticky_piston"));
+    registry[30] = Some(BlockInfo::passable("web"));
+    registry[31] = Some(BlockInfo::passable("tallgrass").supported());
+    registry[32] = Some(BlockInfo::passable("deadbush").supported());
+    registry[33] = Some(BlockInfo::full("piston"));
+    registry[34] = Some(BlockInfo::partial("piston_head").immovable());
+    registry[35] = Some(BlockInfo::full("wool"));
+    registry[36] = Some(BlockInfo::passable("piston_extension").immovable());
+    registry[37] = Some(BlockInfo::passable("yellow_flower").supported());
+    registry[38] = Some(BlockInfo::passable("red_flower").supported());
+    registry[39] = Some(BlockInfo::passable("brown_mushroom").light(1).supported());
+    registry[40] = Some(BlockInfo::passable("red_mushroom").supported());
+    registry[41] = Some(BlockInfo::full("gold_block"));
+    registry[42] = Some(BlockInfo::full("iron_block"));
+    registry[43] = Some(BlockInfo::full("double_stone_slab"));
+    registry[44] = Some(BlockInfo::partial("stone_slab"));
+    registry[45] = Some(BlockInfo::full("brick_block"));
+    registry[46] = Some(BlockInfo::full("tnt"));
+    registry[47] = Some(BlockInfo::full("bookshelf"));
+    registry[48] = Some(BlockInfo::full("mossy_cobblestone"));
+    registry[49] = Some(BlockInfo::full("obsidian").immovable());
+    registry[50] = Some(BlockInfo::passable("torch").light(14).supported());
+    registry[51] = Some(BlockInfo::passable("fire").light(15).supported());
+    registry[52] = Some(BlockInfo::partial("mob_spawner").immovable());
+    registry[53] = Some(BlockInfo::partial("oak_stairs"));
+    registry[54] = Some(BlockInfo::partial("chest").immovable());
+    registry[55] = Some(BlockInfo::passable("redstone_wire").supported());
+    registry[56] = Some(BlockInfo::full("diamond_ore"));
+    registry[57] = Some(BlockInfo::full("diamond_block"));
+    registry[58] = Some(BlockInfo::full("crafting_table"));
+    registry[59] = Some(BlockInfo::passable("wheat").supported());
+    registry[60] = Some(BlockInfo::partial("farmland"));
+    registry[61] = Some(BlockInfo::full("furnace").immovable());
+    registry[62] = Some(BlockInfo::full("lit_furnace").light(13).immovable());
+    registry[63] = Some(BlockInfo::passable("standing_sign").supported().immovable());
+    registry[64] = Some(BlockInfo::partial("wooden_door").supported());
+    registry[65] = Some(BlockInfo::partial("ladder").supported());
+    registry[66] = Some(BlockInfo::passable("rail").supported());
+    registry[67] = Some(BlockInfo::partial("stone_stairs"));
+    registry[68] = Some(BlockInfo::passable("wall_sign").supported().immovable());
+    registry[69] = Some(BlockInfo::passable("lever").supported());
+    registry[70] = Some(BlockInfo::passable("stone_pressure_plate").supported());
+    registry[71] = Some(BlockInfo::partial("iron_door").supported());
+    registry[72] = Some(BlockInfo::passable("wooden_pressure_plate").supported());
+    registry[73] = Some(BlockInfo::full("redstone_ore"));
+    registry[74] = Some(BlockInfo::full("lit_redstone_ore").light(9));
+    registry[75] = Some(BlockInfo::passable("unlit_redstone_torch").supported());
+    registry[76] = Some(BlockInfo::passable("redstone_torch").light(7).supported());
+    registry[77] = Some(BlockInfo::passable("stone_button").supported());
+    registry[78] = Some(BlockInfo::passable("snow_layer").supported());
+    registry[79] = Some(BlockInfo::partial("ice"));
+    registry[80] = Some(BlockInfo::full("snow"));
+    registry[81] = Some(BlockInfo::partial("cactus").supported());
+    registry[82] = Some(BlockInfo::full("clay"));
+    registry[83] = Some(BlockInfo::passable("reeds").supported());
+    registry[84] = Some(BlockInfo::full("jukebox").immovable());
+    registry[85] = Some(BlockInfo::partial("fence"));
+    registry[86] = Some(BlockInfo::full("pumpkin"));
+    registry[87] = Some(BlockInfo::full("netherrack"));
+    registry[88] = Some(BlockInfo::full("soul_sand"));
+    registry[89] = Some(BlockInfo::full("glowstone").light(15));
+    registry[90] = Some(BlockInfo::passable("portal").light(11).immovable());
+    registry[91] = Some(BlockInfo::full("lit_pumpkin").light(15));
+    registry[92] = Some(BlockInfo::partial("cake").supported());
+    registry[93] = Some(BlockInfo::partial("unpowered_repeater").supported());
+    registry[94] = Some(BlockInfo::partial("powered_repeater").light(9).supported());
+    registry[95] = Some(BlockInfo::partial("stained_glass"));
+    registry[96] = Some(BlockInfo::partial("trapdoor"));
+    registry[97] = Some(BlockInfo::full("monster_egg"));
+    registry[98] = Some(BlockInfo::full("stonebrick"));
+    registry[99] = Some(BlockInfo::full("brown_mushroom_block"));
+    registry[100] = Some(BlockInfo::full("red_mushroom_block"));
+    registry[101] = Some(BlockInfo::partial("iron_bars"));
+    registry[102] = Some(BlockInfo::partial("glass_pane"));
+    registry[103] = Some(BlockInfo::full("melon_block"));
+    registry[104] = Some(BlockInfo::passable("pumpkin_stem").supported());
+    registry[105] = Some(BlockInfo::passable("melon_stem").supported());
+    registry[106] = Some(BlockInfo::passable("vine").supported());
+    registry[107] = Some(BlockInfo::partial("fence_gate"));
+    registry[108] = Some(BlockInfo::partial("brick_stairs"));
+    registry[109] = Some(BlockInfo::partial("stone_brick_stairs"));
+    registry[110] = Some(BlockInfo::full("mycelium"));
+    registry[111] = Some(BlockInfo::partial("waterlily").supported());
+    registry[112] = Some(BlockInfo::full("nether_brick"));
+    registry[113] = Some(BlockInfo::partial("nether_brick_fence"));
+    registry[114] = Some(BlockInfo::partial("nether_brick_stairs"));
+    registry[115] = Some(BlockInfo::passable("nether_wart").supported());
+    registry[116] = Some(BlockInfo::partial("enchanting_table").immovable());
+    registry[117] = Some(BlockInfo::partial("brewing_stand").light(1).immovable());
+    registry[118] = Some(BlockInfo::partial("cauldron"));
+    registry[119] = Some(BlockInfo::passable("end_portal").light(15).immovable());
+    registry[120] = Some(BlockInfo::partial("end_portal_frame").light(1).immovable());
+    registry[121] = Some(BlockInfo::full("end_stone"));
+    registry[122] = Some(BlockInfo::partial("dragon_egg").light(1));
+    registry[123] = Some(BlockInfo::full("redstone_lamp"));
+    registry[124] = Some(BlockInfo::full("lit_redstone_lamp").light(15));
+    registry[125] = Some(BlockInfo::full("double_wooden_slab"));
+    registry[126] = Some(BlockInfo::partial("wooden_slab"));
+    registry[127] = Some(BlockInfo::partial("cocoa").supported());
+    registry[128] = Some(BlockInfo::partial("sandstone_stairs"));
+    registry[129] = Some(BlockInfo::full("emerald_ore"));
+    registry[130] = Some(BlockInfo::partial("ender_chest").light(7).immovable());
+    registry[131] = Some(BlockInfo::passable("tripwire_hook").supported());
+    registry[132] = Some(BlockInfo::passable("tripwire").supported());
+    registry[133] = Some(BlockInfo::full("emerald_block"));
+    registry[134] = Some(BlockInfo::partial("spruce_stairs"));
+    registry[135] = Some(BlockInfo::partial("birch_stairs"));
+    registry[136] = Some(BlockInfo::partial("jungle_stairs"));
+    registry[137] = Some(BlockInfo::full("command_block").immovable());
+    registry[138] = Some(BlockInfo::partial("beacon").light(15).immovable());
+    registry[139] = Some(BlockInfo::partial("cobblestone_wall"));
+    registry[140] = Some(BlockInfo::partial("flower_pot").supported().immovable());
+    registry[141] = Some(BlockInfo::passable("carrots").supported());
+    registry[142] = Some(BlockInfo::passable("potatoes").supported());
+    registry[143] = Some(BlockInfo::passable("wooden_button").supported());
+    registry[144] = Some(BlockInfo::partial("skull").immovable());
+    registry[145] = Some(BlockInfo::partial("anvil"));
+    registry[146] = Some(BlockInfo::partial("trapped_chest").immovable());
+    registry[147] = Some(BlockInfo::passable("light_weighted_pressure_plate").supported());
+    registry[148] = Some(BlockInfo::passable("heavy_weighted_pressure_plate").supported());
+    registry[149] = Some(BlockInfo::partial("unpowered_comparator").supported());
+    registry[150] = Some(BlockInfo::partial("powered_comparator").light(9).supported());
+    registry[151] = Some(BlockInfo::partial("daylight_detector").immovable());
+    registry[152] = Some(BlockInfo::full("redstone_block"));
+    registry[153] = Some(BlockInfo::full("quartz_ore"));
+    registry[154] = Some(BlockInfo::partial("hopper").immovable());
+    registry[155] = Some(BlockInfo::full("quartz_block"));
+    registry[156] = Some(BlockInfo::partial("quartz_stairs"));
+    registry[157] = Some(BlockInfo::passable("activator_rail").supported());
+    registry[158] = Some(BlockInfo::full("dropper").immovable());
+    registry[159] = Some(BlockInfo::full("stained_hardened_clay"));
+    registry[160] = Some(BlockInfo::partial("stained_glass_pane"));
+    registry[161] = Some(BlockInfo::partial("leaves2"));
+    registry[162] = Some(BlockInfo::full("log2"));
+    registry[163] = Some(BlockInfo::partial("acacia_stairs"));
+    registry[164] = Some(BlockInfo::partial("dark_oak_stairs"));
+    registry[170] = Some(BlockInfo::full("hay_block"));
+    registry[171] = Some(BlockInfo::partial("carpet").supported());
+    registry[172] = Some(BlockInfo::full("hardened_clay"));
+    registry[173] = Some(BlockInfo::full("coal_block"));
+    registry[174] = Some(BlockInfo::full("packed_ice"));
+    registry[175] = Some(BlockInfo::passable("double_plant").supported());
+    registry
+}
+
+/// Gets the properties of some block type,
+/// or `None` if it is not a vanilla block.
+pub fn block_info(id: BlockID) -> Option<&'static BlockInfo> {
+    BLOCK_REGISTRY.get(usize::from(id.0))?.as_ref()
+}
+
+/// Whether pistons can move some block type.
+/// Unknown blocks are never movable.
+pub fn is_movable(id: BlockID) -> bool {
+    block_info(id).is_some_and(|b| b.movable)
+}
+
+/// Whether entities collide with some block type.
+/// Unknown blocks are never solid.
+pub fn is_solid(id: BlockID) -> bool {
+    block_info(id).is_some_and(|b| b.solid)
+}
+
 #[cfg(test)]
 mod tests {
-    use super::BlockID;
+    use super::{block_info, is_movable, is_solid, BlockID, LightLevel};
 
     #[test]
     fn block_id_test() {
         assert_eq!(256, BlockID::new_with_add(0, 1).unwrap().0);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn light_level_test() {
+        assert_eq!(LightLevel::new(15), Some(LightLevel::MAX));
+        assert_eq!(LightLevel::new(16), None);
+        assert_eq!(LightLevel::MAX.attenuate(1), LightLevel::new(14).unwrap());
+        assert_eq!(LightLevel::new(3).unwrap().attenuate(5), LightLevel::ZERO);
+        assert_eq!(u8::from(LightLevel::MAX), 15);
+    }
+
+    #[test]
+    fn block_registry_test() {
+        let id = |v| BlockID::new(v).unwrap();
+        assert_eq!(block_info(id(0)).unwrap().name, "air");
+        assert_eq!(block_info(id(164)).unwrap().name, "dark_oak_stairs");
+        assert_eq!(block_info(id(175)).unwrap().name, "double_plant");
+        assert!(block_info(id(165)).is_none());
+        assert!(block_info(id(300)).is_none());
+
+        assert!(is_solid(id(1)));
+        assert!(!is_solid(id(0)));
+        assert!(!is_solid(id(9)));
+        assert!(!is_solid(id(300)));
+        assert_eq!(block_info(id(50)).unwrap().emits_light, 14);
+        assert!(block_info(id(50)).unwrap().requires_support);
+        assert!(block_info(id(20)).unwrap().transparent);
+        assert!(is_movable(id(1)));
+        assert!(!is_movable(id(49)));
+        assert!(!is_movable(id(63)));
+        assert!(!is_movable(id(300)));
+    }
+}
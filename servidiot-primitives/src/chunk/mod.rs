@@ -1,4 +1,4 @@
-use crate::{block::BlockID, position::ChunkPosition};
+use crate::{block::{is_solid, BlockID, LightLevel}, position::ChunkPosition};
 
 use self::section::ChunkSection;
 
@@ -43,12 +43,13 @@ impl Chunk {
         }
     }
 
-    /// Generates a bitmap for all present sections in this chunk.
+    /// Generates a bitmap for all present,
+    /// non-empty sections in this chunk.
     pub fn bitmap(&self) -> ChunkBitmap {
         let mut bitmap = ChunkBitmap::empty();
 
         for (idx, value) in self.sections.iter().enumerate() {
-            if value.is_some() {
+            if value.as_ref().is_some_and(|v| !v.is_empty()) {
                 bitmap.set(idx, true);
             }
         }
@@ -95,12 +96,65 @@ impl Chunk {
         &mut self.heightmap
     }
 
+    /// Recalculates the heightmap, the height above
+    /// the topmost solid block of each column.
+    pub fn recalculate_heightmap(&mut self) {
+        for x in 0..Self::LENGTH {
+            for z in 0..Self::WIDTH {
+                self.heightmap[x][z] = self.column_height(x, z);
+            }
+        }
+    }
+
+    /// Recalculates the heightmap of one column, such as after
+    /// blocks in it are removed, giving full sky light to every
+    /// block above its topmost solid block. Light below is kept.
+    pub fn recalculate_column(&mut self, x: usize, z: usize) {
+        let height = self.column_height(x, z);
+        self.heightmap[x][z] = height;
+        for y in usize::from(height)..Self::HEIGHT {
+            self.set_sky_light_at(x, y, z, LightLevel::MAX);
+        }
+    }
+
+    /// The height above the topmost solid block of some column.
+    fn column_height(&self, x: usize, z: usize) -> u8 {
+        let height = (0..Self::HEIGHT)
+            .rev()
+            .find(|&y| self.block_type_at(x, y, z).is_some_and(is_solid))
+            .map_or(0, |y| y + 1);
+        height.min(usize::from(u8::MAX)) as u8
+    }
+
+    /// Recalculates sky light by shining it straight down
+    /// each column until it reaches a solid block.
+    /// Light does not spread sideways.
+    pub fn recalculate_sky_light(&mut self) {
+        for x in 0..Self::LENGTH {
+            for z in 0..Self::WIDTH {
+                let mut level = LightLevel::MAX;
+                for y in (0..Self::HEIGHT).rev() {
+                    if self.block_type_at(x, y, z).is_some_and(is_solid) {
+                        level = LightLevel::ZERO;
+                    }
+                    self.set_sky_light_at(x, y, z, level);
+                }
+            }
+        }
+    }
+
     /// Gets the block at this index.
     pub fn block_type_at(&self, x: usize, y: usize, z: usize) -> Option<BlockID> {
         let (x, y, z, section) = Self::position_to_index(x, y, z)?;
         self.sections[section].as_ref()?.block_type_at(x, y, z)
     }
 
+    /// Gets the meta of the block at this index.
+    pub fn block_meta_at(&self, x: usize, y: usize, z: usize) -> Option<u8> {
+        let (x, y, z, section) = Self::position_to_index(x, y, z)?;
+        self.sections[section].as_ref()?.block_meta_at(x, y, z)
+    }
+
     /// Sets a block ID within this chunk.
     pub fn set_block_type_at(&mut self, x: usize, y: usize, z: usize, ty: BlockID) -> Option<()> {
         let (x, y, z, section) = Self::position_to_index(x, y, z)?;
@@ -109,21 +163,30 @@ impl Chunk {
             .set_block_type_at(x, y, z, ty)
     }
 
+    /// Sets the meta of a block within this chunk.
+    /// Returns `None` if the meta is greater than 15.
+    pub fn set_block_meta_at(&mut self, x: usize, y: usize, z: usize, meta: u8) -> Option<()> {
+        let (x, y, z, section) = Self::position_to_index(x, y, z)?;
+        self.sections[section]
+            .as_mut()?
+            .set_block_meta_at(x, y, z, meta)
+    }
+
     /// Gets the sky light value at some block.
-    pub fn sky_light_at(&self, x: usize, y: usize, z: usize) -> Option<u8> {
+    pub fn sky_light_at(&self, x: usize, y: usize, z: usize) -> Option<LightLevel> {
         let (x, y, z, section) = Self::position_to_index(x, y, z)?;
         self.sections[section].as_ref()?.sky_light_at(x, y, z)
     }
 
     /// Gets the block light value at some block.
-    pub fn block_light_at(&self, x: usize, y: usize, z: usize) -> Option<u8> {
+    pub fn block_light_at(&self, x: usize, y: usize, z: usize) -> Option<LightLevel> {
         let (x, y, z, section) = Self::position_to_index(x, y, z)?;
         self.sections[section].as_ref()?.block_light_at(x, y, z)
     }
 
     /// Sets the sky light value at some block.
     /// Returns the previous value.
-    pub fn set_sky_light_at(&mut self, x: usize, y: usize, z: usize, value: u8) -> Option<u8> {
+    pub fn set_sky_light_at(&mut self, x: usize, y: usize, z: usize, value: LightLevel) -> Option<LightLevel> {
         let (x, y, z, section) = Self::position_to_index(x, y, z)?;
         self.sections[section]
             .as_mut()?
@@ -132,7 +195,7 @@ impl Chunk {
 
     /// Sets the block light value at some block.
     /// Returns the previous value.
-    pub fn set_block_light_at(&mut self, x: usize, y: usize, z: usize, value: u8) -> Option<u8> {
+    pub fn set_block_light_at(&mut self, x: usize, y: usize, z: usize, value: LightLevel) -> Option<LightLevel> {
         let (x, y, z, section) = Self::position_to_index(x, y, z)?;
         self.sections[section]
             .as_mut()?
@@ -181,7 +244,56 @@ impl ChunkBitmap {
 
 #[cfg(test)]
 mod tests {
-    use super::ChunkBitmap;
+    use crate::{block::{BlockID, LightLevel}, position::ChunkPosition};
+
+    use super::{section::ChunkSection, Chunk, ChunkBitmap};
+
+    #[test]
+    fn bitmap_skips_empty_sections() {
+        let mut chunk = Chunk::new(ChunkPosition::new(0, 0));
+        chunk.set_section(0, ChunkSection::empty(0));
+        chunk.set_section(2, ChunkSection::empty(2));
+        chunk
+            .set_block_type_at(1, 33, 1, BlockID::new(1).unwrap())
+            .unwrap();
+
+        let bitmap = chunk.bitmap();
+        assert_eq!(bitmap.get(0), Some(false));
+        assert_eq!(bitmap.get(2), Some(true));
+        assert_eq!(chunk.get_section(2).unwrap().non_air_block_count(), 1);
+    }
+
+    #[test]
+    fn recalculate_heightmap_and_sky_light() {
+        let mut chunk = Chunk::new(ChunkPosition::new(0, 0));
+        chunk.set_section(0, ChunkSection::empty(0));
+        chunk.set_section(1, ChunkSection::empty(1));
+        chunk.set_block_type_at(0, 20, 0, BlockID::new(1).unwrap()).unwrap();
+        // Water is not solid.
+        chunk.set_block_type_at(1, 20, 1, BlockID::new(9).unwrap()).unwrap();
+
+        chunk.set_block_meta_at(1, 20, 1, 3).unwrap();
+        assert_eq!(chunk.block_meta_at(1, 20, 1), Some(3));
+        assert!(chunk.set_block_meta_at(1, 20, 1, 16).is_none());
+
+        chunk.recalculate_heightmap();
+        assert_eq!(chunk.heightmap()[0][0], 21);
+        assert_eq!(chunk.heightmap()[1][1], 0);
+
+        chunk.recalculate_sky_light();
+        assert_eq!(chunk.sky_light_at(0, 21, 0), Some(LightLevel::MAX));
+        assert_eq!(chunk.sky_light_at(0, 20, 0), Some(LightLevel::ZERO));
+        assert_eq!(chunk.sky_light_at(0, 3, 0), Some(LightLevel::ZERO));
+        assert_eq!(chunk.sky_light_at(1, 3, 1), Some(LightLevel::MAX));
+
+        chunk.set_block_type_at(0, 5, 0, BlockID::new(1).unwrap()).unwrap();
+        chunk.set_block_type_at(0, 20, 0, BlockID::new(0).unwrap()).unwrap();
+        chunk.recalculate_column(0, 0);
+        assert_eq!(chunk.heightmap()[0][0], 6);
+        assert_eq!(chunk.sky_light_at(0, 20, 0), Some(LightLevel::MAX));
+        assert_eq!(chunk.sky_light_at(0, 6, 0), Some(LightLevel::MAX));
+        assert_eq!(chunk.sky_light_at(0, 3, 0), Some(LightLevel::ZERO));
+    }
 
     #[test]
     #[allow(clippy::needless_range_loop)]
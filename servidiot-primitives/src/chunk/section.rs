@@ -1,4 +1,6 @@
-use crate::{nibble_vec::NibbleVec, block::BlockID};
+use std::collections::HashMap;
+
+use crate::{nibble_vec::NibbleVec, block::{BlockID, LightLevel}};
 
 
 
@@ -41,6 +43,48 @@ impl ChunkSection {
         }
     }
 
+    /// Sets every block in this section to `block_id`,
+    /// leaving any add data untouched.
+    pub fn fill_blocks(&mut self, block_id: u8) {
+        self.block_types.fill(block_id);
+    }
+
+    /// Sets the meta of every block in this section to
+    /// `meta`. Only the lower four bits are used.
+    pub fn fill_meta(&mut self, meta: u8) {
+        self.block_meta = NibbleVec::fill(meta & 0xF, self.block_meta.len());
+    }
+
+    /// Sets the sky and block light of every block in this
+    /// section. Only the lower four bits of each are used.
+    pub fn fill_light(&mut self, sky: u8, block: u8) {
+        self.skylight = NibbleVec::fill(sky & 0xF, self.skylight.len());
+        self.block_light = NibbleVec::fill(block & 0xF, self.block_light.len());
+    }
+
+    /// The number of blocks in this section which are not air.
+    pub fn non_air_block_count(&self) -> usize {
+        self.block_types.iter().filter(|v| **v != 0).count()
+    }
+
+    /// Whether every block in this section is air.
+    pub fn is_empty(&self) -> bool {
+        self.non_air_block_count() == 0
+    }
+
+    /// Run-length encodes the block types of this section,
+    /// as `(block_id, count)` pairs in index order.
+    pub fn run_length_encode(&self) -> Vec<(u8, u16)> {
+        let mut runs: Vec<(u8, u16)> = vec![];
+        for &block in &self.block_types {
+            match runs.last_mut() {
+                Some((id, count)) if *id == block => *count += 1,
+                _ => runs.push((block, 1)),
+            }
+        }
+        runs
+    }
+
     /// Converts a position within this section to an index.
     /// Returns `None` if the position is out of bounds.
     const fn position_to_index(x: usize, y: usize, z: usize) -> Option<usize> {
@@ -62,6 +106,19 @@ impl ChunkSection {
         }
     }
 
+    /// Gets the meta of the block at this index.
+    pub fn block_meta_at(&self, x: usize, y: usize, z: usize) -> Option<u8> {
+        let index = Self::position_to_index(x, y, z)?;
+        Some(self.block_meta.get(index))
+    }
+
+    /// Sets the meta of a block within this section.
+    /// Returns `None` if the meta is greater than 15.
+    pub fn set_block_meta_at(&mut self, x: usize, y: usize, z: usize, meta: u8) -> Option<()> {
+        let index = Self::position_to_index(x, y, z)?;
+        self.block_meta.set(index, meta)
+    }
+
     /// Sets a block ID within this section.
     pub fn set_block_type_at(&mut self, x: usize, y: usize, z: usize, ty: BlockID) -> Option<()> {
         let index = Self::position_to_index(x, y, z)?;
@@ -73,32 +130,216 @@ impl ChunkSection {
     }
 
     /// Gets the sky light value at some block.
-    pub fn sky_light_at(&self, x: usize, y: usize, z: usize) -> Option<u8> {
+    pub fn sky_light_at(&self, x: usize, y: usize, z: usize) -> Option<LightLevel> {
         let index = Self::position_to_index(x, y, z)?;
-        Some(self.skylight.get(index))
+        LightLevel::new(self.skylight.get(index))
     }
 
     /// Gets the block light value at some block.
-    pub fn block_light_at(&self, x: usize, y: usize, z: usize) -> Option<u8> {
+    pub fn block_light_at(&self, x: usize, y: usize, z: usize) -> Option<LightLevel> {
         let index = Self::position_to_index(x, y, z)?;
-        Some(self.block_light.get(index))
+        LightLevel::new(self.block_light.get(index))
     }
 
     /// Sets the sky light value at some block.
     /// Returns the previous value.
-    pub fn set_sky_light_at(&mut self, x: usize, y: usize, z: usize, value: u8) -> Option<u8> {
+    pub fn set_sky_light_at(&mut self, x: usize, y: usize, z: usize, value: LightLevel) -> Option<LightLevel> {
+        let original = self.sky_light_at(x, y, z)?;
         let index = Self::position_to_index(x, y, z)?;
-        let original = self.skylight.get(index);
-        self.skylight.set(index, value)?;
+        self.skylight.set(index, value.into())?;
         Some(original)
     }
 
     /// Sets the block light value at some block.
     /// Returns the previous value.
-    pub fn set_block_light_at(&mut self, x: usize, y: usize, z: usize, value: u8) -> Option<u8> {
+    pub fn set_block_light_at(&mut self, x: usize, y: usize, z: usize, value: LightLevel) -> Option<LightLevel> {
+        let original = self.block_light_at(x, y, z)?;
         let index = Self::position_to_index(x, y, z)?;
-        let original = self.block_light.get(index);
-        self.block_light.set(index, value)?;
+        self.block_light.set(index, value.into())?;
         Some(original)
     }
+
+    /// Compresses the blocks of this section into a palette.
+    /// Light is not kept.
+    pub fn to_palettized(&self) -> PalettedSection {
+        let mut palette = vec![];
+        let mut indices = HashMap::new();
+        let entries = (0..self.block_types.len())
+            .map(|i| {
+                let add = self.block_types_add.as_ref().map_or(0, |v| v.get(i));
+                let entry = (u16::from(add) << 12) | (u16::from(self.block_types[i]) << 4) | u16::from(self.block_meta.get(i));
+                *indices.entry(entry).or_insert_with(|| {
+                    palette.push(entry);
+                    palette.len() as u64 - 1
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let bits = PalettedSection::bits_for(palette.len());
+        let per_long = 64 / bits;
+        let mut data = vec![0u64; entries.len().div_ceil(per_long)];
+        for (i, entry) in entries.into_iter().enumerate() {
+            data[i / per_long] |= entry << ((i % per_long) * bits);
+        }
+
+        PalettedSection {
+            section_id: self.section_id,
+            palette,
+            data,
+            skylight: self.skylight.clone(),
+            block_light: self.block_light.clone(),
+        }
+    }
+}
+
+/// A chunk section's blocks, stored as indices into
+/// a palette of the distinct blocks it contains.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PalettedSection {
+    /// This section's Y level.
+    pub section_id: u8,
+    /// The distinct blocks in this section, each
+    /// stored as `id << 4 | meta`.
+    pub palette: Vec<u16>,
+    /// Indices into `palette`, packed into longs
+    /// from the least significant bit. An index
+    /// never spans two longs.
+    pub data: Vec<u64>,
+    /// This section's skylight, as in [`ChunkSection`].
+    pub skylight: NibbleVec,
+    /// This section's block light, as in [`ChunkSection`].
+    pub block_light: NibbleVec,
+}
+
+impl PalettedSection {
+    /// The fewest bits used to store an index.
+    pub const MIN_BITS_PER_BLOCK: usize = 4;
+
+    /// The bits used to store each index
+    /// into a palette of `len` entries.
+    pub fn bits_for(len: usize) -> usize {
+        let needed = len.max(1).next_power_of_two().trailing_zeros() as usize;
+        needed.max(Self::MIN_BITS_PER_BLOCK)
+    }
+
+    /// The bits used to store each index in this section.
+    pub fn bits_per_block(&self) -> usize {
+        Self::bits_for(self.palette.len())
+    }
+
+    /// Expands this section back into flat arrays.
+    pub fn to_flat(&self) -> ChunkSection {
+        let mut section = ChunkSection::empty(self.section_id);
+        section.skylight = self.skylight.clone();
+        section.block_light = self.block_light.clone();
+        let bits = self.bits_per_block();
+        let per_long = 64 / bits;
+        let mask = (1u64 << bits) - 1;
+        let entry_at = |i: usize| {
+            let index = (self.data[i / per_long] >> ((i % per_long) * bits)) & mask;
+            self.palette[index as usize]
+        };
+
+        let len = section.block_types.len();
+        for i in 0..len {
+            section.block_types[i] = (entry_at(i) >> 4) as u8;
+        }
+        section.block_meta = NibbleVec::from_fn(len, |i| (entry_at(i) & 0xF) as u8);
+        if self.palette.iter().any(|v| v >> 12 != 0) {
+            section.block_types_add = Some(NibbleVec::from_fn(len, |i| (entry_at(i) >> 12) as u8));
+        }
+        section
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::block::{BlockID, LightLevel};
+
+    use super::{ChunkSection, PalettedSection};
+
+    #[test]
+    fn run_length_encode() {
+        let mut section = ChunkSection::empty(0);
+        assert_eq!(section.run_length_encode(), vec![(0, 4096)]);
+
+        section.set_block_type_at(0, 0, 0, BlockID::new(1).unwrap());
+        section.set_block_type_at(1, 0, 0, BlockID::new(1).unwrap());
+        section.set_block_type_at(15, 15, 15, BlockID::new(7).unwrap());
+        let runs = section.run_length_encode();
+        assert_eq!(runs, vec![(1, 2), (0, 4093), (7, 1)]);
+        assert_eq!(runs.iter().map(|v| usize::from(v.1)).sum::<usize>(), section.block_types.len());
+    }
+
+    /// A section with `distinct` block types, each with its own meta.
+    fn section_with(distinct: usize) -> ChunkSection {
+        let mut section = ChunkSection::empty(3);
+        for i in 0..section.block_types.len() {
+            let ty = (i % distinct) as u8;
+            section.block_types[i] = ty;
+            section.block_meta.set(i, ty % 16);
+        }
+        section
+    }
+
+    #[test]
+    fn palette_round_trip() {
+        for (distinct, bits) in [(1, 4), (16, 4), (256, 8)] {
+            let section = section_with(distinct);
+            let paletted = section.to_palettized();
+            assert_eq!(paletted.bits_per_block(), bits);
+            assert_eq!(paletted.to_flat(), section);
+        }
+    }
+
+    #[test]
+    fn palette_round_trip_light() {
+        let mut section = section_with(3);
+        section.fill_light(15, 0);
+        section.block_light.set(42, 9);
+        section.skylight.set(7, 4);
+
+        let flat = section.to_palettized().to_flat();
+        assert_eq!(flat.block_light.get(42), 9);
+        assert_eq!(flat.skylight.get(7), 4);
+        assert_eq!(flat, section);
+    }
+
+    #[test]
+    fn palette_round_trip_add() {
+        let mut section = ChunkSection::empty(0);
+        section.fill_blocks(2);
+        let mut add = crate::nibble_vec::NibbleVec::fill(0, 4096);
+        add.set(100, 1);
+        section.block_types_add = Some(add);
+
+        let paletted = section.to_palettized();
+        assert_eq!(paletted.palette.len(), 2);
+        assert_eq!(paletted.to_flat().block_type_at(4, 0, 6), BlockID::new(258));
+        assert_eq!(paletted.to_flat(), section);
+    }
+
+    #[test]
+    fn palette_bits() {
+        assert_eq!(PalettedSection::bits_for(1), 4);
+        assert_eq!(PalettedSection::bits_for(16), 4);
+        assert_eq!(PalettedSection::bits_for(17), 5);
+        assert_eq!(PalettedSection::bits_for(256), 8);
+        assert_eq!(PalettedSection::bits_for(257), 9);
+    }
+
+    #[test]
+    fn fill_section() {
+        let mut section = ChunkSection::empty(0);
+        section.fill_blocks(1);
+        section.fill_meta(0x13);
+        section.fill_light(15, 7);
+
+        assert_eq!(section.non_air_block_count(), 4096);
+        assert_eq!(section.block_type_at(3, 15, 9), BlockID::new(1));
+        assert!(section.block_meta.iter().all(|v| v == 3));
+        assert_eq!(section.sky_light_at(0, 0, 0), Some(LightLevel::MAX));
+        assert_eq!(section.block_light_at(15, 15, 15), LightLevel::new(7));
+        assert_eq!(section.skylight.len(), 4096);
+    }
 }
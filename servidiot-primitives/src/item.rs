@@ -17,6 +17,98 @@ pub struct ItemStack {
     pub nbt_data: Option<Value>
 }
 
+impl ItemStack {
+    /// The name this item was given in an anvil, if any.
+    pub fn display_name(&self) -> Option<&str> {
+        let Some(Value::Compound(tag)) = &self.nbt_data else {
+            return None;
+        };
+        match tag.get("display") {
+            Some(Value::Compound(display)) => match display.get("Name") {
+                Some(Value::String(name)) => Some(name),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Names this item, or clears its name if `name` is `None`.
+    /// Replaces any tag which is not a compound.
+    pub fn set_display_name(&mut self, name: Option<&str>) {
+        let display = self.tag_mut().entry("display".to_string()).or_insert_with(|| Value::Compound(Default::default()));
+        if !matches!(display, Value::Compound(_)) {
+            *display = Value::Compound(Default::default());
+        }
+        let Value::Compound(display) = display else {
+            unreachable!("just set to a compound");
+        };
+        match name {
+            Some(name) => {
+                display.insert("Name".to_string(), Value::String(name.to_string()));
+            }
+            None => {
+                display.remove("Name");
+            }
+        }
+    }
+
+    /// The pages written in this book. Empty
+    /// if this item is not a book.
+    pub fn book_pages(&self) -> Vec<&str> {
+        let Some(Value::Compound(tag)) = &self.nbt_data else {
+            return vec![];
+        };
+        match tag.get("pages") {
+            Some(Value::List(pages)) => pages
+                .iter()
+                .filter_map(|v| match v {
+                    Value::String(page) => Some(page.as_str()),
+                    _ => None,
+                })
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    /// The title this book was signed with, if any.
+    pub fn book_title(&self) -> Option<&str> {
+        let Some(Value::Compound(tag)) = &self.nbt_data else {
+            return None;
+        };
+        match tag.get("title") {
+            Some(Value::String(title)) => Some(title),
+            _ => None,
+        }
+    }
+
+    /// Replaces the pages written in this book.
+    /// Replaces any tag which is not a compound.
+    pub fn set_book_pages(&mut self, pages: &[&str]) {
+        let pages = pages.iter().map(|v| Value::String((*v).to_string())).collect();
+        self.tag_mut().insert("pages".to_string(), Value::List(pages));
+    }
+
+    /// Signs this book with a title and its author's name.
+    /// Replaces any tag which is not a compound.
+    pub fn sign_book(&mut self, title: &str, author: &str) {
+        let tag = self.tag_mut();
+        tag.insert("title".to_string(), Value::String(title.to_string()));
+        tag.insert("author".to_string(), Value::String(author.to_string()));
+    }
+
+    /// This item's tag, replaced with an empty
+    /// compound if it is missing or not a compound.
+    fn tag_mut(&mut self) -> &mut nbt::Map<String, Value> {
+        if !matches!(self.nbt_data, Some(Value::Compound(_))) {
+            self.nbt_data = Some(Value::Compound(Default::default()));
+        }
+        let Some(Value::Compound(tag)) = &mut self.nbt_data else {
+            unreachable!("just set to a compound");
+        };
+        tag
+    }
+}
+
 /// Represents an inventory slot.
 #[derive(PartialEq, Clone, Debug)]
 pub enum InventorySlot {
@@ -28,4 +120,34 @@ impl InventorySlot {
     pub fn is_empty(&self) -> bool {
         matches!(self, InventorySlot::Empty)
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::ItemStack;
+
+    #[test]
+    fn display_name_test() {
+        let mut stack = ItemStack { count: 1, meta: 0, id: 276, nbt_data: None };
+        assert_eq!(stack.display_name(), None);
+
+        stack.set_display_name(Some("Excalibur"));
+        assert_eq!(stack.display_name(), Some("Excalibur"));
+
+        stack.set_display_name(None);
+        assert_eq!(stack.display_name(), None);
+    }
+
+    #[test]
+    fn book_test() {
+        let mut stack = ItemStack { count: 1, meta: 0, id: 386, nbt_data: None };
+        assert!(stack.book_pages().is_empty());
+        assert_eq!(stack.book_title(), None);
+
+        stack.set_book_pages(&["Once upon a time", "The end"]);
+        assert_eq!(stack.book_pages(), ["Once upon a time", "The end"]);
+
+        stack.sign_book("A story", "Notch");
+        assert_eq!(stack.book_title(), Some("A story"));
+        assert_eq!(stack.book_pages().len(), 2);
+    }
+}
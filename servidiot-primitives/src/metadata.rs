@@ -5,7 +5,7 @@
 use ahash::HashMap;
 use thiserror::Error;
 
-use crate::position::BlockPosition;
+use crate::{item::InventorySlot, position::BlockPosition};
 
 /// The metadata store.
 #[derive(Default, Debug)]
@@ -64,7 +64,7 @@ pub enum MetadataItem {
     Int(i32),
     Float(f32),
     String(String),
-    Slot, // TODO
+    Slot(InventorySlot),
     Position(BlockPosition)
 }
 
@@ -76,7 +76,7 @@ impl MetadataItem {
             MetadataItem::Int(_) => MetadataTypeKey::Int,
             MetadataItem::Float(_) => MetadataTypeKey::Float,
             MetadataItem::String(_) => MetadataTypeKey::String,
-            MetadataItem::Slot => MetadataTypeKey::Slot,
+            MetadataItem::Slot(_) => MetadataTypeKey::Slot,
             MetadataItem::Position(_) => MetadataTypeKey::Position,
         }
     }
@@ -1,6 +1,11 @@
 #![allow(clippy::cast_sign_loss, clippy::cast_possible_wrap)]
 use serde::{Deserialize, Serialize};
 
+// The backing is stored as `i8`s because that is how NBT
+// serializes byte arrays, but handed out as `u8`s. Casting
+// between the two relies on them having the same layout.
+const _: () = assert!(std::mem::size_of::<i8>() == std::mem::size_of::<u8>());
+
 /// A vec of nibbles.
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, PartialOrd)]
 #[serde(transparent)]
@@ -19,44 +24,97 @@ impl NibbleVec {
     pub fn new_from(v: Vec<u8>) -> Self {
         Self {
             flag: false,
-            backing: unsafe {
-                std::mem::transmute(v)
-            }
+            backing: v.into_iter().map(|b| b as i8).collect(),
         }
     }
 
     pub fn new() -> Self {
         Self { flag: false, backing: Vec::new() }
     }
-    pub fn get(&self, idx: usize) -> u8 {
-        let byte_index = idx / 2;
-        let (a, b) = decompress_nibble(self.backing[byte_index] as u8);
-        if idx % 2 == 0 {
-            a
-        } else {
-            b
+    /// Creates a vec of `len` nibbles, all set to `value`.
+    ///
+    /// # Panics
+    /// Panics if `value` is greater than 15.
+    pub fn fill(value: u8, len: usize) -> Self {
+        let byte = make_nibble_byte(value, value).expect("nibble out of range");
+        let mut this = Self {
+            flag: len % 2 == 1,
+            backing: vec![byte as i8; len.div_ceil(2)],
+        };
+        if this.flag {
+            // keep the unused upper nibble zeroed, as `push` does
+            *this.backing.last_mut().unwrap() = value as i8;
+        }
+        this
+    }
+
+    /// Creates a vec of `len` nibbles, the
+    /// value at each index given by `f`.
+    ///
+    /// # Panics
+    /// Panics if `f` returns a value greater than 15.
+    pub fn from_fn(len: usize, f: impl Fn(usize) -> u8) -> Self {
+        let mut this = Self {
+            flag: false,
+            backing: Vec::with_capacity(len.div_ceil(2)),
+        };
+        for i in 0..len {
+            let value = f(i);
+            assert!(value <= 15, "nibble out of range");
+            this.push(value);
         }
+        this
+    }
+
+    /// An iterator over each nibble.
+    pub fn iter(&self) -> NibbleIter<'_> {
+        NibbleIter { vec: self, idx: 0 }
     }
 
+    /// Gets the nibble at `idx`.
+    ///
+    /// # Panics
+    /// Panics in debug mode if `idx` is out of bounds.
+    pub fn get(&self, idx: usize) -> u8 {
+        debug_assert!(idx < self.len(), "index {idx} out of bounds for length {}", self.len());
+        nibble_at(self.backing[idx / 2] as u8, idx)
+    }
+
+    /// Gets the nibble at `idx` without bounds checking.
+    ///
+    /// # Safety
+    /// `idx` must be less than `self.len()`.
+    pub unsafe fn get_unchecked(&self, idx: usize) -> u8 {
+        debug_assert!(idx < self.len(), "index {idx} out of bounds for length {}", self.len());
+        nibble_at(*self.backing.get_unchecked(idx / 2) as u8, idx)
+    }
+
+    /// Replaces the nibble at `idx`.
     /// Returns `None` if the value is greater than 15.
+    ///
+    /// # Panics
+    /// Panics in debug mode if `idx` is out of bounds.
     pub fn set(&mut self, idx: usize, value: u8) -> Option<()> {
-        let byte_index = idx / 2;
-        let (mut a, mut b) = decompress_nibble(self.backing[byte_index] as u8);
-        if idx % 2 == 0 {
-            a = value;
-        } else {
-            b = value;
-        }
-        self.backing[byte_index] = make_nibble_byte(a, b)? as i8;
+        debug_assert!(idx < self.len(), "index {idx} out of bounds for length {}", self.len());
+        let byte = &mut self.backing[idx / 2];
+        *byte = replace_nibble(*byte as u8, idx, value)? as i8;
         Some(())
     }
+
+    /// Replaces the nibble at `idx` without bounds checking.
+    /// Returns `None` if the value is greater than 15.
+    ///
+    /// # Safety
+    /// `idx` must be less than `self.len()`.
+    pub unsafe fn set_unchecked(&mut self, idx: usize, value: u8) -> Option<()> {
+        debug_assert!(idx < self.len(), "index {idx} out of bounds for length {}", self.len());
+        let byte = self.backing.get_unchecked_mut(idx / 2);
+        *byte = replace_nibble(*byte as u8, idx, value)? as i8;
+        Some(())
+    }
+
     pub fn len(&self) -> usize {
-        let len = self.backing.len() / 2;
-        if self.flag {
-            len - 1
-        } else {
-            len
-        }
+        self.backing.len() * 2 - self.flag as usize
     }
 
     pub fn is_empty(&self) -> bool {
@@ -76,18 +134,117 @@ impl NibbleVec {
         }
         self.flag ^= true;
     }
-    pub fn get_backing(&self) -> &[u8] {
-        unsafe {
-            &*(self.backing.as_slice() as *const [i8] as *const [u8])
+
+    /// Bitwise XOR of each nibble with the nibble at the same
+    /// index of `other`. Returns `None` if the lengths differ.
+    pub fn xor_with(&self, other: &NibbleVec) -> Option<NibbleVec> {
+        self.combine_bytes(other, |a, b| a ^ b)
+    }
+
+    /// Bitwise AND of each nibble with the nibble at the same
+    /// index of `other`. Returns `None` if the lengths differ.
+    pub fn and_with(&self, other: &NibbleVec) -> Option<NibbleVec> {
+        self.combine_bytes(other, |a, b| a & b)
+    }
+
+    /// Bitwise OR of each nibble with the nibble at the same
+    /// index of `other`. Returns `None` if the lengths differ.
+    pub fn or_with(&self, other: &NibbleVec) -> Option<NibbleVec> {
+        self.combine_bytes(other, |a, b| a | b)
+    }
+
+    /// The greater of each nibble and the nibble at the same
+    /// index of `other`. Returns `None` if the lengths differ.
+    pub fn max_per_nibble(&self, other: &NibbleVec) -> Option<NibbleVec> {
+        self.combine_bytes(other, |a, b| {
+            let low = (a & 0x0F).max(b & 0x0F);
+            let high = (a & 0xF0).max(b & 0xF0);
+            low | high
+        })
+    }
+
+    /// Combines the backing bytes of two vecs of the same length.
+    /// `f` must keep the unused upper nibble of the last byte zeroed.
+    fn combine_bytes(&self, other: &NibbleVec, f: impl Fn(u8, u8) -> u8) -> Option<NibbleVec> {
+        if self.len() != other.len() {
+            return None;
         }
+        let backing = self
+            .get_backing()
+            .iter()
+            .zip(other.get_backing())
+            .map(|(a, b)| f(*a, *b) as i8)
+            .collect();
+        Some(Self { flag: self.flag, backing })
+    }
+
+    pub fn get_backing(&self) -> &[u8] {
+        bytemuck::cast_slice(&self.backing)
+    }
+
+    /// Appends raw bytes, two nibbles each, to the backing.
+    pub fn extend_backing(&mut self, bytes: &[u8]) {
+        self.backing.extend_from_slice(bytemuck::cast_slice(bytes));
     }
+}
+/// An iterator over the nibbles of a [`NibbleVec`].
+pub struct NibbleIter<'a> {
+    vec: &'a NibbleVec,
+    idx: usize,
+}
 
-    pub fn backing_mut(&mut self) -> &mut Vec<u8> {
-        unsafe {
-            &mut *(std::ptr::addr_of_mut!(self.backing).cast::<Vec<u8>>())
+impl Iterator for NibbleIter<'_> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        if self.idx >= self.vec.len() {
+            return None;
         }
+        // SAFETY: just checked against the length.
+        let value = unsafe { self.vec.get_unchecked(self.idx) };
+        self.idx += 1;
+        Some(value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.vec.len() - self.idx;
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for NibbleIter<'_> {}
+
+impl<'a> IntoIterator for &'a NibbleVec {
+    type Item = u8;
+    type IntoIter = NibbleIter<'a>;
+
+    fn into_iter(self) -> NibbleIter<'a> {
+        self.iter()
+    }
+}
+
+/// The nibble for `idx` within its byte.
+#[inline(always)]
+fn nibble_at(byte: u8, idx: usize) -> u8 {
+    let (a, b) = decompress_nibble(byte);
+    if idx & 1 == 0 {
+        a
+    } else {
+        b
     }
 }
+
+#[inline(always)]
+fn replace_nibble(byte: u8, idx: usize, value: u8) -> Option<u8> {
+    let (mut a, mut b) = decompress_nibble(byte);
+    if idx & 1 == 0 {
+        a = value;
+    } else {
+        b = value;
+    }
+    make_nibble_byte(a, b)
+}
+
 #[inline(always)]
 fn make_nibble_byte(mut a: u8, mut b: u8) -> Option<u8> {
     if a > 15 || b > 15 {
@@ -127,5 +284,63 @@ mod tests {
                 panic!("fail")
             }
         }
+        assert_eq!(out.len(), SLICE.len());
+    }
+
+    #[test]
+    fn backing_test() {
+        let mut array = NibbleVec::new_from(vec![0x21, 0xF0]);
+        array.extend_backing(&[0x43]);
+        assert_eq!(array.get_backing(), [0x21, 0xF0, 0x43]);
+        assert_eq!(array.iter().collect::<Vec<_>>(), [1, 2, 0, 15, 3, 4]);
+    }
+
+    #[test]
+    fn fill_and_from_fn_test() {
+        let filled = NibbleVec::fill(7, 5);
+        assert_eq!(filled.len(), 5);
+        assert!(filled.iter().all(|v| v == 7));
+
+        let mut pushed = NibbleVec::new();
+        for _ in 0..5 {
+            pushed.push(7);
+        }
+        assert_eq!(filled, pushed);
+
+        let mut array = NibbleVec::from_fn(6, |i| i as u8 * 2);
+        assert_eq!(array.iter().collect::<Vec<_>>(), [0, 2, 4, 6, 8, 10]);
+
+        array.set(3, 15).unwrap();
+        assert_eq!(array.get(3), 15);
+        assert_eq!(array.get(2), 4);
+        assert!(array.set(1, 16).is_none());
+        assert_eq!(array.iter().len(), 6);
+    }
+
+    #[test]
+    fn bitwise_ops_test() {
+        for len in [0, 7, 4096] {
+            let a = NibbleVec::from_fn(len, |i| (i * 7 % 16) as u8);
+            let b = NibbleVec::from_fn(len, |i| (i * 3 % 13) as u8);
+            let reference = |f: fn(u8, u8) -> u8| NibbleVec::from_fn(len, |i| f(a.get(i), b.get(i)));
+
+            assert_eq!(a.xor_with(&b), Some(reference(|x, y| x ^ y)));
+            assert_eq!(a.and_with(&b), Some(reference(|x, y| x & y)));
+            assert_eq!(a.or_with(&b), Some(reference(|x, y| x | y)));
+            assert_eq!(a.max_per_nibble(&b), Some(reference(u8::max)));
+        }
+
+        let short = NibbleVec::fill(1, 3);
+        let long = NibbleVec::fill(1, 4);
+        assert!(short.xor_with(&long).is_none());
+        assert!(long.max_per_nibble(&short).is_none());
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic]
+    fn get_out_of_bounds_test() {
+        let array = NibbleVec::fill(1, 3);
+        array.get(3);
     }
 }
\ No newline at end of file
@@ -27,6 +27,41 @@ pub struct PlayerAbilities {
     #[serde(rename = "instabuild")]
     pub instabreak: bool
 }
+/// A player's experience.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Experience {
+    /// The level shown on the XP bar.
+    pub level: i32,
+    /// The progress across the XP bar
+    /// to the next level, from 0 to 1.
+    pub progress: f32,
+    /// The total experience collected.
+    pub total_points: i32,
+}
+
+impl Experience {
+    /// How many points it takes to go from `level` to the next.
+    pub fn points_to_next_level(level: i32) -> i32 {
+        match level {
+            30.. => 62 + (level - 30) * 7,
+            15.. => 17 + (level - 15) * 3,
+            _ => 17,
+        }
+    }
+
+    /// Collects some experience points,
+    /// levelling up as the bar fills.
+    pub fn add_points(&mut self, points: i32) {
+        self.total_points = self.total_points.saturating_add(points);
+        let mut into_level = self.progress * Self::points_to_next_level(self.level) as f32 + points as f32;
+        while into_level >= Self::points_to_next_level(self.level) as f32 {
+            into_level -= Self::points_to_next_level(self.level) as f32;
+            self.level += 1;
+        }
+        self.progress = into_level / Self::points_to_next_level(self.level) as f32;
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum GamemodeType {
     Survival,
@@ -78,3 +113,23 @@ impl Gamemode {
         n
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Experience;
+
+    #[test]
+    fn add_points_test() {
+        let mut experience = Experience::default();
+        experience.add_points(17);
+        assert_eq!((experience.level, experience.progress, experience.total_points), (1, 0.0, 17));
+
+        experience.add_points(25);
+        assert_eq!(experience.level, 2);
+        assert!((experience.progress - 8.0 / 17.0).abs() < 1e-6);
+        assert_eq!(experience.total_points, 42);
+
+        assert_eq!(Experience::points_to_next_level(15), 17);
+        assert_eq!(Experience::points_to_next_level(30), 62);
+    }
+}
@@ -9,7 +9,7 @@ use std::{
 use thiserror::Error;
 
 /// The position of some block.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct BlockPosition {
     pub x: i32,
     pub y: i32,
@@ -53,6 +53,85 @@ impl BlockPosition {
             z: self.z.wrapping_add(z)
         }
     }
+
+    /// The four blocks beside this one on the
+    /// same level, in -Z, +X, +Z, -X order.
+    pub fn cardinal_neighbors(&self) -> [Self; 4] {
+        [
+            self.offset(0, 0, -1),
+            self.offset(1, 0, 0),
+            self.offset(0, 0, 1),
+            self.offset(-1, 0, 0),
+        ]
+    }
+
+    /// Offsets this position by `distance`
+    /// blocks towards `direction`.
+    pub fn offset_towards(&self, direction: Direction, distance: i32) -> Self {
+        let (x, y, z) = direction.offset();
+        self.offset(x * distance, y * distance, z * distance)
+    }
+
+    pub fn distance_squared(&self, other: &Self) -> i64 {
+        (i64::from(other.x) - i64::from(self.x)).pow(2)
+            + (i64::from(other.y) - i64::from(self.y)).pow(2)
+            + (i64::from(other.z) - i64::from(self.z)).pow(2)
+    }
+}
+
+/// One of the six directions a block can face,
+/// numbered as in block metadata.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Down = 0,
+    Up = 1,
+    North = 2,
+    South = 3,
+    West = 4,
+    East = 5,
+}
+
+impl Direction {
+    /// Gets the direction numbered `id`, or
+    /// `None` if it is greater than 5.
+    pub fn from_id(id: u8) -> Option<Self> {
+        Some(match id {
+            0 => Self::Down,
+            1 => Self::Up,
+            2 => Self::North,
+            3 => Self::South,
+            4 => Self::West,
+            5 => Self::East,
+            _ => return None,
+        })
+    }
+
+    pub fn id(self) -> u8 {
+        self as u8
+    }
+
+    pub fn opposite(self) -> Self {
+        match self {
+            Self::Down => Self::Up,
+            Self::Up => Self::Down,
+            Self::North => Self::South,
+            Self::South => Self::North,
+            Self::West => Self::East,
+            Self::East => Self::West,
+        }
+    }
+
+    /// The (x, y, z) offset of one block in this direction.
+    pub fn offset(self) -> (i32, i32, i32) {
+        match self {
+            Self::Down => (0, -1, 0),
+            Self::Up => (0, 1, 0),
+            Self::North => (0, 0, -1),
+            Self::South => (0, 0, 1),
+            Self::West => (-1, 0, 0),
+            Self::East => (1, 0, 0),
+        }
+    }
 }
 
 
@@ -76,6 +155,21 @@ impl Location {
             dimension
         }
     }
+
+    /// The overworld of the main world.
+    pub const fn overworld() -> Self {
+        Self::new(0, 0)
+    }
+
+    /// The nether of the main world.
+    pub const fn nether() -> Self {
+        Self::new(0, -1)
+    }
+
+    /// The end of the main world.
+    pub const fn the_end() -> Self {
+        Self::new(0, 1)
+    }
 }
 
 
@@ -113,6 +207,29 @@ impl Position {
     pub fn chunk(&self) -> ChunkPosition {
         self.block().chunk()
     }
+
+    pub fn distance_squared_to(&self, other: &Position) -> f64 {
+        (other.x - self.x).powi(2) + (other.y - self.y).powi(2) + (other.z - self.z).powi(2)
+    }
+
+    pub fn distance_to(&self, other: &Position) -> f64 {
+        self.distance_squared_to(other).sqrt()
+    }
+
+    /// Interpolates between this position and `other` by `t`.
+    /// Rotation and `on_ground` are taken from whichever
+    /// position is closer, `other` once `t >= 0.5`.
+    pub fn lerp(&self, other: &Position, t: f64) -> Position {
+        let nearest = if t >= 0.5 { other } else { self };
+        Position {
+            x: self.x + (other.x - self.x) * t,
+            y: self.y + (other.y - self.y) * t,
+            z: self.z + (other.z - self.z) * t,
+            yaw: nearest.yaw,
+            pitch: nearest.pitch,
+            on_ground: nearest.on_ground,
+        }
+    }
 }
 /// Represents the location of an entity.
 #[derive(Clone, Copy, PartialEq, PartialOrd, Debug)]
@@ -128,6 +245,32 @@ impl Deref for EntityLocation {
     }
 }
 
+impl EntityLocation {
+    /// Moves by some offset, keeping the rotation.
+    #[must_use]
+    pub fn translate(mut self, dx: f64, dy: f64, dz: f64) -> Self {
+        self.position.x += dx;
+        self.position.y += dy;
+        self.position.z += dz;
+        self
+    }
+
+    /// Turns by some angles, in degrees. Yaw wraps to
+    /// `[0, 360)`, and pitch stops at straight up or down.
+    #[must_use]
+    pub fn rotate(mut self, dyaw: f32, dpitch: f32) -> Self {
+        self.position.yaw = (self.position.yaw + dyaw).rem_euclid(360.0);
+        self.position.pitch = (self.position.pitch + dpitch).clamp(-90.0, 90.0);
+        self
+    }
+
+    /// The same position in another world or dimension.
+    #[must_use]
+    pub fn with_location(self, location: Location) -> Self {
+        Self { location, ..self }
+    }
+}
+
 pub const MIN_BLOCK_X: i32 = -30_000_000;
 pub const MAX_BLOCK_X: i32 = 30_000_000;
 pub const MIN_BLOCK_Y: i32 = 0;
@@ -243,6 +386,13 @@ impl ChunkPosition {
     }
 
 
+    /// Every block position within this chunk, in the
+    /// order chunk sections store them, from the bottom up.
+    pub fn iter_blocks(&self) -> impl Iterator<Item = BlockPosition> {
+        let min = BlockPosition::new(self.x * 16, MIN_BLOCK_Y, self.z * 16);
+        iteration::BlockVolumeIter::new(min, min.offset(15, MAX_BLOCK_Y - MIN_BLOCK_Y, 15))
+    }
+
     /// Offset this position by (x, y).
     pub fn offset(&self, x: i32, z: i32) -> Self {
         Self {
@@ -254,6 +404,23 @@ impl ChunkPosition {
     pub fn distance_squared(&self, other: &Self) -> i32 {
         (other.x - self.x).pow(2) + (other.z - self.z).pow(2)
     }
+
+    pub fn distance_squared_to(&self, other: &ChunkPosition) -> f64 {
+        (f64::from(other.x) - f64::from(self.x)).powi(2)
+            + (f64::from(other.z) - f64::from(self.z)).powi(2)
+    }
+
+    pub fn distance_to(&self, other: &ChunkPosition) -> f64 {
+        self.distance_squared_to(other).sqrt()
+    }
+
+    /// Interpolates between this position and `other`
+    /// by `t`, rounding to the nearest chunk.
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn lerp(&self, other: &ChunkPosition, t: f64) -> ChunkPosition {
+        let lerp = |a: i32, b: i32| (f64::from(a) + (f64::from(b) - f64::from(a)) * t).round() as i32;
+        ChunkPosition::new(lerp(self.x, other.x), lerp(self.z, other.z))
+    }
 }
 
 
@@ -299,7 +466,7 @@ mod tests {
         MIN_BLOCK_Z,
     };
 
-    use super::CheckedBlockPosition;
+    use super::{BlockPosition, CheckedBlockPosition, ChunkPosition, Direction, EntityLocation, Location, Position};
 
     #[test]
     pub fn checked_block_position_test_err() {
@@ -342,4 +509,79 @@ mod tests {
         assert!(CheckedBlockPosition::new(-30_000_000, 0, 0).is_ok());
         assert!(CheckedBlockPosition::new(0, 0, -30_000_000).is_ok());
     }
+
+    #[test]
+    pub fn cardinal_neighbors_test() {
+        let neighbors = BlockPosition::new(0, 64, 0).cardinal_neighbors();
+        assert_eq!(neighbors, [
+            BlockPosition::new(0, 64, -1),
+            BlockPosition::new(1, 64, 0),
+            BlockPosition::new(0, 64, 1),
+            BlockPosition::new(-1, 64, 0),
+        ]);
+    }
+
+    #[test]
+    pub fn direction_test() {
+        let origin = BlockPosition::new(0, 64, 0);
+        for id in 0..6 {
+            let direction = Direction::from_id(id).unwrap();
+            assert_eq!(direction.id(), id);
+            assert_eq!(direction.opposite().opposite(), direction);
+            assert_eq!(origin.offset_towards(direction, 3).offset_towards(direction.opposite(), 3), origin);
+        }
+        assert!(Direction::from_id(6).is_none());
+        assert_eq!(origin.offset_towards(Direction::North, 2), BlockPosition::new(0, 64, -2));
+        assert_eq!(origin.offset_towards(Direction::Down, 1), BlockPosition::new(0, 63, 0));
+    }
+
+    #[test]
+    pub fn vanilla_locations() {
+        assert_eq!(Location::overworld(), Location::new(0, 0));
+        assert_eq!(Location::nether().dimension, -1);
+        assert_eq!(Location::the_end().dimension, 1);
+    }
+
+    #[test]
+    pub fn position_distance_test() {
+        let a = Position::new(0.0, 0.0, 0.0, 0.0, 0.0, true);
+        let b = Position::new(2.0, 3.0, 6.0, 90.0, 45.0, false);
+        assert_eq!(a.distance_squared_to(&b), 49.0);
+        assert_eq!(a.distance_to(&b), 7.0);
+
+        let mid = a.lerp(&b, 0.5);
+        assert_eq!((mid.x, mid.y, mid.z), (1.0, 1.5, 3.0));
+        assert_eq!((mid.yaw, mid.pitch, mid.on_ground), (90.0, 45.0, false));
+        let near = a.lerp(&b, 0.25);
+        assert_eq!((near.yaw, near.pitch, near.on_ground), (0.0, 0.0, true));
+
+        let c = ChunkPosition::new(0, 0);
+        let d = ChunkPosition::new(3, -4);
+        assert_eq!(c.distance_squared_to(&d), 25.0);
+        assert_eq!(c.distance_to(&d), 5.0);
+        assert_eq!(c.lerp(&d, 0.5), ChunkPosition::new(2, -2));
+    }
+
+    #[test]
+    pub fn entity_location_test() {
+        let start = EntityLocation {
+            position: Position::new(1.0, 64.0, -2.0, 350.0, 10.0, true),
+            location: Location::overworld(),
+        };
+
+        let moved = start.translate(0.5, -1.0, 2.0);
+        assert_eq!((moved.x, moved.y, moved.z), (1.5, 63.0, 0.0));
+        assert_eq!((moved.yaw, moved.pitch, moved.on_ground), (350.0, 10.0, true));
+        assert_eq!(moved.location, start.location);
+
+        let turned = start.rotate(20.0, 100.0);
+        assert_eq!((turned.yaw, turned.pitch), (10.0, 90.0));
+        assert_eq!(start.rotate(-360.0, -200.0).position.yaw, 350.0);
+        assert_eq!(start.rotate(-360.0, -200.0).position.pitch, -90.0);
+        assert_eq!((turned.x, turned.y, turned.z), (start.x, start.y, start.z));
+
+        let nether = start.with_location(Location::nether());
+        assert_eq!(nether.location, Location::nether());
+        assert_eq!(nether.position, start.position);
+    }
 }
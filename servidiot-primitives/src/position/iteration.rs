@@ -60,14 +60,57 @@ impl BoundedStep for BlockPosition {
 
 }
 
+/// Iterates over every block in a rectangular volume,
+/// both corners inclusive. Blocks are yielded in the
+/// order chunk sections store them: x changes fastest,
+/// then z, then y.
+pub struct BlockVolumeIter {
+    min: BlockPosition,
+    max: BlockPosition,
+    next: Option<BlockPosition>,
+}
 
+impl BlockVolumeIter {
+    /// Creates an iterator over the volume between two
+    /// corners, which may be given in any order.
+    pub fn new(min: BlockPosition, max: BlockPosition) -> Self {
+        let (min, max) = (
+            BlockPosition::new(min.x.min(max.x), min.y.min(max.y), min.z.min(max.z)),
+            BlockPosition::new(min.x.max(max.x), min.y.max(max.y), min.z.max(max.z)),
+        );
+        Self { min, max, next: Some(min) }
+    }
+}
 
+impl Iterator for BlockVolumeIter {
+    type Item = BlockPosition;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let current = self.next?;
+        let mut next = current;
+        if next.x < self.max.x {
+            next.x += 1;
+        } else if next.z < self.max.z {
+            next.x = self.min.x;
+            next.z += 1;
+        } else if next.y < self.max.y {
+            next.x = self.min.x;
+            next.z = self.min.z;
+            next.y += 1;
+        } else {
+            self.next = None;
+            return Some(current);
+        }
+        self.next = Some(next);
+        Some(current)
+    }
+}
 
 #[cfg(test)]
 mod tests {
-    use crate::position::BlockPosition;
+    use crate::position::{BlockPosition, ChunkPosition};
 
-    use super::StepIterator;
+    use super::{BlockVolumeIter, StepIterator};
 
     #[test]
     fn iter_test() {
@@ -79,4 +122,30 @@ mod tests {
             println!("Val: {}", i);
         }   
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn block_volume_test() {
+        let blocks = BlockVolumeIter::new(BlockPosition::new(1, 5, -1), BlockPosition::new(-1, 4, 0)).collect::<Vec<_>>();
+        assert_eq!(blocks.len(), 3 * 2 * 2);
+        assert_eq!(blocks[0], BlockPosition::new(-1, 4, -1));
+        assert_eq!(blocks[1], BlockPosition::new(0, 4, -1));
+        assert_eq!(blocks[3], BlockPosition::new(-1, 4, 0));
+        assert_eq!(blocks[6], BlockPosition::new(-1, 5, -1));
+        assert_eq!(*blocks.last().unwrap(), BlockPosition::new(1, 5, 0));
+
+        let single = BlockPosition::new(7, 7, 7);
+        assert_eq!(BlockVolumeIter::new(single, single).collect::<Vec<_>>(), [single]);
+    }
+
+    #[test]
+    fn chunk_blocks_test() {
+        let chunk = ChunkPosition::new(-2, 3);
+        let mut count = 0;
+        for (i, block) in chunk.iter_blocks().enumerate() {
+            assert_eq!(block.chunk(), chunk);
+            assert_eq!(block.x.rem_euclid(16) + block.z.rem_euclid(16) * 16 + block.y * 256, i as i32);
+            count += 1;
+        }
+        assert_eq!(count, 65536);
+    }
+}
@@ -11,9 +11,12 @@ fn main() {
         game_threads: NonZeroUsize::new(2).unwrap(),
         tps: NonZeroU64::new(20).unwrap(),
         bind_addr: SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 25565),
+        ..Default::default()
     })).unwrap();
 
-    runtime.run();
+    if runtime.run() {
+        restart();
+    }
 
     // let mut locker = servidiot_utils::synchronisation::Synchroniser::<u32>::new();
 
@@ -29,3 +32,26 @@ fn main() {
 
     println!("Hello, world!");
 }
+
+/// Starts the server afresh with the same arguments, replacing this
+/// process where possible. Returns only if that failed.
+fn restart() {
+    let exe = match std::env::current_exe() {
+        Ok(exe) => exe,
+        Err(e) => return eprintln!("Failed to restart the server: {e:?}"),
+    };
+    let mut command = std::process::Command::new(exe);
+    command.args(std::env::args_os().skip(1));
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        let e = command.exec();
+        eprintln!("Failed to restart the server: {e:?}");
+    }
+    #[cfg(not(unix))]
+    match command.status() {
+        Ok(status) => std::process::exit(status.code().unwrap_or(1)),
+        Err(e) => eprintln!("Failed to restart the server: {e:?}"),
+    }
+}
@@ -1,6 +1,6 @@
 use std::{
     any::{Any, TypeId},
-    cell::RefCell,
+    cell::{Cell, RefCell},
     collections::HashMap,
 };
 
@@ -10,13 +10,37 @@ pub trait Event: 'static {
     const IMMEDIATE: bool;
 }
 
+/// An event which handlers may cancel. Handlers with a lower
+/// priority should check the flag and skip cancelled events.
+pub trait CancellableEvent: Event {
+    fn cancelled(&self) -> &Cancelled;
+}
+
+/// Whether an event has been cancelled by one of its handlers.
+#[derive(Debug, Default)]
+pub struct Cancelled(Cell<bool>);
+
+impl Cancelled {
+    pub fn cancel(&self) {
+        self.0.set(true);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.get()
+    }
+}
+
 type EventTransformerFn<State> = Box<dyn Fn(&State, &mut dyn Any) -> anyhow::Result<bool>>;
 
 type ImmediateEventHandler<State> = Box<dyn Fn(&State, &dyn Any) -> anyhow::Result<()>>;
 
+type OnceEventHandler<State> = Box<dyn FnOnce(&State, &dyn Any) -> anyhow::Result<()>>;
+
 pub struct EventManager<State> {
-    immediate_handlers: HashMap<TypeId, Vec<ImmediateEventHandler<State>>>,
+    /// Kept sorted by descending priority.
+    immediate_handlers: HashMap<TypeId, Vec<(i32, ImmediateEventHandler<State>)>>,
     transformers: HashMap<TypeId, Vec<EventTransformerFn<State>>>,
+    once_handlers: RefCell<HashMap<TypeId, Vec<OnceEventHandler<State>>>>,
     deferred: RefCell<HashMap<TypeId, Vec<Box<dyn Any>>>>,
 }
 impl<State> Default for EventManager<State> {
@@ -29,21 +53,37 @@ impl<State> EventManager<State> {
         Self {
             immediate_handlers: Default::default(),
             transformers: Default::default(),
+            once_handlers: RefCell::new(Default::default()),
             deferred: RefCell::new(Default::default()),
         }
     }
 
-    /// Registers an immediate event handler.
+    /// Registers an immediate event handler, with a priority of 0.
     pub fn register_handler<E: Event>(
         &mut self,
         f: impl Fn(&State, &E) -> anyhow::Result<()> + 'static,
     ) {
-        self.immediate_handlers
-            .entry(TypeId::of::<E>())
-            .or_default()
-            .push(Box::new(move |state, val| {
-                f(state, val.downcast_ref().expect("checked"))
-            }))
+        self.register_handler_with_priority(f, 0);
+    }
+
+    /// Registers an immediate event handler. Handlers with a higher
+    /// priority run first, such as those which may cancel the event,
+    /// while negative priorities suit post-processing such as logging.
+    /// Handlers of equal priority run in the order they were registered.
+    pub fn register_handler_with_priority<E: Event>(
+        &mut self,
+        f: impl Fn(&State, &E) -> anyhow::Result<()> + 'static,
+        priority: i32,
+    ) {
+        let handlers = self.immediate_handlers.entry(TypeId::of::<E>()).or_default();
+        let index = handlers.partition_point(|(p, _)| *p >= priority);
+        handlers.insert(
+            index,
+            (
+                priority,
+                Box::new(move |state, val| f(state, val.downcast_ref().expect("checked"))),
+            ),
+        );
     }
 
     /// Register a transformer. If the transformer callback returns `false`, cancel the event.
@@ -59,6 +99,21 @@ impl<State> EventManager<State> {
             }))
     }
 
+    /// Registers a handler which is called for the next posted event of type `E`,
+    /// and then removed. Fires when the event is posted, even if it is deferred.
+    pub fn subscribe_once<E: Event>(
+        &self,
+        f: impl FnOnce(&State, &E) -> anyhow::Result<()> + 'static,
+    ) {
+        self.once_handlers
+            .borrow_mut()
+            .entry(TypeId::of::<E>())
+            .or_default()
+            .push(Box::new(move |state, val| {
+                f(state, val.downcast_ref().expect("checked"))
+            }))
+    }
+
     /// Post an event to the event handler.
     pub fn post_event<E: Event>(&self, state: &State, mut event: E) -> anyhow::Result<()> {
         if let Some(transformers) = self.transformers.get(&TypeId::of::<E>()) {
@@ -70,9 +125,16 @@ impl<State> EventManager<State> {
             }
         }
 
+        let once_handlers = self.once_handlers.borrow_mut().remove(&TypeId::of::<E>());
+        if let Some(once_handlers) = once_handlers {
+            for handler in once_handlers {
+                handler(state, &event)?;
+            }
+        }
+
         if E::IMMEDIATE {
             if let Some(h) = self.immediate_handlers.get(&TypeId::of::<E>()) {
-                for handler in h {
+                for (_, handler) in h {
                     handler(state, &event)?;
                 }
             }
@@ -95,4 +157,174 @@ impl<State> EventManager<State> {
             .unwrap_or_default();
         v.into_iter().map(|v| *v.downcast().unwrap())
     }
+
+    /// Collects every deferred event of type `E` without calling any handlers.
+    pub fn drain_pending<E: Event>(&self) -> Vec<E> {
+        self.deferred_events().collect()
+    }
+
+    /// Calls the handlers registered for `E` on each event of a batch,
+    /// continuing past failures. Returns every error encountered.
+    pub fn dispatch_batch<E: Event>(&self, state: &State, events: Vec<E>) -> Vec<anyhow::Error> {
+        let Some(handlers) = self.immediate_handlers.get(&TypeId::of::<E>()) else {
+            return vec![];
+        };
+        let mut errors = vec![];
+        for event in events {
+            for (_, handler) in handlers {
+                if let Err(e) = handler(state, &event) {
+                    errors.push(e);
+                }
+            }
+        }
+        errors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::{Cell, RefCell};
+
+    use super::{CancellableEvent, Cancelled, Event, EventManager};
+
+    struct TestEvent(u32);
+    impl Event for TestEvent {
+        const IMMEDIATE: bool = true;
+    }
+
+    #[derive(Default)]
+    struct CancelEvent(Cancelled);
+    impl Event for CancelEvent {
+        const IMMEDIATE: bool = true;
+    }
+    impl CancellableEvent for CancelEvent {
+        fn cancelled(&self) -> &Cancelled {
+            &self.0
+        }
+    }
+
+    struct DeferredEvent;
+    impl Event for DeferredEvent {
+        const IMMEDIATE: bool = false;
+    }
+
+    #[test]
+    fn subscribe_once_fires_once() {
+        let events = EventManager::<Cell<u32>>::new();
+        let state = Cell::new(0);
+        events.subscribe_once(|state: &Cell<u32>, e: &TestEvent| {
+            state.set(state.get() + e.0);
+            Ok(())
+        });
+
+        events.post_event(&state, TestEvent(5)).unwrap();
+        events.post_event(&state, TestEvent(7)).unwrap();
+        events.post_event(&state, TestEvent(9)).unwrap();
+        assert_eq!(state.get(), 5);
+    }
+
+    #[test]
+    fn subscribe_once_deferred() {
+        let events = EventManager::<Cell<u32>>::new();
+        let state = Cell::new(0);
+        events.subscribe_once(|state: &Cell<u32>, _: &DeferredEvent| {
+            state.set(state.get() + 1);
+            Ok(())
+        });
+
+        events.post_event(&state, DeferredEvent).unwrap();
+        events.post_event(&state, DeferredEvent).unwrap();
+        assert_eq!(state.get(), 1);
+        assert_eq!(events.deferred_events::<DeferredEvent>().count(), 2);
+    }
+
+    #[test]
+    fn subscribe_once_cancelled() {
+        let mut events = EventManager::<Cell<u32>>::new();
+        let state = Cell::new(0);
+        events.register_transformer(|_, e: &mut TestEvent| Ok(e.0 != 0));
+        events.subscribe_once(|state: &Cell<u32>, e: &TestEvent| {
+            state.set(e.0);
+            Ok(())
+        });
+
+        events.post_event(&state, TestEvent(0)).unwrap();
+        assert_eq!(state.get(), 0);
+        events.post_event(&state, TestEvent(3)).unwrap();
+        events.post_event(&state, TestEvent(4)).unwrap();
+        assert_eq!(state.get(), 3);
+    }
+
+    #[test]
+    fn batch_dispatch() {
+        let mut events = EventManager::<Cell<u32>>::new();
+        let state = Cell::new(0);
+        events.register_handler(|state: &Cell<u32>, _: &DeferredEvent| {
+            state.set(state.get() + 1);
+            if state.get() == 2 {
+                anyhow::bail!("second event");
+            }
+            Ok(())
+        });
+
+        for _ in 0..3 {
+            events.post_event(&state, DeferredEvent).unwrap();
+        }
+        assert_eq!(state.get(), 0);
+
+        let batch = events.drain_pending::<DeferredEvent>();
+        assert_eq!(batch.len(), 3);
+        assert!(events.drain_pending::<DeferredEvent>().is_empty());
+
+        let errors = events.dispatch_batch(&state, batch);
+        assert_eq!(state.get(), 3);
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn handler_priority() {
+        let mut events = EventManager::<RefCell<Vec<i32>>>::new();
+        let state = RefCell::new(vec![]);
+        for priority in [0, -5, 10, 0, 3] {
+            events.register_handler_with_priority(
+                move |state: &RefCell<Vec<i32>>, _: &TestEvent| {
+                    state.borrow_mut().push(priority);
+                    Ok(())
+                },
+                priority,
+            );
+        }
+        events.register_handler(|state: &RefCell<Vec<i32>>, _: &TestEvent| {
+            state.borrow_mut().push(100);
+            Ok(())
+        });
+
+        events.post_event(&state, TestEvent(0)).unwrap();
+        assert_eq!(*state.borrow(), [10, 3, 0, 0, 100, -5]);
+    }
+
+    #[test]
+    fn cancelled_by_higher_priority() {
+        let mut events = EventManager::<Cell<u32>>::new();
+        let state = Cell::new(0);
+        events.register_handler(|state: &Cell<u32>, e: &CancelEvent| {
+            if !e.cancelled().is_cancelled() {
+                state.set(state.get() + 1);
+            }
+            Ok(())
+        });
+        events.register_handler_with_priority(
+            |state: &Cell<u32>, e: &CancelEvent| {
+                if state.get() > 0 {
+                    e.cancelled().cancel();
+                }
+                Ok(())
+            },
+            1,
+        );
+
+        events.post_event(&state, CancelEvent::default()).unwrap();
+        events.post_event(&state, CancelEvent::default()).unwrap();
+        assert_eq!(state.get(), 1);
+    }
 }
@@ -29,41 +29,87 @@ impl Resources {
             .insert::<T>(RefCell::new(Box::new(value)));
     }
 
-    /// Immutably gets a value of type `T` from this resource collection.
+    /// Immutably gets a value of type `T` from this resource collection,
+    /// or `None` if there is no value of type `T` present.
     ///
     /// # Panics
-    /// This method will panic if there is no value of type `T` present.
-    pub fn get<T: 'static>(&self) -> Ref<T> {
-        Ref::map(
+    /// This method will panic if the value is already mutably borrowed.
+    pub fn get<T: 'static>(&self) -> Option<Ref<'_, T>> {
+        Some(Ref::map(
             self.resources
-                .get::<T>()
-                .unwrap_or_else(|| {
-                    panic!(
-                        "Tried to get type {} which is not present in Resources",
-                        type_name::<T>()
-                    )
-                })
+                .get::<T>()?
                 .try_borrow().unwrap_or_else(|_| panic!("{} already borrowed", type_name::<T>())),
-            |v| unsafe { v.downcast_ref_unchecked() }, // SAFETY: We assert at insertion time that the value is of this type.
-        )
+            |v| unsafe { v.downcast_unchecked_ref() }, // SAFETY: We assert at insertion time that the value is of this type.
+        ))
     }
 
-    /// Mutably gets a value of type `T` from this resource collection.
+    /// Mutably gets a value of type `T` from this resource collection,
+    /// or `None` if there is no value of type `T` present.
     ///
     /// # Panics
-    /// This method will panic if there is no value of type `T` present.
-    pub fn get_mut<T: 'static>(&self) -> RefMut<T> {
-        RefMut::map(
+    /// This method will panic if the value is already borrowed.
+    pub fn get_mut<T: 'static>(&self) -> Option<RefMut<'_, T>> {
+        Some(RefMut::map(
             self.resources
-                .get::<T>()
-                .unwrap_or_else(|| {
-                    panic!(
-                        "Tried to get type {} which is not present in Resources",
-                        type_name::<T>()
-                    )
-                })
-                .borrow_mut(),
-            |v| unsafe { v.downcast_mut_unchecked() }, // SAFETY: We assert at insertion time that the value is of this type.
-        )
+                .get::<T>()?
+                .try_borrow_mut().unwrap_or_else(|_| panic!("{} already borrowed", type_name::<T>())),
+            |v| unsafe { v.downcast_unchecked_mut() }, // SAFETY: We assert at insertion time that the value is of this type.
+        ))
+    }
+
+    /// Immutably gets a value of type `T` from this resource collection.
+    ///
+    /// # Panics
+    /// This method will panic with `msg` if there is no value of type `T` present.
+    pub fn expect<T: 'static>(&self, msg: &str) -> Ref<'_, T> {
+        self.get()
+            .unwrap_or_else(|| panic!("{}: {} is not present in Resources", msg, type_name::<T>()))
+    }
+
+    /// Mutably gets a value of type `T` from this resource collection.
+    ///
+    /// # Panics
+    /// This method will panic with `msg` if there is no value of type `T` present.
+    pub fn expect_mut<T: 'static>(&self, msg: &str) -> RefMut<'_, T> {
+        self.get_mut()
+            .unwrap_or_else(|| panic!("{}: {} is not present in Resources", msg, type_name::<T>()))
+    }
+
+    /// Gets a value of type `T`, first adding
+    /// the result of `f` if there is none.
+    ///
+    /// As this needs `&mut self`, it can only be used while the
+    /// collection is being built, before it is shared behind an `Rc`.
+    pub fn get_or_insert_with<T: 'static>(&mut self, f: impl FnOnce() -> T) -> Ref<'_, T> {
+        if self.resources.get::<T>().is_none() {
+            self.add(f());
+        }
+        self.expect("just inserted")
+    }
+
+    /// Gets a value of type `T`, first adding
+    /// `T::default()` if there is none.
+    ///
+    /// Like `get_or_insert_with`, this only works while building.
+    pub fn get_or_default<T: 'static + Default>(&mut self) -> Ref<'_, T> {
+        self.get_or_insert_with(T::default)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Resources;
+
+    #[test]
+    fn missing_resources() {
+        let mut resources = Resources::new();
+        assert!(resources.get::<u32>().is_none());
+        assert!(resources.get_mut::<u32>().is_none());
+
+        assert_eq!(*resources.get_or_default::<u32>(), 0);
+        *resources.expect_mut::<u32>("added above") += 5;
+        assert_eq!(*resources.get_or_insert_with(|| 9u32), 5);
+        assert_eq!(*resources.get_or_insert_with(|| 9u64), 9);
+        assert_eq!(resources.get::<u32>().as_deref(), Some(&5));
     }
 }
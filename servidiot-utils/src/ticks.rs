@@ -1,17 +1,68 @@
-use std::{num::NonZeroU64, time::{Duration, Instant}};
+use std::{collections::VecDeque, num::NonZeroU64, sync::Arc, time::{Duration, Instant}};
+
+use parking_lot::Mutex;
+use tokio::time::MissedTickBehavior;
+
+/// Called with how far a tick ran over its budget.
+type LateHandler = Box<dyn FnMut(Duration) + Send>;
 
 pub struct TickLoop<F: FnMut() -> bool> {
     per_second: NonZeroU64,
-    func: F
+    func: F,
+    late_handler: LateHandler,
+    stats: Option<TickStats>
 }
 
 impl<F: FnMut() -> bool> TickLoop<F> {
     pub fn new(per_second: NonZeroU64, func: F) -> Self {
+        Self::with_late_handler(per_second, |over| {
+            tracing::error!("Tick took too long! {}ms over budget", over.as_millis());
+        }, func)
+    }
+
+    /// Creates a loop which calls `late_handler` instead of logging
+    /// whenever a tick runs over its budget.
+    pub fn with_late_handler(per_second: NonZeroU64, late_handler: impl FnMut(Duration) + Send + 'static, func: F) -> Self {
         Self {
             per_second,
-            func
+            func,
+            late_handler: Box::new(late_handler),
+            stats: None
+        }
+    }
+
+    /// Records the duration of every tick in `stats`.
+    #[must_use]
+    pub fn with_stats(mut self, stats: TickStats) -> Self {
+        self.stats = Some(stats);
+        self
+    }
+
+    /// The mean duration of recent ticks. Zero
+    /// if no ticks have been recorded.
+    pub fn mean_tick_duration(&self) -> Duration {
+        self.stats.as_ref().map_or(Duration::ZERO, TickStats::mean_tick_duration)
+    }
+
+    /// The longest duration of recent ticks. Zero
+    /// if no ticks have been recorded.
+    pub fn max_tick_duration(&self) -> Duration {
+        self.stats.as_ref().map_or(Duration::ZERO, TickStats::max_tick_duration)
+    }
+
+    /// Records a finished tick, returning `false`
+    /// if it ran over `budget`.
+    fn finish_tick(&mut self, took: Duration, budget: Duration) -> bool {
+        if let Some(stats) = &self.stats {
+            stats.record(took);
+        }
+        if took > budget {
+            (self.late_handler)(took - budget);
+            return false;
         }
+        true
     }
+
     pub fn run(mut self) {
         let duration = Duration::from_millis(1000 / self.per_second.get());
 
@@ -21,11 +72,144 @@ impl<F: FnMut() -> bool> TickLoop<F> {
                 break;
             }
             let took = start.elapsed();
-            if took < duration {
+            if self.finish_tick(took, duration) {
                 std::thread::sleep(duration - took);
-            } else {
-                tracing::error!("Tick took too long! {}ms", took.as_millis());
             }
         }
     }
-}
\ No newline at end of file
+
+    /// Runs the loop on a Tokio runtime, waiting for each tick
+    /// with `tokio::time::interval` instead of blocking the thread.
+    ///
+    /// The runtime may run other tasks between ticks, but its timer
+    /// has millisecond granularity and ticks start late whenever a
+    /// task is slow to yield, so ticks are less evenly spaced than
+    /// with `run`. The returned future is `Send` if `F` is.
+    pub async fn run_async(mut self) {
+        let duration = Duration::from_millis(1000 / self.per_second.get());
+        let mut interval = tokio::time::interval(duration);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            interval.tick().await;
+            let start = Instant::now();
+            if !(self.func)() {
+                break;
+            }
+            let took = start.elapsed();
+            self.finish_tick(took, duration);
+        }
+    }
+}
+
+/// The durations of the most recent ticks of a [`TickLoop`].
+/// Clones share the same samples, so a handle can be kept
+/// while the loop runs.
+#[derive(Clone)]
+pub struct TickStats {
+    samples: Arc<Mutex<VecDeque<Duration>>>,
+    capacity: usize
+}
+
+impl TickStats {
+    /// Keeps the durations of the last `capacity` ticks.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            samples: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+            capacity
+        }
+    }
+
+    fn record(&self, took: Duration) {
+        let mut samples = self.samples.lock();
+        if samples.len() == self.capacity {
+            samples.pop_front();
+        }
+        if self.capacity > 0 {
+            samples.push_back(took);
+        }
+    }
+
+    /// The mean duration of recent ticks. Zero
+    /// if no ticks have been recorded.
+    pub fn mean_tick_duration(&self) -> Duration {
+        let samples = self.samples.lock();
+        let Ok(count) = u32::try_from(samples.len()) else {
+            return Duration::ZERO;
+        };
+        if count == 0 {
+            return Duration::ZERO;
+        }
+        samples.iter().sum::<Duration>() / count
+    }
+
+    /// The longest duration of recent ticks. Zero
+    /// if no ticks have been recorded.
+    pub fn max_tick_duration(&self) -> Duration {
+        self.samples.lock().iter().max().copied().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{num::NonZeroU64, sync::{atomic::{AtomicU32, Ordering}, Arc}, time::Duration};
+
+    use super::{TickLoop, TickStats};
+
+    #[test]
+    fn run_async_stops() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+        let mut ticks = 0;
+        runtime.block_on(
+            TickLoop::new(NonZeroU64::new(1000).unwrap(), || {
+                ticks += 1;
+                ticks < 3
+            })
+            .run_async(),
+        );
+        assert_eq!(ticks, 3);
+    }
+
+    #[test]
+    fn late_ticks_and_stats() {
+        let stats = TickStats::new(2);
+        let late = Arc::new(AtomicU32::new(0));
+        let late_count = late.clone();
+        let mut ticks = 0;
+        let tick_loop = TickLoop::with_late_handler(
+            NonZeroU64::new(100).unwrap(),
+            move |_| {
+                late_count.fetch_add(1, Ordering::Relaxed);
+            },
+            || {
+                ticks += 1;
+                if ticks == 1 {
+                    std::thread::sleep(Duration::from_millis(15));
+                }
+                ticks < 3
+            },
+        )
+        .with_stats(stats.clone());
+        assert_eq!(tick_loop.mean_tick_duration(), Duration::ZERO);
+        tick_loop.run();
+
+        assert_eq!(late.load(Ordering::Relaxed), 1);
+        // Only the first two ticks finish, and both are kept.
+        assert!(stats.max_tick_duration() >= Duration::from_millis(15));
+        assert!(stats.mean_tick_duration() >= Duration::from_micros(7500));
+    }
+
+    #[test]
+    fn stats_keep_recent_ticks() {
+        let stats = TickStats::new(2);
+        assert_eq!(stats.max_tick_duration(), Duration::ZERO);
+        for millis in [9, 1, 3] {
+            stats.record(Duration::from_millis(millis));
+        }
+        assert_eq!(stats.max_tick_duration(), Duration::from_millis(3));
+        assert_eq!(stats.mean_tick_duration(), Duration::from_millis(2));
+    }
+}
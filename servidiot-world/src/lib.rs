@@ -6,7 +6,7 @@ use std::{
 use fxhash::{FxHashMap, FxHashSet};
 use servidiot_primitives::{
     chunk::Chunk,
-    position::{ChunkLocation, ChunkPosition, DimensionID, Location},
+    position::{BlockPosition, ChunkLocation, ChunkPosition, DimensionID, Location},
 };
 use slotmap::{new_key_type, SlotMap};
 use view::View;
@@ -14,6 +14,7 @@ use view::View;
 pub mod view;
 mod world;
 
+#[derive(Clone)]
 struct TrackedEntity<EntityData> {
     value: EntityData,
     inhabits: ChunkLocation,
@@ -24,6 +25,7 @@ struct TrackedEntity<EntityData> {
 
 new_key_type! { pub struct TrackedEntityKey; }
 
+#[derive(Clone)]
 struct ChunkData {
     entities_within: FxHashSet<TrackedEntityKey>,
     ticket_count: u32,
@@ -37,6 +39,16 @@ pub struct TrackedWorld<EntityData> {
     event_queue: Vec<TrackedWorldEvent<EntityData>>,
 }
 
+/// A copy of the state of a `TrackedWorld`,
+/// which can later be restored.
+#[derive(Clone)]
+pub struct TrackedWorldSnapshot<EntityData: Clone> {
+    chunk_data: FxHashMap<u32, FxHashMap<DimensionID, DimensionData>>,
+    entity_store: SlotMap<TrackedEntityKey, TrackedEntity<EntityData>>,
+    entities_awaiting_chunks: FxHashMap<ChunkLocation, FxHashSet<TrackedEntityKey>>,
+    event_queue: Vec<TrackedWorldEvent<EntityData>>,
+}
+
 impl<EntityData> Default for TrackedWorld<EntityData> {
     fn default() -> Self {
         Self {
@@ -55,6 +67,32 @@ impl<EntityData> TrackedWorld<EntityData> {
         Self::default()
     }
 
+    /// Copies the current state of the world,
+    /// including events not yet polled.
+    pub fn snapshot(&self) -> TrackedWorldSnapshot<EntityData>
+    where
+        EntityData: Clone,
+    {
+        TrackedWorldSnapshot {
+            chunk_data: self.chunk_data.clone(),
+            entity_store: self.entity_store.clone(),
+            entities_awaiting_chunks: self.entities_awaiting_chunks.clone(),
+            event_queue: self.event_queue.clone(),
+        }
+    }
+
+    /// Replaces the state of the world with a snapshot.
+    /// Keys from before the snapshot remain valid.
+    pub fn restore(&mut self, snapshot: TrackedWorldSnapshot<EntityData>)
+    where
+        EntityData: Clone,
+    {
+        self.chunk_data = snapshot.chunk_data;
+        self.entity_store = snapshot.entity_store;
+        self.entities_awaiting_chunks = snapshot.entities_awaiting_chunks;
+        self.event_queue = snapshot.event_queue;
+    }
+
     fn dimension(&mut self, loc: Location) -> &mut DimensionData {
         self.chunk_data
             .entry(loc.world)
@@ -71,14 +109,73 @@ impl<EntityData> TrackedWorld<EntityData> {
         self.dimension(loc.location).get_mut(&loc.position)
     }
 
-    fn chunk(&mut self, loc: ChunkLocation) -> &mut ChunkData {
-        self.try_chunk(loc).unwrap()
+    fn chunk_ref(&self, loc: ChunkLocation) -> Option<&ChunkData> {
+        self.chunk_data
+            .get(&loc.location.world)?
+            .get(&loc.location.dimension)?
+            .get(&loc.position)
     }
 
     pub fn entity(&self, i: TrackedEntityKey) -> &EntityData {
         &self.entity_store[i].value
     }
 
+    /// Every tracked entity, in no particular order.
+    /// Does not emit any events.
+    pub fn iter_entities(&self) -> impl Iterator<Item = (TrackedEntityKey, &EntityData)> {
+        self.entity_store.iter().map(|(k, v)| (k, &v.value))
+    }
+
+    /// Every entity inhabiting a loaded chunk of some dimension.
+    /// Does not emit any events.
+    pub fn iter_entities_in_dimension(
+        &self,
+        loc: Location,
+    ) -> impl Iterator<Item = TrackedEntityKey> + '_ {
+        self.chunk_data
+            .get(&loc.world)
+            .and_then(|v| v.get(&loc.dimension))
+            .into_iter()
+            .flat_map(|v| v.values())
+            .flat_map(|v| v.entities_within.iter().copied())
+    }
+
+    /// Every other entity within `radius_chunks` chunks of `center`.
+    /// Does not emit any events.
+    pub fn entities_within_radius(
+        &self,
+        center: TrackedEntityKey,
+        radius_chunks: u32,
+    ) -> Vec<TrackedEntityKey> {
+        let mut found = FxHashSet::default();
+        for chunk in View::new(self.entity_store[center].inhabits, radius_chunks).chunks() {
+            if let Some(data) = self.chunk_ref(chunk) {
+                found.extend(data.entities_within.iter().copied());
+            }
+        }
+        found.remove(&center);
+        found.into_iter().collect()
+    }
+
+    /// The entity nearest to `center` within `max_radius` chunks.
+    /// Entities are only tracked per chunk, so distance is
+    /// measured between the chunks they inhabit.
+    /// Does not emit any events.
+    pub fn find_closest(
+        &self,
+        center: TrackedEntityKey,
+        max_radius: u32,
+    ) -> Option<TrackedEntityKey> {
+        let our_pos = self.entity_store[center].inhabits.position;
+        self.entities_within_radius(center, max_radius)
+            .into_iter()
+            .min_by(|a, b| {
+                let a = our_pos.distance_squared_to(&self.entity_store[*a].inhabits.position);
+                let b = our_pos.distance_squared_to(&self.entity_store[*b].inhabits.position);
+                a.total_cmp(&b)
+            })
+    }
+
     /// Adds an entity to the tracker. Returns `None` if this entity does not load chunks
     /// but was added to an unloaded chunk.
     pub fn add_entity(
@@ -112,6 +209,18 @@ impl<EntityData> TrackedWorld<EntityData> {
         Some(inserted)
     }
 
+    /// Adds an entity to the tracker in the chunk
+    /// containing `pos`. See [`TrackedWorld::add_entity`].
+    pub fn add_entity_at_block_position(
+        &mut self,
+        d: EntityData,
+        pos: BlockPosition,
+        location: Location,
+        load_radius: Option<u32>,
+    ) -> Option<TrackedEntityKey> {
+        self.add_entity(d, ChunkLocation::new(pos.chunk(), location), load_radius)
+    }
+
     fn event(&mut self, e: TrackedWorldEvent<EntityData>) {
         self.event_queue.push(e);
     }
@@ -191,14 +300,16 @@ impl<EntityData> TrackedWorld<EntityData> {
                             && self.entity_store[e].known_entities.insert(other_entity)
                         {
                             new_entities.insert(other_entity);
-                            self.event(TrackedWorldEvent::EntityViewsEntities(
-                                e,
-                                vec![other_entity],
-                            ))
                         }
                     }
                 }
             }
+            if !new_entities.is_empty() {
+                self.event(TrackedWorldEvent::EntityViewsEntities(
+                    e,
+                    new_entities.iter().copied().collect(),
+                ));
+            }
 
             let our_pos = self.entity_store[e].inhabits;
             for value in self.entity_store[e].known_entities.clone() {
@@ -214,8 +325,8 @@ impl<EntityData> TrackedWorld<EntityData> {
 
                     let dist = our_pos
                         .position
-                        .distance_squared(&self.entity_store[value].inhabits.position);
-                    if dist > (ENTITY_LOAD_DISTANCE as i32 * ENTITY_LOAD_DISTANCE as i32) {
+                        .distance_to(&self.entity_store[value].inhabits.position);
+                    if dist > f64::from(ENTITY_LOAD_DISTANCE) {
                         self.entity_store[e].known_entities.remove(&value);
                         self.event(TrackedWorldEvent::EntityNoLongerViewsEntities(
                             e,
@@ -303,7 +414,9 @@ impl<EntityData> TrackedWorld<EntityData> {
 
         let old_pos = self.entity_store[e].inhabits;
 
-        self.chunk(old_pos).entities_within.remove(&e);
+        if let Some(chunk) = self.try_chunk(old_pos) {
+            chunk.entities_within.remove(&e);
+        }
 
         self.entity_store[e].inhabits = new_pos;
 
@@ -320,11 +433,10 @@ impl<EntityData> TrackedWorld<EntityData> {
             // remove our ticket from chunks no longer in our view
             let mut no_longer = vec![];
             for chunk in old_chunks {
-                let should_remove = {
-                    let ch = self.chunk(chunk);
+                let should_remove = self.try_chunk(chunk).is_some_and(|ch| {
                     ch.ticket_count = ch.ticket_count.saturating_sub(1);
                     ch.ticket_count == 0
-                };
+                });
                 no_longer.push(chunk);
 
                 if should_remove {
@@ -361,6 +473,11 @@ impl<EntityData> TrackedWorld<EntityData> {
         );
     }
 
+    /// Moves an entity to the chunk containing `pos`.
+    pub fn move_entity_to_position(&mut self, e: TrackedEntityKey, pos: BlockPosition, location: Location) {
+        self.move_entity(e, ChunkLocation::new(pos.chunk(), location));
+    }
+
     pub fn add_chunk(&mut self, chunk: ChunkLocation) {
         let mut ticket_count = 0;
         let mut entities_within = FxHashSet::default();
@@ -390,9 +507,33 @@ impl<EntityData> TrackedWorld<EntityData> {
             },
         );
     }
+
+    /// Removes a chunk at once, whether or not anyone can see it.
+    /// Entities which do not load chunks are unloaded from it, while
+    /// those which do stay tracked without a chunk to inhabit. No one
+    /// waits on the chunk afterwards, so it is not requested again
+    /// until an entity's view moves onto it.
+    pub fn force_remove_chunk(&mut self, loc: ChunkLocation) {
+        if let Some(chunk) = self.try_chunk(loc) {
+            for entity in std::mem::take(&mut chunk.entities_within) {
+                if self.entity_store[entity].load_radius.is_none() {
+                    self.unload_entity(entity, true);
+                }
+            }
+        }
+        self.unload_chunk(loc);
+
+        if let Some(awaiting) = self.entities_awaiting_chunks.remove(&loc) {
+            for entity in awaiting {
+                if let Some(data) = self.entity_store.get_mut(entity) {
+                    data.waiting_on.remove(&loc);
+                }
+            }
+        }
+    }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum TrackedWorldEvent<EntityData> {
     UnloadChunk(ChunkLocation, Vec<EntityData>),
     UnloadEntity(EntityData),
@@ -405,10 +546,7 @@ pub enum TrackedWorldEvent<EntityData> {
 
 #[cfg(test)]
 mod tests {
-    use servidiot_primitives::{
-        chunk::Chunk,
-        position::{ChunkLocation, ChunkPosition, Location},
-    };
+    use servidiot_primitives::position::{BlockPosition, ChunkLocation, ChunkPosition, Location};
 
     use crate::{TrackedWorld, TrackedWorldEvent};
 
@@ -437,7 +575,7 @@ mod tests {
     }
 
     fn loc(x: i32, z: i32) -> ChunkLocation {
-        ChunkLocation::new(ChunkPosition::new(x, z), Location::new(0, 0))
+        ChunkLocation::new(ChunkPosition::new(x, z), Location::overworld())
     }
 
     #[test]
@@ -567,13 +705,41 @@ mod tests {
 
     }
 
+    #[test]
+    fn proximity_queries() {
+        let mut tracker = TrackedWorld::<u64>::new();
+        let player = tracker.add_entity(0, loc(0, 0), Some(3)).unwrap();
+
+        let events = tracker.poll_events().collect::<Vec<_>>();
+        for event in events {
+            if let TrackedWorldEvent::RequestLoad(c) = event {
+                tracker.add_chunk(c);
+            }
+        }
+
+        let near = tracker.add_entity(1, loc(1, 0), None).unwrap();
+        let far = tracker.add_entity(2, loc(-2, 2), None).unwrap();
+        let _ = tracker.poll_events().count();
+
+        let mut within = tracker.entities_within_radius(player, 3);
+        within.sort();
+        let mut expected = vec![near, far];
+        expected.sort();
+        assert_eq!(within, expected);
+
+        assert_eq!(tracker.entities_within_radius(player, 1), vec![]);
+        assert_eq!(tracker.find_closest(player, 3), Some(near));
+        assert_eq!(tracker.find_closest(far, 8), Some(player));
+        assert_eq!(tracker.poll_events().count(), 0);
+    }
+
     // #[test]
     // fn world_track_test() {
     //     let mut tracker = TrackedWorld::<u64>::new();
     //     let us = tracker
     //         .add_entity(
     //             0,
-    //             ChunkLocation::new(ChunkPosition::new(0, 0), Location::new(0, 0)),
+    //             ChunkLocation::new(ChunkPosition::new(0, 0), Location::overworld()),
     //             Some(2),
     //         )
     //         .unwrap();
@@ -614,13 +780,13 @@ mod tests {
     //     let npc = tracker
     //         .add_entity(
     //             1,
-    //             ChunkLocation::new(ChunkPosition::new(1, 0), Location::new(0, 0)),
+    //             ChunkLocation::new(ChunkPosition::new(1, 0), Location::overworld()),
     //             None,
     //         )
     //         .unwrap();
     //     tracker.move_entity(
     //         us,
-    //         ChunkLocation::new(ChunkPosition::new(1, 0), Location::new(0, 0)),
+    //         ChunkLocation::new(ChunkPosition::new(1, 0), Location::overworld()),
     //     );
 
     //     loop {
@@ -658,7 +824,7 @@ mod tests {
 
     //     tracker.move_entity(
     //         us,
-    //         ChunkLocation::new(ChunkPosition::new(6, 0), Location::new(0, 0)),
+    //         ChunkLocation::new(ChunkPosition::new(6, 0), Location::overworld()),
     //     );
 
     //     loop {
@@ -692,4 +858,134 @@ mod tests {
     //         }
     //     }
     // }
+
+    #[test]
+    fn entity_iteration() {
+        let mut tracker = TrackedWorld::<u64>::new();
+        let player = tracker.add_entity(0, loc(0, 0), Some(2)).unwrap();
+
+        let events = tracker.poll_events().collect::<Vec<_>>();
+        for event in events {
+            if let TrackedWorldEvent::RequestLoad(c) = event {
+                tracker.add_chunk(c);
+            }
+        }
+        let npc = tracker.add_entity(1, loc(1, 1), None).unwrap();
+        let _ = tracker.poll_events().count();
+
+        let mut all = tracker.iter_entities().collect::<Vec<_>>();
+        all.sort();
+        let mut expected = vec![(player, &0), (npc, &1)];
+        expected.sort();
+        assert_eq!(all, expected);
+
+        let mut in_dimension = tracker
+            .iter_entities_in_dimension(Location::overworld())
+            .collect::<Vec<_>>();
+        in_dimension.sort();
+        let mut expected = vec![player, npc];
+        expected.sort();
+        assert_eq!(in_dimension, expected);
+
+        assert_eq!(tracker.iter_entities_in_dimension(Location::the_end()).count(), 0);
+        assert_eq!(tracker.poll_events().count(), 0);
+    }
+
+    #[test]
+    fn block_position_helpers() {
+        let mut tracker = TrackedWorld::<u64>::new();
+        let player = tracker
+            .add_entity_at_block_position(0, BlockPosition::new(-1, 64, 17), Location::overworld(), Some(2))
+            .unwrap();
+        assert_eq!(tracker.entity_store[player].inhabits, loc(-1, 1));
+
+        tracker.move_entity_to_position(player, BlockPosition::new(40, 10, -3), Location::nether());
+        assert_eq!(
+            tracker.entity_store[player].inhabits,
+            ChunkLocation::new(ChunkPosition::new(2, -1), Location::nether())
+        );
+
+        // Entities which do not load chunks need a loaded chunk.
+        assert!(tracker
+            .add_entity_at_block_position(1, BlockPosition::new(500, 0, 500), Location::overworld(), None)
+            .is_none());
+    }
+
+    #[test]
+    fn restore_snapshot() {
+        let mut tracker = TrackedWorld::<u64>::new();
+        let player = tracker.add_entity(0, loc(0, 0), Some(2)).unwrap();
+        let events = tracker.poll_events().collect::<Vec<_>>();
+        for event in events {
+            if let TrackedWorldEvent::RequestLoad(c) = event {
+                tracker.add_chunk(c);
+            }
+        }
+        let npc = tracker.add_entity(1, loc(1, 0), None).unwrap();
+
+        let snapshot = tracker.snapshot();
+        let run = |tracker: &mut TrackedWorld<u64>| {
+            tracker.move_entity(npc, loc(-1, 1));
+            tracker.move_entity(player, loc(6, 6));
+            let events = tracker.poll_events().map(normalize).collect::<Vec<_>>();
+            (events, tracker.iter_entities().map(|(k, v)| (k, *v)).collect::<Vec<_>>())
+        };
+
+        let first = run(&mut tracker);
+        tracker.restore(snapshot);
+        let second = run(&mut tracker);
+        assert!(!first.0.is_empty());
+        // Views are iterated in no particular order.
+        let sorted = |mut run: (Vec<String>, _)| {
+            run.0.sort();
+            run
+        };
+        assert_eq!(sorted(first), sorted(second));
+    }
+
+    #[test]
+    fn force_remove_chunk() {
+        let mut tracker = TrackedWorld::<u64>::new();
+        let player = tracker.add_entity(0, loc(0, 0), Some(2)).unwrap();
+        let events = tracker.poll_events().collect::<Vec<_>>();
+        for event in events {
+            if let TrackedWorldEvent::RequestLoad(c) = event {
+                tracker.add_chunk(c);
+            }
+        }
+        let _npc = tracker.add_entity(1, loc(0, 0), None).unwrap();
+        let _ = tracker.poll_events().count();
+
+        tracker.force_remove_chunk(loc(0, 0));
+        let events = tracker.poll_events().collect::<Vec<_>>();
+        ensure_has_event!(&events, EXPR TrackedWorldEvent::UnloadEntity(1));
+        ensure_has_event!(&events, EXPR TrackedWorldEvent::UnloadChunk(loc(0, 0), vec![]));
+        assert!(!events.iter().any(|v| matches!(v, TrackedWorldEvent::RequestLoad(_))));
+        assert_eq!(tracker.iter_entities().map(|(k, _)| k).collect::<Vec<_>>(), vec![player]);
+
+        tracker.move_entity(player, loc(1, 0));
+        assert!(!tracker.poll_events().any(|v| v == TrackedWorldEvent::RequestLoad(loc(0, 0))));
+    }
+
+    /// Describes an event independently
+    /// of the order of its contents.
+    fn normalize(event: TrackedWorldEvent<u64>) -> String {
+        fn sorted<T: std::fmt::Debug>(v: Vec<T>) -> Vec<String> {
+            let mut v = v.into_iter().map(|v| format!("{v:?}")).collect::<Vec<_>>();
+            v.sort();
+            v
+        }
+        match event {
+            TrackedWorldEvent::UnloadChunk(c, v) => format!("UnloadChunk({c:?}, {:?})", sorted(v)),
+            TrackedWorldEvent::EntityViewsChunks(e, v) => format!("EntityViewsChunks({e:?}, {:?})", sorted(v)),
+            TrackedWorldEvent::EntityNoLongerViewsChunks(e, v) => {
+                format!("EntityNoLongerViewsChunks({e:?}, {:?})", sorted(v))
+            }
+            TrackedWorldEvent::EntityViewsEntities(e, v) => format!("EntityViewsEntities({e:?}, {:?})", sorted(v)),
+            TrackedWorldEvent::EntityNoLongerViewsEntities(e, v) => {
+                format!("EntityNoLongerViewsEntities({e:?}, {:?})", sorted(v))
+            }
+            event => format!("{event:?}"),
+        }
+    }
 }
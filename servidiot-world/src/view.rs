@@ -39,4 +39,56 @@ impl View {
         let other_chunks = other.chunks();
         self_chunks.difference(&other_chunks).copied().collect::<Vec<_>>().into_iter()
     }
+
+    /// Every chunk in either view.
+    pub fn union(&self, other: &View) -> Vec<ChunkLocation> {
+        let self_chunks = self.chunks();
+        let other_chunks = other.chunks();
+        self_chunks.union(&other_chunks).copied().collect()
+    }
+
+    /// Every chunk in both views.
+    pub fn intersection(&self, other: &View) -> Vec<ChunkLocation> {
+        let self_chunks = self.chunks();
+        let other_chunks = other.chunks();
+        self_chunks.intersection(&other_chunks).copied().collect()
+    }
+
+    /// Whether `chunk` is one of the chunks
+    /// in this view, without building them all.
+    pub fn contains_chunk(&self, chunk: ChunkLocation) -> bool {
+        let c = chunk.position;
+        let center = self.center.position;
+        let radius = self.radius as i32;
+        chunk.location == self.center.location
+            && (center.x - radius..center.x + radius).contains(&c.x)
+            && (center.z - radius..center.z + radius).contains(&c.z)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use servidiot_primitives::position::{ChunkLocation, ChunkPosition, Location};
+
+    use super::View;
+
+    fn loc(x: i32, z: i32) -> ChunkLocation {
+        ChunkLocation::new(ChunkPosition::new(x, z), Location::overworld())
+    }
+
+    #[test]
+    fn set_operations() {
+        let a = View::new(loc(0, 0), 2);
+        let b = View::new(loc(1, 0), 2);
+
+        assert_eq!(a.union(&b).len(), 20);
+        let mut both = a.intersection(&b);
+        both.sort_by_key(|v| (v.position.x, v.position.z));
+        assert_eq!(both, (-1..2).flat_map(|x| (-2..2).map(move |z| loc(x, z))).collect::<Vec<_>>());
+
+        for chunk in a.union(&b) {
+            assert_eq!(a.contains_chunk(chunk), a.chunks().contains(&chunk));
+        }
+        assert!(!a.contains_chunk(ChunkLocation::new(ChunkPosition::new(0, 0), Location::new(1, 0))));
+    }
 }
@@ -7,7 +7,7 @@ use flume::{Receiver, Sender};
 use fxhash::FxHashMap;
 use rayon::ThreadPool;
 use servidiot_anvil::{region::nbt::ChunkRoot, WorldManager};
-use servidiot_primitives::position::{ChunkLocation, DimensionID};
+use servidiot_primitives::position::{ChunkLocation, DimensionID, Location};
 use world::{Response, World};
 
 
@@ -61,20 +61,121 @@ impl Worlds {
     }
 
     /// Add a multiworld world to this manager.
+    /// Dimensions missing on disk are skipped.
     pub fn add(
         &mut self,
         id: u32,
         dimensions: &[DimensionID],
         directory: PathBuf,
     ) -> anyhow::Result<()> {
+        let manager = WorldManager::open(directory);
+        let mut present = Vec::with_capacity(dimensions.len());
+        for dimension in dimensions {
+            if manager.dimension_exists(*dimension)? {
+                present.push(*dimension);
+            } else {
+                tracing::warn!(
+                    "Dimension {} of world {} does not exist on disk, and will not be loaded",
+                    dimension, id
+                );
+            }
+        }
+
         let world = World::new(
             &self.thread_pool,
-            WorldManager::open(directory),
-            dimensions,
+            manager,
+            &present,
             self.thread_commands.0.clone(),
         )?;
 
         self.multiworld_entries.insert(id, world);
         Ok(())
     }
+
+    /// Add a multiworld world to this manager, with every
+    /// dimension present on disk. Worlds without a `level.dat`
+    /// only have their overworld loaded.
+    pub fn add_from_level_dat(&mut self, id: u32, directory: PathBuf) -> anyhow::Result<()> {
+        let dimensions = level_dat_dimensions(&WorldManager::open(directory.clone()), id)?;
+        self.add(id, &dimensions, directory)
+    }
+}
+
+/// The dimensions `add_from_level_dat` loads: the
+/// overworld, and the nether (DIM-1) and the end (DIM1)
+/// if the world has a `level.dat` and they exist on disk.
+fn level_dat_dimensions(manager: &WorldManager, id: u32) -> anyhow::Result<Vec<DimensionID>> {
+    let mut dimensions = vec![Location::overworld().dimension];
+    if manager.load_level_dat()?.is_some() {
+        for dimension in [Location::nether().dimension, Location::the_end().dimension] {
+            if manager.dimension_exists(dimension)? {
+                dimensions.push(dimension);
+            }
+        }
+    } else {
+        tracing::warn!(
+            "World {} has no level.dat, so only its overworld will be loaded",
+            id
+        );
+    }
+    Ok(dimensions)
+}
+
+#[cfg(test)]
+mod tests {
+    use servidiot_anvil::{nbt::level::{LevelData, LevelRoot}, WorldManager};
+
+    use super::level_dat_dimensions;
+
+    fn level_root() -> LevelRoot {
+        LevelRoot {
+            data: LevelData {
+                version: 19133,
+                initialized: true,
+                level_name: "world".to_string(),
+                generator_name: "default".to_string(),
+                generator_version: 1,
+                generator_options: String::new(),
+                world_seed: 0,
+                map_features: true,
+                last_played: 0,
+                cheats_enabled: false,
+                hardcore: false,
+                game_type: 0,
+                difficulty: 0,
+                difficulty_locked: false,
+                level_ticks: 0,
+                day_time: 0,
+                spawn_x: 0,
+                spawn_y: 64,
+                spawn_z: 0,
+                raining: false,
+                rain_time: 0,
+                thundering: false,
+                thunder_time: 0,
+                player: nbt::Value::Compound(Default::default()),
+                game_rules: Default::default(),
+            },
+        }
+    }
+
+    #[test]
+    fn level_dat_dimensions_test() {
+        let dir = std::env::temp_dir().join(format!("servidiot-level-dat-{}", std::process::id()));
+        let mut manager = WorldManager::open(dir.clone());
+        std::fs::create_dir_all(dir.join("DIM-1/region")).unwrap();
+        std::fs::write(dir.join("DIM-1/region/r.0.0.mca"), []).unwrap();
+
+        // Without a level.dat, only the overworld is loaded.
+        assert_eq!(level_dat_dimensions(&manager, 0).unwrap(), vec![0]);
+
+        manager.save_level_dat(&level_root()).unwrap();
+        assert_eq!(level_dat_dimensions(&manager, 0).unwrap(), vec![0, -1]);
+
+        std::fs::create_dir_all(dir.join("DIM1/region")).unwrap();
+        std::fs::write(dir.join("DIM1/region/r.0.0.mca"), []).unwrap();
+        assert_eq!(level_dat_dimensions(&manager, 0).unwrap(), vec![0, -1, 1]);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
 }